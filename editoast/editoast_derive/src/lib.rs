@@ -132,6 +132,7 @@ pub fn model(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
 ///     - **name** (required): the column name in the database
 ///     - **data_type** (required): the SQL column type in the database
 ///     - **sql** (optional, required if **migration** is provided): the SQL query to perform to retrieve the data for the column in the search table
+///     - **table** (optional): the table (or alias from **joins**) holding the column when it isn't stored in the search table, such as a `geometry` column of a layer. No migration is generated for it
 ///     - **index** (optional): whether to create an index for the column in the search table (defaults to `true`)
 ///     - **textual_search** (optional): whether to create a textual search index for the column in the search table (defaults to `false`)
 ///
@@ -51,6 +51,8 @@ struct SearchColumn {
     name: String,
     data_type: String,
     sql: Option<String>, // some search objects may not have a migration
+    /// The table (or alias in `joins`) holding the column when it's not stored in the search table
+    table: Option<String>,
     index: Option<bool>,
     #[darling(default)]
     textual_search: bool,
@@ -80,6 +82,7 @@ enum ColumnType {
     TextualSearchString,
     Boolean,
     Null,
+    Geometry,
     Sequence(Box<ColumnType>),
 }
 
@@ -115,6 +118,7 @@ impl ColumnType {
             }
             "boolean" | "bool" => Some(ColumnType::Boolean),
             "null" => Some(ColumnType::Null),
+            "geometry" => Some(ColumnType::Geometry),
             // handles VARCHAR(240), NUMERIC(4, 2), etc.
             prefix if prefix.contains('(') => {
                 let (prefix, _) = prefix.split_once('(').unwrap();
@@ -147,6 +151,9 @@ impl ColumnType {
             ColumnType::Null => {
                 quote! { crate::views::search::TypeSpec::Type(crate::views::search::AstType::Null) }
             }
+            ColumnType::Geometry => {
+                quote! { crate::views::search::TypeSpec::Type(crate::views::search::AstType::Geometry) }
+            }
             ColumnType::Sequence(ct) => {
                 let ts = ct.to_type_spec();
                 quote! { crate::views::search::TypeSpec::Sequence(Box::new(#ts)) }
@@ -181,6 +188,7 @@ pub fn expand_search(input: &DeriveInput) -> Result<TokenStream> {
         name,
         data_type,
         sql,
+        table,
         index,
         textual_search,
     } in params.columns.iter()
@@ -191,7 +199,8 @@ pub fn expand_search(input: &DeriveInput) -> Result<TokenStream> {
             ))
         })?;
         let ts = st.to_type_spec();
-        let migration = if has_migration {
+        // Columns of joined tables aren't part of the search table
+        let migration = if has_migration && table.is_none() {
             let search_type = if *textual_search {
                 if st != ColumnType::String {
                     return Err(Error::custom(format!(
@@ -227,10 +236,15 @@ pub fn expand_search(input: &DeriveInput) -> Result<TokenStream> {
         } else {
             quote! { None }
         };
+        let table = match table {
+            Some(table) => quote! { Some(#table.to_owned()) },
+            None => quote! { None },
+        };
         criterias.push(quote! {
             crate::views::search::Criteria {
                 name: #name.to_owned(),
                 data_type: #ts,
+                table: #table,
                 migration: #migration,
             }
         });
@@ -13,6 +13,8 @@ mod loading_gauge_limit;
 mod neutral_section;
 mod operational_point;
 mod railjson;
+mod railjson_extract;
+mod railjson_migrations;
 mod route;
 mod side;
 mod sign;
@@ -53,6 +55,12 @@ pub use operational_point::OperationalPointIdentifierExtension;
 pub use operational_point::OperationalPointPart;
 pub use railjson::RailJson;
 pub use railjson::RAILJSON_VERSION;
+pub use railjson_extract::extract_railjson;
+pub use railjson_migrations::downgrade_railjson;
+pub use railjson_migrations::supported_railjson_versions;
+pub use railjson_migrations::upgrade_railjson;
+pub use railjson_migrations::RailJsonMigrationError;
+pub use railjson_migrations::RailJsonObjectUpgrader;
 pub use route::Route;
 pub use route::RoutePath;
 pub use side::Side;
@@ -6,6 +6,7 @@ use utoipa::ToSchema;
 use super::BufferStop;
 use super::Detector;
 use super::Electrification;
+use super::InfraObject;
 use super::NeutralSection;
 use super::OperationalPoint;
 use super::Route;
@@ -52,3 +53,36 @@ pub struct RailJson {
     /// `Detector` is a device that identifies the presence of a train in a TVD section (Track Vacancy Detection section), indicating when a track area is occupied.
     pub detectors: Vec<Detector>,
 }
+
+impl RailJson {
+    /// Splits the infrastructure into its objects
+    pub fn into_objects(self) -> impl Iterator<Item = InfraObject> {
+        let RailJson {
+            operational_points,
+            routes,
+            extended_switch_types,
+            switches,
+            track_sections,
+            speed_sections,
+            neutral_sections,
+            electrifications,
+            signals,
+            buffer_stops,
+            detectors,
+            ..
+        } = self;
+        track_sections
+            .into_iter()
+            .map(InfraObject::from)
+            .chain(signals.into_iter().map(InfraObject::from))
+            .chain(speed_sections.into_iter().map(InfraObject::from))
+            .chain(neutral_sections.into_iter().map(InfraObject::from))
+            .chain(routes.into_iter().map(InfraObject::from))
+            .chain(operational_points.into_iter().map(InfraObject::from))
+            .chain(switches.into_iter().map(InfraObject::from))
+            .chain(extended_switch_types.into_iter().map(InfraObject::from))
+            .chain(detectors.into_iter().map(InfraObject::from))
+            .chain(buffer_stops.into_iter().map(InfraObject::from))
+            .chain(electrifications.into_iter().map(InfraObject::from))
+    }
+}
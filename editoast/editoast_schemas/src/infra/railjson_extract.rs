@@ -0,0 +1,322 @@
+//! Extraction of a self-contained part of a RailJSON infrastructure
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use super::BufferStop;
+use super::Endpoint;
+use super::RailJson;
+use super::TrackEndpoint;
+use super::TrackSection;
+use super::Waypoint;
+use crate::primitives::Identifier;
+
+/// Extracts the track sections matching a predicate along with all the objects attached to them
+///
+/// Objects spanning over several track sections, such as speed sections or operational points,
+/// only keep their parts located on the extracted ones.
+/// Switches connecting extracted track sections to other ones are dropped, the track endpoints
+/// they leave dangling being closed with generated buffer stops.
+/// Routes referencing a dropped object are dropped as well.
+pub fn extract_railjson(
+    railjson: RailJson,
+    mut is_extracted: impl FnMut(&TrackSection) -> bool,
+) -> RailJson {
+    let RailJson {
+        version,
+        operational_points,
+        routes,
+        extended_switch_types,
+        switches,
+        track_sections,
+        speed_sections,
+        neutral_sections,
+        electrifications,
+        signals,
+        buffer_stops,
+        detectors,
+    } = railjson;
+
+    let track_sections: Vec<_> = track_sections
+        .into_iter()
+        .filter(|track| is_extracted(track))
+        .collect();
+    let tracks_length: HashMap<_, _> = track_sections
+        .iter()
+        .map(|track| (track.id.clone(), track.length))
+        .collect();
+    let is_on_tracks = |track: &Identifier| tracks_length.contains_key(track);
+
+    let (switches, crossing_switches): (Vec<_>, Vec<_>) = switches
+        .into_iter()
+        .filter(|switch| switch.ports.values().any(|port| is_on_tracks(&port.track)))
+        .partition(|switch| switch.ports.values().all(|port| is_on_tracks(&port.track)));
+    let switch_ids: HashSet<_> = switches.iter().map(|switch| switch.id.clone()).collect();
+
+    let mut buffer_stops: Vec<_> = buffer_stops
+        .into_iter()
+        .filter(|buffer_stop| is_on_tracks(&buffer_stop.track))
+        .collect();
+    let dangling_endpoints: HashSet<_> = crossing_switches
+        .iter()
+        .flat_map(|switch| switch.ports.values())
+        .filter(|port| is_on_tracks(&port.track))
+        .collect();
+    let mut buffer_stop_ids: HashSet<_> = buffer_stops
+        .iter()
+        .map(|buffer_stop| buffer_stop.id.clone())
+        .collect();
+    for TrackEndpoint { track, endpoint } in dangling_endpoints {
+        let (suffix, position) = match endpoint {
+            Endpoint::Begin => ("begin", 0.),
+            Endpoint::End => ("end", tracks_length[track]),
+        };
+        // The generated id is made unique if a buffer stop already has it
+        let base_id = format!("{}.{suffix}.buffer_stop", track.0);
+        let id = (0..)
+            .map(|index| match index {
+                0 => base_id.clone(),
+                index => format!("{base_id}.{index}"),
+            })
+            .map(Identifier::from)
+            .find(|id| !buffer_stop_ids.contains(id))
+            .unwrap();
+        buffer_stop_ids.insert(id.clone());
+        buffer_stops.push(BufferStop {
+            id,
+            track: track.clone(),
+            position,
+            extensions: Default::default(),
+        });
+    }
+
+    let detectors: Vec<_> = detectors
+        .into_iter()
+        .filter(|detector| is_on_tracks(&detector.track))
+        .collect();
+    let detector_ids: HashSet<_> = detectors.iter().map(|detector| &detector.id).collect();
+    let is_kept_waypoint = |waypoint: &Waypoint| match waypoint {
+        Waypoint::BufferStop { id } => buffer_stop_ids.contains(id),
+        Waypoint::Detector { id } => detector_ids.contains(id),
+    };
+    let routes: Vec<_> = routes
+        .into_iter()
+        .filter(|route| {
+            is_kept_waypoint(&route.entry_point)
+                && is_kept_waypoint(&route.exit_point)
+                && route
+                    .release_detectors
+                    .iter()
+                    .all(|detector| detector_ids.contains(detector))
+                && route
+                    .switches_directions
+                    .keys()
+                    .all(|switch| switch_ids.contains(switch))
+        })
+        .collect();
+    let route_ids: HashSet<_> = routes.iter().map(|route| &route.id).collect();
+
+    let speed_sections = speed_sections
+        .into_iter()
+        .filter_map(|mut speed_section| {
+            speed_section
+                .track_ranges
+                .retain(|range| is_on_tracks(&range.track));
+            if let Some(on_routes) = speed_section.on_routes.as_mut() {
+                on_routes.retain(|route| route_ids.contains(route));
+            }
+            (!speed_section.track_ranges.is_empty()).then_some(speed_section)
+        })
+        .collect();
+    let electrifications = electrifications
+        .into_iter()
+        .filter_map(|mut electrification| {
+            electrification
+                .track_ranges
+                .retain(|range| is_on_tracks(&range.track));
+            (!electrification.track_ranges.is_empty()).then_some(electrification)
+        })
+        .collect();
+    let neutral_sections = neutral_sections
+        .into_iter()
+        .filter_map(|mut neutral_section| {
+            neutral_section
+                .track_ranges
+                .retain(|range| is_on_tracks(&range.track));
+            neutral_section
+                .announcement_track_ranges
+                .retain(|range| is_on_tracks(&range.track));
+            (!neutral_section.track_ranges.is_empty()).then_some(neutral_section)
+        })
+        .collect();
+    let operational_points = operational_points
+        .into_iter()
+        .filter_map(|mut operational_point| {
+            operational_point
+                .parts
+                .retain(|part| is_on_tracks(&part.track));
+            (!operational_point.parts.is_empty()).then_some(operational_point)
+        })
+        .collect();
+    let signals = signals
+        .into_iter()
+        .filter(|signal| is_on_tracks(&signal.track))
+        .collect();
+
+    RailJson {
+        version,
+        operational_points,
+        routes,
+        extended_switch_types,
+        switches,
+        track_sections,
+        speed_sections,
+        neutral_sections,
+        electrifications,
+        signals,
+        buffer_stops,
+        detectors,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::extract_railjson;
+    use crate::infra::ApplicableDirectionsTrackRange;
+    use crate::infra::BufferStop;
+    use crate::infra::Detector;
+    use crate::infra::Endpoint;
+    use crate::infra::RailJson;
+    use crate::infra::Route;
+    use crate::infra::SpeedSection;
+    use crate::infra::Switch;
+    use crate::infra::TrackEndpoint;
+    use crate::infra::TrackSection;
+    use crate::infra::Waypoint;
+    use crate::primitives::OSRDIdentified;
+
+    fn track_section(id: &str) -> TrackSection {
+        TrackSection {
+            id: id.into(),
+            ..Default::default()
+        }
+    }
+
+    fn detector(id: &str, track: &str) -> Detector {
+        Detector {
+            id: id.into(),
+            track: track.into(),
+            ..Default::default()
+        }
+    }
+
+    fn link(id: &str, begin_track: &str, end_track: &str) -> Switch {
+        Switch {
+            id: id.into(),
+            ports: HashMap::from([
+                ("A".into(), TrackEndpoint::new(begin_track, Endpoint::End)),
+                ("B".into(), TrackEndpoint::new(end_track, Endpoint::Begin)),
+            ]),
+            ..Default::default()
+        }
+    }
+
+    fn route(id: &str, entry: &str, exit: &str, switch: &str) -> Route {
+        Route {
+            id: id.into(),
+            entry_point: Waypoint::new_detector(entry),
+            exit_point: Waypoint::new_detector(exit),
+            switches_directions: HashMap::from([(switch.into(), "A_B".into())]),
+            ..Default::default()
+        }
+    }
+
+    fn ids<T: OSRDIdentified>(objects: &[T]) -> Vec<&str> {
+        let mut ids: Vec<_> = objects
+            .iter()
+            .map(|object| object.get_id().as_str())
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    /// Three consecutive tracks A -> B -> C, only A and B being extracted
+    #[test]
+    fn extract_tracks() {
+        let railjson = RailJson {
+            track_sections: vec![track_section("A"), track_section("B"), track_section("C")],
+            switches: vec![link("link.A.B", "A", "B"), link("link.B.C", "B", "C")],
+            detectors: vec![
+                detector("D.A", "A"),
+                detector("D.B", "B"),
+                detector("D.C", "C"),
+            ],
+            buffer_stops: vec![BufferStop {
+                id: "BS.C".into(),
+                track: "C".into(),
+                position: 100.,
+                ..Default::default()
+            }],
+            routes: vec![
+                route("D.A->D.B", "D.A", "D.B", "link.A.B"),
+                route("D.B->D.C", "D.B", "D.C", "link.B.C"),
+            ],
+            speed_sections: vec![SpeedSection {
+                id: "speed".into(),
+                track_ranges: vec![
+                    ApplicableDirectionsTrackRange {
+                        track: "A".into(),
+                        ..Default::default()
+                    },
+                    ApplicableDirectionsTrackRange {
+                        track: "C".into(),
+                        ..Default::default()
+                    },
+                ],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let extracted = extract_railjson(railjson, |track| track.id.0 != "C");
+
+        assert_eq!(ids(&extracted.track_sections), vec!["A", "B"]);
+        assert_eq!(ids(&extracted.switches), vec!["link.A.B"]);
+        assert_eq!(ids(&extracted.detectors), vec!["D.A", "D.B"]);
+        assert_eq!(ids(&extracted.routes), vec!["D.A->D.B"]);
+        assert_eq!(extracted.speed_sections[0].track_ranges.len(), 1);
+        let [buffer_stop] = extracted.buffer_stops.as_slice() else {
+            panic!("expected a single generated buffer stop");
+        };
+        assert_eq!(buffer_stop.id.0, "B.end.buffer_stop");
+        assert_eq!(buffer_stop.track.0, "B");
+        assert_eq!(buffer_stop.position, 100.);
+    }
+
+    #[test]
+    fn extract_tracks_generated_buffer_stop_id_collision() {
+        let railjson = RailJson {
+            track_sections: vec![track_section("A"), track_section("B")],
+            switches: vec![link("link.A.B", "A", "B")],
+            buffer_stops: vec![BufferStop {
+                id: "A.end.buffer_stop".into(),
+                track: "A".into(),
+                position: 0.,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let extracted = extract_railjson(railjson, |track| track.id.0 == "A");
+
+        assert_eq!(
+            ids(&extracted.buffer_stops),
+            vec!["A.end.buffer_stop", "A.end.buffer_stop.1"]
+        );
+        let generated = &extracted.buffer_stops[1];
+        assert_eq!(generated.track.0, "A");
+        assert_eq!(generated.position, 100.);
+    }
+}
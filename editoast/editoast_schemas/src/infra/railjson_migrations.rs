@@ -0,0 +1,507 @@
+//! Upgrades and downgrades of RailJSON payloads between versions of the format
+//!
+//! Each step mirrors a database migration that changed the format of the infra objects.
+//! Payloads are handled as raw JSON since older versions can't be deserialized as [super::RailJson].
+//! Steps apply to each object independently, so that objects can be migrated while being streamed.
+
+use serde_json::json;
+use serde_json::Map;
+use serde_json::Value;
+use thiserror::Error;
+
+use super::RAILJSON_VERSION;
+
+type JsonObject = Map<String, Value>;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum RailJsonMigrationError {
+    #[error("Missing railjson version")]
+    MissingVersion,
+    #[error("Unsupported railjson version '{0}'")]
+    UnsupportedVersion(String),
+    #[error("Cannot downgrade railjson from version '{from}' to the newer version '{to}'")]
+    NewerTargetVersion { from: String, to: String },
+}
+
+/// Migrates an object of a collection of the payload, e.g. `"signals"`
+type ObjectMigration = fn(&str, &mut JsonObject);
+
+/// A change of the RailJSON format between two consecutive versions
+#[derive(Debug)]
+struct MigrationStep {
+    from: &'static str,
+    to: &'static str,
+    upgrade: ObjectMigration,
+    downgrade: ObjectMigration,
+}
+
+const MIGRATION_STEPS: [MigrationStep; 6] = [
+    MigrationStep {
+        from: "3.4.6",
+        to: "3.4.7",
+        upgrade: remove_detector_applicable_directions,
+        downgrade: add_detector_applicable_directions,
+    },
+    MigrationStep {
+        from: "3.4.7",
+        to: "3.4.8",
+        upgrade: split_tvm,
+        downgrade: merge_tvm,
+    },
+    MigrationStep {
+        from: "3.4.8",
+        to: "3.4.9",
+        upgrade: upgrade_to_neutral_signs,
+        downgrade: downgrade_from_neutral_signs,
+    },
+    MigrationStep {
+        from: "3.4.9",
+        to: "3.4.10",
+        upgrade: |_, _| {},
+        downgrade: remove_speed_section_routes,
+    },
+    MigrationStep {
+        from: "3.4.10",
+        to: "3.4.11",
+        upgrade: add_signal_parameters,
+        downgrade: remove_signal_parameters,
+    },
+    MigrationStep {
+        from: "3.4.11",
+        to: "3.4.12",
+        upgrade: remove_schematic,
+        downgrade: add_schematic,
+    },
+];
+
+/// Returns the RailJSON versions that can be imported and exported, from the oldest to the current one
+pub fn supported_railjson_versions() -> Vec<&'static str> {
+    MIGRATION_STEPS
+        .iter()
+        .map(|step| step.from)
+        .chain([RAILJSON_VERSION])
+        .collect()
+}
+
+fn version_index(version: &str) -> Result<usize, RailJsonMigrationError> {
+    supported_railjson_versions()
+        .iter()
+        .position(|supported| *supported == version)
+        .ok_or_else(|| RailJsonMigrationError::UnsupportedVersion(version.to_owned()))
+}
+
+fn railjson_version(railjson: &JsonObject) -> Result<&str, RailJsonMigrationError> {
+    railjson
+        .get("version")
+        .and_then(Value::as_str)
+        .ok_or(RailJsonMigrationError::MissingVersion)
+}
+
+fn as_railjson_object(railjson: &mut Value) -> Result<&mut JsonObject, RailJsonMigrationError> {
+    railjson
+        .as_object_mut()
+        .ok_or(RailJsonMigrationError::MissingVersion)
+}
+
+/// Applies a migration to all the objects of the payload
+fn migrate_objects(railjson: &mut JsonObject, migration: ObjectMigration) {
+    for (collection, objects) in railjson.iter_mut() {
+        let objects = objects.as_array_mut().into_iter().flatten();
+        for object in objects.filter_map(Value::as_object_mut) {
+            migration(collection, object);
+        }
+    }
+}
+
+/// Upgrades the objects of a RailJSON payload one by one to the current version of the format
+#[derive(Debug, Clone, Copy)]
+pub struct RailJsonObjectUpgrader {
+    steps: &'static [MigrationStep],
+}
+
+impl RailJsonObjectUpgrader {
+    pub fn new(version: &str) -> Result<Self, RailJsonMigrationError> {
+        let from = version_index(version)?;
+        Ok(Self {
+            steps: &MIGRATION_STEPS[from..],
+        })
+    }
+
+    /// Returns whether the objects are already in the current version
+    pub fn is_noop(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Upgrades an object of a collection of the payload, e.g. `"signals"`
+    pub fn upgrade(&self, collection: &str, object: &mut Value) {
+        let Some(object) = object.as_object_mut() else {
+            return;
+        };
+        for step in self.steps {
+            (step.upgrade)(collection, object);
+        }
+    }
+}
+
+/// Upgrades a RailJSON payload to the current version of the format
+///
+/// Payloads already in the current version are left untouched.
+pub fn upgrade_railjson(railjson: &mut Value) -> Result<(), RailJsonMigrationError> {
+    let railjson = as_railjson_object(railjson)?;
+    let from = version_index(railjson_version(railjson)?)?;
+    for step in &MIGRATION_STEPS[from..] {
+        migrate_objects(railjson, step.upgrade);
+        railjson.insert("version".to_owned(), json!(step.to));
+    }
+    Ok(())
+}
+
+/// Downgrades a RailJSON payload to an older supported version of the format
+pub fn downgrade_railjson(
+    railjson: &mut Value,
+    version: &str,
+) -> Result<(), RailJsonMigrationError> {
+    let railjson = as_railjson_object(railjson)?;
+    let current_version = railjson_version(railjson)?.to_owned();
+    let from = version_index(&current_version)?;
+    let to = version_index(version)?;
+    if to > from {
+        return Err(RailJsonMigrationError::NewerTargetVersion {
+            from: current_version,
+            to: version.to_owned(),
+        });
+    }
+    for step in MIGRATION_STEPS[to..from].iter().rev() {
+        migrate_objects(railjson, step.downgrade);
+        railjson.insert("version".to_owned(), json!(step.from));
+    }
+    Ok(())
+}
+
+/// Iterates over the logical signals of a signal
+fn logical_signals_mut(signal: &mut JsonObject) -> impl Iterator<Item = &mut JsonObject> {
+    signal
+        .get_mut("logical_signals")
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_object_mut)
+}
+
+/// Iterates over the signs of the `psl_sncf` extension of a speed section
+fn psl_signs_mut(speed_section: &mut JsonObject) -> impl Iterator<Item = &mut JsonObject> {
+    speed_section
+        .get_mut("extensions")
+        .and_then(|extensions| extensions.get_mut("psl_sncf"))
+        .and_then(Value::as_object_mut)
+        .into_iter()
+        .flat_map(|psl| {
+            psl.iter_mut()
+                .flat_map(|(field, signs)| match field.as_str() {
+                    "z" => vec![signs],
+                    "r" | "announcement" => signs.as_array_mut().into_iter().flatten().collect(),
+                    _ => vec![],
+                })
+        })
+        .filter_map(Value::as_object_mut)
+}
+
+fn json_object(value: Value) -> JsonObject {
+    match value {
+        Value::Object(object) => object,
+        _ => unreachable!("expected a JSON object"),
+    }
+}
+
+fn is_digits(value: &str) -> bool {
+    !value.is_empty() && value.chars().all(|c| c.is_ascii_digit())
+}
+
+fn remove_detector_applicable_directions(collection: &str, object: &mut JsonObject) {
+    if collection == "detectors" {
+        object.remove("applicable_directions");
+    }
+}
+
+fn add_detector_applicable_directions(collection: &str, object: &mut JsonObject) {
+    if collection == "detectors" {
+        object.insert("applicable_directions".to_owned(), json!("BOTH"));
+    }
+}
+
+/// Splits the `TVM` signaling system into `TVM300` and `TVM430` depending on the `is_430` setting
+fn split_tvm(collection: &str, object: &mut JsonObject) {
+    if collection != "signals" {
+        return;
+    }
+    for logical_signal in logical_signals_mut(object) {
+        if logical_signal.get("signaling_system") != Some(&json!("TVM")) {
+            continue;
+        }
+        let is_430 = logical_signal
+            .get("settings")
+            .and_then(|settings| settings.get("is_430"))
+            == Some(&json!("true"));
+        let signaling_system = if is_430 { "TVM430" } else { "TVM300" };
+        *logical_signal = json_object(json!({
+            "signaling_system": signaling_system,
+            "settings": { "Nf": "true" },
+            "next_signaling_systems": [],
+        }));
+    }
+}
+
+fn merge_tvm(collection: &str, object: &mut JsonObject) {
+    if collection != "signals" {
+        return;
+    }
+    for logical_signal in logical_signals_mut(object) {
+        let is_430 = match logical_signal
+            .get("signaling_system")
+            .and_then(Value::as_str)
+        {
+            Some("TVM300") => "false",
+            Some("TVM430") => "true",
+            _ => continue,
+        };
+        *logical_signal = json_object(json!({
+            "signaling_system": "TVM",
+            "settings": { "is_430": is_430 },
+            "next_signaling_systems": [],
+        }));
+    }
+}
+
+/// Gives a direction to the speed limit signs instead of angles, adds units to the
+/// electrification voltages and replaces the zero speed limits by missing ones
+fn upgrade_to_neutral_signs(collection: &str, object: &mut JsonObject) {
+    match collection {
+        "speed_sections" => {
+            for sign in psl_signs_mut(object) {
+                sign.remove("angle_geo");
+                sign.remove("angle_sch");
+                sign.insert("direction".to_owned(), json!("START_TO_STOP"));
+            }
+            if object.get("speed_limit").and_then(Value::as_f64) == Some(0.) {
+                object.insert("speed_limit".to_owned(), Value::Null);
+            }
+            if let Some(Value::Object(speed_limit_by_tag)) = object.get_mut("speed_limit_by_tag") {
+                speed_limit_by_tag
+                    .retain(|_, speed| speed.as_f64().map_or(true, |speed| speed > 0.));
+            }
+        }
+        "electrifications" => {
+            let Some(voltage) = object.get("voltage").and_then(Value::as_str) else {
+                return;
+            };
+            let voltage = voltage
+                .split(';')
+                .map(|voltage| {
+                    if is_digits(voltage) {
+                        format!("{voltage}V")
+                    } else {
+                        voltage.to_owned()
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(";");
+            object.insert("voltage".to_owned(), json!(voltage));
+        }
+        _ => {}
+    }
+}
+
+fn downgrade_from_neutral_signs(collection: &str, object: &mut JsonObject) {
+    match collection {
+        "speed_sections" => {
+            for sign in psl_signs_mut(object) {
+                sign.remove("direction");
+                sign.insert("angle_geo".to_owned(), json!(0));
+                sign.insert("angle_sch".to_owned(), json!(0));
+            }
+        }
+        "electrifications" => {
+            let Some(voltage) = object.get("voltage").and_then(Value::as_str) else {
+                return;
+            };
+            let voltage = voltage
+                .split(';')
+                .map(|voltage| match voltage.strip_suffix('V') {
+                    Some(value) if is_digits(value) => value,
+                    _ => voltage,
+                })
+                .collect::<Vec<_>>()
+                .join(";");
+            object.insert("voltage".to_owned(), json!(voltage));
+        }
+        _ => {}
+    }
+}
+
+fn remove_speed_section_routes(collection: &str, object: &mut JsonObject) {
+    if collection == "speed_sections" {
+        object.remove("on_routes");
+    }
+}
+
+/// Adds the default and conditional parameters of the logical signals, `jaune_cli` being disabled for BAL
+fn add_signal_parameters(collection: &str, object: &mut JsonObject) {
+    if collection != "signals" {
+        return;
+    }
+    for logical_signal in logical_signals_mut(object) {
+        let default_parameters = if logical_signal.get("signaling_system") == Some(&json!("BAL")) {
+            json!({ "jaune_cli": "false" })
+        } else {
+            json!({})
+        };
+        logical_signal
+            .entry("default_parameters")
+            .or_insert(default_parameters);
+        logical_signal
+            .entry("conditional_parameters")
+            .or_insert(json!([]));
+    }
+}
+
+fn remove_signal_parameters(collection: &str, object: &mut JsonObject) {
+    if collection != "signals" {
+        return;
+    }
+    for logical_signal in logical_signals_mut(object) {
+        logical_signal.remove("default_parameters");
+        logical_signal.remove("conditional_parameters");
+    }
+}
+
+/// Removes the schematic geometry of the track sections and the sight distance of TVM signals
+fn remove_schematic(collection: &str, object: &mut JsonObject) {
+    match collection {
+        "track_sections" => {
+            object.remove("sch");
+        }
+        "signals" => {
+            let is_tvm = logical_signals_mut(object)
+                .filter_map(|logical_signal| logical_signal.get("signaling_system"))
+                .any(|system| *system == json!("TVM300") || *system == json!("TVM430"));
+            if is_tvm {
+                object.insert("sight_distance".to_owned(), json!(0.0));
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Uses the geographic geometry of the track sections as their schematic one
+fn add_schematic(collection: &str, object: &mut JsonObject) {
+    if collection != "track_sections" {
+        return;
+    }
+    if let Some(geo) = object.get("geo").cloned() {
+        object.insert("sch".to_owned(), geo);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::infra::RailJson;
+
+    #[test]
+    fn last_step_is_current_version() {
+        assert_eq!(MIGRATION_STEPS.last().unwrap().to, RAILJSON_VERSION);
+        for steps in MIGRATION_STEPS.windows(2) {
+            assert_eq!(steps[0].to, steps[1].from);
+        }
+    }
+
+    #[test]
+    fn upgrade_oldest_version() {
+        let mut railjson = json!({
+            "version": "3.4.6",
+            "detectors": [
+                { "id": "D", "track": "T", "position": 10.0, "applicable_directions": "BOTH" }
+            ],
+            "signals": [{
+                "id": "S",
+                "track": "T",
+                "position": 10.0,
+                "direction": "START_TO_STOP",
+                "sight_distance": 400.0,
+                "logical_signals": [
+                    { "signaling_system": "TVM", "settings": { "is_430": "true" }, "next_signaling_systems": [] },
+                    { "signaling_system": "BAL", "settings": { "Nf": "true" }, "next_signaling_systems": [] }
+                ],
+            }],
+            "electrifications": [{ "id": "E", "voltage": "1500;25000V", "track_ranges": [] }],
+            "track_sections": [],
+            "speed_sections": [],
+            "switches": [],
+            "extended_switch_types": [],
+            "buffer_stops": [],
+            "routes": [],
+            "operational_points": [],
+            "neutral_sections": [],
+        });
+
+        upgrade_railjson(&mut railjson).unwrap();
+
+        assert_eq!(railjson["version"], json!(RAILJSON_VERSION));
+        assert!(railjson["detectors"][0]
+            .get("applicable_directions")
+            .is_none());
+        let logical_signals = &railjson["signals"][0]["logical_signals"];
+        assert_eq!(logical_signals[0]["signaling_system"], json!("TVM430"));
+        assert_eq!(
+            logical_signals[1]["default_parameters"],
+            json!({ "jaune_cli": "false" })
+        );
+        assert_eq!(railjson["signals"][0]["sight_distance"], json!(0.0));
+        assert_eq!(
+            railjson["electrifications"][0]["voltage"],
+            json!("1500V;25000V")
+        );
+        serde_json::from_value::<RailJson>(railjson)
+            .expect("the upgraded railjson should be valid");
+    }
+
+    #[test]
+    fn downgrade_then_upgrade() {
+        let railjson = serde_json::to_value(RailJson::default()).unwrap();
+        let mut downgraded = railjson.clone();
+        downgrade_railjson(&mut downgraded, "3.4.9").unwrap();
+        assert_eq!(downgraded["version"], json!("3.4.9"));
+        upgrade_railjson(&mut downgraded).unwrap();
+        assert_eq!(downgraded, railjson);
+    }
+
+    #[test]
+    fn upgrade_object() {
+        let upgrader = RailJsonObjectUpgrader::new("3.4.11").unwrap();
+        assert!(!upgrader.is_noop());
+        let mut track_section = json!({ "id": "T", "sch": {}, "geo": {} });
+        upgrader.upgrade("track_sections", &mut track_section);
+        assert_eq!(track_section, json!({ "id": "T", "geo": {} }));
+        assert!(RailJsonObjectUpgrader::new(RAILJSON_VERSION)
+            .unwrap()
+            .is_noop());
+    }
+
+    #[test]
+    fn unsupported_version() {
+        let mut railjson = json!({ "version": "2.0.0" });
+        assert_eq!(
+            upgrade_railjson(&mut railjson),
+            Err(RailJsonMigrationError::UnsupportedVersion(
+                "2.0.0".to_owned()
+            ))
+        );
+        let mut railjson = json!({ "version": "3.4.8" });
+        assert!(matches!(
+            downgrade_railjson(&mut railjson, RAILJSON_VERSION),
+            Err(RailJsonMigrationError::NewerTargetVersion { .. })
+        ));
+    }
+}
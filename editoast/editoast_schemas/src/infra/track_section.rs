@@ -59,6 +59,14 @@ impl TrackSection {
     pub fn geo_bbox(&self) -> BoundingBox {
         Self::bbox(&self.geo)
     }
+
+    /// Returns whether the geometry of the track section goes through a bounding box
+    pub fn intersects(&self, bbox: &BoundingBox) -> bool {
+        match &self.geo.value {
+            LineString(points) => bbox.intersects_line_string(points),
+            _ => panic!("track sections can only be represented by LineStrings"),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -43,6 +43,56 @@ impl BoundingBox {
         self.0 .0 <= self.1 .0 && self.0 .1 <= self.1 .1
     }
 
+    /// Returns whether the two bounding boxes share at least a point
+    pub fn intersects(&self, other: &Self) -> bool {
+        self.0 .0 <= other.1 .0
+            && other.0 .0 <= self.1 .0
+            && self.0 .1 <= other.1 .1
+            && other.0 .1 <= self.1 .1
+    }
+
+    /// Returns whether a line string, given by its points, has at least a point in the bounding box
+    pub fn intersects_line_string(&self, points: &[geojson::Position]) -> bool {
+        let point = |position: &geojson::Position| (position[0], position[1]);
+        match points {
+            [] => false,
+            [position] => self.intersects_segment(point(position), point(position)),
+            _ => points
+                .windows(2)
+                .any(|segment| self.intersects_segment(point(&segment[0]), point(&segment[1]))),
+        }
+    }
+
+    /// Clips a segment with the bounding box (Liang–Barsky) and returns whether anything is left
+    fn intersects_segment(&self, (x0, y0): (f64, f64), (x1, y1): (f64, f64)) -> bool {
+        let (dx, dy) = (x1 - x0, y1 - y0);
+        let (mut t_min, mut t_max) = (0., 1.);
+        for (p, q) in [
+            (-dx, x0 - self.0 .0),
+            (dx, self.1 .0 - x0),
+            (-dy, y0 - self.0 .1),
+            (dy, self.1 .1 - y0),
+        ] {
+            if p == 0. {
+                // Parallel to this edge, the segment is either fully inside or outside of it
+                if q < 0. {
+                    return false;
+                }
+                continue;
+            }
+            let t = q / p;
+            if p < 0. {
+                t_min = t.max(t_min);
+            } else {
+                t_max = t.min(t_max);
+            }
+            if t_min > t_max {
+                return false;
+            }
+        }
+        true
+    }
+
     pub fn from_geojson(value: geojson::Value) -> Result<Self, GeometryError> {
         match value {
             LineString(segments) => Ok(Self::from_iter(segments.into_iter().map(|points| {
@@ -140,4 +190,25 @@ mod tests {
         assert!(!BoundingBox((0., 1.), (1., 0.)).is_valid());
         assert!(!BoundingBox::default().is_valid());
     }
+
+    #[test]
+    fn test_intersects() {
+        let a = BoundingBox((0., 0.), (2., 2.));
+        assert!(a.intersects(&BoundingBox((1., 1.), (3., 3.))));
+        assert!(a.intersects(&BoundingBox((2., 0.), (3., 1.))));
+        assert!(!a.intersects(&BoundingBox((3., 0.), (4., 1.))));
+        assert!(!a.intersects(&BoundingBox((0., 3.), (1., 4.))));
+    }
+
+    #[test]
+    fn test_intersects_line_string() {
+        let a = BoundingBox((0., 0.), (2., 2.));
+        // Crossing the box without any point inside
+        assert!(a.intersects_line_string(&[vec![-1., 1.], vec![3., 1.]]));
+        assert!(a.intersects_line_string(&[vec![1., 1.]]));
+        // Going around the box: the envelopes intersect but not the line string
+        assert!(!a.intersects_line_string(&[vec![-1., 3.], vec![3., 3.], vec![3., -1.]]));
+        assert!(!a.intersects_line_string(&[vec![1., 5.], vec![5., 1.]]));
+        assert!(!a.intersects_line_string(&[]));
+    }
 }
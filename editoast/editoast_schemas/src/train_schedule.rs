@@ -24,6 +24,9 @@ pub use comfort::Comfort;
 mod train_schedule_base;
 pub use train_schedule_base::TrainScheduleBase;
 
+mod recurrence;
+pub use recurrence::Recurrence;
+
 mod allowance;
 pub use allowance::Allowance;
 pub use allowance::AllowanceDistribution;
@@ -37,6 +40,7 @@ pub use rjs_power_restriction_range::RjsPowerRestrictionRange;
 
 editoast_common::schemas! {
     train_schedule_base::schemas(),
+    recurrence::schemas(),
     margins::schemas(),
     schedule_item::schemas(),
     path_item::schemas(),
@@ -0,0 +1,199 @@
+use chrono::DateTime;
+use chrono::Datelike;
+use chrono::Duration;
+use chrono::NaiveDate;
+use chrono::Utc;
+use chrono::Weekday;
+use serde::de::Error as SerdeError;
+use serde::Deserialize;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::primitives::PositiveDuration;
+
+editoast_common::schemas! {
+    Recurrence,
+}
+
+/// When a periodic train runs
+///
+/// Every day of operation between the dates of the first and last departures, the train
+/// departs every `interval` from the time of day of the first departure to the time of day of
+/// the last one. If the time of day of the last departure is before the one of the first
+/// departure, the departures of a day go on past midnight, until the next day.
+/// Dates and times are in UTC.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Recurrence {
+    /// Time between two consecutive departures of the same day
+    #[schema(value_type = chrono::Duration)]
+    pub interval: PositiveDuration,
+    pub first_departure: DateTime<Utc>,
+    pub last_departure: DateTime<Utc>,
+    /// Days of the week the train runs (`Mon`, `Tue`…), every day if empty
+    ///
+    /// The departures past midnight belong to the day of the first departure of their series.
+    #[schema(value_type = Vec<String>)]
+    #[serde(default)]
+    pub days_of_operation: Vec<Weekday>,
+    /// Days the train doesn't run
+    #[serde(default)]
+    pub exceptions: Vec<NaiveDate>,
+}
+
+impl<'de> Deserialize<'de> for Recurrence {
+    fn deserialize<D>(deserializer: D) -> Result<Recurrence, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct Internal {
+            interval: PositiveDuration,
+            first_departure: DateTime<Utc>,
+            last_departure: DateTime<Utc>,
+            #[serde(default)]
+            days_of_operation: Vec<Weekday>,
+            #[serde(default)]
+            exceptions: Vec<NaiveDate>,
+        }
+        let internal = Internal::deserialize(deserializer)?;
+
+        if internal.interval.is_zero() {
+            return Err(SerdeError::custom("The interval can't be null"));
+        }
+        if internal.last_departure < internal.first_departure {
+            return Err(SerdeError::custom(
+                "The last departure can't be before the first one",
+            ));
+        }
+        Ok(Recurrence {
+            interval: internal.interval,
+            first_departure: internal.first_departure,
+            last_departure: internal.last_departure,
+            days_of_operation: internal.days_of_operation,
+            exceptions: internal.exceptions,
+        })
+    }
+}
+
+impl Recurrence {
+    /// Returns true if the train runs on the given day
+    pub fn runs_on(&self, day: NaiveDate) -> bool {
+        (self.days_of_operation.is_empty() || self.days_of_operation.contains(&day.weekday()))
+            && !self.exceptions.contains(&day)
+    }
+
+    /// Returns whether the departures of a day go on past midnight
+    fn wraps_midnight(&self) -> bool {
+        self.last_departure.time() < self.first_departure.time()
+    }
+
+    /// Iterates over the departure times of the occurrences, in chronological order
+    pub fn departures(&self) -> impl Iterator<Item = DateTime<Utc>> + '_ {
+        let interval: Duration = *self.interval;
+        let first_time = self.first_departure.time();
+        let last_time = self.last_departure.time();
+        // The duration between the first and last departures of a day
+        let span = if self.wraps_midnight() {
+            last_time - first_time + Duration::days(1)
+        } else {
+            last_time - first_time
+        };
+        let last_day = (self.last_departure - span).date_naive();
+        self.first_departure
+            .date_naive()
+            .iter_days()
+            .take_while(move |day| *day <= last_day)
+            .filter(|day| self.runs_on(*day))
+            .flat_map(move |day| {
+                let first = day.and_time(first_time).and_utc();
+                let last = first + span;
+                std::iter::successors(Some(first), move |departure| Some(*departure + interval))
+                    .take_while(move |departure| *departure <= last)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use chrono::Utc;
+    use serde_json::from_value;
+    use serde_json::json;
+
+    use super::Recurrence;
+
+    #[test]
+    fn departures() {
+        let recurrence: Recurrence = from_value(json!({
+            "interval": "PT30M",
+            // Friday
+            "first_departure": "2024-06-28T08:00:00Z",
+            "last_departure": "2024-07-02T09:00:00Z",
+            "days_of_operation": ["Mon", "Tue", "Fri"],
+            "exceptions": ["2024-07-01"],
+        }))
+        .unwrap();
+        let departures: Vec<_> = recurrence.departures().collect();
+        let at = |month, day, hour, min| {
+            Utc.with_ymd_and_hms(2024, month, day, hour, min, 0)
+                .unwrap()
+        };
+        assert_eq!(
+            departures,
+            vec![
+                at(6, 28, 8, 0),
+                at(6, 28, 8, 30),
+                at(6, 28, 9, 0),
+                at(7, 2, 8, 0),
+                at(7, 2, 8, 30),
+                at(7, 2, 9, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn deserialize_invalid_recurrence() {
+        let recurrence = |interval: &str, first: &str, last: &str| {
+            from_value::<Recurrence>(json!({
+                "interval": interval,
+                "first_departure": first,
+                "last_departure": last,
+            }))
+        };
+        assert!(recurrence("PT1H", "2024-06-28T08:00:00Z", "2024-06-28T20:00:00Z").is_ok());
+        assert!(recurrence("PT0S", "2024-06-28T08:00:00Z", "2024-06-28T20:00:00Z").is_err());
+        assert!(recurrence("PT1H", "2024-06-28T08:00:00Z", "2024-06-27T20:00:00Z").is_err());
+        assert!(recurrence("PT1H", "2024-06-28T08:00:00Z", "2024-06-29T07:00:00Z").is_ok());
+    }
+
+    #[test]
+    fn departures_past_midnight() {
+        let recurrence: Recurrence = from_value(json!({
+            "interval": "PT30M",
+            // Friday
+            "first_departure": "2024-06-28T23:00:00Z",
+            "last_departure": "2024-07-01T00:30:00Z",
+            "days_of_operation": ["Fri", "Sun"],
+        }))
+        .unwrap();
+        let departures: Vec<_> = recurrence.departures().collect();
+        let at = |month, day, hour, min| {
+            Utc.with_ymd_and_hms(2024, month, day, hour, min, 0)
+                .unwrap()
+        };
+        assert_eq!(
+            departures,
+            vec![
+                at(6, 28, 23, 0),
+                at(6, 28, 23, 30),
+                at(6, 29, 0, 0),
+                at(6, 29, 0, 30),
+                at(6, 30, 23, 0),
+                at(6, 30, 23, 30),
+                at(7, 1, 0, 0),
+                at(7, 1, 0, 30),
+            ]
+        );
+    }
+}
@@ -0,0 +1,12 @@
+CREATE TABLE infra_revision (
+    id int8 PRIMARY KEY GENERATED BY DEFAULT AS IDENTITY,
+    infra_id int8 NOT NULL REFERENCES infra(id) ON DELETE CASCADE,
+    version varchar(40) NOT NULL,
+    author varchar(255),
+    created timestamptz NOT NULL DEFAULT NOW(),
+    operations jsonb NOT NULL,
+    inverse_operations jsonb NOT NULL,
+    undone boolean NOT NULL DEFAULT FALSE
+);
+
+CREATE INDEX "infra_revision_infra_id" ON "infra_revision" ("infra_id");
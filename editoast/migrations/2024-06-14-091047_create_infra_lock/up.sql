@@ -0,0 +1,8 @@
+CREATE TABLE infra_lock (
+    id int8 PRIMARY KEY GENERATED BY DEFAULT AS IDENTITY,
+    infra_id int8 NOT NULL REFERENCES infra(id) ON DELETE CASCADE,
+    object jsonb NOT NULL,
+    owner varchar(255) NOT NULL,
+    expires timestamptz NOT NULL,
+    UNIQUE (infra_id, object)
+);
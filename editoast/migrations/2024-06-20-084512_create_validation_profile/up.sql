@@ -0,0 +1,5 @@
+CREATE TABLE validation_profile (
+    id int8 PRIMARY KEY GENERATED BY DEFAULT AS IDENTITY,
+    name varchar(255) NOT NULL UNIQUE,
+    rules jsonb NOT NULL
+);
@@ -0,0 +1 @@
+DROP TABLE infra_import;
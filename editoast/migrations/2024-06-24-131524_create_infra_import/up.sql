@@ -0,0 +1,8 @@
+CREATE TABLE infra_import (
+    id int8 PRIMARY KEY GENERATED BY DEFAULT AS IDENTITY,
+    infra_id int8 NOT NULL REFERENCES infra(id) ON DELETE CASCADE,
+    source varchar(255) NOT NULL,
+    imported timestamptz NOT NULL,
+    railjson jsonb NOT NULL,
+    UNIQUE (infra_id, source)
+);
@@ -0,0 +1,9 @@
+CREATE TABLE train_pattern (
+    id int8 PRIMARY KEY GENERATED BY DEFAULT AS IDENTITY,
+    timetable_id int8 NOT NULL REFERENCES timetable_v2(id) ON DELETE CASCADE,
+    template jsonb NOT NULL,
+    recurrence jsonb NOT NULL
+);
+ALTER TABLE train_schedule_v2
+ADD COLUMN pattern_id int8 NULL REFERENCES train_pattern(id) ON DELETE CASCADE;
+CREATE INDEX ON train_schedule_v2(pattern_id);
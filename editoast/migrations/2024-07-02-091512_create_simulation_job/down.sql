@@ -0,0 +1,2 @@
+DROP TABLE simulation_job_train;
+DROP TABLE simulation_job;
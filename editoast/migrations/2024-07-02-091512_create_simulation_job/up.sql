@@ -0,0 +1,16 @@
+CREATE TABLE simulation_job (
+    id int8 PRIMARY KEY GENERATED BY DEFAULT AS IDENTITY,
+    timetable_id int8 NOT NULL REFERENCES timetable_v2(id) ON DELETE CASCADE,
+    infra_id int8 NOT NULL REFERENCES infra(id) ON DELETE CASCADE,
+    creation_date timestamptz NOT NULL,
+    heartbeat timestamptz NOT NULL
+);
+CREATE TABLE simulation_job_train (
+    id int8 PRIMARY KEY GENERATED BY DEFAULT AS IDENTITY,
+    job_id int8 NOT NULL REFERENCES simulation_job(id) ON DELETE CASCADE,
+    train_schedule_id int8 NOT NULL REFERENCES train_schedule_v2(id) ON DELETE CASCADE,
+    status smallint NOT NULL,
+    summary jsonb NOT NULL,
+    UNIQUE (job_id, train_schedule_id)
+);
+CREATE INDEX ON simulation_job_train(job_id, status);
@@ -0,0 +1,3 @@
+ALTER TABLE infra
+DROP COLUMN forked_from,
+DROP COLUMN fork_revision_id;
@@ -0,0 +1,3 @@
+ALTER TABLE infra
+ADD COLUMN forked_from int8 REFERENCES infra(id) ON DELETE SET NULL,
+ADD COLUMN fork_revision_id int8;
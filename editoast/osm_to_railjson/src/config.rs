@@ -0,0 +1,201 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::Path;
+
+use osm4routing::Coord;
+use osm4routing::Edge;
+use serde::Deserialize;
+
+/// Configuration of the OpenStreetMap import, read from a YAML file
+///
+/// ```yaml
+/// require:
+///   railway: [rail, subway]
+/// reject:
+///   service: [yard]
+/// include_service_tracks: false
+/// default_speed: "100"
+/// default_voltage: "1500"
+/// clip:
+///   bbox: [2.25, 48.81, 2.42, 48.90]
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OsmImportConfig {
+    /// A way is imported if it has at least one of these tags (`*` matches any value)
+    pub require: BTreeMap<String, Vec<String>>,
+    /// A way is ignored if it has one of these tags (`*` matches any value)
+    pub reject: BTreeMap<String, Vec<String>>,
+    /// Imports the yards, sidings and spurs by ignoring the rejections on the `service` tag
+    pub include_service_tracks: bool,
+    /// Speed limit of the ways without `maxspeed` tag, in the same format (e.g. `100` or `60 mph`)
+    pub default_speed: Option<String>,
+    /// Voltage of the ways without `voltage` tag that aren't tagged `electrified=no`
+    pub default_voltage: Option<String>,
+    /// Only imports the ways entirely inside this region
+    #[serde(with = "serde_yaml::with::singleton_map")]
+    pub clip: Option<Region>,
+}
+
+impl Default for OsmImportConfig {
+    fn default() -> Self {
+        let tags = |tags: &[(&str, &[&str])]| {
+            tags.iter()
+                .map(|(key, values)| {
+                    (
+                        key.to_string(),
+                        values.iter().map(|v| v.to_string()).collect(),
+                    )
+                })
+                .collect()
+        };
+        Self {
+            require: tags(&[("railway", &["rail"])]),
+            reject: tags(&[
+                ("service", &["yard", "siding", "spur"]),
+                ("building", &["*"]),
+                ("area", &["yes"]),
+                ("gauge", &["600"]),
+                ("roller_coaster", &["*"]),
+                ("construction", &["*"]),
+            ]),
+            include_service_tracks: false,
+            default_speed: None,
+            default_voltage: None,
+            clip: None,
+        }
+    }
+}
+
+impl OsmImportConfig {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let file = std::fs::File::open(path)?;
+        Ok(serde_yaml::from_reader(file)?)
+    }
+
+    /// Builds a reader applying the tag filters
+    pub fn reader(&self) -> osm4routing::Reader {
+        let mut reader = osm4routing::Reader::new();
+        for (key, values) in &self.require {
+            for value in values {
+                reader = reader.require(key, value);
+            }
+        }
+        for (key, values) in &self.reject {
+            if self.include_service_tracks && key == "service" {
+                continue;
+            }
+            for value in values {
+                reader = reader.reject(key, value);
+            }
+        }
+        reader
+    }
+
+    /// Returns true if the edge is inside the region to import
+    pub fn keep(&self, edge: &Edge) -> bool {
+        match &self.clip {
+            Some(region) => edge.geometry.iter().all(|coord| region.contains(*coord)),
+            None => true,
+        }
+    }
+}
+
+/// A region given in WGS84 coordinates
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum Region {
+    /// `[min_lon, min_lat, max_lon, max_lat]`
+    Bbox([f64; 4]),
+    /// List of `[lon, lat]`, the polygon is implicitly closed
+    Polygon(Vec<[f64; 2]>),
+}
+
+impl Region {
+    pub fn contains(&self, coord: Coord) -> bool {
+        match self {
+            Region::Bbox([min_lon, min_lat, max_lon, max_lat]) => {
+                (*min_lon..=*max_lon).contains(&coord.lon)
+                    && (*min_lat..=*max_lat).contains(&coord.lat)
+            }
+            // Ray casting: the point is inside if a ray crosses an odd number of edges
+            Region::Polygon(points) => {
+                let mut inside = false;
+                let mut previous = match points.last() {
+                    Some(point) => point,
+                    None => return false,
+                };
+                for point in points {
+                    let ([x1, y1], [x2, y2]) = (previous, point);
+                    if (y1 > &coord.lat) != (y2 > &coord.lat)
+                        && coord.lon < (x2 - x1) * (coord.lat - y1) / (y2 - y1) + x1
+                    {
+                        inside = !inside;
+                    }
+                    previous = point;
+                }
+                inside
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use osm4routing::Coord;
+
+    use super::*;
+
+    #[test]
+    fn parse_config() {
+        let config: OsmImportConfig = serde_yaml::from_str(
+            r#"
+            require:
+              railway: [subway]
+            include_service_tracks: true
+            default_speed: "80"
+            clip:
+              polygon: [[0, 0], [1, 0], [0, 1]]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(vec!["subway"], config.require["railway"]);
+        assert!(config.reject.contains_key("service"));
+        assert!(config.include_service_tracks);
+        assert_eq!(Some("80"), config.default_speed.as_deref());
+        assert!(matches!(config.clip, Some(Region::Polygon(_))));
+    }
+
+    #[test]
+    fn bbox_contains() {
+        let region = Region::Bbox([0., 0., 1., 1.]);
+        assert!(region.contains(Coord { lon: 0.5, lat: 1. }));
+        assert!(!region.contains(Coord { lon: 1.5, lat: 0.5 }));
+    }
+
+    #[test]
+    fn polygon_contains() {
+        let region = Region::Polygon(vec![[0., 0.], [1., 0.], [0., 1.]]);
+        assert!(region.contains(Coord { lon: 0.2, lat: 0.2 }));
+        assert!(!region.contains(Coord { lon: 0.8, lat: 0.8 }));
+        assert!(!region.contains(Coord {
+            lon: -0.1,
+            lat: 0.2
+        }));
+    }
+
+    #[test]
+    fn keep_edges_inside_region() {
+        let config = OsmImportConfig {
+            clip: Some(Region::Bbox([0., 0., 1., 1.])),
+            ..Default::default()
+        };
+        let edge = |lon| Edge {
+            geometry: vec![Coord { lon: 0.5, lat: 0.5 }, Coord { lon, lat: 0.5 }],
+            ..Default::default()
+        };
+        assert!(config.keep(&edge(0.8)));
+        assert!(!config.keep(&edge(1.2)));
+        assert!(OsmImportConfig::default().keep(&edge(1.2)));
+    }
+}
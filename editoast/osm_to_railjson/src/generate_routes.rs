@@ -174,7 +174,6 @@ impl Graph {
     // The routes don’t go beyond a detector or a buffer stop
     fn one_to_all_routes(&self, start: Node) -> Vec<Route> {
         let mut result = vec![];
-        let mut count = 0;
         let mut parent = HashMap::new();
         let mut stack = Vec::from([&start]);
 
@@ -186,8 +185,7 @@ impl Graph {
                         match &succ {
                             // All routes end at a buffer or detector and we build it
                             Node::BufferStop(_) | Node::Detector(_) => {
-                                result.push(self.build_route(count, succ, &parent));
-                                count += 1;
+                                result.push(self.build_route(succ, &parent));
                             }
                             Node::TrackEndpoint(_track_endpoint) => {
                                 stack.push(succ);
@@ -238,7 +236,9 @@ impl Graph {
     }
 
     // Once we found a route, we must build by scanning the predecessors
-    fn build_route(&self, count: u64, end: &Node, pred: &HashMap<&Node, &Node>) -> Route {
+    // A node is only reached once from a given start, so the route is identified by its waypoints
+    // which keeps the identifier stable from one import to the next
+    fn build_route(&self, end: &Node, pred: &HashMap<&Node, &Node>) -> Route {
         let mut switches_directions = HashMap::new();
 
         let mut last_direction = Direction::StartToStop;
@@ -271,7 +271,7 @@ impl Graph {
         };
 
         Route {
-            id: format!("{}-{count}", entry_point.get_id()).into(),
+            id: format!("{}-{}", entry_point.get_id(), exit_point.get_id()).into(),
             entry_point_direction,
             entry_point,
             exit_point,
@@ -378,7 +378,8 @@ mod tests {
         pred.insert(&t2, &t1);
         pred.insert(&end, &t2);
 
-        let route = graph.build_route(0, &end, &pred);
+        let route = graph.build_route(&end, &pred);
+        assert_eq!("start-end", route.id.as_str());
         assert!(route.entry_point.is_buffer_stop());
         assert!(route.exit_point.is_buffer_stop());
         assert_eq!(1, route.switches_directions.len());
@@ -397,8 +398,11 @@ mod tests {
         The test case has one switch and one detector
     */
     fn generate_routes() {
-        let railjson =
-            crate::osm_to_railjson::parse_osm("src/tests/routes.osm.pbf".into()).unwrap();
+        let railjson = crate::osm_to_railjson::parse_osm(
+            "src/tests/routes.osm.pbf".into(),
+            &Default::default(),
+        )
+        .unwrap();
         let routes = super::routes(&railjson);
         assert_eq!(6, routes.len());
         let routes_with_switches_count = routes
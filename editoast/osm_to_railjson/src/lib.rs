@@ -1,5 +1,12 @@
+mod config;
 mod generate_routes;
 mod osm_to_railjson;
+mod report;
 mod utils;
 
+pub use config::OsmImportConfig;
+pub use config::Region;
 pub use osm_to_railjson::osm_to_railjson;
+pub use osm_to_railjson::parse_osm;
+pub use osm_to_railjson::parse_osm_with_report;
+pub use report::ImportReport;
@@ -8,6 +8,12 @@ use tracing::info;
 
 use super::utils::*;
 use crate::generate_routes;
+use crate::report;
+use crate::report::DropReason;
+use crate::report::DroppedEdge;
+use crate::report::ImportReport;
+use crate::report::UnclassifiedNode;
+use crate::OsmImportConfig;
 use editoast_schemas::infra::RailJson;
 use editoast_schemas::infra::TrackSection;
 /// Run the osm-to-railjson subcommand
@@ -15,36 +21,65 @@ use editoast_schemas::infra::TrackSection;
 pub fn osm_to_railjson(
     osm_pbf_in: PathBuf,
     railjson_out: PathBuf,
+    config: &OsmImportConfig,
 ) -> Result<(), Box<dyn Error + Send + Sync>> {
     info!(
         "🗺️ Converting {} to {}",
         osm_pbf_in.display(),
         railjson_out.display()
     );
-    let railjson = parse_osm(osm_pbf_in)?;
+    let railjson = parse_osm(osm_pbf_in, config)?;
     let file = std::fs::File::create(railjson_out)?;
     serde_json::to_writer(file, &railjson)?;
     Ok(())
 }
 
-pub fn parse_osm(osm_pbf_in: PathBuf) -> Result<RailJson, Box<dyn Error + Send + Sync>> {
-    let (nodes, edges) = osm4routing::Reader::new()
-        .require("railway", "rail")
-        .reject("service", "yard")
-        .reject("service", "siding")
-        .reject("service", "spur")
-        .reject("building", "*")
-        .reject("area", "yes")
-        .reject("gauge", "600")
-        .reject("roller_coaster", "*")
-        .reject("construction", "*")
+pub fn parse_osm(
+    osm_pbf_in: PathBuf,
+    config: &OsmImportConfig,
+) -> Result<RailJson, Box<dyn Error + Send + Sync>> {
+    parse_osm_with_report(osm_pbf_in, config).map(|(railjson, _)| railjson)
+}
+
+/// Converts OpenStreetMap data to railjson, reporting what was skipped
+pub fn parse_osm_with_report(
+    osm_pbf_in: PathBuf,
+    config: &OsmImportConfig,
+) -> Result<(RailJson, ImportReport), Box<dyn Error + Send + Sync>> {
+    let (nodes, edges) = config
+        .reader()
         .read_tag("maxspeed")
         .read_tag("maxspeed:forward")
         .read_tag("maxspeed:backward")
         .read_tag("voltage")
+        .read_tag("electrified")
+        .read_tag("incline")
+        .read_tag("railway:loading_gauge")
         .read(osm_pbf_in.to_str().unwrap())?;
+    let (edges, outside_edges): (Vec<_>, Vec<_>) = edges.into_iter().partition(|e| config.keep(e));
     info!("🗺️ We have {} nodes and {} edges", nodes.len(), edges.len());
 
+    let mut report = ImportReport::default();
+    let dropped_edges = outside_edges
+        .iter()
+        .map(|e| (e, DropReason::OutsideRegion))
+        .chain(edges.iter().filter_map(|e| {
+            if e.properties.train != osm4routing::TrainAccessibility::Allowed {
+                Some((e, DropReason::NotTrainAccessible))
+            } else if e.source == e.target {
+                Some((e, DropReason::Loop))
+            } else {
+                None
+            }
+        }));
+    report.dropped_edges = dropped_edges
+        .map(|(e, reason)| DroppedEdge {
+            id: e.id.clone(),
+            osm_way: e.osm_id.0,
+            reason,
+        })
+        .collect();
+
     let rail_edges = edges
         .iter()
         .filter(|e| e.properties.train == osm4routing::TrainAccessibility::Allowed)
@@ -57,14 +92,23 @@ pub fn parse_osm(osm_pbf_in: PathBuf) -> Result<RailJson, Box<dyn Error + Send +
     }
 
     let nodes_tracks = NodeToTrack::from_edges(&edges);
-    let signals = signals(&osm_pbf_in, &nodes_tracks, &adjacencies);
+    let (signals, ignored_signals) = signals(&osm_pbf_in, &nodes_tracks, &adjacencies);
+    report.ignored_signals = ignored_signals;
+    let elevations = elevations(&osm_pbf_in, &nodes_tracks);
     let mut railjson = RailJson {
         extended_switch_types: vec![],
         detectors: signals.iter().map(detector).collect(),
         signals,
-        speed_sections: rail_edges.clone().flat_map(speed_sections).collect(),
-        electrifications: rail_edges.clone().flat_map(electrifications).collect(),
+        speed_sections: rail_edges
+            .clone()
+            .flat_map(|e| speed_sections(e, config.default_speed.as_ref()))
+            .collect(),
+        electrifications: rail_edges
+            .clone()
+            .flat_map(|e| electrifications(e, config.default_voltage.as_ref()))
+            .collect(),
         operational_points: operational_points(&osm_pbf_in, &nodes_tracks),
+        neutral_sections: neutral_sections(&osm_pbf_in, &nodes_tracks),
         ..Default::default()
     };
 
@@ -76,6 +120,9 @@ pub fn parse_osm(osm_pbf_in: PathBuf) -> Result<RailJson, Box<dyn Error + Send +
             TrackSection {
                 id: e.id.as_str().into(),
                 length: e.length(),
+                slopes: slopes(e, &elevations),
+                curves: curves(e),
+                loading_gauge_limits: loading_gauge_limits(e),
                 geo: geo.clone(),
                 ..Default::default()
             }
@@ -103,6 +150,12 @@ pub fn parse_osm(osm_pbf_in: PathBuf) -> Result<RailJson, Box<dyn Error + Send +
                 .push(edge_to_buffer(&node, adj.edges[0], 0)),
             (2, 0) => {
                 // This can happens when data is truncated (e.g. cropped to a region, or the output track is a service track)
+                debug!("node {id} with 2 edges too sharp to be linked, ended with buffer stops");
+                report.unclassified_nodes.push(UnclassifiedNode {
+                    osm_node: id,
+                    edges: edges_count,
+                    branches: branches_count,
+                });
                 railjson
                     .buffer_stops
                     .push(edge_to_buffer(&node, adj.edges[0], 0));
@@ -116,13 +169,21 @@ pub fn parse_osm(osm_pbf_in: PathBuf) -> Result<RailJson, Box<dyn Error + Send +
             (4, 4) => railjson
                 .switches
                 .push(double_slip_switch(node, &adj.branches)),
-            _ => debug!("node {id} with {edges_count} edges and {branches_count} branches"),
+            _ => {
+                debug!("node {id} with {edges_count} edges and {branches_count} branches");
+                report.unclassified_nodes.push(UnclassifiedNode {
+                    osm_node: id,
+                    edges: edges_count,
+                    branches: branches_count,
+                });
+            }
         }
     }
     debug!("Start generating routes");
     railjson.routes = generate_routes::routes(&railjson);
     debug!("Done, got {} routes", railjson.routes.len());
-    Ok(railjson)
+    report.components = report::components(&railjson);
+    Ok((railjson, report))
 }
 
 #[cfg(test)]
@@ -134,13 +195,15 @@ mod tests {
     use std::collections::HashMap;
 
     use super::*;
+    use crate::report::IgnoredSignalReason;
 
     #[test]
     fn convert_osm_to_railjson() {
         let output = tempfile::NamedTempFile::new().unwrap();
         assert!(osm_to_railjson(
             "src/tests/minimal_rail.osm.pbf".into(),
-            output.path().into()
+            output.path().into(),
+            &Default::default()
         )
         .is_ok());
 
@@ -154,7 +217,8 @@ mod tests {
         fn port_eq(ports: &HashMap<Identifier, TrackEndpoint>, name: &str, expected: &str) -> bool {
             ports.get(&name.into()).unwrap().track.0 == expected
         }
-        let mut railjson = parse_osm("src/tests/switches.osm.pbf".into()).unwrap();
+        let mut railjson =
+            parse_osm("src/tests/switches.osm.pbf".into(), &Default::default()).unwrap();
         assert_eq!(4, railjson.switches.len());
         assert_eq!(18, railjson.buffer_stops.len());
 
@@ -208,21 +272,41 @@ mod tests {
 
     #[test]
     fn parse_signals() {
-        let railjson = parse_osm("src/tests/signals.osm.pbf".into()).unwrap();
+        let railjson = parse_osm("src/tests/signals.osm.pbf".into(), &Default::default()).unwrap();
         assert_eq!(1, railjson.signals.len());
         assert_eq!(1, railjson.detectors.len());
     }
 
     #[test]
     fn ignore_signals_at_end_of_line() {
-        let railjson = parse_osm("src/tests/signal_at_end_of_line.osm.pbf".into()).unwrap();
+        let (railjson, report) = parse_osm_with_report(
+            "src/tests/signal_at_end_of_line.osm.pbf".into(),
+            &Default::default(),
+        )
+        .unwrap();
         assert!(railjson.signals.is_empty());
         assert_eq!(2, railjson.buffer_stops.len());
+        assert_eq!(1, report.ignored_signals.len());
+        assert_eq!(
+            IgnoredSignalReason::LineEnd,
+            report.ignored_signals[0].reason
+        );
+    }
+
+    #[test]
+    fn report_components() {
+        let (railjson, report) =
+            parse_osm_with_report("src/tests/routes.osm.pbf".into(), &Default::default()).unwrap();
+        let track_sections: usize = report.components.iter().map(|c| c.track_sections).sum();
+        let routes: usize = report.components.iter().map(|c| c.routes).sum();
+        assert_eq!(railjson.track_sections.len(), track_sections);
+        assert_eq!(railjson.routes.len(), routes);
+        assert!(report.unclassified_nodes.is_empty());
     }
 
     #[test]
     fn parse_speed() {
-        let rj = parse_osm("src/tests/minimal_rail.osm.pbf".into()).unwrap();
+        let rj = parse_osm("src/tests/minimal_rail.osm.pbf".into(), &Default::default()).unwrap();
         assert_eq!(2, rj.speed_sections.len());
         let forward = rj
             .speed_sections
@@ -240,14 +324,14 @@ mod tests {
 
     #[test]
     fn parse_electrifications() {
-        let rj = parse_osm("src/tests/minimal_rail.osm.pbf".into()).unwrap();
+        let rj = parse_osm("src/tests/minimal_rail.osm.pbf".into(), &Default::default()).unwrap();
         assert_eq!(1, rj.electrifications.len());
         assert_eq!("15000V", rj.electrifications[0].voltage);
     }
 
     #[test]
     fn parse_stations() {
-        let rj = parse_osm("src/tests/station.osm.pbf".into()).unwrap();
+        let rj = parse_osm("src/tests/station.osm.pbf".into(), &Default::default()).unwrap();
         assert_eq!(1, rj.operational_points.len());
         let op = &rj.operational_points[0];
         assert_eq!(2, op.parts.len());
@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+
+use editoast_schemas::infra::RailJson;
+use editoast_schemas::infra::Waypoint;
+use serde::Serialize;
+
+/// What was skipped or couldn’t be converted during an OpenStreetMap import
+///
+/// Meant to be serialized to track the quality of the imports over time.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct ImportReport {
+    pub dropped_edges: Vec<DroppedEdge>,
+    /// Nodes where several tracks meet but that couldn’t be converted to a switch or buffer stops
+    ///
+    /// The tracks meeting with too sharp an angle at a node with two edges are still ended
+    /// with buffer stops, but the node is reported as well.
+    pub unclassified_nodes: Vec<UnclassifiedNode>,
+    pub ignored_signals: Vec<IgnoredSignal>,
+    /// Sets of track sections connected by switches, largest first
+    pub components: Vec<Component>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DroppedEdge {
+    pub id: String,
+    pub osm_way: i64,
+    pub reason: DropReason,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DropReason {
+    /// The edge isn’t entirely inside the clipping region
+    OutsideRegion,
+    NotTrainAccessible,
+    /// The edge starts and ends on the same node
+    Loop,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UnclassifiedNode {
+    pub osm_node: i64,
+    pub edges: usize,
+    /// Pairs of edges whose angle is flat enough for a train to go from one to the other
+    pub branches: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IgnoredSignal {
+    pub osm_node: i64,
+    pub reason: IgnoredSignalReason,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IgnoredSignalReason {
+    /// The signal is at the end of a track, where a buffer stop is created
+    LineEnd,
+    NotOnTrack,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Component {
+    pub track_sections: usize,
+    pub routes: usize,
+}
+
+/// Groups the track sections connected by switches and counts the routes starting in each group
+pub fn components(railjson: &RailJson) -> Vec<Component> {
+    let track_index: HashMap<_, _> = railjson
+        .track_sections
+        .iter()
+        .enumerate()
+        .map(|(index, track)| (track.id.as_str(), index))
+        .collect();
+
+    // Union-find of the track sections
+    let mut parents: Vec<usize> = (0..railjson.track_sections.len()).collect();
+    fn root(parents: &mut [usize], mut index: usize) -> usize {
+        while parents[index] != index {
+            parents[index] = parents[parents[index]];
+            index = parents[index];
+        }
+        index
+    }
+    for switch in &railjson.switches {
+        let mut tracks = switch
+            .ports
+            .values()
+            .filter_map(|port| track_index.get(port.track.as_str()).copied());
+        if let Some(first) = tracks.next() {
+            for track in tracks {
+                let (a, b) = (root(&mut parents, first), root(&mut parents, track));
+                parents[a] = b;
+            }
+        }
+    }
+
+    let mut components = HashMap::<usize, Component>::new();
+    for index in 0..parents.len() {
+        components
+            .entry(root(&mut parents, index))
+            .or_insert(Component {
+                track_sections: 0,
+                routes: 0,
+            })
+            .track_sections += 1;
+    }
+
+    let waypoint_tracks: HashMap<_, _> = railjson
+        .detectors
+        .iter()
+        .map(|detector| (detector.id.as_str(), detector.track.as_str()))
+        .chain(
+            railjson
+                .buffer_stops
+                .iter()
+                .map(|buffer_stop| (buffer_stop.id.as_str(), buffer_stop.track.as_str())),
+        )
+        .collect();
+    for route in &railjson.routes {
+        let (Waypoint::Detector { id } | Waypoint::BufferStop { id }) = &route.entry_point;
+        let index = waypoint_tracks
+            .get(id.as_str())
+            .and_then(|track| track_index.get(track));
+        if let Some(index) = index {
+            if let Some(component) = components.get_mut(&root(&mut parents, *index)) {
+                component.routes += 1;
+            }
+        }
+    }
+
+    let mut components: Vec<_> = components.into_values().collect();
+    components.sort_by_key(|c| std::cmp::Reverse((c.track_sections, c.routes)));
+    components
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use editoast_schemas::infra::BufferStop;
+    use editoast_schemas::infra::Endpoint;
+    use editoast_schemas::infra::Route;
+    use editoast_schemas::infra::Switch;
+    use editoast_schemas::infra::TrackEndpoint;
+    use editoast_schemas::infra::TrackSection;
+
+    use super::*;
+
+    #[test]
+    fn connected_components() {
+        let track = |id: &str| TrackSection {
+            id: id.into(),
+            ..Default::default()
+        };
+        let switch = Switch {
+            id: "switch".into(),
+            switch_type: "link".into(),
+            ports: HashMap::from([
+                ("A".into(), TrackEndpoint::new("a", Endpoint::End)),
+                ("B".into(), TrackEndpoint::new("b", Endpoint::Begin)),
+            ]),
+            ..Default::default()
+        };
+        let railjson = RailJson {
+            track_sections: vec![track("a"), track("b"), track("c")],
+            switches: vec![switch],
+            buffer_stops: vec![BufferStop {
+                id: "buffer".into(),
+                track: "c".into(),
+                position: 0.,
+                extensions: Default::default(),
+            }],
+            routes: vec![Route {
+                id: "route".into(),
+                entry_point: Waypoint::BufferStop {
+                    id: "buffer".into(),
+                },
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            vec![
+                Component {
+                    track_sections: 2,
+                    routes: 0
+                },
+                Component {
+                    track_sections: 1,
+                    routes: 1
+                }
+            ],
+            components(&railjson)
+        );
+    }
+}
@@ -1,11 +1,15 @@
 use editoast_schemas::infra::ApplicableDirections;
 use editoast_schemas::infra::ApplicableDirectionsTrackRange;
 use editoast_schemas::infra::BufferStop;
+use editoast_schemas::infra::Curve;
 use editoast_schemas::infra::Detector;
 use editoast_schemas::infra::Direction;
+use editoast_schemas::infra::DirectionalTrackRange;
 use editoast_schemas::infra::Electrification;
 use editoast_schemas::infra::Endpoint;
+use editoast_schemas::infra::LoadingGaugeLimit;
 use editoast_schemas::infra::LogicalSignal;
+use editoast_schemas::infra::NeutralSection;
 use editoast_schemas::infra::OperationalPoint;
 use editoast_schemas::infra::OperationalPointExtensions;
 use editoast_schemas::infra::OperationalPointIdentifierExtension;
@@ -14,11 +18,13 @@ use editoast_schemas::infra::Side;
 use editoast_schemas::infra::Signal;
 use editoast_schemas::infra::SignalExtensions;
 use editoast_schemas::infra::SignalSncfExtension;
+use editoast_schemas::infra::Slope;
 use editoast_schemas::infra::Speed;
 use editoast_schemas::infra::SpeedSection;
 use editoast_schemas::infra::Switch;
 use editoast_schemas::infra::TrackEndpoint;
 use editoast_schemas::primitives::Identifier;
+use editoast_schemas::rolling_stock::LoadingGaugeType;
 use osm4routing::Coord;
 use osm4routing::Edge;
 use osm4routing::NodeId;
@@ -28,6 +34,15 @@ use std::str::FromStr;
 use tracing::error;
 use tracing::warn;
 
+use crate::report::IgnoredSignal;
+use crate::report::IgnoredSignalReason;
+
+/// Above this radius (in meters), a track is considered straight
+const MAX_CURVE_RADIUS: f64 = 10_000.;
+
+/// Length (in meters) of the neutral sections whose length isn’t tagged
+const DEFAULT_NEUTRAL_SECTION_LENGTH: f64 = 100.;
+
 // Given an edge and a coordinate, returns the coordinates used to compute the angle
 // It uses the nearest OpenStreetMap node, and the other as the the rails might do a loop
 // that would result in a bad angle
@@ -225,6 +240,16 @@ impl<'a> NodeToTrack<'a> {
     /// If there is an ambiguity (the node is at intersection), we just pick one
     /// We log weird situations (the are 3 edges for that node)
     pub fn track_and_position(&self, id: NodeId) -> Option<(Identifier, f64)> {
+        self.edge(id)
+            .map(|edge| (edge.id.clone().into(), edge.length_until(&id)))
+    }
+
+    /// Returns true if the OSM node belongs to a track
+    pub fn contains(&self, id: NodeId) -> bool {
+        self.nodes_edges.contains_key(&id)
+    }
+
+    fn edge(&self, id: NodeId) -> Option<&'a Edge> {
         self.nodes_edges.get(&id).and_then(|edges| {
             if edges.is_empty() {
                 error!("Missing edge for node {}", id.0);
@@ -232,56 +257,68 @@ impl<'a> NodeToTrack<'a> {
             } else if edges.len() >= 3 {
                 warn!("Too many edges for node {}", id.0);
             }
-            Some((edges[0].id.clone().into(), edges[0].length_until(&id)))
+            Some(edges[0])
         })
     }
 }
 
+/// Builds the main signals, returning the signals that were ignored as well
 pub fn signals(
     osm_pbf_in: &std::path::PathBuf,
     nodes_to_tracks: &NodeToTrack,
     adjacencies: &HashMap<osm4routing::NodeId, NodeAdjacencies>,
-) -> Vec<Signal> {
+) -> (Vec<Signal>, Vec<IgnoredSignal>) {
     let file = std::fs::File::open(osm_pbf_in).unwrap();
     let mut pbf = osmpbfreader::OsmPbfReader::new(file);
-    pbf.iter()
+    let mut ignored = vec![];
+    let signals = pbf
+        .iter()
         .flatten()
         .filter(main_signal)
         .flat_map(|obj| match obj {
             osmpbfreader::OsmObj::Node(node) => Some(node),
             _ => None,
         })
-        .filter(|node| adjacencies.get(&node.id).map_or(0, |adj| adj.edges.len()) != 1) // Ignore all the nodes that are at the end of a track, as it will be buffer stops
         .flat_map(|node| {
-            if let Some((track, position)) = nodes_to_tracks.track_and_position(node.id) {
-                let mut settings = HashMap::new();
-                settings.insert("Nf".into(), "true".into());
-
-                Some(Signal {
-                    id: node.id.0.to_string().into(),
-                    direction: direction(&node),
-                    track,
-                    position,
-                    sight_distance: 400.,
-                    logical_signals: vec![LogicalSignal {
-                        signaling_system: "BAL".to_string(),
-                        settings,
-                        ..Default::default()
-                    }],
-                    extensions: SignalExtensions {
-                        sncf: Some(sncf_extensions(&node)),
-                    },
-                })
-            } else {
-                None
+            let ignore = |reason| IgnoredSignal {
+                osm_node: node.id.0,
+                reason,
+            };
+            // Ignore all the nodes that are at the end of a track, as it will be buffer stops
+            if adjacencies.get(&node.id).map_or(0, |adj| adj.edges.len()) == 1 {
+                ignored.push(ignore(IgnoredSignalReason::LineEnd));
+                return None;
             }
+            let Some((track, position)) = nodes_to_tracks.track_and_position(node.id) else {
+                ignored.push(ignore(IgnoredSignalReason::NotOnTrack));
+                return None;
+            };
+            let mut settings = HashMap::new();
+            settings.insert("Nf".into(), "true".into());
+
+            Some(Signal {
+                id: node.id.0.to_string().into(),
+                direction: direction(&node),
+                track,
+                position,
+                sight_distance: 400.,
+                logical_signals: vec![LogicalSignal {
+                    signaling_system: "BAL".to_string(),
+                    settings,
+                    ..Default::default()
+                }],
+                extensions: SignalExtensions {
+                    sncf: Some(sncf_extensions(&node)),
+                },
+            })
         })
-        .collect()
+        .collect();
+    (signals, ignored)
 }
 
-pub fn speed_sections(edge: &Edge) -> Vec<SpeedSection> {
+pub fn speed_sections(edge: &Edge, default_speed: Option<&String>) -> Vec<SpeedSection> {
     let speeds = match (
-        edge.tags.get("maxspeed"),
+        edge.tags.get("maxspeed").or(default_speed),
         edge.tags.get("maxspeed:forward"),
         edge.tags.get("maxspeed:backward"),
     ) {
@@ -403,31 +440,241 @@ pub fn edge_to_buffer(node: &NodeId, edge: &Edge, count: i64) -> BufferStop {
     }
 }
 
-pub fn electrifications(edge: &Edge) -> Option<Electrification> {
+/// Builds the electrification of an edge from its `voltage` tag
+/// The default voltage is used when the edge has no such tag, unless it is tagged `electrified=no`
+pub fn electrifications(edge: &Edge, default_voltage: Option<&String>) -> Option<Electrification> {
     // TODO: handle multiple overlapping electrifications
     // Specific infrastructures can support multiple electrifications (e.g. "voltage"="600;1500;3000;15000;25000").
     // Short term solution : pick the first one, i.g. "600;1500;3000;15000;25000" -> "600V"
-    edge.tags.get("voltage").and_then(|voltage| {
-        voltage
-            .split(';')
-            .next()
-            .map(|v| {
-                if v.parse::<f64>().is_ok() {
-                    format!("{}V", v)
-                } else {
-                    v.to_string()
+    let default_voltage =
+        default_voltage.filter(|_| edge.tags.get("electrified").map(String::as_str) != Some("no"));
+    edge.tags
+        .get("voltage")
+        .or(default_voltage)
+        .and_then(|voltage| {
+            voltage
+                .split(';')
+                .next()
+                .map(|v| {
+                    if v.parse::<f64>().is_ok() {
+                        format!("{}V", v)
+                    } else {
+                        v.to_string()
+                    }
+                })
+                .map(|parsed_voltage| Electrification {
+                    id: edge.id.clone().into(),
+                    voltage: parsed_voltage.into(),
+                    track_ranges: vec![ApplicableDirectionsTrackRange::new(
+                        edge.id.clone(),
+                        0.,
+                        edge.length(),
+                        ApplicableDirections::Both,
+                    )],
+                })
+        })
+}
+
+/// Returns the position of each coordinate of the geometry along the edge
+fn positions(edge: &Edge) -> Vec<f64> {
+    let mut position = 0.;
+    let mut positions = vec![position];
+    for coords in edge.geometry.windows(2) {
+        position += coords[0].distance_to(coords[1]);
+        positions.push(position);
+    }
+    positions
+}
+
+/// Computes the radius of the circle going through the three coordinates
+/// Returns None when the coordinates are aligned or stacked
+fn curvature_radius(a: Coord, b: Coord, c: Coord) -> Option<f64> {
+    let ab = a.distance_to(b);
+    let bc = b.distance_to(c);
+    let ca = c.distance_to(a);
+    if ab < 1. || bc < 1. {
+        return None;
+    }
+    // Heron’s formula
+    let s = (ab + bc + ca) / 2.;
+    let area = (s * (s - ab) * (s - bc) * (s - ca)).max(0.).sqrt();
+    if area <= f64::EPSILON {
+        return None;
+    }
+    Some(ab * bc * ca / (4. * area))
+}
+
+/// Derives the curves of an edge from the curvature of its geometry
+/// Each intermediate coordinate of the geometry defines a curve going from the middle of the previous segment
+/// to the middle of the next one
+pub fn curves(edge: &Edge) -> Vec<Curve> {
+    let positions = positions(edge);
+    edge.geometry
+        .windows(3)
+        .zip(positions.windows(3))
+        .filter_map(|(coords, positions)| {
+            curvature_radius(coords[0], coords[1], coords[2])
+                .filter(|radius| *radius < MAX_CURVE_RADIUS)
+                .map(|radius| Curve {
+                    radius,
+                    begin: (positions[0] + positions[1]) / 2.,
+                    end: (positions[1] + positions[2]) / 2.,
+                })
+        })
+        .collect()
+}
+
+/// Parses an `incline` tag into a gradient in m/km
+/// The value is a percentage if not suffixed by `‰`, positive when going up in the direction of the way
+fn gradient(incline: &str) -> Option<f64> {
+    let (value, factor) = if let Some(value) = incline.strip_suffix('‰') {
+        (value, 1.)
+    } else {
+        (incline.strip_suffix('%').unwrap_or(incline), 10.)
+    };
+    f64::from_str(value.trim()).map(|value| value * factor).ok()
+}
+
+/// Reads the elevation of the nodes that belong to a track
+pub fn elevations(
+    osm_pbf_in: &std::path::PathBuf,
+    nodes_to_tracks: &NodeToTrack,
+) -> HashMap<NodeId, f64> {
+    let file = std::fs::File::open(osm_pbf_in).unwrap();
+    let mut pbf = osmpbfreader::OsmPbfReader::new(file);
+    pbf.iter()
+        .flatten()
+        .flat_map(|obj| match obj {
+            osmpbfreader::OsmObj::Node(node) => Some(node),
+            _ => None,
+        })
+        .filter(|node| nodes_to_tracks.contains(node.id))
+        .flat_map(|node| {
+            let ele = node.tags.get("ele")?;
+            match f64::from_str(ele.trim_end_matches('m').trim()) {
+                Ok(elevation) => Some((node.id, elevation)),
+                Err(_) => {
+                    warn!("Invalid elevation '{ele}' for node {}", node.id.0);
+                    None
                 }
-            })
-            .map(|parsed_voltage| Electrification {
-                id: edge.id.clone().into(),
-                voltage: parsed_voltage.into(),
-                track_ranges: vec![ApplicableDirectionsTrackRange::new(
-                    edge.id.clone(),
-                    0.,
-                    edge.length(),
-                    ApplicableDirections::Both,
-                )],
-            })
+            }
+        })
+        .collect()
+}
+
+/// Builds the slopes of an edge
+/// The `incline` tag of the way applies to the whole edge. Without it, the gradients are computed
+/// between the consecutive nodes whose elevation is known.
+pub fn slopes(edge: &Edge, elevations: &HashMap<NodeId, f64>) -> Vec<Slope> {
+    if let Some(incline) = edge.tags.get("incline") {
+        match gradient(incline) {
+            Some(gradient) => {
+                return vec![Slope {
+                    gradient,
+                    begin: 0.,
+                    end: edge.length(),
+                }]
+            }
+            None => warn!("Invalid incline '{incline}' for way {}", edge.osm_id.0),
+        }
+    }
+
+    let elevated_positions: Vec<_> = edge
+        .nodes
+        .iter()
+        .zip(positions(edge))
+        .filter_map(|(node, position)| elevations.get(node).map(|ele| (position, *ele)))
+        .collect();
+    elevated_positions
+        .windows(2)
+        .filter(|points| points[1].0 > points[0].0)
+        .map(|points| {
+            let ((begin, begin_ele), (end, end_ele)) = (points[0], points[1]);
+            Slope {
+                gradient: (end_ele - begin_ele) / (end - begin) * 1000.,
+                begin,
+                end,
+            }
+        })
+        .collect()
+}
+
+/// Maps the `railway:loading_gauge` tag to loading gauge limits covering the whole edge
+/// Several gauges can be separated by `;`
+pub fn loading_gauge_limits(edge: &Edge) -> Vec<LoadingGaugeLimit> {
+    let Some(gauges) = edge.tags.get("railway:loading_gauge") else {
+        return vec![];
+    };
+    gauges
+        .split(';')
+        .map(|gauge| gauge.trim().to_uppercase())
+        .filter_map(|gauge| {
+            match serde_json::from_value::<LoadingGaugeType>(gauge.clone().into()) {
+                Ok(category) => Some(LoadingGaugeLimit {
+                    category,
+                    begin: 0.,
+                    end: edge.length(),
+                }),
+                Err(_) => {
+                    warn!("Unknown loading gauge '{gauge}' for way {}", edge.osm_id.0);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// Builds neutral sections from the `railway:power_supply` phase and system separation nodes
+/// The section is centered on the node and is `length` tag long. A section is created for each direction.
+/// System separations require to lower the pantograph.
+pub fn neutral_sections(
+    osm_pbf_in: &std::path::PathBuf,
+    nodes_to_tracks: &NodeToTrack,
+) -> Vec<NeutralSection> {
+    let file = std::fs::File::open(osm_pbf_in).unwrap();
+    let mut pbf = osmpbfreader::OsmPbfReader::new(file);
+    pbf.iter()
+        .flatten()
+        .flat_map(|obj| match obj {
+            osmpbfreader::OsmObj::Node(node) => Some(node),
+            _ => None,
+        })
+        .flat_map(|node| {
+            let lower_pantograph = match node.tags.get("railway:power_supply")?.as_str() {
+                "phase_separation" => false,
+                "system_separation" => true,
+                _ => return None,
+            };
+            let edge = nodes_to_tracks.edge(node.id)?;
+            let length = node
+                .tags
+                .get("length")
+                .and_then(|length| f64::from_str(length.trim_end_matches('m').trim()).ok())
+                .unwrap_or(DEFAULT_NEUTRAL_SECTION_LENGTH);
+            Some(neutral_section(node.id, edge, length, lower_pantograph))
+        })
+        .flatten()
+        .collect()
+}
+
+fn neutral_section(
+    node: NodeId,
+    edge: &Edge,
+    length: f64,
+    lower_pantograph: bool,
+) -> [NeutralSection; 2] {
+    let position = edge.length_until(&node);
+    let begin = (position - length / 2.).max(0.);
+    let end = (position + length / 2.).min(edge.length());
+    [
+        (Direction::StartToStop, "forward"),
+        (Direction::StopToStart, "backward"),
+    ]
+    .map(|(direction, suffix)| NeutralSection {
+        id: format!("{}-{suffix}", node.0).into(),
+        track_ranges: vec![DirectionalTrackRange::new(&edge.id, begin, end, direction)],
+        lower_pantograph,
+        ..Default::default()
     })
 }
 
@@ -562,11 +809,47 @@ mod tests {
             ..Default::default()
         };
 
-        let electrification = electrifications(&edge).unwrap();
+        let electrification = electrifications(&edge, None).unwrap();
 
         assert_eq!(electrification.voltage, expected.into());
     }
 
+    #[rstest]
+    #[case(None, Some("1500V"))]
+    #[case(Some("no"), None)]
+    fn test_default_voltage(#[case] electrified: Option<&str>, #[case] expected: Option<&str>) {
+        let edge = Edge {
+            id: "1".into(),
+            tags: electrified
+                .map(|electrified| ("electrified".into(), electrified.into()))
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        };
+
+        let electrification = electrifications(&edge, Some(&"1500".to_string()));
+
+        assert_eq!(
+            electrification.map(|e| e.voltage),
+            expected.map(|voltage| voltage.into())
+        );
+    }
+
+    #[test]
+    fn test_default_speed() {
+        let edge = Edge {
+            id: "1".into(),
+            tags: HashMap::from([("maxspeed:forward".into(), "100".into())]),
+            ..Default::default()
+        };
+
+        let sections = speed_sections(&edge, Some(&"60".to_string()));
+
+        assert_eq!(2, sections.len());
+        assert!((60. / 3.6 - sections[1].speed_limit.unwrap().0).abs() < 0.1);
+        assert_eq!(1, speed_sections(&edge, None).len());
+    }
+
     #[test]
     fn test_no_voltage() {
         let edge = Edge {
@@ -574,8 +857,100 @@ mod tests {
             ..Default::default()
         };
 
-        let electrification = electrifications(&edge);
+        let electrification = electrifications(&edge, None);
 
         assert!(electrification.is_none());
     }
+
+    fn edge_with_tag(key: &str, value: &str) -> Edge {
+        Edge {
+            id: "1".into(),
+            nodes: vec![NodeId(0), NodeId(1)],
+            geometry: vec![Coord { lon: 0., lat: 0. }, Coord { lon: 0.01, lat: 0. }],
+            tags: HashMap::from([(key.into(), value.into())]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_curves() {
+        // Coordinates on a circle with a radius of 1000m
+        let to_degrees = |meters: f64| (meters / 6_378_100.).to_degrees();
+        let geometry: Vec<_> = [0., 0.05, 0.1, 0.15]
+            .iter()
+            .map(|angle: &f64| Coord {
+                lon: to_degrees(1000. * angle.cos()),
+                lat: to_degrees(1000. * angle.sin()),
+            })
+            .collect();
+        let edge = Edge {
+            nodes: (0..4).map(NodeId).collect(),
+            geometry,
+            ..Default::default()
+        };
+        let curves = curves(&edge);
+        assert_eq!(2, curves.len());
+        assert!((curves[0].radius - 1000.).abs() < 1.);
+        assert!((curves[0].begin - 25.).abs() < 0.1);
+        assert_eq!(curves[0].end, curves[1].begin);
+    }
+
+    #[test]
+    fn test_no_curves_on_straight_track() {
+        let edge = Edge {
+            nodes: (0..3).map(NodeId).collect(),
+            geometry: vec![
+                Coord { lon: 0., lat: 0. },
+                Coord { lon: 0.01, lat: 0. },
+                Coord { lon: 0.02, lat: 0. },
+            ],
+            ..Default::default()
+        };
+        assert!(curves(&edge).is_empty());
+    }
+
+    #[rstest]
+    #[case("0.5%", 5.)]
+    #[case("-1.2", -12.)]
+    #[case("8‰", 8.)]
+    fn test_incline(#[case] input: &str, #[case] expected: f64) {
+        let edge = edge_with_tag("incline", input);
+        let slopes = slopes(&edge, &HashMap::new());
+        assert_eq!(1, slopes.len());
+        assert!((slopes[0].gradient - expected).abs() < 1e-9);
+        assert_eq!(edge.length(), slopes[0].end);
+    }
+
+    #[test]
+    fn test_slopes_from_elevations() {
+        let edge = Edge {
+            id: "1".into(),
+            nodes: vec![NodeId(0), NodeId(1)],
+            geometry: vec![Coord { lon: 0., lat: 0. }, Coord { lon: 0.01, lat: 0. }],
+            ..Default::default()
+        };
+        let elevations = HashMap::from([(NodeId(0), 100.), (NodeId(1), 90.)]);
+        let slopes = slopes(&edge, &elevations);
+        assert_eq!(1, slopes.len());
+        assert!((slopes[0].gradient - -10000. / edge.length()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_loading_gauge_limits() {
+        let edge = edge_with_tag("railway:loading_gauge", "GB1;fr3.3;unknown");
+        let limits = loading_gauge_limits(&edge);
+        assert_eq!(2, limits.len());
+        assert_eq!(LoadingGaugeType::GB1, limits[0].category);
+        assert_eq!(LoadingGaugeType::Fr3_3, limits[1].category);
+    }
+
+    #[test]
+    fn test_neutral_section_clamped_to_edge() {
+        let edge = edge_with_tag("voltage", "25000");
+        let [forward, backward] = neutral_section(NodeId(0), &edge, 100., false);
+        assert_eq!("0-forward", forward.id.as_str());
+        assert_eq!(0., forward.track_ranges[0].begin);
+        assert_eq!(50., forward.track_ranges[0].end);
+        assert_eq!(Direction::StopToStart, backward.track_ranges[0].direction);
+    }
 }
@@ -0,0 +1,42 @@
+mod railjson_to_railml;
+mod railml_to_railjson;
+mod utils;
+
+pub use railjson_to_railml::export_railml;
+pub use railjson_to_railml::railjson_to_railml;
+pub use railml_to_railjson::parse_railml;
+pub use railml_to_railjson::railml_to_railjson;
+
+use editoast_schemas::infra::RailJson;
+use serde::Deserialize;
+use serde::Serialize;
+use thiserror::Error;
+use utoipa::ToSchema;
+
+/// The `designator` register naming the signaling systems of a `signalIS`
+const SIGNALING_SYSTEM_REGISTER: &str = "signalingSystem";
+
+#[derive(Debug, Error)]
+pub enum RailMlError {
+    #[error("invalid XML document: {0}")]
+    Xml(#[from] roxmltree::Error),
+    #[error("missing '{0}' element")]
+    MissingElement(&'static str),
+}
+
+/// A railML element that could not be converted to RailJSON
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct UnmappedElement {
+    /// The name of the railML element
+    pub element: String,
+    pub id: Option<String>,
+    /// Why the element was not converted
+    pub reason: String,
+}
+
+/// The result of a railML import
+#[derive(Debug)]
+pub struct RailMlImport {
+    pub railjson: RailJson,
+    pub unmapped: Vec<UnmappedElement>,
+}
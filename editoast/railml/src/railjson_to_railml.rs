@@ -0,0 +1,610 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt::Write;
+use std::path::PathBuf;
+
+use editoast_schemas::infra::builtin_node_types_list;
+use editoast_schemas::infra::ApplicableDirections;
+use editoast_schemas::infra::ApplicableDirectionsTrackRange;
+use editoast_schemas::infra::Direction;
+use editoast_schemas::infra::Endpoint;
+use editoast_schemas::infra::RailJson;
+use editoast_schemas::infra::Signal;
+use editoast_schemas::infra::SwitchType;
+use editoast_schemas::infra::TrackEndpoint;
+use editoast_schemas::infra::TrackSection;
+use geojson::Value::LineString;
+use tracing::info;
+
+use crate::utils::haversine_distance;
+use crate::SIGNALING_SYSTEM_REGISTER;
+
+const RAILML_NAMESPACE: &str = "https://www.railml.org/schemas/3.2";
+
+/// Run the railjson-to-railml subcommand
+/// Converts a railjson file to a railML 3 infrastructure file
+pub fn railjson_to_railml(
+    railjson_in: PathBuf,
+    railml_out: PathBuf,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    info!(
+        "🚉 Converting {} to {}",
+        railjson_in.display(),
+        railml_out.display()
+    );
+    let file = std::fs::File::open(railjson_in)?;
+    let railjson: RailJson = serde_json::from_reader(std::io::BufReader::new(file))?;
+    std::fs::write(railml_out, export_railml(&railjson))?;
+    Ok(())
+}
+
+/// Converts a RailJSON infrastructure to a railML 3.2 document
+///
+/// Every switch port connection is exported as a `netRelation`, only point switches being exported
+/// as `switchIS` as well. Routes, neutral sections and speed sections without a speed limit
+/// have no railML counterpart here and are left out.
+pub fn export_railml(railjson: &RailJson) -> String {
+    let tracks: HashMap<_, _> = railjson
+        .track_sections
+        .iter()
+        .map(|track| (track.id.as_str(), track))
+        .collect();
+    let mut xml = String::new();
+    writeln!(xml, r#"<?xml version="1.0" encoding="UTF-8"?>"#).unwrap();
+    writeln!(xml, r#"<railML xmlns="{RAILML_NAMESPACE}" version="3.2">"#).unwrap();
+    xml.push_str(
+        r#"  <common id="common">
+    <positioning>
+      <geometricPositioningSystems>
+        <geometricPositioningSystem id="gps" crsDefinition="EPSG:4326"/>
+      </geometricPositioningSystems>
+    </positioning>
+  </common>
+  <infrastructure id="infrastructure">
+    <topology>
+      <netElements>
+"#,
+    );
+    for track in &railjson.track_sections {
+        net_element(&mut xml, track);
+    }
+    xml.push_str("      </netElements>\n      <netRelations>\n");
+    let switch_types: HashMap<_, _> = builtin_node_types_list()
+        .into_iter()
+        .chain(railjson.extended_switch_types.iter().cloned())
+        .map(|switch_type| (switch_type.id.0.clone(), switch_type))
+        .collect();
+    let mut point_switches = vec![];
+    for switch in &railjson.switches {
+        let Some(switch_type) = switch_types.get(switch.switch_type.as_str()) else {
+            continue;
+        };
+        for (src, dst) in port_connections(switch_type) {
+            let (Some(a), Some(b)) = (
+                switch.ports.get(&src.as_str().into()),
+                switch.ports.get(&dst.as_str().into()),
+            ) else {
+                continue;
+            };
+            // Links are made of a single net relation which keeps the switch id
+            let id = if switch_type.id.0 == "link" {
+                switch.id.0.clone()
+            } else {
+                format!("{}_{src}_{dst}", switch.id.0)
+            };
+            net_relation(&mut xml, &id, a, b);
+        }
+        if switch_type.id.0 == "point_switch" {
+            point_switches.push(switch);
+        }
+    }
+    xml.push_str("      </netRelations>\n    </topology>\n    <functionalInfrastructure>\n");
+
+    xml.push_str("      <bufferStops>\n");
+    for buffer_stop in &railjson.buffer_stops {
+        element(
+            &mut xml,
+            "bufferStop",
+            &[("id", buffer_stop.id.as_str())],
+            |xml| {
+                spot_location(
+                    xml,
+                    &buffer_stop.id,
+                    &tracks,
+                    &buffer_stop.track,
+                    buffer_stop.position,
+                    None,
+                )
+            },
+        );
+    }
+    xml.push_str("      </bufferStops>\n      <signalsIS>\n");
+    for signal in &railjson.signals {
+        element(&mut xml, "signalIS", &[("id", signal.id.as_str())], |xml| {
+            for logical_signal in &signal.logical_signals {
+                writeln!(
+                    xml,
+                    r#"          <designator register="{SIGNALING_SYSTEM_REGISTER}" entry="{}"/>"#,
+                    escape(&logical_signal.signaling_system)
+                )
+                .unwrap();
+            }
+            spot_location(
+                xml,
+                &signal.id,
+                &tracks,
+                &signal.track,
+                signal.position,
+                Some(match signal.direction {
+                    Direction::StartToStop => ApplicableDirections::StartToStop,
+                    Direction::StopToStart => ApplicableDirections::StopToStart,
+                }),
+            );
+            if let Some(movement_type) = train_movement_type(signal) {
+                writeln!(
+                    xml,
+                    r#"          <isTrainMovementSignal type="{movement_type}"/>"#
+                )
+                .unwrap();
+            }
+        });
+    }
+    xml.push_str("      </signalsIS>\n      <switchesIS>\n");
+    for switch in point_switches {
+        let base = &switch.ports[&"A".into()];
+        let id = &switch.id.0;
+        element(
+            &mut xml,
+            "switchIS",
+            &[
+                ("id", id.as_str()),
+                ("continueCourse", "right"),
+                ("branchCourse", "left"),
+            ],
+            |xml| {
+                let position = match base.endpoint {
+                    Endpoint::Begin => 0.,
+                    Endpoint::End => tracks.get(base.track.as_str()).map_or(0., |t| t.length),
+                };
+                spot_location(xml, id, &tracks, &base.track, position, None);
+                writeln!(
+                    xml,
+                    r#"          <leftBranch netRelationRef="{}"/>"#,
+                    escape(&format!("{id}_A_B2"))
+                )
+                .unwrap();
+                writeln!(
+                    xml,
+                    r#"          <rightBranch netRelationRef="{}"/>"#,
+                    escape(&format!("{id}_A_B1"))
+                )
+                .unwrap();
+            },
+        );
+    }
+    xml.push_str("      </switchesIS>\n      <trainDetectionElements>\n");
+    for detector in &railjson.detectors {
+        element(
+            &mut xml,
+            "trainDetectionElement",
+            &[("id", detector.id.as_str())],
+            |xml| {
+                spot_location(
+                    xml,
+                    &detector.id,
+                    &tracks,
+                    &detector.track,
+                    detector.position,
+                    None,
+                )
+            },
+        );
+    }
+    xml.push_str("      </trainDetectionElements>\n      <speeds>\n");
+    for speed_section in &railjson.speed_sections {
+        let Some(speed_limit) = speed_section.speed_limit else {
+            continue;
+        };
+        // railML speeds are given in km/h
+        let max_speed = (speed_limit.0 * 3.6).to_string();
+        element(
+            &mut xml,
+            "speedSection",
+            &[("id", speed_section.id.as_str()), ("maxSpeed", &max_speed)],
+            |xml| linear_locations(xml, &speed_section.id, &speed_section.track_ranges),
+        );
+    }
+    xml.push_str("      </speeds>\n      <electrifications>\n");
+    let mut voltages = vec![];
+    for electrification in &railjson.electrifications {
+        let voltage = &electrification.voltage.0;
+        if !voltages.contains(&voltage) {
+            voltages.push(voltage);
+        }
+        let system = format!("es_{voltage}");
+        element(
+            &mut xml,
+            "electrificationSection",
+            &[("id", electrification.id.as_str())],
+            |xml| {
+                linear_locations(xml, &electrification.id, &electrification.track_ranges);
+                writeln!(
+                    xml,
+                    r#"          <electrificationSystemRef ref="{}"/>"#,
+                    escape(&system)
+                )
+                .unwrap();
+            },
+        );
+    }
+    xml.push_str("      </electrifications>\n      <electrificationSystems>\n");
+    for voltage in voltages {
+        let id = format!("es_{voltage}");
+        let value = voltage.trim_end_matches('V');
+        element(
+            &mut xml,
+            "electrificationSystem",
+            &[("id", id.as_str()), ("voltage", value)],
+            |_| {},
+        );
+    }
+    xml.push_str("      </electrificationSystems>\n      <operationalPoints>\n");
+    for operational_point in &railjson.operational_points {
+        element(
+            &mut xml,
+            "operationalPoint",
+            &[("id", operational_point.id.as_str())],
+            |xml| {
+                if let Some(identifier) = &operational_point.extensions.identifier {
+                    writeln!(
+                        xml,
+                        r#"          <name name="{}" language="en"/>"#,
+                        escape(&identifier.name.0)
+                    )
+                    .unwrap();
+                    writeln!(
+                        xml,
+                        r#"          <designator register="_UIC" entry="{}"/>"#,
+                        identifier.uic
+                    )
+                    .unwrap();
+                }
+                for (index, part) in operational_point.parts.iter().enumerate() {
+                    spot_location(
+                        xml,
+                        &format!("{}_{index}", operational_point.id.0),
+                        &tracks,
+                        &part.track,
+                        part.position,
+                        None,
+                    );
+                }
+            },
+        );
+    }
+    xml.push_str("      </operationalPoints>\n    </functionalInfrastructure>\n  </infrastructure>\n</railML>\n");
+    xml
+}
+
+/// Escapes a string to be used as an XML attribute value
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for character in value.chars() {
+        match character {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(character),
+        }
+    }
+    escaped
+}
+
+/// The railML type of a signal, a stop signal being `main`
+fn train_movement_type(signal: &Signal) -> Option<&'static str> {
+    let is_set = |setting: &str| {
+        signal.logical_signals.iter().any(|logical_signal| {
+            logical_signal
+                .settings
+                .get(&setting.into())
+                .is_some_and(|value| value.0 == "true")
+        })
+    };
+    if is_set("Nf") {
+        Some("main")
+    } else if is_set("distant") {
+        Some("distant")
+    } else {
+        None
+    }
+}
+
+/// Writes a functional infrastructure element, the content being written by `children`
+fn element(
+    xml: &mut String,
+    name: &str,
+    attributes: &[(&str, &str)],
+    children: impl FnOnce(&mut String),
+) {
+    write!(xml, "        <{name}").unwrap();
+    for (attribute, value) in attributes {
+        write!(xml, r#" {attribute}="{}""#, escape(value)).unwrap();
+    }
+    xml.push_str(">\n");
+    children(xml);
+    writeln!(xml, "        </{name}>").unwrap();
+}
+
+/// Lists the distinct port connections of a switch type
+fn port_connections(switch_type: &SwitchType) -> Vec<(String, String)> {
+    let mut groups: Vec<_> = switch_type.groups.iter().collect();
+    groups.sort_by(|(a, _), (b, _)| a.0.cmp(&b.0));
+    let mut seen = HashSet::new();
+    let mut connections = vec![];
+    for connection in groups.into_iter().flat_map(|(_, connections)| connections) {
+        let (src, dst) = (connection.src.0.clone(), connection.dst.0.clone());
+        if seen.insert((src.clone(), dst.clone())) && !seen.contains(&(dst.clone(), src.clone())) {
+            connections.push((src, dst));
+        }
+    }
+    connections
+}
+
+fn net_element(xml: &mut String, track: &TrackSection) {
+    let id = escape(&track.id);
+    writeln!(
+        xml,
+        r#"        <netElement id="{id}" length="{}">"#,
+        track.length
+    )
+    .unwrap();
+    let points = match &track.geo.value {
+        LineString(points) => points.as_slice(),
+        _ => &[],
+    };
+    if !points.is_empty() {
+        writeln!(
+            xml,
+            r#"          <associatedPositioningSystem id="{id}_aps">"#
+        )
+        .unwrap();
+        let distances: Vec<_> = std::iter::once(0.)
+            .chain(points.windows(2).scan(0., |distance, segment| {
+                *distance += haversine_distance(&segment[0], &segment[1]);
+                Some(*distance)
+            }))
+            .collect();
+        let total = distances.last().copied().unwrap_or_default();
+        for (index, (point, distance)) in points.iter().zip(distances).enumerate() {
+            let intrinsic_coord = if total > 0. { distance / total } else { 0. };
+            writeln!(
+                xml,
+                r#"            <intrinsicCoordinate id="{id}_ic_{index}" intrinsicCoord="{intrinsic_coord}"><geometricCoordinate positioningSystemRef="gps" x="{}" y="{}"/></intrinsicCoordinate>"#,
+                point[0], point[1]
+            )
+            .unwrap();
+        }
+        xml.push_str("          </associatedPositioningSystem>\n");
+    }
+    xml.push_str("        </netElement>\n");
+}
+
+fn net_relation(xml: &mut String, id: &str, a: &TrackEndpoint, b: &TrackEndpoint) {
+    let position = |endpoint: &TrackEndpoint| match endpoint.endpoint {
+        Endpoint::Begin => 0,
+        Endpoint::End => 1,
+    };
+    writeln!(
+        xml,
+        r#"        <netRelation id="{}" positionOnA="{}" positionOnB="{}" navigability="Both"><elementA ref="{}"/><elementB ref="{}"/></netRelation>"#,
+        escape(id),
+        position(a),
+        position(b),
+        escape(&a.track),
+        escape(&b.track),
+    )
+    .unwrap();
+}
+
+fn application_direction(directions: ApplicableDirections) -> &'static str {
+    match directions {
+        ApplicableDirections::StartToStop => "normal",
+        ApplicableDirections::StopToStart => "reverse",
+        ApplicableDirections::Both => "both",
+    }
+}
+
+fn spot_location(
+    xml: &mut String,
+    id: &str,
+    tracks: &HashMap<&str, &TrackSection>,
+    track: &str,
+    position: f64,
+    direction: Option<ApplicableDirections>,
+) {
+    let intrinsic_coord = tracks
+        .get(track)
+        .filter(|track| track.length > 0.)
+        .map_or(0., |track| position / track.length);
+    writeln!(
+        xml,
+        r#"          <spotLocation id="{}_sl" netElementRef="{}" intrinsicCoord="{intrinsic_coord}" pos="{position}" applicationDirection="{}"/>"#,
+        escape(id),
+        escape(track),
+        application_direction(direction.unwrap_or(ApplicableDirections::Both)),
+    )
+    .unwrap();
+}
+
+fn linear_locations(xml: &mut String, id: &str, track_ranges: &[ApplicableDirectionsTrackRange]) {
+    for (index, range) in track_ranges.iter().enumerate() {
+        writeln!(
+            xml,
+            r#"          <linearLocation id="{}_ll_{index}" applicationDirection="{}"><associatedNetElement netElementRef="{}" posBegin="{}" posEnd="{}"/></linearLocation>"#,
+            escape(id),
+            application_direction(range.applicable_directions),
+            escape(&range.track),
+            range.begin,
+            range.end,
+        )
+        .unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use editoast_schemas::infra::ApplicableDirections;
+    use editoast_schemas::infra::ApplicableDirectionsTrackRange;
+    use editoast_schemas::infra::Detector;
+    use editoast_schemas::infra::Direction;
+    use editoast_schemas::infra::Electrification;
+    use editoast_schemas::infra::Endpoint;
+    use editoast_schemas::infra::LogicalSignal;
+    use editoast_schemas::infra::OperationalPoint;
+    use editoast_schemas::infra::OperationalPointExtensions;
+    use editoast_schemas::infra::OperationalPointIdentifierExtension;
+    use editoast_schemas::infra::OperationalPointPart;
+    use editoast_schemas::infra::RailJson;
+    use editoast_schemas::infra::Signal;
+    use editoast_schemas::infra::Speed;
+    use editoast_schemas::infra::SpeedSection;
+    use editoast_schemas::infra::Switch;
+    use editoast_schemas::infra::TrackEndpoint;
+    use editoast_schemas::infra::TrackSection;
+    use geojson::Geometry;
+    use geojson::Value::LineString;
+    use pretty_assertions::assert_eq;
+
+    use super::export_railml;
+    use crate::parse_railml;
+
+    fn track_section(id: &str, from: f64, to: f64) -> TrackSection {
+        TrackSection {
+            id: id.into(),
+            length: 1000.,
+            geo: Geometry::new(LineString(vec![vec![from, 48.], vec![to, 48.]])),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn export_and_import_railml() {
+        let railjson = RailJson {
+            track_sections: vec![
+                track_section("T1", 2., 2.01),
+                track_section("T2", 2.01, 2.02),
+                track_section("T3", 2.01, 2.02),
+                track_section("T4 & co", 2.02, 2.03),
+            ],
+            switches: vec![
+                Switch {
+                    id: "SW".into(),
+                    switch_type: "point_switch".into(),
+                    ports: HashMap::from([
+                        ("A".into(), TrackEndpoint::new("T1", Endpoint::End)),
+                        ("B1".into(), TrackEndpoint::new("T2", Endpoint::Begin)),
+                        ("B2".into(), TrackEndpoint::new("T3", Endpoint::Begin)),
+                    ]),
+                    ..Default::default()
+                },
+                Switch {
+                    id: "LINK".into(),
+                    switch_type: "link".into(),
+                    ports: HashMap::from([
+                        ("A".into(), TrackEndpoint::new("T2", Endpoint::End)),
+                        ("B".into(), TrackEndpoint::new("T4 & co", Endpoint::Begin)),
+                    ]),
+                    ..Default::default()
+                },
+            ],
+            signals: vec![Signal {
+                id: "S".into(),
+                track: "T1".into(),
+                position: 400.,
+                direction: Direction::StopToStart,
+                logical_signals: vec![LogicalSignal {
+                    signaling_system: "BAL".to_owned(),
+                    settings: HashMap::from([("Nf".into(), "true".into())]),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+            detectors: vec![Detector {
+                id: "D".into(),
+                track: "T4 & co".into(),
+                position: 250.,
+                ..Default::default()
+            }],
+            speed_sections: vec![SpeedSection {
+                id: "SPEED".into(),
+                speed_limit: Some(Speed(25.)),
+                track_ranges: vec![ApplicableDirectionsTrackRange::new(
+                    "T2",
+                    0.,
+                    500.,
+                    ApplicableDirections::StartToStop,
+                )],
+                ..Default::default()
+            }],
+            electrifications: vec![Electrification {
+                id: "E".into(),
+                voltage: "25000V".into(),
+                track_ranges: vec![ApplicableDirectionsTrackRange::new(
+                    "T1",
+                    0.,
+                    1000.,
+                    ApplicableDirections::Both,
+                )],
+            }],
+            operational_points: vec![OperationalPoint {
+                id: "OP".into(),
+                parts: vec![OperationalPointPart {
+                    track: "T3".into(),
+                    position: 100.,
+                    extensions: Default::default(),
+                }],
+                extensions: OperationalPointExtensions {
+                    identifier: Some(OperationalPointIdentifierExtension {
+                        name: "Station <A>".into(),
+                        uic: 87,
+                    }),
+                    ..Default::default()
+                },
+            }],
+            ..Default::default()
+        };
+
+        let railml = export_railml(&railjson);
+        let import = parse_railml(&railml).unwrap();
+
+        assert_eq!(import.unmapped, vec![]);
+        let mut imported = import.railjson;
+        imported.switches.sort_by(|a, b| a.id.0.cmp(&b.id.0));
+        let mut expected = railjson;
+        expected.switches.sort_by(|a, b| a.id.0.cmp(&b.id.0));
+        expected.switches[1].group_change_delay = 4.;
+        assert_eq!(imported.track_sections, expected.track_sections);
+        assert_eq!(imported.switches, expected.switches);
+        assert_eq!(imported.signals[0].position, 400.);
+        assert_eq!(imported.signals[0].direction, Direction::StopToStart);
+        assert_eq!(
+            imported.signals[0].logical_signals,
+            expected.signals[0].logical_signals
+        );
+        assert_eq!(imported.detectors, expected.detectors);
+        assert_eq!(
+            imported.speed_sections[0].track_ranges,
+            expected.speed_sections[0].track_ranges
+        );
+        assert_eq!(imported.electrifications, expected.electrifications);
+        assert_eq!(
+            imported.operational_points[0].extensions,
+            expected.operational_points[0].extensions
+        );
+    }
+}
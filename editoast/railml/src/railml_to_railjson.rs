@@ -0,0 +1,793 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::error::Error;
+use std::path::PathBuf;
+
+use editoast_schemas::infra::ApplicableDirections;
+use editoast_schemas::infra::ApplicableDirectionsTrackRange;
+use editoast_schemas::infra::BufferStop;
+use editoast_schemas::infra::Detector;
+use editoast_schemas::infra::Direction;
+use editoast_schemas::infra::Electrification;
+use editoast_schemas::infra::Endpoint;
+use editoast_schemas::infra::LogicalSignal;
+use editoast_schemas::infra::OperationalPoint;
+use editoast_schemas::infra::OperationalPointExtensions;
+use editoast_schemas::infra::OperationalPointIdentifierExtension;
+use editoast_schemas::infra::OperationalPointPart;
+use editoast_schemas::infra::RailJson;
+use editoast_schemas::infra::Signal;
+use editoast_schemas::infra::Speed;
+use editoast_schemas::infra::SpeedSection;
+use editoast_schemas::infra::Switch;
+use editoast_schemas::infra::TrackEndpoint;
+use editoast_schemas::infra::TrackSection;
+use editoast_schemas::primitives::NonBlankString;
+use geojson::Geometry;
+use geojson::Value::LineString;
+use roxmltree::Document;
+use roxmltree::Node;
+use tracing::debug;
+use tracing::info;
+use tracing::warn;
+
+use crate::utils::haversine_length;
+use crate::RailMlError;
+use crate::RailMlImport;
+use crate::UnmappedElement;
+use crate::SIGNALING_SYSTEM_REGISTER;
+
+/// The containers of `functionalInfrastructure` that are converted
+const MAPPED_CONTAINERS: [&str; 8] = [
+    "bufferStops",
+    "signalsIS",
+    "switchesIS",
+    "trainDetectionElements",
+    "speeds",
+    "operationalPoints",
+    "electrifications",
+    "electrificationSystems",
+];
+
+/// Run the railml-to-railjson subcommand
+/// Converts a railML 3 infrastructure file to railjson, logging the elements that were not mapped
+pub fn railml_to_railjson(
+    railml_in: PathBuf,
+    railjson_out: PathBuf,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    info!(
+        "🚉 Converting {} to {}",
+        railml_in.display(),
+        railjson_out.display()
+    );
+    let railml = std::fs::read_to_string(railml_in)?;
+    let RailMlImport { railjson, unmapped } = parse_railml(&railml)?;
+    for UnmappedElement {
+        element,
+        id,
+        reason,
+    } in &unmapped
+    {
+        warn!(
+            "Unmapped {element} '{}': {reason}",
+            id.as_deref().unwrap_or_default()
+        );
+    }
+    let file = std::fs::File::create(railjson_out)?;
+    serde_json::to_writer(file, &railjson)?;
+    Ok(())
+}
+
+/// Converts a railML 3 infrastructure to RailJSON
+///
+/// Geometric coordinates are expected to be WGS84 longitudes (`x`) and latitudes (`y`).
+/// Positions are read from the `pos` attribute of the locations, or computed from the
+/// `intrinsicCoord` one otherwise.
+///
+/// The signaling systems of a signal are read from its designators in the `signalingSystem`
+/// register, its settings from the type of its `isTrainMovementSignal`.
+pub fn parse_railml(railml: &str) -> Result<RailMlImport, RailMlError> {
+    let document = Document::parse(railml)?;
+    let infrastructure = document
+        .descendants()
+        .find(|node| node.tag_name().name() == "infrastructure")
+        .ok_or(RailMlError::MissingElement("infrastructure"))?;
+    let mut converter = Converter::default();
+    converter.track_sections(infrastructure);
+    converter.switches(infrastructure);
+    converter.report_unsupported_containers(infrastructure);
+
+    let railjson = RailJson {
+        buffer_stops: converter.located(infrastructure, "bufferStop", |id, track, position| {
+            BufferStop {
+                id: id.into(),
+                track: track.into(),
+                position,
+                extensions: Default::default(),
+            }
+        }),
+        detectors: converter.located(
+            infrastructure,
+            "trainDetectionElement",
+            |id, track, position| Detector {
+                id: id.into(),
+                track: track.into(),
+                position,
+                extensions: Default::default(),
+            },
+        ),
+        signals: converter.signals(infrastructure),
+        speed_sections: converter.speed_sections(infrastructure),
+        electrifications: converter.electrifications(&document, infrastructure),
+        operational_points: converter.operational_points(infrastructure),
+        switches: std::mem::take(&mut converter.switches),
+        track_sections: converter.track_sections.into_values().collect(),
+        ..Default::default()
+    };
+    debug!(
+        "Converted {} track sections, {} elements were not mapped",
+        railjson.track_sections.len(),
+        converter.unmapped.len()
+    );
+    Ok(RailMlImport {
+        railjson,
+        unmapped: converter.unmapped,
+    })
+}
+
+/// Iterates over the descendants of a node with the given name, whatever their namespace
+fn elements<'a, 'input: 'a>(
+    node: Node<'a, 'input>,
+    name: &'a str,
+) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    node.descendants()
+        .filter(move |child| child.is_element() && child.tag_name().name() == name)
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children()
+        .find(|child| child.is_element() && child.tag_name().name() == name)
+}
+
+fn float_attribute(node: Node, name: &str) -> Option<f64> {
+    node.attribute(name)?.parse().ok()
+}
+
+fn endpoint(position_on_element: Option<&str>) -> Option<Endpoint> {
+    match position_on_element? {
+        "0" => Some(Endpoint::Begin),
+        "1" => Some(Endpoint::End),
+        _ => None,
+    }
+}
+
+fn application_direction(node: Node) -> ApplicableDirections {
+    match node.attribute("applicationDirection") {
+        Some("normal") => ApplicableDirections::StartToStop,
+        Some("reverse") => ApplicableDirections::StopToStart,
+        _ => ApplicableDirections::Both,
+    }
+}
+
+/// A connection between two track endpoints described by a `netRelation`
+struct NetRelation<'a, 'input> {
+    node: Node<'a, 'input>,
+    endpoints: [TrackEndpoint; 2],
+}
+
+/// Builds the logical signal of a supported signaling system
+///
+/// A `main` or `combined` train movement signal is a stop signal, a `distant` one announces
+/// the next signal.
+fn logical_signal(signaling_system: &str, movement_type: Option<&str>) -> Option<LogicalSignal> {
+    let flag = |value: bool| NonBlankString::from(value.to_string());
+    let stop = flag(matches!(movement_type, Some("main" | "combined")));
+    let settings = match signaling_system {
+        "BAL" => HashMap::from([("Nf".into(), stop)]),
+        "BAPR" => HashMap::from([
+            ("Nf".into(), stop),
+            ("distant".into(), flag(movement_type == Some("distant"))),
+        ]),
+        "TVM300" | "TVM430" => HashMap::new(),
+        _ => return None,
+    };
+    Some(LogicalSignal {
+        signaling_system: signaling_system.to_owned(),
+        settings,
+        ..Default::default()
+    })
+}
+
+#[derive(Default)]
+struct Converter {
+    track_sections: BTreeMap<String, TrackSection>,
+    switches: Vec<Switch>,
+    unmapped: Vec<UnmappedElement>,
+}
+
+impl Converter {
+    fn unmapped(&mut self, node: Node, reason: impl Into<String>) {
+        self.unmapped.push(UnmappedElement {
+            element: node.tag_name().name().to_owned(),
+            id: node.attribute("id").map(str::to_owned),
+            reason: reason.into(),
+        });
+    }
+
+    /// Converts the micro level `netElement`, the ones aggregating others being ignored
+    fn track_sections(&mut self, infrastructure: Node) {
+        for net_element in elements(infrastructure, "netElement") {
+            if child(net_element, "elementCollectionUnordered").is_some()
+                || child(net_element, "elementCollectionOrdered").is_some()
+            {
+                continue;
+            }
+            let Some(id) = net_element.attribute("id") else {
+                self.unmapped(net_element, "missing id");
+                continue;
+            };
+            let mut coordinates: Vec<_> = elements(net_element, "intrinsicCoordinate")
+                .filter_map(|coordinate| {
+                    let intrinsic_coord = float_attribute(coordinate, "intrinsicCoord")?;
+                    let geometric = child(coordinate, "geometricCoordinate")?;
+                    let x = float_attribute(geometric, "x")?;
+                    let y = float_attribute(geometric, "y")?;
+                    Some((intrinsic_coord, vec![x, y]))
+                })
+                .collect();
+            coordinates.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+            let points: Vec<_> = coordinates.into_iter().map(|(_, point)| point).collect();
+            if points.len() < 2 {
+                self.unmapped(net_element, "less than two geometric coordinates");
+                continue;
+            }
+            let length =
+                float_attribute(net_element, "length").unwrap_or_else(|| haversine_length(&points));
+            self.track_sections.insert(
+                id.to_owned(),
+                TrackSection {
+                    id: id.into(),
+                    length,
+                    geo: Geometry::new(LineString(points)),
+                    ..Default::default()
+                },
+            );
+        }
+    }
+
+    fn net_relation<'a, 'input>(
+        &mut self,
+        net_relation: Node<'a, 'input>,
+    ) -> Option<NetRelation<'a, 'input>> {
+        let element_endpoint = |element: &str, position: &str| {
+            let track = child(net_relation, element)?.attribute("ref")?;
+            let endpoint = endpoint(net_relation.attribute(position))?;
+            self.track_sections
+                .contains_key(track)
+                .then(|| TrackEndpoint::new(track, endpoint))
+        };
+        let endpoints = [
+            element_endpoint("elementA", "positionOnA"),
+            element_endpoint("elementB", "positionOnB"),
+        ];
+        match endpoints {
+            [Some(a), Some(b)] => Some(NetRelation {
+                node: net_relation,
+                endpoints: [a, b],
+            }),
+            _ => {
+                self.unmapped(net_relation, "invalid or unknown net elements");
+                None
+            }
+        }
+    }
+
+    /// Converts the `switchIS` to point switches and the other navigable `netRelation` to links
+    fn switches(&mut self, infrastructure: Node) {
+        let mut relations = HashMap::new();
+        for net_relation in elements(infrastructure, "netRelation") {
+            if net_relation.attribute("navigability") == Some("None") {
+                continue;
+            }
+            if let Some(relation) = self.net_relation(net_relation) {
+                let id = net_relation.attribute("id").unwrap_or_default().to_owned();
+                relations.insert(id, relation);
+            }
+        }
+
+        let mut used_relations = HashSet::new();
+        for switch in elements(infrastructure, "switchIS") {
+            let branch = |name| {
+                let relation = child(switch, name)?.attribute("netRelationRef")?;
+                relations.get(relation).map(|branch| (relation, branch))
+            };
+            let (Some((left_id, left)), Some((right_id, right))) =
+                (branch("leftBranch"), branch("rightBranch"))
+            else {
+                self.unmapped(switch, "missing or unknown branch net relations");
+                continue;
+            };
+            let Some(base) = left
+                .endpoints
+                .iter()
+                .find(|endpoint| right.endpoints.contains(endpoint))
+            else {
+                self.unmapped(switch, "the branches have no common track endpoint");
+                continue;
+            };
+            let other_end = |relation: &NetRelation| {
+                relation
+                    .endpoints
+                    .iter()
+                    .find(|endpoint| *endpoint != base)
+                    .cloned()
+                    .unwrap_or_else(|| base.clone())
+            };
+            // The continuing course is the straight branch
+            let (straight, diverging) = match switch.attribute("continueCourse") {
+                Some("left") => (other_end(left), other_end(right)),
+                _ => (other_end(right), other_end(left)),
+            };
+            used_relations.extend([left_id.to_owned(), right_id.to_owned()]);
+            self.switches.push(Switch {
+                id: switch.attribute("id").unwrap_or(left_id).into(),
+                switch_type: "point_switch".into(),
+                group_change_delay: 4.,
+                ports: HashMap::from([
+                    ("A".into(), base.clone()),
+                    ("B1".into(), straight),
+                    ("B2".into(), diverging),
+                ]),
+                ..Default::default()
+            });
+        }
+
+        let links: Vec<_> = relations
+            .into_iter()
+            .filter(|(id, _)| !used_relations.contains(id))
+            .collect();
+        let mut endpoint_links = HashMap::<&TrackEndpoint, usize>::new();
+        for (_, relation) in &links {
+            for endpoint in &relation.endpoints {
+                *endpoint_links.entry(endpoint).or_default() += 1;
+            }
+        }
+        let mut switches = vec![];
+        for (id, relation) in &links {
+            if relation
+                .endpoints
+                .iter()
+                .any(|endpoint| endpoint_links[endpoint] > 1)
+            {
+                self.unmapped(
+                    relation.node,
+                    "connects a track endpoint shared with other net relations outside of a switch",
+                );
+                continue;
+            }
+            let [a, b] = relation.endpoints.clone();
+            switches.push(Switch {
+                id: id.as_str().into(),
+                switch_type: "link".into(),
+                ports: HashMap::from([("A".into(), a), ("B".into(), b)]),
+                ..Default::default()
+            });
+        }
+        self.switches.extend(switches);
+    }
+
+    fn report_unsupported_containers(&mut self, infrastructure: Node) {
+        let Some(functional_infrastructure) =
+            elements(infrastructure, "functionalInfrastructure").next()
+        else {
+            return;
+        };
+        for container in functional_infrastructure
+            .children()
+            .filter(Node::is_element)
+        {
+            if MAPPED_CONTAINERS.contains(&container.tag_name().name()) {
+                continue;
+            }
+            for element in container.children().filter(Node::is_element) {
+                self.unmapped(element, "unsupported element type");
+            }
+        }
+    }
+
+    /// Finds the track and the position of a `spotLocation`
+    fn spot_location(&self, location: Node) -> Option<(String, f64, ApplicableDirections)> {
+        let track = location.attribute("netElementRef")?;
+        let track_section = self.track_sections.get(track)?;
+        let position = float_attribute(location, "pos").or_else(|| {
+            float_attribute(location, "intrinsicCoord")
+                .map(|coordinate| coordinate * track_section.length)
+        })?;
+        Some((
+            track.to_owned(),
+            position.clamp(0., track_section.length),
+            application_direction(location),
+        ))
+    }
+
+    /// Finds the track ranges of the `linearLocation` of an element
+    fn track_ranges(&self, element: Node) -> Vec<ApplicableDirectionsTrackRange> {
+        elements(element, "linearLocation")
+            .flat_map(|location| {
+                let applicable_directions = application_direction(location);
+                elements(location, "associatedNetElement").filter_map(move |net_element| {
+                    let track = net_element.attribute("netElementRef")?;
+                    let length = self.track_sections.get(track)?.length;
+                    let position = |pos, intrinsic_coord| {
+                        float_attribute(net_element, pos)
+                            .or_else(|| {
+                                float_attribute(net_element, intrinsic_coord)
+                                    .map(|coordinate| coordinate * length)
+                            })
+                            .map(|position| position.clamp(0., length))
+                    };
+                    let begin = position("posBegin", "intrinsicCoordBegin").unwrap_or(0.);
+                    let end = position("posEnd", "intrinsicCoordEnd").unwrap_or(length);
+                    Some(ApplicableDirectionsTrackRange {
+                        track: track.into(),
+                        begin: begin.min(end),
+                        end: begin.max(end),
+                        applicable_directions,
+                    })
+                })
+            })
+            .collect()
+    }
+
+    /// Converts the elements with a single `spotLocation`
+    fn located<T>(
+        &mut self,
+        infrastructure: Node,
+        name: &str,
+        convert: impl Fn(&str, &str, f64) -> T,
+    ) -> Vec<T> {
+        let mut objects = vec![];
+        for element in elements(infrastructure, name) {
+            let id = element.attribute("id");
+            let location =
+                child(element, "spotLocation").and_then(|location| self.spot_location(location));
+            match (id, location) {
+                (Some(id), Some((track, position, _))) => {
+                    objects.push(convert(id, &track, position))
+                }
+                (None, _) => self.unmapped(element, "missing id"),
+                (_, None) => self.unmapped(element, "missing or invalid spot location"),
+            }
+        }
+        objects
+    }
+
+    fn signals(&mut self, infrastructure: Node) -> Vec<Signal> {
+        let mut signals = vec![];
+        for element in elements(infrastructure, "signalIS") {
+            let movement_type = child(element, "isTrainMovementSignal")
+                .and_then(|movement_signal| movement_signal.attribute("type"));
+            let logical_signals: Option<Vec<_>> = elements(element, "designator")
+                .filter(|designator| {
+                    designator.attribute("register") == Some(SIGNALING_SYSTEM_REGISTER)
+                })
+                .map(|designator| logical_signal(designator.attribute("entry")?, movement_type))
+                .collect();
+            let location =
+                child(element, "spotLocation").and_then(|location| self.spot_location(location));
+            match (element.attribute("id"), location, logical_signals) {
+                (None, _, _) => self.unmapped(element, "missing id"),
+                (_, None, _) => self.unmapped(element, "missing or invalid spot location"),
+                (_, _, None) => self.unmapped(element, "unsupported signaling system"),
+                (_, _, Some(logical_signals)) if logical_signals.is_empty() => {
+                    self.unmapped(element, "missing signaling system")
+                }
+                (Some(id), Some((track, position, direction)), Some(logical_signals)) => signals
+                    .push(Signal {
+                        id: id.into(),
+                        track: track.into(),
+                        position,
+                        direction: match direction {
+                            ApplicableDirections::StopToStart => Direction::StopToStart,
+                            _ => Direction::StartToStop,
+                        },
+                        logical_signals,
+                        ..Default::default()
+                    }),
+            }
+        }
+        signals
+    }
+
+    fn speed_sections(&mut self, infrastructure: Node) -> Vec<SpeedSection> {
+        let mut speed_sections = vec![];
+        for element in elements(infrastructure, "speedSection") {
+            let track_ranges = self.track_ranges(element);
+            let (Some(id), Some(max_speed)) = (
+                element.attribute("id"),
+                float_attribute(element, "maxSpeed"),
+            ) else {
+                self.unmapped(element, "missing id or maximum speed");
+                continue;
+            };
+            if track_ranges.is_empty() {
+                self.unmapped(element, "missing or invalid linear location");
+                continue;
+            }
+            speed_sections.push(SpeedSection {
+                id: id.into(),
+                // railML speeds are given in km/h
+                speed_limit: Some(Speed(max_speed / 3.6)),
+                track_ranges,
+                ..Default::default()
+            });
+        }
+        speed_sections
+    }
+
+    fn electrifications(
+        &mut self,
+        document: &Document,
+        infrastructure: Node,
+    ) -> Vec<Electrification> {
+        let voltages: HashMap<_, _> = elements(document.root(), "electrificationSystem")
+            .filter_map(|system| Some((system.attribute("id")?, system.attribute("voltage")?)))
+            .collect();
+        let mut electrifications = vec![];
+        for element in elements(infrastructure, "electrificationSection") {
+            let voltage = child(element, "electrificationSystemRef")
+                .and_then(|system| system.attribute("ref"))
+                .and_then(|system| voltages.get(system));
+            let (Some(id), Some(voltage)) = (element.attribute("id"), voltage) else {
+                self.unmapped(element, "missing id or electrification system");
+                continue;
+            };
+            let track_ranges = self.track_ranges(element);
+            if track_ranges.is_empty() {
+                self.unmapped(element, "missing or invalid linear location");
+                continue;
+            }
+            electrifications.push(Electrification {
+                id: id.into(),
+                voltage: format!("{voltage}V").into(),
+                track_ranges,
+            });
+        }
+        electrifications
+    }
+
+    fn operational_points(&mut self, infrastructure: Node) -> Vec<OperationalPoint> {
+        let mut operational_points = vec![];
+        for element in elements(infrastructure, "operationalPoint") {
+            let Some(id) = element.attribute("id") else {
+                self.unmapped(element, "missing id");
+                continue;
+            };
+            let parts: Vec<_> = elements(element, "spotLocation")
+                .filter_map(|location| self.spot_location(location))
+                .map(|(track, position, _)| OperationalPointPart {
+                    track: track.into(),
+                    position,
+                    extensions: Default::default(),
+                })
+                .collect();
+            if parts.is_empty() {
+                self.unmapped(element, "missing or invalid spot location");
+                continue;
+            }
+            let uic = elements(element, "designator")
+                .find(|designator| designator.attribute("register") == Some("_UIC"))
+                .and_then(|designator| designator.attribute("entry")?.parse().ok())
+                .unwrap_or_default();
+            let identifier = child(element, "name")
+                .and_then(|name| name.attribute("name"))
+                .map(|name| OperationalPointIdentifierExtension {
+                    name: name.into(),
+                    uic,
+                });
+            operational_points.push(OperationalPoint {
+                id: id.into(),
+                parts,
+                extensions: OperationalPointExtensions {
+                    identifier,
+                    ..Default::default()
+                },
+            });
+        }
+        operational_points
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use editoast_schemas::infra::ApplicableDirections;
+    use editoast_schemas::infra::Direction;
+    use editoast_schemas::infra::Endpoint;
+    use editoast_schemas::infra::LogicalSignal;
+    use editoast_schemas::infra::TrackEndpoint;
+    use pretty_assertions::assert_eq;
+
+    use super::parse_railml;
+
+    const RAILML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<railML xmlns="https://www.railml.org/schemas/3.2" version="3.2">
+  <infrastructure id="is">
+    <topology>
+      <netElements>
+        <netElement id="ne_1" length="1000">
+          <associatedPositioningSystem id="aps_1">
+            <intrinsicCoordinate id="ic_1_0" intrinsicCoord="0"><geometricCoordinate positioningSystemRef="gps" x="2.0" y="48.0"/></intrinsicCoordinate>
+            <intrinsicCoordinate id="ic_1_1" intrinsicCoord="1"><geometricCoordinate positioningSystemRef="gps" x="2.01" y="48.0"/></intrinsicCoordinate>
+          </associatedPositioningSystem>
+        </netElement>
+        <netElement id="ne_2" length="500">
+          <associatedPositioningSystem id="aps_ne_2">
+            <intrinsicCoordinate id="ic_ne_2_0" intrinsicCoord="0"><geometricCoordinate positioningSystemRef="gps" x="2.01" y="48.0"/></intrinsicCoordinate>
+            <intrinsicCoordinate id="ic_ne_2_1" intrinsicCoord="1"><geometricCoordinate positioningSystemRef="gps" x="2.02" y="48.0"/></intrinsicCoordinate>
+          </associatedPositioningSystem>
+        </netElement>
+        <netElement id="ne_3" length="500">
+          <associatedPositioningSystem id="aps_ne_3">
+            <intrinsicCoordinate id="ic_ne_3_0" intrinsicCoord="0"><geometricCoordinate positioningSystemRef="gps" x="2.01" y="48.0"/></intrinsicCoordinate>
+            <intrinsicCoordinate id="ic_ne_3_1" intrinsicCoord="1"><geometricCoordinate positioningSystemRef="gps" x="2.02" y="48.0"/></intrinsicCoordinate>
+          </associatedPositioningSystem>
+        </netElement>
+        <netElement id="ne_4" length="200">
+          <associatedPositioningSystem id="aps_ne_4">
+            <intrinsicCoordinate id="ic_ne_4_0" intrinsicCoord="0"><geometricCoordinate positioningSystemRef="gps" x="2.02" y="48.0"/></intrinsicCoordinate>
+            <intrinsicCoordinate id="ic_ne_4_1" intrinsicCoord="1"><geometricCoordinate positioningSystemRef="gps" x="2.023" y="48.0"/></intrinsicCoordinate>
+          </associatedPositioningSystem>
+        </netElement>
+        <netElement id="ne_5"/>
+        <netElement id="macro"><elementCollectionUnordered id="ecu"><elementPart ref="ne_1"/></elementCollectionUnordered></netElement>
+      </netElements>
+      <netRelations>
+        <netRelation id="nr_1_2" positionOnA="1" positionOnB="0" navigability="Both"><elementA ref="ne_1"/><elementB ref="ne_2"/></netRelation>
+        <netRelation id="nr_1_3" positionOnA="1" positionOnB="0" navigability="Both"><elementA ref="ne_1"/><elementB ref="ne_3"/></netRelation>
+        <netRelation id="nr_2_4" positionOnA="1" positionOnB="0" navigability="Both"><elementA ref="ne_2"/><elementB ref="ne_4"/></netRelation>
+      </netRelations>
+    </topology>
+    <functionalInfrastructure>
+      <bufferStops>
+        <bufferStop id="bs_1"><spotLocation id="bs_1_sl" netElementRef="ne_1" intrinsicCoord="0"/></bufferStop>
+      </bufferStops>
+      <signalsIS>
+        <signalIS id="sig_1">
+          <designator register="signalingSystem" entry="BAPR"/>
+          <spotLocation id="sig_1_sl" netElementRef="ne_1" pos="800" applicationDirection="reverse"/>
+          <isTrainMovementSignal type="distant"/>
+        </signalIS>
+        <signalIS id="sig_2"><spotLocation id="sig_2_sl" netElementRef="ne_1" pos="900"/></signalIS>
+        <signalIS id="sig_3">
+          <designator register="signalingSystem" entry="ETCS"/>
+          <spotLocation id="sig_3_sl" netElementRef="ne_1" pos="900"/>
+        </signalIS>
+      </signalsIS>
+      <switchesIS>
+        <switchIS id="sw_1" continueCourse="right" branchCourse="left">
+          <spotLocation id="sw_1_sl" netElementRef="ne_1" intrinsicCoord="1"/>
+          <leftBranch netRelationRef="nr_1_3"/>
+          <rightBranch netRelationRef="nr_1_2"/>
+        </switchIS>
+      </switchesIS>
+      <trainDetectionElements>
+        <trainDetectionElement id="tde_1" type="axleCounter"><spotLocation id="tde_1_sl" netElementRef="ne_1" intrinsicCoord="0.5"/></trainDetectionElement>
+        <trainDetectionElement id="tde_2" type="axleCounter"><spotLocation id="tde_2_sl" netElementRef="unknown" intrinsicCoord="0.5"/></trainDetectionElement>
+      </trainDetectionElements>
+      <speeds>
+        <speedSection id="speed_1" maxSpeed="90">
+          <linearLocation id="speed_1_ll" applicationDirection="normal">
+            <associatedNetElement netElementRef="ne_2" intrinsicCoordBegin="0" intrinsicCoordEnd="0.5"/>
+          </linearLocation>
+        </speedSection>
+      </speeds>
+      <levelCrossingsIS>
+        <levelCrossingIS id="lc_1"><spotLocation id="lc_1_sl" netElementRef="ne_2" intrinsicCoord="0.5"/></levelCrossingIS>
+      </levelCrossingsIS>
+    </functionalInfrastructure>
+  </infrastructure>
+</railML>
+"#;
+
+    #[test]
+    fn parse_tracks_and_switches() {
+        let import = parse_railml(RAILML).unwrap();
+        let railjson = import.railjson;
+
+        let tracks: Vec<_> = railjson
+            .track_sections
+            .iter()
+            .map(|track| (track.id.0.as_str(), track.length))
+            .collect();
+        assert_eq!(
+            tracks,
+            vec![
+                ("ne_1", 1000.),
+                ("ne_2", 500.),
+                ("ne_3", 500.),
+                ("ne_4", 200.)
+            ]
+        );
+
+        let mut switches: Vec<_> = railjson
+            .switches
+            .iter()
+            .map(|switch| (switch.id.0.as_str(), switch.switch_type.0.as_str()))
+            .collect();
+        switches.sort();
+        assert_eq!(switches, vec![("nr_2_4", "link"), ("sw_1", "point_switch")]);
+        let point_switch = railjson
+            .switches
+            .iter()
+            .find(|switch| switch.id.0 == "sw_1")
+            .unwrap();
+        assert_eq!(
+            point_switch.ports[&"A".into()],
+            TrackEndpoint::new("ne_1", Endpoint::End)
+        );
+        assert_eq!(
+            point_switch.ports[&"B1".into()],
+            TrackEndpoint::new("ne_2", Endpoint::Begin)
+        );
+    }
+
+    #[test]
+    fn parse_located_objects() {
+        let import = parse_railml(RAILML).unwrap();
+        let railjson = import.railjson;
+
+        assert_eq!(railjson.buffer_stops[0].position, 0.);
+        assert_eq!(railjson.signals[0].position, 800.);
+        assert_eq!(railjson.signals[0].direction, Direction::StopToStart);
+        assert_eq!(
+            railjson.signals[0].logical_signals,
+            vec![LogicalSignal {
+                signaling_system: "BAPR".to_owned(),
+                settings: HashMap::from([
+                    ("Nf".into(), "false".into()),
+                    ("distant".into(), "true".into()),
+                ]),
+                ..Default::default()
+            }]
+        );
+        assert_eq!(railjson.detectors.len(), 1);
+        assert_eq!(railjson.detectors[0].position, 500.);
+        let speed_section = &railjson.speed_sections[0];
+        assert_eq!(speed_section.speed_limit.unwrap().0, 25.);
+        assert_eq!(speed_section.track_ranges[0].end, 250.);
+        assert_eq!(
+            speed_section.track_ranges[0].applicable_directions,
+            ApplicableDirections::StartToStop
+        );
+    }
+
+    #[test]
+    fn report_unmapped_elements() {
+        let import = parse_railml(RAILML).unwrap();
+        let mut unmapped: Vec<_> = import
+            .unmapped
+            .iter()
+            .map(|element| (element.element.as_str(), element.id.as_deref()))
+            .collect();
+        unmapped.sort();
+        assert_eq!(
+            unmapped,
+            vec![
+                ("levelCrossingIS", Some("lc_1")),
+                ("netElement", Some("ne_5")),
+                ("signalIS", Some("sig_2")),
+                ("signalIS", Some("sig_3")),
+                ("trainDetectionElement", Some("tde_2")),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_invalid_railml() {
+        assert!(parse_railml("<railML></railML>").is_err());
+        assert!(parse_railml("not xml").is_err());
+    }
+}
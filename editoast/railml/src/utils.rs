@@ -0,0 +1,19 @@
+/// Earth's mean radius in meters
+const EARTH_RADIUS: f64 = 6_378_100.0;
+
+/// Computes the distance in meters between two WGS84 coordinates using the Haversine formula
+pub fn haversine_distance(a: &[f64], b: &[f64]) -> f64 {
+    let (a_lon, a_lat) = (a[0].to_radians(), a[1].to_radians());
+    let (b_lon, b_lat) = (b[0].to_radians(), b[1].to_radians());
+    let h = ((b_lat - a_lat) / 2.).sin().powi(2)
+        + a_lat.cos() * b_lat.cos() * ((b_lon - a_lon) / 2.).sin().powi(2);
+    2. * EARTH_RADIUS * h.sqrt().atan2((1. - h).sqrt())
+}
+
+/// Computes the length in meters of a line of WGS84 coordinates
+pub fn haversine_length(points: &[Vec<f64>]) -> f64 {
+    points
+        .windows(2)
+        .map(|segment| haversine_distance(&segment[0], &segment[1]))
+        .sum()
+}
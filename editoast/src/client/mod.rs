@@ -5,6 +5,8 @@ mod telemetry_config;
 use std::env;
 use std::path::PathBuf;
 
+use chrono::FixedOffset;
+use chrono::NaiveDate;
 use clap::Args;
 use clap::Parser;
 use clap::Subcommand;
@@ -55,6 +57,8 @@ pub enum Commands {
     ElectricalProfiles(ElectricalProfilesCommands),
     ImportRollingStock(ImportRollingStockArgs),
     OsmToRailjson(OsmToRailjsonArgs),
+    RailmlToRailjson(RailmlToRailjsonArgs),
+    RailjsonToRailml(RailjsonToRailmlArgs),
     #[command(about, long_about = "Prints the OpenApi of the service")]
     Openapi,
     #[command(subcommand, about, long_about = "Search engine related commands")]
@@ -71,15 +75,46 @@ pub enum TimetablesCommands {
     Export(ExportTimetableArgs),
 }
 
+#[derive(ValueEnum, Debug, Derivative, Clone, Copy, PartialEq)]
+#[derivative(Default)]
+pub enum TimetableFormat {
+    /// A JSON array of train schedules
+    #[derivative(Default)]
+    Json,
+    /// A directory holding the files of a GTFS feed
+    Gtfs,
+    /// A NeTEx XML file
+    Netex,
+}
+
 #[derive(Args, Debug, Derivative)]
 #[derivative(Default)]
-#[command(about, long_about = "Import a train schedule given a JSON file")]
+#[command(
+    about,
+    long_about = "Import a train schedule given a JSON file, a GTFS feed or a NeTEx file"
+)]
 pub struct ImportTimetableArgs {
     /// The timetable id on which attach the trains to
     #[arg(long)]
     pub id: Option<i64>,
     /// The input file path
     pub path: PathBuf,
+    /// The format of the input, GTFS and NeTEx imports need an infra, a rolling stock and a date
+    #[arg(long, value_enum, default_value_t = TimetableFormat::Json)]
+    pub format: TimetableFormat,
+    /// The infra whose operational points are matched with the stops, by UIC code or trigram
+    #[arg(long, required_if_eq_any([("format", "gtfs"), ("format", "netex")]))]
+    pub infra_id: Option<i64>,
+    /// The name of the rolling stock of the imported trains
+    #[arg(long, required_if_eq_any([("format", "gtfs"), ("format", "netex")]))]
+    pub rolling_stock: Option<String>,
+    /// The day the imported trains run
+    #[arg(long, required_if_eq_any([("format", "gtfs"), ("format", "netex")]))]
+    pub date: Option<NaiveDate>,
+    /// The offset from UTC of the imported times
+    #[derivative(Default(value = "FixedOffset::east_opt(0).unwrap()"))]
+    #[arg(long, default_value = "+00:00", allow_hyphen_values = true)]
+    pub utc_offset: FixedOffset,
 }
 
 #[derive(Args, Debug, Derivative)]
@@ -90,6 +125,17 @@ pub struct ExportTimetableArgs {
     pub id: i64,
     /// The output file path
     pub path: PathBuf,
+    /// GTFS exports hold the simulated times of the trains, rather than the requested ones
+    #[arg(long, value_enum, default_value_t = TimetableFormat::Json)]
+    pub format: TimetableFormat,
+    /// The infra the trains are simulated on
+    #[arg(long, required_if_eq("format", "gtfs"))]
+    pub infra_id: Option<i64>,
+    #[derivative(Default(value = r#""http://localhost:8080".into()"#))]
+    #[clap(long, env = "OSRD_BACKEND_URL", default_value_t = String::from("http://localhost:8080"))]
+    pub backend_url: String,
+    #[clap(long, env = "OSRD_BACKEND_TOKEN", default_value_t = String::from(""))]
+    pub backend_token: String,
 }
 
 #[derive(Subcommand, Debug)]
@@ -109,9 +155,11 @@ pub enum SearchCommands {
 #[derive(Subcommand, Debug)]
 pub enum InfraCommands {
     Clone(InfraCloneArgs),
+    Diff(InfraDiffArgs),
     Clear(ClearArgs),
     Generate(GenerateArgs),
     ImportRailjson(ImportRailjsonArgs),
+    ExportLayers(ExportLayersArgs),
 }
 
 #[derive(Args, Debug, Derivative, Clone)]
@@ -179,6 +227,26 @@ pub struct ImportRailjsonArgs {
     pub generate: bool,
 }
 
+#[derive(Args, Debug, Clone)]
+#[command(
+    about,
+    long_about = "Export the map layers of an infra as GeoJSON files or as a GeoPackage"
+)]
+pub struct ExportLayersArgs {
+    /// Infrastructure ID
+    pub infra_id: u64,
+    /// Output GeoPackage file if its extension is `.gpkg`, otherwise directory of GeoJSON files
+    pub output: PathBuf,
+    /// Only export the features intersecting this bounding box, clipped to it
+    #[arg(
+        long,
+        num_args = 4,
+        allow_negative_numbers = true,
+        value_names = ["MIN_LON", "MIN_LAT", "MAX_LON", "MAX_LAT"]
+    )]
+    pub bbox: Option<Vec<f64>>,
+}
+
 #[derive(Args, Debug)]
 #[command(about, long_about = "Add a set of electrical profiles")]
 pub struct ImportProfileSetArgs {
@@ -197,6 +265,24 @@ pub struct InfraCloneArgs {
     pub new_name: Option<String>,
 }
 
+#[derive(Args, Debug, Clone)]
+#[command(about, long_about = "Compare the objects of two infrastructures")]
+pub struct InfraDiffArgs {
+    /// Base infrastructure ID
+    pub base_id: u64,
+    /// ID of the infrastructure compared to the base one
+    pub other_id: u64,
+    /// Compare the base infrastructure as it was right after this revision
+    #[arg(long)]
+    pub base_revision: Option<i64>,
+    /// Compare the other infrastructure as it was right after this revision
+    #[arg(long)]
+    pub other_revision: Option<i64>,
+    /// Output the differences as a list of edition operations
+    #[arg(long)]
+    pub operations: bool,
+}
+
 #[derive(Args, Debug)]
 #[command(
     about,
@@ -232,6 +318,33 @@ pub struct OsmToRailjsonArgs {
     pub osm_pbf_in: PathBuf,
     /// Output file in Railjson format
     pub railjson_out: PathBuf,
+    /// YAML file configuring the tag filters, the default speed and voltage and the imported region
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+    /// Output file of a JSON report listing what was skipped and the errors of the resulting infra
+    #[arg(long)]
+    pub report: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+#[command(
+    about,
+    long_about = "Converts a railML 3 infrastructure to railjson, logging the unmapped elements"
+)]
+pub struct RailmlToRailjsonArgs {
+    /// Input file in the railML 3 format
+    pub railml_in: PathBuf,
+    /// Output file in Railjson format
+    pub railjson_out: PathBuf,
+}
+
+#[derive(Args, Debug)]
+#[command(about, long_about = "Converts a railjson to a railML 3 infrastructure")]
+pub struct RailjsonToRailmlArgs {
+    /// Input file in Railjson format
+    pub railjson_in: PathBuf,
+    /// Output file in the railML 3 format
+    pub railml_out: PathBuf,
 }
 
 #[derive(Args, Debug)]
@@ -40,6 +40,9 @@ pub struct ConflictDetectionResponse {
 pub struct Conflict {
     /// List of train ids involved in the conflict
     pub train_ids: Vec<i64>,
+    /// List of work schedule ids involved in the conflict
+    #[serde(default)]
+    pub work_schedule_ids: Vec<i64>,
     /// Datetime of the start of the conflict
     pub start_time: DateTime<Utc>,
     /// Datetime of the end of the conflict
@@ -55,6 +58,8 @@ pub enum ConflictType {
     Spacing,
     /// Conflict caused by two trains requiring incompatible routes at the same time
     Routing,
+    /// Conflict caused by a train running on a track range during a work schedule
+    WorkSchedule,
 }
 
 impl AsCoreRequest<Json<ConflictDetectionResponse>> for ConflictDetectionRequest {
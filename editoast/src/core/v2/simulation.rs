@@ -152,6 +152,27 @@ pub struct ReportTrain {
     pub scheduled_points_honored: bool,
 }
 
+impl ReportTrain {
+    /// Returns the time (in ms) at which the head of the train first reaches a position (in mm)
+    ///
+    /// Positions past the end of the simulation are reached at the end of the simulation.
+    pub fn time_at(&self, position: u64) -> u64 {
+        let ReportTrain {
+            positions, times, ..
+        } = self;
+        let index = positions.partition_point(|p| *p < position);
+        if index == 0 {
+            return times.first().copied().unwrap_or_default();
+        }
+        if index == positions.len() {
+            return times.last().copied().unwrap_or_default();
+        }
+        let (p0, p1) = (positions[index - 1], positions[index]);
+        let (t0, t1) = (times[index - 1], times[index]);
+        t0 + ((position - p0) as f64 / (p1 - p0) as f64 * (t1 - t0) as f64).round() as u64
+    }
+}
+
 #[derive(Deserialize, Serialize, Clone, Debug, ToSchema)]
 pub struct CompleteReportTrain {
     #[serde(flatten)]
@@ -222,6 +222,26 @@ impl EditoastError for serde_json::Error {
     }
 }
 
+impl EditoastError for rusqlite::Error {
+    fn get_status(&self) -> StatusCode {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+
+    fn get_type(&self) -> &str {
+        "editoast:SqliteError"
+    }
+}
+
+impl EditoastError for std::io::Error {
+    fn get_status(&self) -> StatusCode {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+
+    fn get_type(&self) -> &str {
+        "editoast:IoError"
+    }
+}
+
 impl EditoastError for json_patch::PatchError {
     fn get_status(&self) -> StatusCode {
         StatusCode::INTERNAL_SERVER_ERROR
@@ -232,6 +252,17 @@ impl EditoastError for json_patch::PatchError {
     }
 }
 
+/// Handle the failures of blocking tasks
+impl EditoastError for tokio::task::JoinError {
+    fn get_status(&self) -> StatusCode {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+
+    fn get_type(&self) -> &str {
+        "editoast:JoinError"
+    }
+}
+
 inventory::submit! {
     crate::error::ErrorDefinition::new("editoast:geometry:UnexpectedGeometry", "UnexpectedGeometry", "GeometryError", 404u16, r#"{"expected":"String","actual":"String"}"#)
 }
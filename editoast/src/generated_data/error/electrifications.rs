@@ -10,6 +10,8 @@ use crate::generated_data::infra_error::InfraError;
 use crate::infra_cache::Graph;
 use crate::infra_cache::InfraCache;
 use crate::infra_cache::ObjectCache;
+use editoast_schemas::infra::Endpoint;
+use editoast_schemas::infra::TrackEndpoint;
 use editoast_schemas::primitives::OSRDIdentified;
 use editoast_schemas::primitives::ObjectRef;
 use editoast_schemas::primitives::ObjectType;
@@ -19,8 +21,10 @@ pub const OBJECT_GENERATORS: [ObjectErrorGenerator<NoContext>; 2] = [
     ObjectErrorGenerator::new(2, check_electrification_track_ranges),
 ];
 
-pub const GLOBAL_GENERATORS: [GlobalErrorGenerator<NoContext>; 1] =
-    [GlobalErrorGenerator::new(check_overlapping)];
+pub const GLOBAL_GENERATORS: [GlobalErrorGenerator<NoContext>; 2] = [
+    GlobalErrorGenerator::new(check_overlapping),
+    GlobalErrorGenerator::new(check_gaps),
+];
 
 /// Check if a track section has empty electrification
 pub fn check_empty(electrification: &ObjectCache, _: &InfraCache, _: &Graph) -> Vec<InfraError> {
@@ -109,9 +113,91 @@ pub fn check_overlapping(infra_cache: &InfraCache, _: &Graph) -> Vec<InfraError>
         .collect()
 }
 
+/// Checks that there is no gap in the electrification of a line.
+/// A gap is a part of a track section that isn't electrified while being surrounded by electrified
+/// parts, either on the same track section or on the linked ones.
+pub fn check_gaps(infra_cache: &InfraCache, graph: &Graph) -> Vec<InfraError> {
+    // Electrified ranges of each track section, sorted and merged
+    let mut electrified: HashMap<&String, Vec<(f64, f64)>> = Default::default();
+    for electrification in infra_cache.electrifications().values() {
+        let electrification = electrification.unwrap_electrification();
+        for track_range in electrification.track_ranges.iter() {
+            electrified
+                .entry(&track_range.track.0)
+                .or_default()
+                .push((track_range.begin, track_range.end));
+        }
+    }
+    for ranges in electrified.values_mut() {
+        ranges.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let mut merged: Vec<(f64, f64)> = vec![];
+        for &(begin, end) in ranges.iter() {
+            match merged.last_mut() {
+                Some(last) if begin <= last.1 => last.1 = last.1.max(end),
+                _ => merged.push((begin, end)),
+            }
+        }
+        *ranges = merged;
+    }
+
+    let is_electrified_at = |track_endpoint: &TrackEndpoint| {
+        let Some(ranges) = electrified.get(&track_endpoint.track.0) else {
+            return false;
+        };
+        match track_endpoint.endpoint {
+            Endpoint::Begin => ranges.first().is_some_and(|(begin, _)| *begin <= 0.),
+            Endpoint::End => infra_cache
+                .track_sections()
+                .get(&track_endpoint.track.0)
+                .is_some_and(|track| {
+                    let length = track.unwrap_track_section().length;
+                    ranges.last().is_some_and(|(_, end)| *end >= length)
+                }),
+        }
+    };
+    let is_linked_to_electrified = |track_endpoint: &TrackEndpoint| {
+        graph
+            .get_neighbour_groups(track_endpoint)
+            .into_iter()
+            .filter_map(|group| graph.get_neighbour(track_endpoint, group))
+            .any(is_electrified_at)
+    };
+
+    let mut infra_errors = vec![];
+    for track in infra_cache.track_sections().values() {
+        let track = track.unwrap_track_section();
+        let ranges = electrified
+            .get(&track.obj_id)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        // The current non electrified part starts at `gap_begin`
+        // `gap_bounded` tells if an electrified part precedes it
+        let mut gap_begin = 0.;
+        let mut gap_bounded = is_linked_to_electrified(&track.get_begin());
+        for &(begin, end) in ranges {
+            if begin > gap_begin && gap_bounded {
+                infra_errors.push(InfraError::new_electrification_gap(
+                    track,
+                    [gap_begin, begin.min(track.length)],
+                ));
+            }
+            gap_begin = end;
+            gap_bounded = true;
+        }
+        if gap_begin < track.length && gap_bounded && is_linked_to_electrified(&track.get_end()) {
+            infra_errors.push(InfraError::new_electrification_gap(
+                track,
+                [gap_begin, track.length],
+            ));
+        }
+    }
+    infra_errors
+}
+
 #[cfg(test)]
 mod tests {
     use super::check_electrification_track_ranges;
+    use super::check_gaps;
     use super::InfraError;
     use crate::generated_data::error::electrifications::check_overlapping;
     use crate::infra_cache::tests::create_electrification_cache;
@@ -172,4 +258,18 @@ mod tests {
             InfraError::new_overlapping_electrifications("Cat_error_1", "Cat_error_2");
         assert_eq!(infra_error, errors[0]);
     }
+
+    #[test]
+    fn gaps() {
+        let mut infra_cache = create_small_infra_cache();
+        let track_ranges = vec![("A", 0., 100.), ("A", 200., 500.), ("C", 0., 500.)];
+        let electrification = create_electrification_cache("Cat", track_ranges);
+        infra_cache.add(electrification).unwrap();
+        let errors = check_gaps(&infra_cache, &Graph::load(&infra_cache));
+        assert_eq!(2, errors.len());
+        let track_a = infra_cache.track_sections()["A"].unwrap_track_section();
+        let track_b = infra_cache.track_sections()["B"].unwrap_track_section();
+        assert!(errors.contains(&InfraError::new_electrification_gap(track_a, [100., 200.])));
+        assert!(errors.contains(&InfraError::new_electrification_gap(track_b, [0., 500.])));
+    }
 }
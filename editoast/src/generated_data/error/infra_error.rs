@@ -29,15 +29,19 @@ pub struct InfraError {
 
 #[derive(Serialize, Deserialize, PartialEq, Debug, EnumDiscriminants, Clone, ToSchema)]
 #[strum(serialize_all = "snake_case")]
-#[strum_discriminants(derive(ToSchema, Deserialize, EnumString, AsRefStr))]
+#[strum_discriminants(derive(ToSchema, Serialize, Deserialize, Hash, EnumString, AsRefStr))]
 #[strum_discriminants(name(InfraErrorTypeLabel))]
 #[strum_discriminants(serde(rename_all = "snake_case", deny_unknown_fields))]
 #[strum_discriminants(strum(serialize_all = "snake_case"))]
 #[serde(tag = "error_type", rename_all = "snake_case", deny_unknown_fields)]
 pub enum InfraErrorType {
+    DisconnectedOperationalPointParts,
     DuplicatedGroup {
         original_group_path: String,
     },
+    ElectrificationGap {
+        range: [f64; 2],
+    },
     EmptyObject,
     InvalidGroup {
         group: String,
@@ -52,7 +56,11 @@ pub enum InfraErrorType {
     MissingBufferStop {
         endpoint: Endpoint,
     },
+    MissingSignalDetector,
     NodeEndpointsNotUnique,
+    NonMonotonicSpeedLimit {
+        tag: String,
+    },
     ObjectOutOfPath {
         reference: ObjectRef,
     },
@@ -73,6 +81,7 @@ pub enum InfraErrorType {
     UnknownPortName {
         port_name: String,
     },
+    UnreachableTrackSection,
     UnusedPort {
         port_name: String,
     },
@@ -309,6 +318,63 @@ impl InfraError {
         }
     }
 
+    /// Create a new disconnected operational point parts error.
+    /// The field is the part lying on another connected component than the first part.
+    pub fn new_disconnected_operational_point_parts<T: AsRef<str>, O: OSRDObject>(
+        obj: &O,
+        field: T,
+    ) -> Self {
+        Self {
+            obj_id: obj.get_id().clone(),
+            obj_type: obj.get_type(),
+            field: Some(field.as_ref().into()),
+            is_warning: false,
+            sub_type: InfraErrorType::DisconnectedOperationalPointParts,
+        }
+    }
+
+    pub fn new_electrification_gap<O: OSRDObject>(track: &O, range: [f64; 2]) -> Self {
+        Self {
+            obj_id: track.get_id().clone(),
+            obj_type: track.get_type(),
+            field: None,
+            is_warning: true,
+            sub_type: InfraErrorType::ElectrificationGap { range },
+        }
+    }
+
+    pub fn new_missing_signal_detector<O: OSRDObject>(signal: &O) -> Self {
+        Self {
+            obj_id: signal.get_id().clone(),
+            obj_type: signal.get_type(),
+            field: None,
+            is_warning: false,
+            sub_type: InfraErrorType::MissingSignalDetector,
+        }
+    }
+
+    pub fn new_non_monotonic_speed_limit<T: AsRef<str>, O: OSRDObject>(obj: &O, tag: T) -> Self {
+        Self {
+            obj_id: obj.get_id().clone(),
+            obj_type: obj.get_type(),
+            field: Some(format!("speed_limit_by_tag.{}", tag.as_ref())),
+            is_warning: true,
+            sub_type: InfraErrorType::NonMonotonicSpeedLimit {
+                tag: tag.as_ref().into(),
+            },
+        }
+    }
+
+    pub fn new_unreachable_track_section<O: OSRDObject>(track: &O) -> Self {
+        Self {
+            obj_id: track.get_id().clone(),
+            obj_type: track.get_type(),
+            field: None,
+            is_warning: false,
+            sub_type: InfraErrorType::UnreachableTrackSection,
+        }
+    }
+
     pub fn get_sub_type(&self) -> &InfraErrorType {
         &self.sub_type
     }
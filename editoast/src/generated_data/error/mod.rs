@@ -193,7 +193,7 @@ pub async fn generate_infra_errors(infra_cache: &InfraCache) -> Vec<InfraError>
             infra_cache,
             &graph,
             &track_sections::OBJECT_GENERATORS,
-            &[],
+            &track_sections::GLOBAL_GENERATORS,
         )),
         Box::pin(generate_errors(
             ObjectType::Signal,
@@ -242,7 +242,7 @@ pub async fn generate_infra_errors(infra_cache: &InfraCache) -> Vec<InfraError>
             infra_cache,
             &graph,
             &operational_points::OBJECT_GENERATORS,
-            &[],
+            &operational_points::GLOBAL_GENERATORS,
         )),
         Box::pin(generate_errors(
             ObjectType::Switch,
@@ -459,7 +459,7 @@ mod test {
             &small_infra_cache,
             &graph,
             &track_sections::OBJECT_GENERATORS,
-            &[],
+            &track_sections::GLOBAL_GENERATORS,
         )
         .await
         .is_empty());
@@ -522,7 +522,7 @@ mod test {
             &small_infra_cache,
             &graph,
             &operational_points::OBJECT_GENERATORS,
-            &[],
+            &operational_points::GLOBAL_GENERATORS,
         )
         .await
         .is_empty());
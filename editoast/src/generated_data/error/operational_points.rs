@@ -1,3 +1,4 @@
+use super::GlobalErrorGenerator;
 use super::NoContext;
 use crate::generated_data::error::ObjectErrorGenerator;
 use crate::generated_data::infra_error::InfraError;
@@ -11,6 +12,8 @@ pub const OBJECT_GENERATORS: [ObjectErrorGenerator<NoContext>; 2] = [
     ObjectErrorGenerator::new(1, check_empty),
     ObjectErrorGenerator::new(2, check_op_parts),
 ];
+pub const GLOBAL_GENERATORS: [GlobalErrorGenerator<NoContext>; 1] =
+    [GlobalErrorGenerator::new(check_disconnected_parts)];
 
 /// Check if operational point is empty
 pub fn check_empty(op: &ObjectCache, _: &InfraCache, _: &Graph) -> Vec<InfraError> {
@@ -56,12 +59,41 @@ pub fn check_op_parts(op: &ObjectCache, infra_cache: &InfraCache, _: &Graph) ->
     infra_errors
 }
 
+/// Check that all the parts of an operational point are connected to each other
+pub fn check_disconnected_parts(infra_cache: &InfraCache, graph: &Graph) -> Vec<InfraError> {
+    let components = graph.track_components(infra_cache);
+    let mut infra_errors = vec![];
+    for op in infra_cache.operational_points().values() {
+        let op = op.unwrap_operational_point();
+        // Parts with an invalid track reference are ignored
+        let mut parts_components = op
+            .parts
+            .iter()
+            .enumerate()
+            .filter_map(|(index, part)| Some((index, components.get(&part.track.0)?)));
+        let Some((_, first_component)) = parts_components.next() else {
+            continue;
+        };
+        for (index, component) in parts_components {
+            if component != first_component {
+                infra_errors.push(InfraError::new_disconnected_operational_point_parts(
+                    op,
+                    format!("parts.{index}.track"),
+                ));
+            }
+        }
+    }
+    infra_errors
+}
+
 #[cfg(test)]
 mod tests {
+    use super::check_disconnected_parts;
     use super::check_op_parts;
     use super::InfraError;
     use crate::infra_cache::tests::create_operational_point_cache;
     use crate::infra_cache::tests::create_small_infra_cache;
+    use crate::infra_cache::tests::create_track_section_cache;
     use crate::infra_cache::Graph;
     use editoast_schemas::primitives::ObjectRef;
     use editoast_schemas::primitives::ObjectType;
@@ -88,4 +120,24 @@ mod tests {
         let infra_error = InfraError::new_out_of_range(&op, "parts.0.position", 530., [0.0, 500.]);
         assert_eq!(infra_error, errors[0]);
     }
+
+    #[test]
+    fn disconnected_parts() {
+        let mut infra_cache = create_small_infra_cache();
+        infra_cache
+            .add(create_track_section_cache("E", 500.))
+            .unwrap();
+        let mut op = create_operational_point_cache("OP_error", "A", 250.);
+        let mut part = op.parts[0].clone();
+        part.track = "C".into();
+        op.parts.push(part.clone());
+        part.track = "E".into();
+        op.parts.push(part);
+        infra_cache.add(op.clone()).unwrap();
+        let errors = check_disconnected_parts(&infra_cache, &Graph::load(&infra_cache));
+        assert_eq!(1, errors.len());
+        let infra_error =
+            InfraError::new_disconnected_operational_point_parts(&op, "parts.2.track");
+        assert_eq!(infra_error, errors[0]);
+    }
 }
@@ -1,4 +1,3 @@
-use std::collections::HashMap;
 use std::collections::HashSet;
 
 use super::GlobalErrorGenerator;
@@ -7,6 +6,7 @@ use crate::generated_data::infra_error::InfraError;
 use crate::infra_cache::Graph;
 use crate::infra_cache::InfraCache;
 use crate::infra_cache::ObjectCache;
+use editoast_schemas::infra::Direction;
 use editoast_schemas::infra::Waypoint;
 use editoast_schemas::primitives::Identifier;
 use editoast_schemas::primitives::OSRDIdentified;
@@ -173,28 +173,44 @@ fn check_path(
         }
     }
 
-    // Search for detectors out of the path
-    let track_ranges: HashMap<_, _> = route_path
+    // Search for release detectors out of the path
+    // Each track range is stored with its offset from the beginning of the path
+    let mut path_offset = 0.;
+    let track_ranges: Vec<_> = route_path
         .track_ranges
         .iter()
-        .map(|track| (&track.track.0, (track.begin, track.end)))
+        .map(|track_range| {
+            let range_offset = path_offset;
+            path_offset += track_range.end - track_range.begin;
+            (track_range, range_offset)
+        })
         .collect();
+    let path_length = path_offset;
 
     for (index, detector) in route.release_detectors.iter().enumerate() {
         let detector = infra_cache.detectors().get::<String>(detector).unwrap();
         let detector = detector.unwrap_detector();
-        let track_range = track_ranges.get(&detector.track);
-        if let Some(track_range) = track_range {
-            if (track_range.0..=track_range.1).contains(&detector.position) {
-                continue;
+        let on_path = track_ranges.iter().any(|(track_range, range_offset)| {
+            if track_range.track.0 != detector.track
+                || !(track_range.begin..=track_range.end).contains(&detector.position)
+            {
+                return false;
             }
+            let offset = range_offset
+                + match track_range.direction {
+                    Direction::StartToStop => detector.position - track_range.begin,
+                    Direction::StopToStart => track_range.end - detector.position,
+                };
+            // Release detectors lie between the entry and exit points of the route
+            0. < offset && offset < path_length
+        });
+        if !on_path {
+            res.push(InfraError::new_object_out_of_path(
+                route,
+                format!("release_detectors.{index}"),
+                detector.get_ref(),
+            ));
         }
-
-        res.push(InfraError::new_object_out_of_path(
-            route,
-            format!("release_detectors.{index}"),
-            detector.get_ref(),
-        ));
     }
 
     (res, context)
@@ -445,6 +461,30 @@ mod tests {
         assert_eq!(infra_error, errors[0]);
     }
 
+    #[test]
+    fn release_detector_on_exit_point() {
+        let mut infra_cache = create_small_infra_cache();
+        let route = create_route_cache(
+            "ErrorRoute",
+            Waypoint::new_buffer_stop("BF1"),
+            Direction::StartToStop,
+            Waypoint::new_detector("D1"),
+            vec!["D1".into()],
+            [("link".into(), "LINK".into())].into(),
+        );
+        infra_cache.add(route.clone()).unwrap();
+        let graph = Graph::load(&infra_cache);
+        let ctx = Default::default();
+        let (errors, _) = check_path(&route.clone().into(), &infra_cache, &graph, ctx);
+        assert_eq!(1, errors.len());
+        let infra_error = InfraError::new_object_out_of_path(
+            &route,
+            "release_detectors.0",
+            ObjectRef::new(ObjectType::Detector, "D1"),
+        );
+        assert_eq!(infra_error, errors[0]);
+    }
+
     #[test]
     fn switch_out_of_path() {
         let mut infra_cache = create_small_infra_cache();
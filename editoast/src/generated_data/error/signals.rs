@@ -1,15 +1,21 @@
+use std::collections::HashSet;
+
 use super::NoContext;
 use crate::generated_data::error::ObjectErrorGenerator;
 use crate::generated_data::infra_error::InfraError;
 use crate::infra_cache::Graph;
 use crate::infra_cache::InfraCache;
 use crate::infra_cache::ObjectCache;
+use editoast_schemas::infra::Direction;
+use editoast_schemas::infra::Endpoint;
+use editoast_schemas::infra::TrackEndpoint;
 use editoast_schemas::primitives::ObjectRef;
 use editoast_schemas::primitives::ObjectType;
 
-pub const OBJECT_GENERATORS: [ObjectErrorGenerator<NoContext>; 2] = [
+pub const OBJECT_GENERATORS: [ObjectErrorGenerator<NoContext>; 3] = [
     ObjectErrorGenerator::new(1, check_invalid_ref),
     ObjectErrorGenerator::new(2, check_out_of_range),
+    ObjectErrorGenerator::new(3, check_missing_detector),
 ];
 
 /// Retrieve invalid refs for signals
@@ -51,11 +57,82 @@ pub fn check_out_of_range(
     }
 }
 
+/// Check that a detector is located in front of the signal within its sight distance.
+/// The search follows the direction of the signal and goes through all the switch groups.
+pub fn check_missing_detector(
+    signal: &ObjectCache,
+    infra_cache: &InfraCache,
+    graph: &Graph,
+) -> Vec<InfraError> {
+    let signal = signal.unwrap_signal();
+    // Each item is a track, a position on it, a direction and the remaining distance to look at
+    let mut to_visit = vec![(
+        signal.track.clone(),
+        signal.position,
+        *signal.direction,
+        signal.sight_distance,
+    )];
+    let mut visited = HashSet::new();
+    while let Some((track_id, position, direction, distance)) = to_visit.pop() {
+        if !visited.insert((track_id.clone(), direction)) {
+            continue;
+        }
+        let Some(track) = infra_cache.track_sections().get(&track_id) else {
+            continue;
+        };
+        let track = track.unwrap_track_section();
+        let (begin, end) = match direction {
+            Direction::StartToStop => (position, position + distance),
+            Direction::StopToStart => (position - distance, position),
+        };
+        let has_detector = infra_cache
+            .get_track_refs_type(&track_id, ObjectType::Detector)
+            .iter()
+            .filter_map(|detector| infra_cache.detectors().get(&detector.obj_id))
+            .any(|detector| (begin..=end).contains(&detector.unwrap_detector().position));
+        if has_detector {
+            return vec![];
+        }
+
+        // Continue the search on the next track sections
+        let (remaining_distance, endpoint) = match direction {
+            Direction::StartToStop => (end - track.length, Endpoint::End),
+            Direction::StopToStart => (-begin, Endpoint::Begin),
+        };
+        if remaining_distance <= 0. {
+            continue;
+        }
+        let track_endpoint = TrackEndpoint::new(&track_id, endpoint);
+        for group in graph.get_neighbour_groups(&track_endpoint) {
+            let neighbour = graph.get_neighbour(&track_endpoint, group).unwrap();
+            let Some(neighbour_track) = infra_cache.track_sections().get(&neighbour.track.0) else {
+                continue;
+            };
+            let (position, direction) = match neighbour.endpoint {
+                Endpoint::Begin => (0., Direction::StartToStop),
+                Endpoint::End => (
+                    neighbour_track.unwrap_track_section().length,
+                    Direction::StopToStart,
+                ),
+            };
+            to_visit.push((
+                neighbour.track.0.clone(),
+                position,
+                direction,
+                remaining_distance,
+            ));
+        }
+    }
+    vec![InfraError::new_missing_signal_detector(signal)]
+}
+
 #[cfg(test)]
 mod tests {
     use super::check_invalid_ref;
+    use super::check_missing_detector;
     use super::check_out_of_range;
     use super::InfraError;
+    use crate::infra_cache::tests::create_detector_cache;
     use crate::infra_cache::tests::create_signal_cache;
     use crate::infra_cache::tests::create_small_infra_cache;
     use crate::infra_cache::Graph;
@@ -92,4 +169,35 @@ mod tests {
         let infra_error = InfraError::new_out_of_range(&signal, "position", 530., [0.0, 500.]);
         assert_eq!(infra_error, errors[0]);
     }
+
+    #[test]
+    fn missing_detector() {
+        let mut infra_cache = create_small_infra_cache();
+        let signal = create_signal_cache("S_error", "C", 100.);
+        infra_cache.add(signal.clone()).unwrap();
+        let errors = check_missing_detector(
+            &signal.clone().into(),
+            &infra_cache,
+            &Graph::load(&infra_cache),
+        );
+        assert_eq!(1, errors.len());
+        let infra_error = InfraError::new_missing_signal_detector(&signal);
+        assert_eq!(infra_error, errors[0]);
+    }
+
+    #[test]
+    fn detector_on_next_track() {
+        let mut infra_cache = create_small_infra_cache();
+        let signal = create_signal_cache("S", "A", 400.);
+        infra_cache.add(signal.clone()).unwrap();
+        infra_cache
+            .add(create_detector_cache("D2", "B", 50.))
+            .unwrap();
+        let errors = check_missing_detector(
+            &signal.clone().into(),
+            &infra_cache,
+            &Graph::load(&infra_cache),
+        );
+        assert!(errors.is_empty());
+    }
 }
@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 use std::collections::HashSet;
 
+use itertools::Itertools;
 use rangemap::RangeMap;
 
 use super::GlobalErrorGenerator;
@@ -16,9 +17,10 @@ use editoast_schemas::primitives::OSRDIdentified;
 use editoast_schemas::primitives::ObjectRef;
 use editoast_schemas::primitives::ObjectType;
 
-pub const OBJECT_GENERATORS: [ObjectErrorGenerator<NoContext>; 2] = [
+pub const OBJECT_GENERATORS: [ObjectErrorGenerator<NoContext>; 3] = [
     ObjectErrorGenerator::new(1, check_empty),
     ObjectErrorGenerator::new(2, check_speed_section_track_ranges),
+    ObjectErrorGenerator::new(3, check_non_monotonic_speed_limits),
 ];
 pub const GLOBAL_GENERATORS: [GlobalErrorGenerator<NoContext>; 1] =
     [GlobalErrorGenerator::new(check_overlapping)];
@@ -74,6 +76,26 @@ pub fn check_speed_section_track_ranges(
     infra_errors
 }
 
+/// Check that the speed limits by tag only restrict the default speed limit
+pub fn check_non_monotonic_speed_limits(
+    speed_section: &ObjectCache,
+    _: &InfraCache,
+    _: &Graph,
+) -> Vec<InfraError> {
+    let speed_section = speed_section.unwrap_speed_section();
+    let Some(speed_limit) = speed_section.speed_limit else {
+        return vec![];
+    };
+    speed_section
+        .speed_limit_by_tag
+        .iter()
+        .filter(|(_, tag_speed_limit)| tag_speed_limit.0 > speed_limit.0)
+        .map(|(tag, _)| &tag.0)
+        .sorted()
+        .map(|tag| InfraError::new_non_monotonic_speed_limit(speed_section, tag))
+        .collect()
+}
+
 fn get_directions(directions: ApplicableDirections) -> Vec<Direction> {
     match directions {
         ApplicableDirections::Both => vec![Direction::StartToStop, Direction::StopToStart],
@@ -144,6 +166,7 @@ pub fn check_overlapping(infra_cache: &InfraCache, _: &Graph) -> Vec<InfraError>
 
 #[cfg(test)]
 mod tests {
+    use super::check_non_monotonic_speed_limits;
     use super::check_speed_section_track_ranges;
     use super::InfraError;
     use crate::generated_data::error::speed_sections::check_overlapping;
@@ -189,6 +212,27 @@ mod tests {
         assert_eq!(infra_error, errors[0]);
     }
 
+    #[test]
+    fn non_monotonic_speed_limits() {
+        let mut speed_section = create_speed_section_cache("SP_error", vec![("A", 20., 220.)]);
+        speed_section.speed_limit = Some(Speed(30.));
+        speed_section
+            .speed_limit_by_tag
+            .insert("fast".into(), Speed(40.));
+        speed_section
+            .speed_limit_by_tag
+            .insert("slow".into(), Speed(20.));
+        let infra_cache = create_small_infra_cache();
+        let errors = check_non_monotonic_speed_limits(
+            &speed_section.clone().into(),
+            &infra_cache,
+            &Graph::load(&infra_cache),
+        );
+        assert_eq!(1, errors.len());
+        let infra_error = InfraError::new_non_monotonic_speed_limit(&speed_section, "fast");
+        assert_eq!(infra_error, errors[0]);
+    }
+
     #[test]
     fn overlapping_default() {
         let mut infra_cache = create_small_infra_cache();
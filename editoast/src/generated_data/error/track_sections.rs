@@ -1,14 +1,20 @@
+use std::collections::HashSet;
+
+use super::GlobalErrorGenerator;
 use super::NoContext;
 use crate::generated_data::error::ObjectErrorGenerator;
 use crate::generated_data::infra_error::InfraError;
 use crate::infra_cache::Graph;
 use crate::infra_cache::InfraCache;
 use crate::infra_cache::ObjectCache;
+use editoast_schemas::primitives::ObjectType;
 
 pub const OBJECT_GENERATORS: [ObjectErrorGenerator<NoContext>; 2] = [
     ObjectErrorGenerator::new(1, check_slope_out_of_range),
     ObjectErrorGenerator::new(1, check_curve_out_of_range),
 ];
+pub const GLOBAL_GENERATORS: [GlobalErrorGenerator<NoContext>; 1] =
+    [GlobalErrorGenerator::new(check_unreachable)];
 
 /// Retrieve slopes out of range
 pub fn check_slope_out_of_range(track: &ObjectCache, _: &InfraCache, _: &Graph) -> Vec<InfraError> {
@@ -50,12 +56,36 @@ pub fn check_curve_out_of_range(track: &ObjectCache, _: &InfraCache, _: &Graph)
     errors
 }
 
+/// Retrieve track sections that can't be reached from any buffer stop
+pub fn check_unreachable(infra_cache: &InfraCache, graph: &Graph) -> Vec<InfraError> {
+    let components = graph.track_components(infra_cache);
+    // Components containing at least a buffer stop
+    let reachable_components: HashSet<_> = infra_cache
+        .track_sections()
+        .keys()
+        .filter(|track_id| {
+            !infra_cache
+                .get_track_refs_type(track_id, ObjectType::BufferStop)
+                .is_empty()
+        })
+        .map(|track_id| components[track_id])
+        .collect();
+    infra_cache
+        .track_sections()
+        .values()
+        .map(|track| track.unwrap_track_section())
+        .filter(|track| !reachable_components.contains(&components[&track.obj_id]))
+        .map(InfraError::new_unreachable_track_section)
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use rstest::rstest;
 
     use super::check_curve_out_of_range;
     use super::check_slope_out_of_range;
+    use super::check_unreachable;
     use super::InfraError;
     use crate::infra_cache::tests::create_small_infra_cache;
     use crate::infra_cache::tests::create_track_section_cache;
@@ -114,4 +144,15 @@ mod tests {
             assert_eq!(errors.len(), 0);
         }
     }
+
+    #[test]
+    fn unreachable() {
+        let mut infra_cache = create_small_infra_cache();
+        let track = create_track_section_cache("E", 100.);
+        infra_cache.add(track.clone()).unwrap();
+        let errors = check_unreachable(&infra_cache, &Graph::load(&infra_cache));
+        assert_eq!(1, errors.len());
+        let infra_error = InfraError::new_unreachable_track_section(&track);
+        assert_eq!(infra_error, errors[0]);
+    }
 }
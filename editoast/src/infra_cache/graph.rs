@@ -2,6 +2,7 @@ use std::collections::HashMap;
 
 use crate::infra_cache::object_cache::SwitchCache;
 use crate::infra_cache::InfraCache;
+use editoast_schemas::infra::Endpoint;
 use editoast_schemas::infra::TrackEndpoint;
 use editoast_schemas::primitives::Identifier;
 
@@ -82,6 +83,31 @@ impl<'a> Graph<'a> {
             .map(|groups| groups.keys().cloned().collect())
             .unwrap_or_default()
     }
+
+    /// Compute the connected components of the track sections of an infra.
+    /// Returns for each track section the index of the component it belongs to.
+    pub fn track_components(&self, infra_cache: &InfraCache) -> HashMap<String, usize> {
+        let mut components = HashMap::new();
+        for (component, track_id) in infra_cache.track_sections().keys().enumerate() {
+            if components.contains_key(track_id) {
+                continue;
+            }
+            let mut to_visit = vec![track_id.clone()];
+            while let Some(track_id) = to_visit.pop() {
+                if components.contains_key(&track_id) {
+                    continue;
+                }
+                for endpoint in [Endpoint::Begin, Endpoint::End] {
+                    let track_endpoint = TrackEndpoint::new(&track_id, endpoint);
+                    if let Some(groups) = self.links.get(&track_endpoint) {
+                        to_visit.extend(groups.values().map(|neighbour| neighbour.track.0.clone()));
+                    }
+                }
+                components.insert(track_id, component);
+            }
+        }
+        components
+    }
 }
 
 #[cfg(test)]
@@ -91,6 +117,7 @@ mod tests {
     use super::Graph;
     use crate::infra_cache::tests::create_small_infra_cache;
     use crate::infra_cache::tests::create_track_endpoint;
+    use crate::infra_cache::tests::create_track_section_cache;
     use crate::infra_cache::InfraCache;
     use editoast_schemas::infra::Endpoint;
     use editoast_schemas::primitives::Identifier;
@@ -175,4 +202,20 @@ mod tests {
         assert_eq!(groups.len(), 1);
         assert!(groups.contains(&&"LINK".into()));
     }
+
+    #[test]
+    fn track_components() {
+        let mut infra_cache = create_small_infra_cache();
+        infra_cache
+            .add(create_track_section_cache("E", 500.))
+            .unwrap();
+        let graph = Graph::load(&infra_cache);
+
+        let components = graph.track_components(&infra_cache);
+        assert_eq!(components.len(), 5);
+        for track in ["B", "C", "D"] {
+            assert_eq!(components[track], components["A"]);
+        }
+        assert_ne!(components["E"], components["A"]);
+    }
 }
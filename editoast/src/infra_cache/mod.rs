@@ -24,10 +24,12 @@ use editoast_schemas::infra::DirectionalTrackRange;
 use editoast_schemas::infra::DoubleSlipSwitch;
 use editoast_schemas::infra::Electrification;
 use editoast_schemas::infra::Endpoint;
+use editoast_schemas::infra::InfraObject;
 use editoast_schemas::infra::Link;
 use editoast_schemas::infra::NeutralSection;
 use editoast_schemas::infra::OperationalPointPart;
 use editoast_schemas::infra::PointSwitch;
+use editoast_schemas::infra::RailJson;
 use editoast_schemas::infra::Route;
 use editoast_schemas::infra::RoutePath;
 use editoast_schemas::infra::SingleSlipSwitch;
@@ -285,6 +287,7 @@ impl From<TrackQueryable> for TrackSectionCache {
     fn from(track: TrackQueryable) -> Self {
         let geo: Geometry =
             serde_json::from_str(&track.geo).expect("invalid track section geometry");
+        let (begin_geo, end_geo) = TrackSectionCache::extremities_geo(&geo);
         Self {
             obj_id: track.obj_id,
             length: track.length,
@@ -293,6 +296,8 @@ impl From<TrackQueryable> for TrackSectionCache {
             line_code: track.line_code,
             bbox_geo: BoundingBox::from_geometry(geo)
                 .expect("tracksections' geometry must be LineStrings"),
+            begin_geo,
+            end_geo,
         }
     }
 }
@@ -443,7 +448,7 @@ impl InfraCache {
 
         // Load signal tracks references
         sql_query(
-            "SELECT obj_id, data->>'track' AS track, (data->>'position')::float AS position, data->'logical_signals' as logical_signals FROM infra_object_signal WHERE infra_id = $1")
+            "SELECT obj_id, data->>'track' AS track, (data->>'position')::float AS position, data->'direction' AS direction, (data->>'sight_distance')::float AS sight_distance, data->'logical_signals' as logical_signals FROM infra_object_signal WHERE infra_id = $1")
         .bind::<BigInt, _>(infra_id)
         .load::<SignalCache>(conn).await?.into_iter().try_for_each(|signal|
             infra_cache.add(signal)
@@ -521,6 +526,29 @@ impl InfraCache {
         Ok(infra_cache)
     }
 
+    /// Builds the cache of an infra that isn't stored in the database
+    pub fn from_railjson(railjson: &RailJson) -> Result<InfraCache> {
+        Self::from_objects(railjson.clone().into_objects())
+    }
+
+    /// Builds the cache of the given infra objects, along with the builtin switch types
+    pub fn from_objects(objects: impl IntoIterator<Item = InfraObject>) -> Result<InfraCache> {
+        let operations = objects
+            .into_iter()
+            .map(|object| CacheOperation::Create(ObjectCache::from(object)))
+            .collect::<Vec<_>>();
+        let mut infra_cache = Self::default();
+        infra_cache.apply_operations(&operations)?;
+
+        // Add builtin switch nodes
+        infra_cache.add::<SwitchType>(Link.into())?;
+        infra_cache.add::<SwitchType>(PointSwitch.into())?;
+        infra_cache.add::<SwitchType>(Crossing.into())?;
+        infra_cache.add::<SwitchType>(SingleSlipSwitch.into())?;
+        infra_cache.add::<SwitchType>(DoubleSlipSwitch.into())?;
+        Ok(infra_cache)
+    }
+
     /// This function tries to get the infra from the cache, if it fails, it loads it from the database
     /// If the infra is not found in the database, it returns `None`
     pub async fn get_or_load<'a>(
@@ -879,6 +907,7 @@ pub enum InfraCacheEditoastError {
 #[cfg(test)]
 pub mod tests {
     use chashmap::CHashMap;
+    use diesel_json::Json as DieselJson;
     use editoast_schemas::infra::BufferStop;
     use editoast_schemas::infra::Detector;
     use editoast_schemas::infra::Waypoint;
@@ -904,6 +933,7 @@ pub mod tests {
     use editoast_schemas::infra::Electrification;
     use editoast_schemas::infra::Endpoint;
     use editoast_schemas::infra::OperationalPoint;
+    use editoast_schemas::infra::RailJson;
     use editoast_schemas::infra::Route;
     use editoast_schemas::infra::Signal;
     use editoast_schemas::infra::SpeedSection;
@@ -917,6 +947,20 @@ pub mod tests {
     use editoast_schemas::primitives::NonBlankString;
     use editoast_schemas::primitives::OSRDIdentified;
 
+    #[test]
+    fn cache_from_railjson() {
+        let railjson = RailJson {
+            track_sections: vec![TrackSection::default()],
+            detectors: vec![Detector::default()],
+            ..Default::default()
+        };
+        let infra_cache = InfraCache::from_railjson(&railjson).unwrap();
+
+        assert_eq!(infra_cache.track_sections().len(), 1);
+        assert_eq!(infra_cache.detectors().len(), 1);
+        assert!(infra_cache.switch_types().contains_key("point_switch"));
+    }
+
     #[rstest]
     async fn load_track_section() {
         let db_pool = DbConnectionPoolV2::for_tests();
@@ -1126,6 +1170,8 @@ pub mod tests {
             obj_id: obj_id.as_ref().into(),
             track: track.as_ref().into(),
             position,
+            direction: DieselJson(Direction::StartToStop),
+            sight_distance: 400.,
             logical_signals: Default::default(),
         }
     }
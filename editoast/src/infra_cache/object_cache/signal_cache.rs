@@ -9,6 +9,7 @@ use editoast_schemas::primitives::ObjectType;
 
 use crate::infra_cache::Cache;
 use crate::infra_cache::ObjectCache;
+use editoast_schemas::infra::Direction;
 use editoast_schemas::infra::LogicalSignal;
 use editoast_schemas::infra::Signal;
 
@@ -25,6 +26,12 @@ pub struct SignalCache {
     pub position: f64,
     #[derivative(Hash = "ignore", PartialEq = "ignore")]
     #[diesel(sql_type = Jsonb)]
+    pub direction: DieselJson<Direction>,
+    #[derivative(Hash = "ignore", PartialEq = "ignore")]
+    #[diesel(sql_type = Double)]
+    pub sight_distance: f64,
+    #[derivative(Hash = "ignore", PartialEq = "ignore")]
+    #[diesel(sql_type = Jsonb)]
     pub logical_signals: DieselJson<Vec<LogicalSignal>>,
 }
 
@@ -55,12 +62,16 @@ impl SignalCache {
         obj_id: String,
         track: String,
         position: f64,
+        direction: Direction,
+        sight_distance: f64,
         logical_signals: Vec<LogicalSignal>,
     ) -> Self {
         Self {
             obj_id,
             track,
             position,
+            direction: DieselJson(direction),
+            sight_distance,
             logical_signals: DieselJson(logical_signals),
         }
     }
@@ -68,6 +79,13 @@ impl SignalCache {
 
 impl From<Signal> for SignalCache {
     fn from(sig: Signal) -> Self {
-        Self::new(sig.id.0, sig.track.0, sig.position, sig.logical_signals)
+        Self::new(
+            sig.id.0,
+            sig.track.0,
+            sig.position,
+            sig.direction,
+            sig.sight_distance,
+            sig.logical_signals,
+        )
     }
 }
@@ -6,6 +6,8 @@ use editoast_schemas::infra::TrackEndpoint;
 use editoast_schemas::primitives::OSRDIdentified;
 use editoast_schemas::primitives::OSRDTyped;
 use editoast_schemas::primitives::ObjectType;
+use geos::geojson::Geometry;
+use geos::geojson::Value;
 
 use crate::infra_cache::Cache;
 use crate::infra_cache::ObjectCache;
@@ -26,6 +28,12 @@ pub struct TrackSectionCache {
     pub curves: Vec<Curve>,
     #[derivative(Hash = "ignore", PartialEq = "ignore")]
     pub bbox_geo: BoundingBox,
+    /// Coordinates of the beginning of the track
+    #[derivative(Hash = "ignore", PartialEq = "ignore")]
+    pub begin_geo: (f64, f64),
+    /// Coordinates of the end of the track
+    #[derivative(Hash = "ignore", PartialEq = "ignore")]
+    pub end_geo: (f64, f64),
 }
 
 impl OSRDTyped for TrackSectionCache {
@@ -54,12 +62,35 @@ impl TrackSectionCache {
             track: self.obj_id.clone().into(),
         }
     }
+
+    /// Returns the coordinates of an endpoint of the track
+    pub fn get_endpoint_geo(&self, endpoint: Endpoint) -> (f64, f64) {
+        match endpoint {
+            Endpoint::Begin => self.begin_geo,
+            Endpoint::End => self.end_geo,
+        }
+    }
+
+    /// Returns the coordinates of the first and last points of a track section geometry
+    pub fn extremities_geo(geo: &Geometry) -> ((f64, f64), (f64, f64)) {
+        let Value::LineString(points) = &geo.value else {
+            panic!("tracksections' geometry must be LineStrings");
+        };
+        let point = |point: Option<&Vec<f64>>| match point.map(Vec::as_slice) {
+            Some([lon, lat, ..]) => (*lon, *lat),
+            _ => (0., 0.),
+        };
+        (point(points.first()), point(points.last()))
+    }
 }
 
 impl From<TrackSection> for TrackSectionCache {
     fn from(track: TrackSection) -> Self {
+        let (begin_geo, end_geo) = Self::extremities_geo(&track.geo);
         TrackSectionCache {
             bbox_geo: track.geo_bbox(),
+            begin_geo,
+            end_geo,
             obj_id: track.id.0,
             length: track.length,
             curves: track.curves,
@@ -2,20 +2,33 @@ pub mod create;
 mod delete;
 mod update;
 
+use std::collections::HashMap;
 use std::ops::Deref as _;
 
+use diesel::sql_query;
+use diesel::sql_types::BigInt;
+use diesel::sql_types::Jsonb;
+use diesel::sql_types::Text;
+use diesel::OptionalExtension;
+use diesel::QueryableByName;
+use diesel_async::RunQueryDsl;
 use editoast_derive::EditoastError;
+use editoast_schemas::primitives::OSRDIdentified as _;
 use editoast_schemas::primitives::OSRDObject as _;
 use json_patch::Patch;
 use serde::Deserialize;
 use serde::Serialize;
+use serde_json::json;
+use serde_json::Value;
 use thiserror::Error;
 pub use update::UpdateOperation;
 use utoipa::ToSchema;
 
 pub use self::delete::DeleteOperation;
 use crate::error::Result;
+use crate::infra_cache::CacheOperationError;
 use crate::infra_cache::ObjectCache;
+use crate::modelsv2::get_table;
 use crate::modelsv2::DbConnection;
 use editoast_schemas::infra::InfraObject;
 use editoast_schemas::primitives::ObjectRef;
@@ -137,6 +150,19 @@ impl CacheOperation {
 }
 
 impl Operation {
+    /// Returns the reference of the object targeted by the operation
+    pub fn object_ref(&self) -> ObjectRef {
+        match self {
+            Operation::Create(infra_object) => infra_object.get_ref(),
+            Operation::Update(UpdateOperation {
+                obj_id, obj_type, ..
+            })
+            | Operation::Delete(DeleteOperation { obj_id, obj_type }) => {
+                ObjectRef::new(*obj_type, obj_id)
+            }
+        }
+    }
+
     pub async fn apply(
         &self,
         infra_id: i64,
@@ -157,6 +183,195 @@ impl Operation {
             }
         }
     }
+
+    /// Applies the operation and also returns the operation that reverts it
+    ///
+    /// The state of the targeted object is read before being modified in order to build the inverse operation.
+    pub async fn apply_and_invert(
+        &self,
+        infra_id: i64,
+        conn: &mut DbConnection,
+    ) -> Result<(Option<InfraObject>, Operation)> {
+        let previous = match self {
+            Operation::Create(_) => None,
+            Operation::Update(UpdateOperation {
+                obj_id, obj_type, ..
+            })
+            | Operation::Delete(DeleteOperation { obj_id, obj_type }) => {
+                Some(load_object_data(infra_id, *obj_type, obj_id, conn).await?)
+            }
+        };
+        let railjson = self.apply(infra_id, conn).await?;
+        let inverse = self.invert(previous)?;
+        Ok((railjson, inverse))
+    }
+
+    /// Builds the operation that reverts this one
+    ///
+    /// `previous` is the RailJSON data of the targeted object before this operation is applied.
+    /// It's ignored for creations and must be provided for updates and deletions.
+    pub fn invert(&self, previous: Option<Value>) -> Result<Operation> {
+        let inverse = match (self, previous) {
+            (Operation::Create(infra_object), _) => {
+                Operation::Delete(infra_object.get_ref().into())
+            }
+            (Operation::Update(update), Some(previous)) => {
+                let mut next = previous.clone();
+                json_patch::patch(&mut next, &update.railjson_patch).map_err(|err| {
+                    OperationError::InvalidPatch {
+                        error: err.to_string(),
+                    }
+                })?;
+                Operation::Update(UpdateOperation {
+                    obj_id: update.obj_id.clone(),
+                    obj_type: update.obj_type,
+                    railjson_patch: json_patch::diff(&next, &previous),
+                })
+            }
+            (Operation::Delete(delete), Some(previous)) => {
+                let infra_object = serde_json::from_value(json!({
+                    "obj_type": delete.obj_type,
+                    "railjson": previous,
+                }))?;
+                Operation::Create(Box::new(infra_object))
+            }
+            (Operation::Update(UpdateOperation { obj_id, .. }), None)
+            | (Operation::Delete(DeleteOperation { obj_id, .. }), None) => {
+                return Err(OperationError::MissingPreviousState {
+                    obj_id: obj_id.clone(),
+                }
+                .into());
+            }
+        };
+        Ok(inverse)
+    }
+}
+
+/// Computes the outcome of a batch of operations without modifying the infra
+///
+/// The objects targeted by the batch are read from the infra, taking the previous operations
+/// of the batch into account. Returns the resulting objects along with the matching cache operations.
+pub async fn simulate_operations(
+    operations: &[Operation],
+    infra_id: i64,
+    conn: &mut DbConnection,
+) -> Result<(Vec<InfraObject>, Vec<CacheOperation>)> {
+    // The current data of the objects already targeted by the batch, `None` once deleted
+    let mut objects: HashMap<ObjectRef, Option<Value>> = HashMap::new();
+    let mut railjsons = vec![];
+    let mut cache_operations = vec![];
+    for operation in operations {
+        match operation {
+            Operation::Create(infra_object) => {
+                if infra_object.get_id().is_empty() {
+                    return Err(OperationError::EmptyId.into());
+                }
+                let infra_object = infra_object.deref().clone();
+                let object_ref = infra_object.get_ref();
+                let exists = match objects.get(&object_ref) {
+                    Some(data) => data.is_some(),
+                    None => {
+                        find_object_data(infra_id, object_ref.obj_type, &object_ref.obj_id, conn)
+                            .await?
+                            .is_some()
+                    }
+                };
+                if exists {
+                    return Err(CacheOperationError::DuplicateIdsProvided {
+                        obj_type: object_ref.obj_type.to_string(),
+                        obj_id: object_ref.obj_id,
+                    }
+                    .into());
+                }
+                objects.insert(object_ref, Some(infra_object.get_data()));
+                railjsons.push(infra_object.clone());
+                cache_operations.push(CacheOperation::Create(ObjectCache::from(infra_object)));
+            }
+            Operation::Update(update) => {
+                let object_ref = ObjectRef::new(update.obj_type, &update.obj_id);
+                let data = match objects.get(&object_ref) {
+                    Some(data) => data.clone(),
+                    None => Some(
+                        load_object_data(infra_id, update.obj_type, &update.obj_id, conn).await?,
+                    ),
+                };
+                let Some(data) = data else {
+                    return Err(OperationError::ObjectNotFound {
+                        obj_id: update.obj_id.clone(),
+                        infra_id,
+                    }
+                    .into());
+                };
+                let infra_object = update.patch_data(data)?;
+                objects.insert(object_ref, Some(infra_object.get_data()));
+                railjsons.push(infra_object.clone());
+                cache_operations.push(CacheOperation::Update(ObjectCache::from(infra_object)));
+            }
+            Operation::Delete(delete) => {
+                let object_ref = ObjectRef::from(delete.clone());
+                let exists = match objects.get(&object_ref) {
+                    Some(data) => data.is_some(),
+                    None => {
+                        load_object_data(infra_id, delete.obj_type, &delete.obj_id, conn).await?;
+                        true
+                    }
+                };
+                if !exists {
+                    return Err(OperationError::ObjectNotFound {
+                        obj_id: delete.obj_id.clone(),
+                        infra_id,
+                    }
+                    .into());
+                }
+                objects.insert(object_ref.clone(), None);
+                cache_operations.push(CacheOperation::Delete(object_ref));
+            }
+        }
+    }
+    Ok((railjsons, cache_operations))
+}
+
+/// Loads the raw RailJSON data of an infra object
+pub async fn load_object_data(
+    infra_id: i64,
+    obj_type: ObjectType,
+    obj_id: &str,
+    conn: &mut DbConnection,
+) -> Result<Value> {
+    find_object_data(infra_id, obj_type, obj_id, conn)
+        .await?
+        .ok_or_else(|| {
+            OperationError::ObjectNotFound {
+                obj_id: obj_id.to_owned(),
+                infra_id,
+            }
+            .into()
+        })
+}
+
+/// Loads the raw RailJSON data of an infra object, `None` if it doesn't exist
+async fn find_object_data(
+    infra_id: i64,
+    obj_type: ObjectType,
+    obj_id: &str,
+    conn: &mut DbConnection,
+) -> Result<Option<Value>> {
+    #[derive(QueryableByName)]
+    struct DataObject {
+        #[diesel(sql_type = Jsonb)]
+        data: Value,
+    }
+
+    let object = sql_query(format!(
+        "SELECT data FROM {} WHERE infra_id = $1 AND obj_id = $2",
+        get_table(&obj_type)
+    ))
+    .bind::<BigInt, _>(infra_id)
+    .bind::<Text, _>(obj_id)
+    .get_result::<DataObject>(conn)
+    .await
+    .optional()?;
+    Ok(object.map(|object| object.data))
 }
 
 pub fn patch_infra_object(infra_object: &InfraObject, json_patch: &Patch) -> Result<InfraObject> {
@@ -232,4 +447,90 @@ enum OperationError {
     ModifyId,
     #[error("A Json Patch error occurred: '{error}'")]
     InvalidPatch { error: String },
+    #[error("The previous state of object '{obj_id}' is required to revert its edition")]
+    #[editoast_error(status = 500)]
+    MissingPreviousState { obj_id: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use editoast_schemas::infra::TrackSection;
+    use json_patch::PatchOperation;
+    use json_patch::ReplaceOperation;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn invert_create() {
+        let track = TrackSection {
+            id: "track".into(),
+            ..Default::default()
+        };
+        let creation = Operation::Create(Box::new(InfraObject::TrackSection { railjson: track }));
+        assert_eq!(
+            creation.invert(None).unwrap(),
+            Operation::Delete(DeleteOperation {
+                obj_id: "track".to_string(),
+                obj_type: ObjectType::TrackSection,
+            })
+        );
+    }
+
+    #[test]
+    fn invert_update() {
+        let previous = serde_json::to_value(TrackSection {
+            id: "track".into(),
+            length: 10.0,
+            ..Default::default()
+        })
+        .unwrap();
+        let railjson_patch = Patch(vec![PatchOperation::Replace(ReplaceOperation {
+            path: "/length".to_string().parse().unwrap(),
+            value: json!(20.0),
+        })]);
+        let update = Operation::Update(UpdateOperation {
+            obj_id: "track".to_string(),
+            obj_type: ObjectType::TrackSection,
+            railjson_patch: railjson_patch.clone(),
+        });
+
+        let Operation::Update(inverse) = update.invert(Some(previous.clone())).unwrap() else {
+            panic!("the inverse of an update should be an update");
+        };
+
+        let mut data = previous.clone();
+        json_patch::patch(&mut data, &railjson_patch).unwrap();
+        assert_eq!(data["length"], json!(20.0));
+        json_patch::patch(&mut data, &inverse.railjson_patch).unwrap();
+        assert_eq!(data, previous);
+    }
+
+    #[test]
+    fn invert_delete() {
+        let track = TrackSection {
+            id: "track".into(),
+            length: 10.0,
+            ..Default::default()
+        };
+        let deletion = Operation::Delete(DeleteOperation {
+            obj_id: "track".to_string(),
+            obj_type: ObjectType::TrackSection,
+        });
+        assert_eq!(
+            deletion
+                .invert(Some(serde_json::to_value(&track).unwrap()))
+                .unwrap(),
+            Operation::Create(Box::new(InfraObject::TrackSection { railjson: track }))
+        );
+    }
+
+    #[test]
+    fn invert_delete_without_previous_state() {
+        let deletion = Operation::Delete(DeleteOperation {
+            obj_id: "track".to_string(),
+            obj_type: ObjectType::TrackSection,
+        });
+        assert!(deletion.invert(None).is_err());
+    }
 }
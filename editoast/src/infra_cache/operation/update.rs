@@ -77,6 +77,11 @@ impl UpdateOperation {
             Err(err) => Err(err.into()),
         }
     }
+
+    /// Applies the patch to the given RailJSON data without saving the resulting object
+    pub fn patch_data(&self, data: Value) -> Result<InfraObject> {
+        DataObject { data }.patch_and_check(self)
+    }
 }
 
 #[derive(QueryableByName)]
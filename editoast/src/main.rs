@@ -12,10 +12,14 @@ mod models;
 mod modelsv2;
 mod redis_utils;
 mod tables;
+mod timetable_exchange;
 mod views;
 
 use crate::core::CoreClient;
 use crate::error::InternalError;
+use crate::generated_data::generate_infra_errors;
+use crate::generated_data::infra_error::InfraError;
+use crate::modelsv2::infra::InfraDiff;
 use crate::modelsv2::DbConnectionPool;
 use crate::modelsv2::DbConnectionPoolV2;
 use crate::modelsv2::Infra;
@@ -30,11 +34,13 @@ use clap::Parser;
 use client::PostgresConfig;
 use client::{
     ClearArgs, Client, Color, Commands, DeleteProfileSetArgs, ElectricalProfilesCommands,
-    ExportTimetableArgs, GenerateArgs, ImportProfileSetArgs, ImportRailjsonArgs,
-    ImportRollingStockArgs, ImportTimetableArgs, InfraCloneArgs, InfraCommands, ListProfileSetArgs,
-    MakeMigrationArgs, RedisConfig, RefreshArgs, RunserverArgs, SearchCommands, TimetablesCommands,
+    ExportLayersArgs, ExportTimetableArgs, GenerateArgs, ImportProfileSetArgs, ImportRailjsonArgs,
+    ImportRollingStockArgs, ImportTimetableArgs, InfraCloneArgs, InfraCommands, InfraDiffArgs,
+    ListProfileSetArgs, MakeMigrationArgs, OsmToRailjsonArgs, RedisConfig, RefreshArgs,
+    RunserverArgs, SearchCommands, TimetableFormat, TimetablesCommands,
 };
 use editoast_schemas::infra::ElectricalProfileSetData;
+use editoast_schemas::primitives::BoundingBox;
 use editoast_schemas::rolling_stock::RollingStock;
 use editoast_schemas::train_schedule::TrainScheduleBase;
 use modelsv2::{
@@ -44,6 +50,9 @@ use modelsv2::{
 use modelsv2::{Changeset, RollingStockModel};
 use opentelemetry_datadog::DatadogPropagator;
 use opentelemetry_sdk::propagation::TraceContextPropagator;
+use osm_to_railjson::ImportReport;
+use osm_to_railjson::OsmImportConfig;
+use timetable_exchange::{gtfs, netex};
 use views::v2::train_schedule::{TrainScheduleForm, TrainScheduleResult};
 
 use crate::modelsv2::DbConnection;
@@ -51,7 +60,6 @@ use colored::*;
 use diesel::sql_query;
 use diesel_async::RunQueryDsl;
 use diesel_json::Json as DieselJson;
-use editoast_schemas::infra::RailJson;
 use infra_cache::InfraCache;
 use map::MapLayers;
 use modelsv2::electrical_profiles::ElectricalProfileSet;
@@ -60,6 +68,7 @@ use opentelemetry::KeyValue;
 use opentelemetry_otlp::WithExportConfig as _;
 use opentelemetry_sdk::Resource;
 pub use redis_utils::{RedisClient, RedisConnection};
+use serde::Serialize;
 use std::error::Error;
 use std::fs::File;
 use std::io::{BufReader, IsTerminal};
@@ -192,8 +201,12 @@ async fn run() -> Result<(), Box<dyn Error + Send + Sync>> {
     match client.command {
         Commands::Runserver(args) => runserver(args, pg_config, redis_config).await,
         Commands::ImportRollingStock(args) => import_rolling_stock(args, db_pool.pool_v1()).await,
-        Commands::OsmToRailjson(args) => {
-            osm_to_railjson::osm_to_railjson(args.osm_pbf_in, args.railjson_out)
+        Commands::OsmToRailjson(args) => convert_osm_to_railjson(args).await,
+        Commands::RailmlToRailjson(args) => {
+            railml::railml_to_railjson(args.railml_in, args.railjson_out)
+        }
+        Commands::RailjsonToRailml(args) => {
+            railml::railjson_to_railml(args.railjson_in, args.railml_out)
         }
         Commands::Openapi => {
             generate_openapi();
@@ -220,15 +233,19 @@ async fn run() -> Result<(), Box<dyn Error + Send + Sync>> {
         },
         Commands::Infra(subcommand) => match subcommand {
             InfraCommands::Clone(args) => clone_infra(args, db_pool.pool_v1()).await,
+            InfraCommands::Diff(args) => diff_infra(args, db_pool.pool_v1()).await,
             InfraCommands::Clear(args) => clear_infra(args, db_pool.pool_v1(), redis_config).await,
             InfraCommands::Generate(args) => {
                 generate_infra(args, db_pool.pool_v1(), redis_config).await
             }
             InfraCommands::ImportRailjson(args) => import_railjson(args, db_pool.pool_v1()).await,
+            InfraCommands::ExportLayers(args) => export_layers(args, db_pool.pool_v1()).await,
         },
         Commands::Timetables(subcommand) => match subcommand {
             TimetablesCommands::Import(args) => trains_import(args, db_pool.pool_v1()).await,
-            TimetablesCommands::Export(args) => trains_export(args, db_pool.pool_v1()).await,
+            TimetablesCommands::Export(args) => {
+                trains_export(args, db_pool.pool_v1(), redis_config).await
+            }
         },
     }
 }
@@ -236,6 +253,7 @@ async fn run() -> Result<(), Box<dyn Error + Send + Sync>> {
 async fn trains_export(
     args: ExportTimetableArgs,
     db_pool: Arc<DbConnectionPool>,
+    redis_config: RedisConfig,
 ) -> Result<(), Box<dyn Error + Send + Sync>> {
     let conn = &mut db_pool.get().await?;
     let train_ids = match TimetableWithTrains::retrieve(conn, args.id).await? {
@@ -251,13 +269,42 @@ async fn trains_export(
 
     assert!(missing.is_empty());
 
-    let train_schedules: Vec<TrainScheduleBase> = train_schedules
-        .into_iter()
-        .map(|ts| Into::<TrainScheduleResult>::into(ts).train_schedule)
-        .collect();
+    match args.format {
+        TimetableFormat::Json => {
+            let train_schedules: Vec<TrainScheduleBase> = train_schedules
+                .into_iter()
+                .map(|ts| Into::<TrainScheduleResult>::into(ts).train_schedule)
+                .collect();
 
-    let file = File::create(args.path.clone())?;
-    serde_json::to_writer_pretty(file, &train_schedules)?;
+            let file = File::create(args.path.clone())?;
+            serde_json::to_writer_pretty(file, &train_schedules)?;
+        }
+        TimetableFormat::Gtfs => {
+            let infra_id = args.infra_id.expect("required by the GTFS format");
+            let Some(infra) = Infra::retrieve(conn, infra_id).await? else {
+                let error = CliError::new(1, format!("❌ Infra not found, id: {infra_id}"));
+                return Err(Box::new(error));
+            };
+            let redis_client = Arc::new(RedisClient::new(redis_config)?);
+            let core_client = Arc::new(CoreClient::new_direct(
+                args.backend_url.parse()?,
+                args.backend_token.clone(),
+            ));
+            timetable_exchange::export_gtfs(
+                conn,
+                redis_client,
+                core_client,
+                &infra,
+                train_schedules,
+                &args.path,
+            )
+            .await?;
+        }
+        TimetableFormat::Netex => {
+            let error = CliError::new(1, "❌ Timetables can't be exported to NeTEx");
+            return Err(Box::new(error));
+        }
+    }
 
     println!(
         "✅ Train schedules exported to {0}",
@@ -271,18 +318,24 @@ async fn trains_import(
     args: ImportTimetableArgs,
     db_pool: Arc<DbConnectionPool>,
 ) -> Result<(), Box<dyn Error + Send + Sync>> {
-    let train_file = match File::open(args.path.clone()) {
-        Ok(file) => file,
-        Err(e) => {
-            let error = CliError::new(
-                1,
-                format!("❌ Could not open file {:?} ({:?})", args.path, e),
-            );
-            return Err(Box::new(error));
+    let conn = &mut db_pool.get().await?;
+    let train_schedules: Vec<TrainScheduleBase> = match args.format {
+        TimetableFormat::Json => {
+            let train_file = match File::open(args.path.clone()) {
+                Ok(file) => file,
+                Err(e) => {
+                    let error = CliError::new(
+                        1,
+                        format!("❌ Could not open file {:?} ({:?})", args.path, e),
+                    );
+                    return Err(Box::new(error));
+                }
+            };
+            serde_json::from_reader(BufReader::new(train_file))?
         }
+        TimetableFormat::Gtfs | TimetableFormat::Netex => read_trips(conn, &args).await?,
     };
 
-    let conn = &mut db_pool.get().await?;
     let timetable = match args.id {
         Some(timetable) => match Timetable::retrieve(conn, timetable).await? {
             Some(timetable) => timetable,
@@ -297,8 +350,6 @@ async fn trains_import(
         }
     };
 
-    let train_schedules: Vec<TrainScheduleBase> =
-        serde_json::from_reader(BufReader::new(train_file))?;
     let changesets: Vec<TrainScheduleChangeset> = train_schedules
         .into_iter()
         .map(|train_schedule| {
@@ -320,6 +371,31 @@ async fn trains_import(
     Ok(())
 }
 
+/// Reads the trips of a GTFS feed or a NeTEx file as train schedules
+async fn read_trips(
+    conn: &mut DbConnection,
+    args: &ImportTimetableArgs,
+) -> Result<Vec<TrainScheduleBase>, Box<dyn Error + Send + Sync>> {
+    let (Some(infra_id), Some(rolling_stock), Some(date)) =
+        (args.infra_id, &args.rolling_stock, args.date)
+    else {
+        let error = CliError::new(
+            1,
+            "❌ An infra, a rolling stock and a date are needed to import GTFS and NeTEx timetables",
+        );
+        return Err(Box::new(error));
+    };
+    if !Infra::exists(conn, infra_id).await? {
+        let error = CliError::new(1, format!("❌ Infra not found, id: {infra_id}"));
+        return Err(Box::new(error));
+    }
+    let trips = match args.format {
+        TimetableFormat::Gtfs => gtfs::read_gtfs(&args.path, date, args.utc_offset)?,
+        _ => netex::read_netex(&fs::read_to_string(&args.path)?, date, args.utc_offset)?,
+    };
+    Ok(timetable_exchange::import_trips(conn, infra_id, trips, rolling_stock).await?)
+}
+
 fn log_received_request(req: &ServiceRequest) {
     let request_line = if req.query_string().is_empty() {
         format!("{} {} {:?}", req.method(), req.path(), req.version())
@@ -378,6 +454,16 @@ async fn runserver(
     // Setup shared states
     let infra_caches = Data::new(CHashMap::<i64, InfraCache>::default());
 
+    // Resume the simulation jobs left behind by a stopped instance
+    tokio::spawn(views::v2::timetable::resume_stale_jobs(
+        db_pool_v2.clone().into_inner(),
+        Arc::new(redis.clone()),
+        Arc::new(CoreClient::new_direct(
+            args.backend_url.parse().expect("invalid backend_url value"),
+            args.backend_token.clone(),
+        )),
+    ));
+
     let server = HttpServer::new(move || {
         // Build CORS
         let cors = {
@@ -602,6 +688,33 @@ async fn clone_infra(
     Ok(())
 }
 
+async fn diff_infra(
+    args: InfraDiffArgs,
+    db_pool: Arc<DbConnectionPool>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let conn = &mut db_pool.get().await?;
+    let mut snapshots = vec![];
+    for (infra_id, revision) in [
+        (args.base_id, args.base_revision),
+        (args.other_id, args.other_revision),
+    ] {
+        let infra = Infra::retrieve(conn, infra_id as i64)
+            .await?
+            .ok_or_else(|| {
+                CliError::new(1, format!("❌ Infrastructure not found, ID: {infra_id}"))
+            })?;
+        snapshots.push(infra.snapshot(conn, revision).await?);
+    }
+    let diff = InfraDiff::new(&snapshots[0], &snapshots[1]);
+    let output = if args.operations {
+        serde_json::to_string_pretty(&diff.into_operations())?
+    } else {
+        serde_json::to_string_pretty(&diff)?
+    };
+    println!("{output}");
+    Ok(())
+}
+
 async fn import_railjson(
     args: ImportRailjsonArgs,
     db_pool: Arc<DbConnectionPool>,
@@ -625,10 +738,13 @@ async fn import_railjson(
     let infra = Infra::changeset()
         .name(args.infra_name)
         .last_railjson_version();
-    let railjson: RailJson = serde_json::from_reader(BufReader::new(railjson_file))?;
 
     println!("🍞 Importing infra {infra_name}");
-    let mut infra = infra.persist(railjson, db_pool.clone()).await?;
+    let mut infra = infra
+        .persist_stream(railjson_file, db_pool.clone(), |object_type, count| {
+            println!("🛤  {count} objects of type {object_type} imported");
+        })
+        .await?;
 
     let mut conn = db_pool.get().await?;
     infra
@@ -649,6 +765,83 @@ async fn import_railjson(
     Ok(())
 }
 
+async fn export_layers(
+    args: ExportLayersArgs,
+    db_pool: Arc<DbConnectionPool>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let conn = &mut db_pool.get().await?;
+    let infra = Infra::retrieve(conn, args.infra_id as i64)
+        .await?
+        .ok_or_else(|| {
+            CliError::new(
+                1,
+                format!("❌ Infrastructure not found, ID: {}", args.infra_id),
+            )
+        })?;
+    let bbox = args
+        .bbox
+        .map(|bbox| BoundingBox((bbox[0], bbox[1]), (bbox[2], bbox[3])));
+    let layers = map::export_layers(conn, &MapLayers::parse(), infra.id, bbox.as_ref()).await?;
+
+    let is_geopackage = args
+        .output
+        .extension()
+        .is_some_and(|extension| extension == "gpkg");
+    if is_geopackage {
+        map::write_geopackage(&args.output, &layers)?;
+    } else {
+        fs::create_dir_all(&args.output)?;
+        for (layer_name, layer) in &layers {
+            let file = File::create(args.output.join(format!("{layer_name}.geojson")))?;
+            serde_json::to_writer(file, layer)?;
+        }
+    }
+    println!(
+        "✅ {} layers of infra {}[{}] exported to {}",
+        layers.len(),
+        infra.name.bold(),
+        infra.id,
+        args.output.display()
+    );
+    Ok(())
+}
+
+/// Report of an OpenStreetMap conversion, with the errors the infra would have once imported
+#[derive(Serialize)]
+struct OsmImportReport {
+    #[serde(flatten)]
+    report: ImportReport,
+    infra_errors: Vec<InfraError>,
+}
+
+async fn convert_osm_to_railjson(
+    args: OsmToRailjsonArgs,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let config = match args.config {
+        Some(path) => OsmImportConfig::load(&path)?,
+        None => OsmImportConfig::default(),
+    };
+    let Some(report_path) = args.report else {
+        return osm_to_railjson::osm_to_railjson(args.osm_pbf_in, args.railjson_out, &config);
+    };
+
+    let (railjson, report) = osm_to_railjson::parse_osm_with_report(args.osm_pbf_in, &config)?;
+    let infra_errors = generate_infra_errors(&InfraCache::from_railjson(&railjson)?).await;
+    serde_json::to_writer(File::create(&args.railjson_out)?, &railjson)?;
+    let report = OsmImportReport {
+        report,
+        infra_errors,
+    };
+    serde_json::to_writer_pretty(File::create(&report_path)?, &report)?;
+    println!(
+        "✅ Railjson written to {}, {} infra errors reported in {}",
+        args.railjson_out.display(),
+        report.infra_errors.len(),
+        report_path.display()
+    );
+    Ok(())
+}
+
 async fn electrical_profile_set_import(
     args: ImportProfileSetArgs,
     db_pool: Arc<DbConnectionPool>,
@@ -884,6 +1077,7 @@ mod tests {
     use diesel::sql_query;
     use diesel::sql_types::Text;
     use diesel_async::RunQueryDsl;
+    use editoast_schemas::infra::RailJson;
     use modelsv2::DeleteStatic;
     use rand::distributions::Alphanumeric;
     use rand::{thread_rng, Rng};
@@ -908,6 +1102,7 @@ mod tests {
         let args = ImportTimetableArgs {
             path: file.path().into(),
             id: Some(timetable.id),
+            ..Default::default()
         };
         let result = trains_import(args, db_pool.clone()).await;
         assert!(result.is_ok(), "{:?}", result);
@@ -917,14 +1112,16 @@ mod tests {
         let args = ExportTimetableArgs {
             path: export_file.path().into(),
             id: timetable.id,
+            ..Default::default()
         };
-        let export_result = trains_export(args, db_pool.clone()).await;
+        let export_result = trains_export(args, db_pool.clone(), RedisConfig::default()).await;
         assert!(export_result.is_ok(), "{:?}", export_result);
 
         // Test to reimport the exported import
         let reimport_args = ImportTimetableArgs {
             path: export_file.path().into(),
             id: Some(timetable.id),
+            ..Default::default()
         };
         let reimport_result = trains_import(reimport_args, db_pool.clone()).await;
         assert!(reimport_result.is_ok(), "{:?}", reimport_result);
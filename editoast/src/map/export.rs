@@ -0,0 +1,247 @@
+use std::collections::BTreeMap;
+
+use diesel::sql_query;
+use diesel::sql_types::BigInt;
+use diesel::sql_types::Double;
+use diesel::sql_types::Jsonb;
+use diesel::sql_types::Text;
+use diesel_async::RunQueryDsl;
+use editoast_schemas::primitives::BoundingBox;
+use geos::geojson::Feature;
+use geos::geojson::FeatureCollection;
+use geos::geojson::Geometry;
+use geos::geojson::JsonObject;
+use serde_json::Value as JsonValue;
+
+use super::MapLayers;
+use super::View;
+use crate::error::Result;
+use crate::modelsv2::DbConnection;
+
+/// The layer view whose data is exported
+const EXPORTED_VIEW: &str = "geo";
+
+#[derive(QueryableByName, Debug)]
+struct LayerFeature {
+    #[diesel(sql_type = Text)]
+    geo_json: String,
+    #[diesel(sql_type = Jsonb)]
+    data: JsonValue,
+}
+
+/// Exports the features of every map layer of an infra, in WGS84 coordinates
+///
+/// The properties of the features are flattened as done for the MVT tiles.
+/// When a bounding box is given, only the features intersecting it are exported and their geometry is clipped to it.
+pub async fn export_layers(
+    conn: &mut DbConnection,
+    map_layers: &MapLayers,
+    infra_id: i64,
+    bbox: Option<&BoundingBox>,
+) -> Result<BTreeMap<String, FeatureCollection>> {
+    let mut layers = BTreeMap::new();
+    for (layer_name, layer) in &map_layers.layers {
+        let Some(view) = layer.views.get(EXPORTED_VIEW) else {
+            continue;
+        };
+        let mut query = sql_query(get_export_sql_query(
+            &layer.table_name,
+            view,
+            bbox.is_some(),
+        ))
+        .into_boxed()
+        .bind::<BigInt, _>(infra_id);
+        if let Some(BoundingBox((x_min, y_min), (x_max, y_max))) = bbox {
+            query = query
+                .bind::<Double, _>(*x_min)
+                .bind::<Double, _>(*y_min)
+                .bind::<Double, _>(*x_max)
+                .bind::<Double, _>(*y_max);
+        }
+        let records: Vec<LayerFeature> = query.get_results(conn).await?;
+        let features = records
+            .into_iter()
+            .map(|record| {
+                let geometry: Geometry = serde_json::from_str(&record.geo_json)?;
+                Ok(Feature {
+                    geometry: Some(geometry),
+                    properties: Some(flatten_properties(record.data)),
+                    ..Default::default()
+                })
+            })
+            .collect::<Result<_>>()?;
+        layers.insert(
+            layer_name.clone(),
+            FeatureCollection {
+                bbox: None,
+                features,
+                foreign_members: None,
+            },
+        );
+    }
+    Ok(layers)
+}
+
+/// Flattens the data of a layer object into feature properties
+///
+/// Nested keys are joined with `_` and arrays are serialized as JSON strings.
+pub fn flatten_properties(data: JsonValue) -> JsonObject {
+    fn flatten(properties: &mut JsonObject, value: JsonValue, name: String) {
+        match value {
+            JsonValue::Object(values) => {
+                for (key, value) in values {
+                    let key = if name.is_empty() {
+                        key
+                    } else {
+                        format!("{name}_{key}")
+                    };
+                    flatten(properties, value, key);
+                }
+            }
+            JsonValue::Array(values) => {
+                properties.insert(
+                    name,
+                    JsonValue::String(JsonValue::Array(values).to_string()),
+                );
+            }
+            JsonValue::Null => (),
+            value => {
+                properties.insert(name, value);
+            }
+        }
+    }
+
+    let mut properties = JsonObject::new();
+    flatten(&mut properties, data, String::new());
+    properties
+}
+
+/// Creates an SQL query to get the geo json data of a whole layer
+///
+/// The query is bound to the infra id and, if `clipped`, to the WGS84 bounding box coordinates.
+fn get_export_sql_query(table_name: &str, view: &View, clipped: bool) -> String {
+    let (geometry, bbox_condition) = if clipped {
+        (
+            format!("ST_Intersection({}, bbox.geom)", view.on_field),
+            format!("AND {} && bbox.geom", view.on_field),
+        )
+    } else {
+        (view.on_field.clone(), String::new())
+    };
+    let bbox = if clipped {
+        "ST_Transform(ST_MakeEnvelope($2, $3, $4, $5, 4326), 3857)"
+    } else {
+        "NULL::geometry"
+    };
+    format!(
+        "
+        WITH bbox AS (
+            SELECT {bbox} AS geom
+        ), matches AS (
+             SELECT
+                 ST_AsGeoJson(ST_Transform({geometry}, 4326)) AS geo_json,
+                 layer.id AS id
+             FROM {table_name} layer
+             CROSS JOIN bbox
+             WHERE layer.infra_id = $1 {bbox_condition}
+                   AND NOT ST_IsEmpty({geometry})
+        )
+        SELECT
+            matches.geo_json as geo_json,
+            {data_expr} {exclude_fields} AS data
+        FROM matches
+        INNER JOIN {table_name} layer on matches.id = layer.id
+        {joins}
+        WHERE geo_json is not NULL {where_condition}
+        ORDER BY layer.id
+        ",
+        data_expr = view.data_expr,
+        exclude_fields = &view
+            .exclude_fields
+            .iter()
+            .map(|field| format!("- '{field}'"))
+            .collect::<Vec<_>>()
+            .join(" "),
+        joins = view.joins.join(" "),
+        where_condition = &view
+            .where_expr
+            .iter()
+            .map(|field| format!("AND ({field})"))
+            .collect::<Vec<_>>()
+            .join(" "),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use editoast_schemas::primitives::BoundingBox;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+    use serde_json::json;
+
+    use super::export_layers;
+    use super::flatten_properties;
+    use crate::fixtures::tests::db_pool;
+    use crate::fixtures::tests::small_infra;
+    use crate::fixtures::tests::TestFixture;
+    use crate::infra_cache::InfraCache;
+    use crate::map::MapLayers;
+    use crate::modelsv2::DbConnectionPool;
+    use crate::modelsv2::Infra;
+
+    #[test]
+    fn flatten_nested_properties() {
+        let properties = flatten_properties(json!({
+            "id": "a",
+            "extensions": {
+                "sncf": { "line_code": 1 },
+                "source": null
+            },
+            "slopes": [{ "begin": 0 }]
+        }));
+        assert_eq!(
+            serde_json::Value::Object(properties),
+            json!({
+                "id": "a",
+                "extensions_sncf_line_code": 1,
+                "slopes": "[{\"begin\":0}]"
+            })
+        );
+    }
+
+    #[rstest]
+    async fn export_small_infra_layers(
+        #[future] small_infra: TestFixture<Infra>,
+        db_pool: Arc<DbConnectionPool>,
+    ) {
+        let mut small_infra = small_infra.await;
+        let conn = &mut db_pool.get().await.unwrap();
+        let infra_cache = InfraCache::load(conn, &small_infra.model).await.unwrap();
+        small_infra
+            .model
+            .refresh(db_pool.clone(), true, &infra_cache)
+            .await
+            .unwrap();
+        let map_layers = MapLayers::parse();
+
+        let layers = export_layers(conn, &map_layers, small_infra.id(), None)
+            .await
+            .unwrap();
+        assert_eq!(layers.len(), map_layers.layers.len());
+        let track_sections = &layers["track_sections"].features;
+        assert_eq!(track_sections.len(), 31);
+        let properties = track_sections[0].properties.as_ref().unwrap();
+        assert!(properties.contains_key("id"));
+        assert!(!properties.contains_key("geo"));
+
+        let bbox = BoundingBox((-0.4, 49.45), (-0.3, 49.55));
+        let clipped = export_layers(conn, &map_layers, small_infra.id(), Some(&bbox))
+            .await
+            .unwrap();
+        let clipped_track_sections = &clipped["track_sections"].features;
+        assert!(!clipped_track_sections.is_empty());
+        assert!(clipped_track_sections.len() < track_sections.len());
+    }
+}
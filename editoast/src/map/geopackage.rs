@@ -0,0 +1,411 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use geos::geojson::FeatureCollection;
+use geos::geojson::Value as GeoJsonValue;
+use rusqlite::params;
+use rusqlite::types::Value as SqlValue;
+use rusqlite::Connection;
+use serde_json::Value as JsonValue;
+
+/// The GeoPackage `application_id`, "GPKG" in ASCII
+const GEOPACKAGE_APPLICATION_ID: i32 = 0x4750_4B47;
+/// The GeoPackage version 1.3.0
+const GEOPACKAGE_VERSION: i32 = 10300;
+const WGS84_SRS_ID: i32 = 4326;
+const WGS84_DEFINITION: &str = r#"GEOGCS["WGS 84",DATUM["WGS_1984",SPHEROID["WGS 84",6378137,298.257223563,AUTHORITY["EPSG","7030"]],AUTHORITY["EPSG","6326"]],PRIMEM["Greenwich",0,AUTHORITY["EPSG","8901"]],UNIT["degree",0.0174532925199433,AUTHORITY["EPSG","9122"]],AUTHORITY["EPSG","4326"]]"#;
+
+/// Writes map layers to a GeoPackage file, each layer being stored in its own feature table
+///
+/// The features are expected to be in WGS84 coordinates with flattened properties,
+/// as returned by [super::export_layers].
+pub fn write_geopackage(
+    path: &Path,
+    layers: &BTreeMap<String, FeatureCollection>,
+) -> rusqlite::Result<()> {
+    let mut conn = Connection::open(path)?;
+    conn.pragma_update(None, "application_id", GEOPACKAGE_APPLICATION_ID)?;
+    conn.pragma_update(None, "user_version", GEOPACKAGE_VERSION)?;
+    let transaction = conn.transaction()?;
+    transaction.execute_batch(
+        "
+        CREATE TABLE gpkg_spatial_ref_sys (
+            srs_name TEXT NOT NULL,
+            srs_id INTEGER NOT NULL PRIMARY KEY,
+            organization TEXT NOT NULL,
+            organization_coordsys_id INTEGER NOT NULL,
+            definition TEXT NOT NULL,
+            description TEXT
+        );
+        CREATE TABLE gpkg_contents (
+            table_name TEXT NOT NULL PRIMARY KEY,
+            data_type TEXT NOT NULL,
+            identifier TEXT UNIQUE,
+            description TEXT DEFAULT '',
+            last_change DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ','now')),
+            min_x DOUBLE,
+            min_y DOUBLE,
+            max_x DOUBLE,
+            max_y DOUBLE,
+            srs_id INTEGER,
+            CONSTRAINT fk_gc_r_srs_id FOREIGN KEY (srs_id) REFERENCES gpkg_spatial_ref_sys(srs_id)
+        );
+        CREATE TABLE gpkg_geometry_columns (
+            table_name TEXT NOT NULL,
+            column_name TEXT NOT NULL,
+            geometry_type_name TEXT NOT NULL,
+            srs_id INTEGER NOT NULL,
+            z TINYINT NOT NULL,
+            m TINYINT NOT NULL,
+            CONSTRAINT pk_geom_cols PRIMARY KEY (table_name, column_name),
+            CONSTRAINT fk_gc_tn FOREIGN KEY (table_name) REFERENCES gpkg_contents(table_name),
+            CONSTRAINT fk_gc_srs FOREIGN KEY (srs_id) REFERENCES gpkg_spatial_ref_sys (srs_id)
+        );
+        INSERT INTO gpkg_spatial_ref_sys VALUES
+            ('Undefined cartesian SRS', -1, 'NONE', -1, 'undefined', NULL),
+            ('Undefined geographic SRS', 0, 'NONE', 0, 'undefined', NULL);
+        ",
+    )?;
+    transaction.execute(
+        "INSERT INTO gpkg_spatial_ref_sys VALUES ('WGS 84 geodetic', ?1, 'EPSG', ?1, ?2, NULL)",
+        params![WGS84_SRS_ID, WGS84_DEFINITION],
+    )?;
+
+    for (layer_name, layer) in layers {
+        let columns = property_columns(layer);
+        let table = quote_identifier(layer_name);
+        let column_definitions: String = columns
+            .iter()
+            .map(|(name, sql_type)| format!(", {} {sql_type}", quote_identifier(name)))
+            .collect();
+        transaction.execute_batch(&format!(
+            "CREATE TABLE {table} (fid INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL, geom GEOMETRY{column_definitions});"
+        ))?;
+
+        let column_names: String = columns
+            .iter()
+            .map(|(name, _)| format!(", {}", quote_identifier(name)))
+            .collect();
+        let placeholders: String = (0..columns.len())
+            .map(|index| format!(", ?{}", index + 2))
+            .collect();
+        let mut insert = transaction.prepare(&format!(
+            "INSERT INTO {table} (geom{column_names}) VALUES (?1{placeholders})"
+        ))?;
+        let mut layer_envelope = Envelope::default();
+        for feature in &layer.features {
+            let geometry = feature.geometry.as_ref().map(|geometry| {
+                let envelope = Envelope::of(&geometry.value);
+                layer_envelope.extend(&envelope);
+                geometry_blob(&geometry.value, &envelope)
+            });
+            let mut values = vec![geometry.map_or(SqlValue::Null, SqlValue::Blob)];
+            values.extend(columns.iter().map(|(name, _)| {
+                feature
+                    .properties
+                    .as_ref()
+                    .and_then(|properties| properties.get(name))
+                    .map_or(SqlValue::Null, sql_value)
+            }));
+            insert.execute(rusqlite::params_from_iter(values))?;
+        }
+        drop(insert);
+
+        transaction.execute(
+            "INSERT INTO gpkg_contents (table_name, data_type, identifier, min_x, min_y, max_x, max_y, srs_id)
+            VALUES (?1, 'features', ?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                layer_name,
+                layer_envelope.bounds().map(|bounds| bounds[0]),
+                layer_envelope.bounds().map(|bounds| bounds[2]),
+                layer_envelope.bounds().map(|bounds| bounds[1]),
+                layer_envelope.bounds().map(|bounds| bounds[3]),
+                WGS84_SRS_ID
+            ],
+        )?;
+        transaction.execute(
+            "INSERT INTO gpkg_geometry_columns VALUES (?1, 'geom', 'GEOMETRY', ?2, 0, 0)",
+            params![layer_name, WGS84_SRS_ID],
+        )?;
+    }
+    transaction.commit()
+}
+
+fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Lists the properties of a layer along with the SQLite type fitting all their values
+fn property_columns(layer: &FeatureCollection) -> Vec<(String, &'static str)> {
+    let mut columns = BTreeMap::<String, &'static str>::new();
+    for properties in layer.features.iter().filter_map(|f| f.properties.as_ref()) {
+        for (name, value) in properties {
+            let sql_type = match value {
+                JsonValue::Bool(_) => "INTEGER",
+                JsonValue::Number(number) if number.is_f64() => "REAL",
+                JsonValue::Number(_) => "INTEGER",
+                _ => "TEXT",
+            };
+            let column_type = columns.entry(name.clone()).or_insert(sql_type);
+            *column_type = match (*column_type, sql_type) {
+                (current, new) if current == new => current,
+                ("INTEGER", "REAL") | ("REAL", "INTEGER") => "REAL",
+                _ => "TEXT",
+            };
+        }
+    }
+    columns.into_iter().collect()
+}
+
+fn sql_value(value: &JsonValue) -> SqlValue {
+    match value {
+        JsonValue::Null => SqlValue::Null,
+        JsonValue::Bool(value) => SqlValue::Integer(*value as i64),
+        JsonValue::Number(number) => match number.as_i64() {
+            Some(value) => SqlValue::Integer(value),
+            None => SqlValue::Real(number.as_f64().unwrap_or_default()),
+        },
+        JsonValue::String(value) => SqlValue::Text(value.clone()),
+        value => SqlValue::Text(value.to_string()),
+    }
+}
+
+/// The bounds of a geometry, as `[min_x, max_x, min_y, max_y]` following the GeoPackage envelope order
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct Envelope(Option<[f64; 4]>);
+
+impl Envelope {
+    fn of(geometry: &GeoJsonValue) -> Self {
+        let mut envelope = Self::default();
+        envelope.add_geometry(geometry);
+        envelope
+    }
+
+    fn bounds(&self) -> Option<[f64; 4]> {
+        self.0
+    }
+
+    fn add_point(&mut self, point: &[f64]) {
+        let (x, y) = (point[0], point[1]);
+        self.0 = Some(match self.0 {
+            None => [x, x, y, y],
+            Some([min_x, max_x, min_y, max_y]) => {
+                [min_x.min(x), max_x.max(x), min_y.min(y), max_y.max(y)]
+            }
+        });
+    }
+
+    fn extend(&mut self, other: &Self) {
+        if let Some([min_x, max_x, min_y, max_y]) = other.0 {
+            self.add_point(&[min_x, min_y]);
+            self.add_point(&[max_x, max_y]);
+        }
+    }
+
+    fn add_geometry(&mut self, geometry: &GeoJsonValue) {
+        match geometry {
+            GeoJsonValue::Point(point) => self.add_point(point),
+            GeoJsonValue::MultiPoint(points) | GeoJsonValue::LineString(points) => {
+                points.iter().for_each(|point| self.add_point(point))
+            }
+            GeoJsonValue::MultiLineString(lines) | GeoJsonValue::Polygon(lines) => lines
+                .iter()
+                .flatten()
+                .for_each(|point| self.add_point(point)),
+            GeoJsonValue::MultiPolygon(polygons) => polygons
+                .iter()
+                .flatten()
+                .flatten()
+                .for_each(|point| self.add_point(point)),
+            GeoJsonValue::GeometryCollection(geometries) => geometries
+                .iter()
+                .for_each(|geometry| self.add_geometry(&geometry.value)),
+        }
+    }
+}
+
+/// Encodes a geometry in the GeoPackage binary format: a header followed by the little endian WKB
+fn geometry_blob(geometry: &GeoJsonValue, envelope: &Envelope) -> Vec<u8> {
+    let mut blob = b"GP".to_vec();
+    blob.push(0); // version 1
+    match envelope.bounds() {
+        // Little endian, with a [min_x, max_x, min_y, max_y] envelope
+        Some(bounds) => {
+            blob.push(0b0000_0011);
+            blob.extend(WGS84_SRS_ID.to_le_bytes());
+            bounds
+                .iter()
+                .for_each(|bound| blob.extend(bound.to_le_bytes()));
+        }
+        // Little endian and empty geometry, without envelope
+        None => {
+            blob.push(0b0001_0001);
+            blob.extend(WGS84_SRS_ID.to_le_bytes());
+        }
+    }
+    write_wkb(&mut blob, geometry);
+    blob
+}
+
+fn write_wkb(wkb: &mut Vec<u8>, geometry: &GeoJsonValue) {
+    fn write_header(wkb: &mut Vec<u8>, geometry_type: u32) {
+        wkb.push(1); // little endian
+        wkb.extend(geometry_type.to_le_bytes());
+    }
+    fn write_points(wkb: &mut Vec<u8>, points: &[Vec<f64>]) {
+        wkb.extend((points.len() as u32).to_le_bytes());
+        for point in points {
+            wkb.extend(point[0].to_le_bytes());
+            wkb.extend(point[1].to_le_bytes());
+        }
+    }
+    fn write_rings(wkb: &mut Vec<u8>, rings: &[Vec<Vec<f64>>]) {
+        wkb.extend((rings.len() as u32).to_le_bytes());
+        for ring in rings {
+            write_points(wkb, ring);
+        }
+    }
+
+    match geometry {
+        GeoJsonValue::Point(point) => {
+            write_header(wkb, 1);
+            wkb.extend(point[0].to_le_bytes());
+            wkb.extend(point[1].to_le_bytes());
+        }
+        GeoJsonValue::LineString(points) => {
+            write_header(wkb, 2);
+            write_points(wkb, points);
+        }
+        GeoJsonValue::Polygon(rings) => {
+            write_header(wkb, 3);
+            write_rings(wkb, rings);
+        }
+        GeoJsonValue::MultiPoint(points) => {
+            write_header(wkb, 4);
+            wkb.extend((points.len() as u32).to_le_bytes());
+            for point in points {
+                write_wkb(wkb, &GeoJsonValue::Point(point.clone()));
+            }
+        }
+        GeoJsonValue::MultiLineString(lines) => {
+            write_header(wkb, 5);
+            wkb.extend((lines.len() as u32).to_le_bytes());
+            for line in lines {
+                write_header(wkb, 2);
+                write_points(wkb, line);
+            }
+        }
+        GeoJsonValue::MultiPolygon(polygons) => {
+            write_header(wkb, 6);
+            wkb.extend((polygons.len() as u32).to_le_bytes());
+            for polygon in polygons {
+                write_header(wkb, 3);
+                write_rings(wkb, polygon);
+            }
+        }
+        GeoJsonValue::GeometryCollection(geometries) => {
+            write_header(wkb, 7);
+            wkb.extend((geometries.len() as u32).to_le_bytes());
+            for geometry in geometries {
+                write_wkb(wkb, &geometry.value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use geos::geojson::Feature;
+    use geos::geojson::FeatureCollection;
+    use geos::geojson::Geometry;
+    use geos::geojson::Value as GeoJsonValue;
+    use pretty_assertions::assert_eq;
+    use rusqlite::Connection;
+    use serde_json::json;
+
+    use super::write_geopackage;
+    use super::GEOPACKAGE_APPLICATION_ID;
+
+    fn feature(geometry: GeoJsonValue, properties: serde_json::Value) -> Feature {
+        Feature {
+            geometry: Some(Geometry::new(geometry)),
+            properties: properties.as_object().cloned(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn write_layers() {
+        let layers = BTreeMap::from([
+            (
+                "signals".to_owned(),
+                FeatureCollection {
+                    bbox: None,
+                    features: vec![
+                        feature(
+                            GeoJsonValue::Point(vec![2., 48.]),
+                            json!({"id": "S1", "angle": 90, "position": 1.5}),
+                        ),
+                        feature(
+                            GeoJsonValue::Point(vec![3., 49.]),
+                            json!({"id": "S2", "angle": 45.5}),
+                        ),
+                    ],
+                    foreign_members: None,
+                },
+            ),
+            (
+                "track_sections".to_owned(),
+                FeatureCollection {
+                    bbox: None,
+                    features: vec![feature(
+                        GeoJsonValue::MultiLineString(vec![vec![vec![2., 48.], vec![3., 49.]]]),
+                        json!({"id": "T1", "length": 100}),
+                    )],
+                    foreign_members: None,
+                },
+            ),
+        ]);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("layers.gpkg");
+
+        write_geopackage(&path, &layers).unwrap();
+
+        let conn = Connection::open(&path).unwrap();
+        let application_id: i32 = conn
+            .query_row("PRAGMA application_id", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(application_id, GEOPACKAGE_APPLICATION_ID);
+        let contents: Vec<(String, f64, f64)> = conn
+            .prepare("SELECT table_name, min_x, max_y FROM gpkg_contents ORDER BY table_name")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            contents,
+            vec![
+                ("signals".to_owned(), 2., 49.),
+                ("track_sections".to_owned(), 2., 49.)
+            ]
+        );
+        let signals: Vec<(String, f64, Option<f64>, Vec<u8>)> = conn
+            .prepare("SELECT id, angle, position, geom FROM signals ORDER BY fid")
+            .unwrap()
+            .query_map([], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(signals[0].0, "S1");
+        assert_eq!(signals[1].1, 45.5);
+        assert_eq!(signals[1].2, None);
+        // Header (8 bytes), envelope (32 bytes) and WKB point (21 bytes)
+        assert_eq!(&signals[0].3[..2], b"GP");
+        assert_eq!(signals[0].3.len(), 8 + 32 + 21);
+    }
+}
@@ -1,6 +1,11 @@
+mod export;
+mod geopackage;
 mod layer_cache;
 mod layers;
 
+pub use export::export_layers;
+pub use geopackage::write_geopackage;
+
 pub use layers::Layer;
 pub use layers::MapLayers;
 pub use layers::View;
@@ -1,4 +1,6 @@
+mod diff;
 pub mod errors;
+mod merge;
 mod object_queryable;
 mod railjson_data;
 mod route_from_waypoint_result;
@@ -6,6 +8,8 @@ mod speed_limit_tags;
 mod splited_track_section_with_data;
 mod voltage;
 
+use std::collections::HashMap;
+use std::io::Read;
 use std::pin::Pin;
 
 use chrono::NaiveDateTime;
@@ -13,36 +17,56 @@ use chrono::Utc;
 use derivative::Derivative;
 use diesel::sql_query;
 use diesel::sql_types::BigInt;
+use diesel::OptionalExtension;
+use diesel::QueryDsl;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::AsyncConnection;
 use diesel_async::RunQueryDsl;
 use editoast_derive::ModelV2;
+use futures::future;
 use futures::future::try_join_all;
+use futures::pin_mut;
+use futures::stream;
 use futures::Future;
+use futures::Stream;
+use futures::StreamExt;
 use serde::Deserialize;
 use serde::Serialize;
 use std::sync::Arc;
 use strum::IntoEnumIterator;
+use tokio::sync::mpsc;
 use tracing::debug;
-use tracing::error;
 use uuid::Uuid;
 
+use crate::error::InternalError;
 use crate::error::Result;
 use crate::generated_data;
 use crate::infra_cache::InfraCache;
 use crate::modelsv2::get_geometry_layer_table;
 use crate::modelsv2::get_table;
+use crate::modelsv2::infra_revision::InfraRevision;
 use crate::modelsv2::prelude::*;
-use crate::modelsv2::railjson::persist_railjson;
+use crate::modelsv2::railjson::stream_railjson;
+use crate::modelsv2::railjson::RailJsonBatch;
+use crate::modelsv2::railjson::RailJsonError;
+use crate::modelsv2::railjson::RAILJSON_BATCH_SIZE;
 use crate::modelsv2::Create;
 use crate::modelsv2::DbConnection;
 use crate::modelsv2::DbConnectionPool;
 use crate::tables::infra::dsl;
+pub use diff::InfraDiff;
+pub use diff::InfraSnapshot;
 use editoast_schemas::infra::RailJson;
 use editoast_schemas::infra::RAILJSON_VERSION;
 use editoast_schemas::primitives::ObjectType;
+pub use merge::InfraMerge;
+pub use merge::MergeConflict;
 pub use object_queryable::ObjectQueryable;
 
 editoast_common::schemas! {
     Infra,
+    diff::schemas(),
+    merge::schemas(),
     object_queryable::schemas(),
 }
 
@@ -69,25 +93,97 @@ pub struct Infra {
     pub created: NaiveDateTime,
     #[derivative(Default(value = "Utc::now().naive_utc()"))]
     pub modified: NaiveDateTime,
+    /// The infra this one was cloned from
+    #[serde(skip)]
+    pub forked_from: Option<i64>,
+    /// The latest applied revision of the original infra at the time of the clone
+    #[serde(skip)]
+    pub fork_revision_id: Option<i64>,
 }
 
 impl InfraChangeset {
+    /// Creates the infra with all the objects of a railjson
+    ///
+    /// Everything is inserted in a single transaction: on failure, nothing is persisted.
     pub async fn persist(
         self,
         railjson: RailJson,
         db_pool: Arc<DbConnectionPool>,
     ) -> Result<Infra> {
-        let conn = &mut db_pool.get().await?;
-        let infra = self.create(conn).await?;
-        // TODO: lock infra for update
-        debug!("🛤  Begin importing all railjson objects");
-        if let Err(e) = persist_railjson(db_pool, infra.id, railjson).await {
-            error!("Could not import infrastructure {}. Rolling back", infra.id);
-            infra.delete(conn).await?;
-            return Err(e);
-        };
-        debug!("🛤  Import finished successfully");
-        Ok(infra)
+        if railjson.version != RAILJSON_VERSION {
+            return Err(RailJsonError::UnsupportedVersion {
+                actual: railjson.version,
+                expected: RAILJSON_VERSION.to_string(),
+            }
+            .into());
+        }
+        let batches = RailJsonBatch::split(railjson, RAILJSON_BATCH_SIZE);
+        self.persist_batches(
+            stream::iter(batches.into_iter().map(Ok)),
+            db_pool,
+            |_, _| {},
+        )
+        .await
+    }
+
+    /// Creates the infra with all the objects of a railjson read incrementally
+    ///
+    /// Unlike [InfraChangeset::persist] the railjson is never fully loaded in memory: its objects
+    /// are parsed in a blocking task and inserted by batches as they are read.
+    /// Older railjson versions are upgraded on the fly.
+    /// `progress` is called after each batch with the type of its objects and the number of
+    /// objects of this type imported so far.
+    pub async fn persist_stream(
+        self,
+        reader: impl Read + Send + 'static,
+        db_pool: Arc<DbConnectionPool>,
+        progress: impl FnMut(ObjectType, usize) + Send,
+    ) -> Result<Infra> {
+        // Bounds the number of batches parsed ahead of the insertions
+        let (sender, receiver) = mpsc::channel(4);
+        let parser = tokio::task::spawn_blocking(move || stream_railjson(reader, sender));
+        let batches = stream::unfold(receiver, |mut receiver| async move {
+            let batch = receiver.recv().await?;
+            Some((batch, receiver))
+        })
+        // The channel is closed once the parser is done. Its outcome is checked before the
+        // transaction is committed: if it panicked, the objects after the last batch are missing.
+        .chain(
+            stream::once(parser)
+                .filter_map(|parsed| future::ready(parsed.err().map(|error| Err(error.into())))),
+        );
+        self.persist_batches(batches, db_pool, progress).await
+    }
+
+    async fn persist_batches(
+        self,
+        batches: impl Stream<Item = Result<RailJsonBatch>> + Send,
+        db_pool: Arc<DbConnectionPool>,
+        mut progress: impl FnMut(ObjectType, usize) + Send,
+    ) -> Result<Infra> {
+        let mut conn = db_pool.get().await?;
+        conn.transaction::<_, InternalError, _>(|conn| {
+            async move {
+                // No need to lock the infra, it's not visible until the transaction is committed
+                let infra = self.create(conn).await?;
+                debug!("🛤  Begin importing all railjson objects");
+                let mut imported: HashMap<ObjectType, usize> = HashMap::new();
+                pin_mut!(batches);
+                while let Some(batch) = batches.next().await {
+                    let batch = batch?;
+                    let object_type = batch.object_type();
+                    let count = batch.len();
+                    batch.persist(conn, infra.id).await?;
+                    let imported = imported.entry(object_type).or_default();
+                    *imported += count;
+                    progress(object_type, *imported);
+                }
+                debug!("🛤  Import finished successfully");
+                Ok(infra)
+            }
+            .scope_boxed()
+        })
+        .await
     }
 
     #[must_use = "builder methods are intended to be chained"]
@@ -107,6 +203,20 @@ impl Infra {
             .collect()
     }
 
+    /// Retrieves an infra and locks it for update until the end of the current transaction
+    pub async fn retrieve_for_update(
+        conn: &mut DbConnection,
+        infra_id: i64,
+    ) -> Result<Option<Infra>> {
+        Ok(dsl::infra
+            .find(infra_id)
+            .for_update()
+            .first(conn)
+            .await
+            .optional()?
+            .map(Self::from_row))
+    }
+
     pub async fn bump_version(&mut self, conn: &mut DbConnection) -> Result<()> {
         let new_version = self
             .version
@@ -125,9 +235,12 @@ impl Infra {
     pub async fn clone(&self, db_pool: Arc<DbConnectionPool>, new_name: String) -> Result<Infra> {
         // Duplicate infra shell
         let mut conn = db_pool.get().await?;
+        let fork_revision = InfraRevision::last_applied(&mut conn, self.id, 1).await?;
         let cloned_infra = <Self as Clone>::clone(self)
             .into_changeset()
             .name(new_name)
+            .forked_from(Some(self.id))
+            .fork_revision_id(fork_revision.first().map(|revision| revision.id))
             .created(Utc::now().naive_utc())
             .modified(Utc::now().naive_utc())
             .create(&mut conn)
@@ -241,6 +354,8 @@ pub mod tests {
     use editoast_schemas::primitives::OSRDIdentified;
     use pretty_assertions::assert_eq;
     use rstest::rstest;
+    use std::io::Cursor;
+    use std::io::Read;
     use std::ops::DerefMut;
     use uuid::Uuid;
 
@@ -307,6 +422,71 @@ pub mod tests {
         assert_eq!(res.unwrap_err().get_type(), expected_error.get_type());
     }
 
+    #[rstest]
+    async fn persist_stream_rollbacks_on_invalid_railjson() {
+        let pool = db_pool();
+        let railjson = RailJson {
+            track_sections: (0..10).map(|_| Default::default()).collect(),
+            ..Default::default()
+        };
+        // Detectors come after the track sections, which are inserted before the failure
+        let railjson = serde_json::to_string(&railjson).unwrap().replace(
+            r#""detectors":[]"#,
+            r#""detectors":[{"unknown_field":true}]"#,
+        );
+        let name = format!("persist_stream_rollback_{}", Uuid::new_v4());
+        let res = Infra::changeset()
+            .name(name.clone())
+            .last_railjson_version()
+            .persist_stream(Cursor::new(railjson), pool.clone(), |_, _| {})
+            .await;
+        assert!(res.is_err());
+        let conn = &mut pool.get().await.unwrap();
+        assert!(Infra::all(conn)
+            .await
+            .iter()
+            .all(|infra| infra.name != name));
+    }
+
+    /// Reads until the end of the wrapped reader, then panics
+    struct PanickingReader<R>(R);
+
+    impl<R: Read> Read for PanickingReader<R> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            match self.0.read(buf)? {
+                0 => panic!("the railjson reader failed"),
+                read => Ok(read),
+            }
+        }
+    }
+
+    #[rstest]
+    async fn persist_stream_rollbacks_on_parser_panic() {
+        let pool = db_pool();
+        let railjson = RailJson {
+            track_sections: (0..10).map(|_| Default::default()).collect(),
+            ..Default::default()
+        };
+        // All the objects are sent before the parser panics
+        let railjson = serde_json::to_string(&railjson).unwrap();
+        let name = format!("persist_stream_panic_{}", Uuid::new_v4());
+        let res = Infra::changeset()
+            .name(name.clone())
+            .last_railjson_version()
+            .persist_stream(
+                PanickingReader(Cursor::new(railjson)),
+                pool.clone(),
+                |_, _| {},
+            )
+            .await;
+        assert!(res.is_err());
+        let conn = &mut pool.get().await.unwrap();
+        assert!(Infra::all(conn)
+            .await
+            .iter()
+            .all(|infra| infra.name != name));
+    }
+
     #[rstest]
     // The fixture leaks the persisted infra because we explicitely opened a
     // connection. This should be fixed by the testing utils rework. The ignore
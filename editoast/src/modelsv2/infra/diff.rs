@@ -0,0 +1,326 @@
+use std::collections::BTreeMap;
+
+use diesel::sql_query;
+use diesel::sql_types::BigInt;
+use diesel::sql_types::Jsonb;
+use diesel::sql_types::Text;
+use diesel::QueryableByName;
+use diesel_async::RunQueryDsl;
+use editoast_derive::EditoastError;
+use editoast_schemas::infra::InfraObject;
+use editoast_schemas::primitives::OSRDIdentified as _;
+use editoast_schemas::primitives::OSRDObject as _;
+use editoast_schemas::primitives::ObjectRef;
+use editoast_schemas::primitives::ObjectType;
+use enum_map::EnumMap;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::json;
+use strum::IntoEnumIterator;
+use thiserror::Error;
+use utoipa::ToSchema;
+
+use super::Infra;
+use crate::error::Result;
+use crate::infra_cache::operation::patch_infra_object;
+use crate::infra_cache::operation::DeleteOperation;
+use crate::infra_cache::operation::Operation;
+use crate::infra_cache::operation::UpdateOperation;
+use crate::infra_cache::InfraCache;
+use crate::modelsv2::get_table;
+use crate::modelsv2::infra_revision::InfraRevision;
+use crate::modelsv2::DbConnection;
+use crate::modelsv2::Retrieve;
+
+editoast_common::schemas! {
+    InfraDiff,
+}
+
+#[derive(Debug, Error, EditoastError)]
+#[editoast_error(base_id = "infra:diff")]
+pub enum InfraDiffError {
+    #[error("Revision '{revision_id}' could not be found among the applied revisions of infra '{infra_id}'")]
+    #[editoast_error(status = 404)]
+    RevisionNotFound { infra_id: i64, revision_id: i64 },
+    #[error("Infras '{infra_id}' and '{other_infra_id}' are not related by a clone")]
+    #[editoast_error(status = 400)]
+    UnrelatedInfras { infra_id: i64, other_infra_id: i64 },
+}
+
+/// All the objects of an infra at a given point of its history
+#[derive(Debug, Default, Clone)]
+pub struct InfraSnapshot(pub(super) EnumMap<ObjectType, BTreeMap<String, InfraObject>>);
+
+impl InfraSnapshot {
+    fn insert(&mut self, object: InfraObject) {
+        self.0[object.get_type()].insert(object.get_id().clone(), object);
+    }
+
+    /// Applies an operation to the objects of the snapshot
+    pub fn apply(&mut self, operation: &Operation) -> Result<()> {
+        match operation {
+            Operation::Create(object) => self.insert(object.as_ref().clone()),
+            Operation::Update(UpdateOperation {
+                obj_id,
+                obj_type,
+                railjson_patch,
+            }) => {
+                if let Some(object) = self.0[*obj_type].get_mut(obj_id) {
+                    *object = patch_infra_object(object, railjson_patch)?;
+                }
+            }
+            Operation::Delete(DeleteOperation { obj_id, obj_type }) => {
+                self.0[*obj_type].remove(obj_id);
+            }
+        }
+        Ok(())
+    }
+
+    /// Reverts the given revisions, expected newest first
+    fn roll_back(&mut self, revisions: &[InfraRevision]) -> Result<()> {
+        for revision in revisions {
+            for operation in &revision.inverse_operations {
+                self.apply(operation)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Builds an infra cache from the objects of the snapshot
+    pub fn infra_cache(&self) -> Result<InfraCache> {
+        InfraCache::from_objects(
+            self.0
+                .values()
+                .flat_map(|objects| objects.values().cloned()),
+        )
+    }
+}
+
+impl FromIterator<InfraObject> for InfraSnapshot {
+    fn from_iter<T: IntoIterator<Item = InfraObject>>(iter: T) -> Self {
+        let mut snapshot = Self::default();
+        iter.into_iter().for_each(|object| snapshot.insert(object));
+        snapshot
+    }
+}
+
+/// The differences between the objects of two infras
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct InfraDiff {
+    /// Objects only found in the compared infra
+    pub created: Vec<InfraObject>,
+    /// Objects only found in the base infra
+    pub deleted: Vec<ObjectRef>,
+    /// Patches turning objects of the base infra into their counterpart in the compared infra
+    #[schema(inline)]
+    pub modified: Vec<UpdateOperation>,
+}
+
+impl InfraDiff {
+    /// Compares two snapshots object by object
+    pub fn new(base: &InfraSnapshot, other: &InfraSnapshot) -> Self {
+        let mut diff = Self::default();
+        for obj_type in ObjectType::iter() {
+            let base_objects = &base.0[obj_type];
+            let other_objects = &other.0[obj_type];
+            for (obj_id, base_object) in base_objects {
+                match other_objects.get(obj_id) {
+                    None => diff.deleted.push(base_object.get_ref()),
+                    Some(other_object) if other_object != base_object => {
+                        diff.modified.push(UpdateOperation {
+                            obj_id: obj_id.clone(),
+                            obj_type,
+                            railjson_patch: json_patch::diff(
+                                &base_object.get_data(),
+                                &other_object.get_data(),
+                            ),
+                        })
+                    }
+                    Some(_) => (),
+                }
+            }
+            diff.created.extend(
+                other_objects
+                    .iter()
+                    .filter(|(obj_id, _)| !base_objects.contains_key(*obj_id))
+                    .map(|(_, object)| object.clone()),
+            );
+        }
+        diff
+    }
+
+    /// Converts the differences into a batch of operations turning the base infra into the compared one
+    pub fn into_operations(self) -> Vec<Operation> {
+        let created = self
+            .created
+            .into_iter()
+            .map(|object| Operation::Create(Box::new(object)));
+        let modified = self.modified.into_iter().map(Operation::Update);
+        let deleted = self
+            .deleted
+            .into_iter()
+            .map(|object_ref| Operation::Delete(object_ref.into()));
+        created.chain(modified).chain(deleted).collect()
+    }
+}
+
+impl Infra {
+    /// Loads all the objects of the infra
+    ///
+    /// If a revision is given, the objects are loaded as they were right after this revision was applied.
+    pub async fn snapshot(
+        &self,
+        conn: &mut DbConnection,
+        revision_id: Option<i64>,
+    ) -> Result<InfraSnapshot> {
+        #[derive(QueryableByName)]
+        struct ObjectData {
+            #[diesel(sql_type = Text)]
+            obj_id: String,
+            #[diesel(sql_type = Jsonb)]
+            data: serde_json::Value,
+        }
+
+        let mut snapshot = InfraSnapshot::default();
+        for obj_type in ObjectType::iter() {
+            let objects = sql_query(format!(
+                "SELECT obj_id, data FROM {} WHERE infra_id = $1",
+                get_table(&obj_type)
+            ))
+            .bind::<BigInt, _>(self.id)
+            .load::<ObjectData>(conn)
+            .await?;
+            for ObjectData { obj_id, data } in objects {
+                let object = serde_json::from_value(json!({
+                    "obj_type": obj_type,
+                    "railjson": data,
+                }))?;
+                snapshot.0[obj_type].insert(obj_id, object);
+            }
+        }
+
+        let Some(revision_id) = revision_id else {
+            return Ok(snapshot);
+        };
+        let not_found = || InfraDiffError::RevisionNotFound {
+            infra_id: self.id,
+            revision_id,
+        };
+        let revision = InfraRevision::retrieve_or_fail(conn, revision_id, not_found).await?;
+        if revision.infra_id != self.id || revision.undone {
+            return Err(not_found().into());
+        }
+        snapshot.roll_back(&InfraRevision::applied_after(conn, self.id, revision_id).await?)?;
+        Ok(snapshot)
+    }
+
+    /// Loads all the objects of the infra as they were before its first recorded revision
+    pub async fn initial_snapshot(&self, conn: &mut DbConnection) -> Result<InfraSnapshot> {
+        let mut snapshot = self.snapshot(conn, None).await?;
+        snapshot.roll_back(&InfraRevision::applied_after(conn, self.id, 0).await?)?;
+        Ok(snapshot)
+    }
+
+    /// Loads all the objects of the infra as they were when it was cloned from `other`, or `other` from it
+    ///
+    /// Only the revisions applied after the fork point are rolled back.
+    pub async fn fork_snapshot(
+        &self,
+        conn: &mut DbConnection,
+        other: &Infra,
+    ) -> Result<InfraSnapshot> {
+        let fork = if self.forked_from == Some(other.id) {
+            self
+        } else if other.forked_from == Some(self.id) {
+            other
+        } else {
+            return Err(InfraDiffError::UnrelatedInfras {
+                infra_id: self.id,
+                other_infra_id: other.id,
+            }
+            .into());
+        };
+        // Revision ids come from a single sequence, so the fork point splits both histories
+        let fork_point = fork.fork_revision_id.unwrap_or(0);
+        let mut snapshot = self.snapshot(conn, None).await?;
+        snapshot.roll_back(&InfraRevision::applied_after(conn, self.id, fork_point).await?)?;
+        Ok(snapshot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use editoast_schemas::infra::BufferStop;
+    use editoast_schemas::infra::Detector;
+    use editoast_schemas::infra::TrackSection;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn track(id: &str, length: f64) -> InfraObject {
+        InfraObject::TrackSection {
+            railjson: TrackSection {
+                id: id.into(),
+                length,
+                ..Default::default()
+            },
+        }
+    }
+
+    fn detector(id: &str) -> InfraObject {
+        InfraObject::Detector {
+            railjson: Detector {
+                id: id.into(),
+                ..Default::default()
+            },
+        }
+    }
+
+    fn buffer_stop(id: &str) -> InfraObject {
+        InfraObject::BufferStop {
+            railjson: BufferStop {
+                id: id.into(),
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn diff_identical_snapshots() {
+        let snapshot = InfraSnapshot::from_iter([track("track", 10.0), detector("detector")]);
+        assert_eq!(InfraDiff::new(&snapshot, &snapshot), InfraDiff::default());
+    }
+
+    #[test]
+    fn diff_snapshots() {
+        let base = InfraSnapshot::from_iter([track("track", 10.0), detector("detector")]);
+        let other = InfraSnapshot::from_iter([track("track", 20.0), buffer_stop("buffer_stop")]);
+
+        let diff = InfraDiff::new(&base, &other);
+
+        assert_eq!(diff.created, vec![buffer_stop("buffer_stop")]);
+        assert_eq!(
+            diff.deleted,
+            vec![ObjectRef::new(ObjectType::Detector, "detector")]
+        );
+        assert_eq!(diff.modified.len(), 1);
+        assert_eq!(diff.modified[0].obj_id, "track");
+        assert_eq!(
+            serde_json::to_value(&diff.modified[0].railjson_patch).unwrap(),
+            json!([{ "op": "replace", "path": "/length", "value": 20.0 }])
+        );
+    }
+
+    #[test]
+    fn replaying_diff_operations() {
+        let base = InfraSnapshot::from_iter([track("track", 10.0), detector("detector")]);
+        let other = InfraSnapshot::from_iter([track("track", 20.0), buffer_stop("buffer_stop")]);
+
+        let mut replayed = base.clone();
+        for operation in InfraDiff::new(&base, &other).into_operations() {
+            replayed.apply(&operation).unwrap();
+        }
+
+        assert_eq!(InfraDiff::new(&replayed, &other), InfraDiff::default());
+    }
+}
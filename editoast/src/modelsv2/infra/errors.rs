@@ -6,6 +6,8 @@ use super::Infra;
 use crate::error::Result;
 use crate::generated_data::infra_error::{InfraError, InfraErrorTypeLabel};
 use crate::modelsv2::pagination::load_for_pagination;
+use crate::modelsv2::validation_profile::Severity;
+use crate::modelsv2::validation_profile::ValidationProfile;
 use crate::modelsv2::DbConnection;
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, Deserialize, utoipa::ToSchema)]
@@ -18,9 +20,15 @@ pub enum Level {
 }
 
 impl Infra {
+    /// Returns a page of the errors of the infra
+    ///
+    /// If a validation profile is given, the errors of disabled types are left out and
+    /// the severity overrides are taken into account, both for filtering and in the results.
+    #[allow(clippy::too_many_arguments)]
     pub async fn get_paginated_errors(
         &self,
         conn: &mut DbConnection,
+        profile: Option<&ValidationProfile>,
         level: Level,
         error_type: Option<InfraErrorTypeLabel>,
         object_id: Option<Identifier>,
@@ -38,9 +46,39 @@ impl Infra {
             Box::new(sql::<Bool>("TRUE"))
         }
 
+        fn type_labels(labels: Vec<InfraErrorTypeLabel>) -> Vec<String> {
+            labels
+                .into_iter()
+                .map(|label| label.as_ref().to_owned())
+                .collect()
+        }
+        let (disabled, forced_warnings, forced_errors) = profile
+            .map(|profile| {
+                (
+                    type_labels(profile.disabled_types()),
+                    type_labels(profile.types_with_severity(Severity::Warning)),
+                    type_labels(profile.types_with_severity(Severity::Error)),
+                )
+            })
+            .unwrap_or_default();
+
+        let profile_filter: Filter =
+            Box::new(sql::<Text>("information->>'error_type'").ne_all(disabled));
+        // An error is of the requested level if its type is forced to it by the profile,
+        // or if it has the requested level by default and its type isn't forced to the other one.
+        let level_filter =
+            |is_warning: &'static str, forced: Vec<String>, others: Vec<String>| -> Filter {
+                Box::new(
+                    sql::<Text>("information->>'error_type'")
+                        .eq_any(forced)
+                        .or(sql::<Text>("information->>'is_warning'")
+                            .eq(is_warning)
+                            .and(sql::<Text>("information->>'error_type'").ne_all(others))),
+                )
+            };
         let level_filter: Filter = match level {
-            Level::Warnings => Box::new(sql::<Text>("information->>'is_warning'").eq("true")),
-            Level::Errors => Box::new(sql::<Text>("information->>'is_warning'").eq("false")),
+            Level::Warnings => level_filter("true", forced_warnings, forced_errors),
+            Level::Errors => level_filter("false", forced_errors, forced_warnings),
             Level::All => sql_true(),
         };
         let error_type_filter: Filter = error_type
@@ -58,6 +96,7 @@ impl Infra {
         let query = dsl::infra_layer_error
             .select(dsl::information)
             .filter(dsl::infra_id.eq(self.id))
+            .filter(profile_filter)
             .filter(level_filter)
             .filter(error_type_filter)
             .filter(object_id_filter);
@@ -69,7 +108,14 @@ impl Infra {
         }
         let (results, count): (Vec<Result>, _) =
             load_for_pagination(conn, query, page, page_size).await?;
-        let results = results.into_iter().map(|r| r.information.0).collect();
+        let results = results
+            .into_iter()
+            .map(|r| r.information.0)
+            .filter_map(|error| match profile {
+                Some(profile) => profile.apply(error),
+                None => Some(error),
+            })
+            .collect();
         Ok((results, count))
     }
 }
@@ -0,0 +1,345 @@
+use std::collections::BTreeSet;
+
+use editoast_schemas::infra::InfraObject;
+use editoast_schemas::primitives::OSRDObject as _;
+use editoast_schemas::primitives::ObjectType;
+use itertools::Itertools;
+use json_patch::Patch;
+use serde::Deserialize;
+use serde::Serialize;
+use strum::IntoEnumIterator;
+use utoipa::ToSchema;
+
+use super::diff::InfraDiff;
+use super::diff::InfraSnapshot;
+use crate::infra_cache::operation::patch_infra_object;
+use crate::infra_cache::operation::Operation;
+use crate::infra_cache::operation::UpdateOperation;
+
+editoast_common::schemas! {
+    InfraMerge,
+    MergeConflict,
+}
+
+/// An object changed differently on both sides of a merge
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct MergeConflict {
+    pub obj_type: ObjectType,
+    pub obj_id: String,
+    /// JSON pointers of the conflicting fields, empty when the whole object conflicts
+    /// (e.g. an object deleted on one side and modified on the other)
+    pub fields: Vec<String>,
+}
+
+/// The result of merging the changes made on an infra onto another one
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct InfraMerge {
+    /// Operations applying the changes that don't conflict onto the target infra
+    pub operations: Vec<Operation>,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+impl InfraMerge {
+    /// Merges the changes made from `ancestor` to `source` onto `target`
+    ///
+    /// Both `source` and `target` are expected to derive from `ancestor`. An object is in
+    /// conflict when both sides changed it differently, unless the changes touch distinct fields.
+    pub fn new(ancestor: &InfraSnapshot, source: &InfraSnapshot, target: &InfraSnapshot) -> Self {
+        Self::merge(ancestor, source, target, true)
+    }
+
+    /// Like [InfraMerge::new], but an object changed on both sides is always in conflict
+    ///
+    /// Used when the target changes must be kept whole, such as manual editions of imported objects.
+    pub fn strict(
+        ancestor: &InfraSnapshot,
+        source: &InfraSnapshot,
+        target: &InfraSnapshot,
+    ) -> Self {
+        Self::merge(ancestor, source, target, false)
+    }
+
+    fn merge(
+        ancestor: &InfraSnapshot,
+        source: &InfraSnapshot,
+        target: &InfraSnapshot,
+        merge_fields: bool,
+    ) -> Self {
+        let mut changes = InfraDiff::default();
+        let mut conflicts = vec![];
+        for obj_type in ObjectType::iter() {
+            // Objects only found in the target infra can't be affected by the source changes
+            let obj_ids: BTreeSet<_> = ancestor.0[obj_type]
+                .keys()
+                .chain(source.0[obj_type].keys())
+                .collect();
+            for obj_id in obj_ids {
+                let base = ancestor.0[obj_type].get(obj_id);
+                let theirs = source.0[obj_type].get(obj_id);
+                let ours = target.0[obj_type].get(obj_id);
+                if theirs == base || theirs == ours {
+                    continue;
+                }
+                match (base, ours, theirs) {
+                    (_, None, Some(theirs)) if base.is_none() => {
+                        changes.created.push(theirs.clone())
+                    }
+                    (_, Some(ours), None) if base == Some(ours) => {
+                        changes.deleted.push(ours.get_ref())
+                    }
+                    (_, Some(ours), Some(theirs)) if base == Some(ours) => {
+                        changes.modified.push(UpdateOperation {
+                            obj_id: obj_id.clone(),
+                            obj_type,
+                            railjson_patch: json_patch::diff(&ours.get_data(), &theirs.get_data()),
+                        })
+                    }
+                    (Some(base), Some(ours), Some(theirs)) if merge_fields => {
+                        match merge_object(base, ours, theirs) {
+                            Ok(railjson_patch) => changes.modified.push(UpdateOperation {
+                                obj_id: obj_id.clone(),
+                                obj_type,
+                                railjson_patch,
+                            }),
+                            Err(fields) => conflicts.push(MergeConflict {
+                                obj_type,
+                                obj_id: obj_id.clone(),
+                                fields,
+                            }),
+                        }
+                    }
+                    (_, Some(ours), Some(theirs)) => conflicts.push(MergeConflict {
+                        obj_type,
+                        obj_id: obj_id.clone(),
+                        fields: json_patch::diff(&ours.get_data(), &theirs.get_data())
+                            .0
+                            .iter()
+                            .map(|operation| operation.path().to_string())
+                            .collect(),
+                    }),
+                    _ => conflicts.push(MergeConflict {
+                        obj_type,
+                        obj_id: obj_id.clone(),
+                        fields: vec![],
+                    }),
+                }
+            }
+        }
+        Self {
+            operations: changes.into_operations(),
+            conflicts,
+        }
+    }
+}
+
+/// Merges the field changes of an object modified on both sides
+///
+/// Returns the patch to apply on `ours`, or the conflicting fields.
+fn merge_object(
+    base: &InfraObject,
+    ours: &InfraObject,
+    theirs: &InfraObject,
+) -> Result<Patch, Vec<String>> {
+    let base_data = base.get_data();
+    let ours_data = ours.get_data();
+    let theirs_data = theirs.get_data();
+    let ours_patch = json_patch::diff(&base_data, &ours_data);
+    let theirs_patch = json_patch::diff(&base_data, &theirs_data);
+
+    let mut conflicts = vec![];
+    let mut patch = vec![];
+    for operation in theirs_patch.0 {
+        let path = operation.path().as_str();
+        if !ours_patch
+            .0
+            .iter()
+            .any(|ours_operation| paths_overlap(path, ours_operation.path().as_str()))
+        {
+            patch.push(operation);
+        } else if ours_data.pointer(path) != theirs_data.pointer(path) {
+            conflicts.push(path.to_owned());
+        }
+    }
+    if !conflicts.is_empty() {
+        return Err(conflicts.into_iter().unique().collect());
+    }
+
+    // Patches of arrays rely on indices, the merged object may not be valid anymore
+    let merged = patch_infra_object(ours, &Patch(patch)).map_err(|_| vec![])?;
+    Ok(json_patch::diff(&ours_data, &merged.get_data()))
+}
+
+/// Whether a JSON pointer targets a value within the other one
+fn paths_overlap(path: &str, other: &str) -> bool {
+    let (short, long) = if path.len() <= other.len() {
+        (path, other)
+    } else {
+        (other, path)
+    };
+    long.strip_prefix(short)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+#[cfg(test)]
+mod tests {
+    use editoast_schemas::infra::Detector;
+    use editoast_schemas::infra::TrackSection;
+    use editoast_schemas::infra::TrackSectionExtensions;
+    use editoast_schemas::infra::TrackSectionSncfExtension;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::*;
+
+    fn track(id: &str, length: f64, line_name: &str) -> InfraObject {
+        InfraObject::TrackSection {
+            railjson: TrackSection {
+                id: id.into(),
+                length,
+                extensions: TrackSectionExtensions {
+                    sncf: Some(TrackSectionSncfExtension {
+                        line_name: line_name.into(),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        }
+    }
+
+    fn detector(id: &str, position: f64) -> InfraObject {
+        InfraObject::Detector {
+            railjson: Detector {
+                id: id.into(),
+                track: "track".into(),
+                position,
+                ..Default::default()
+            },
+        }
+    }
+
+    fn apply(snapshot: &InfraSnapshot, operations: &[Operation]) -> InfraSnapshot {
+        let mut snapshot = snapshot.clone();
+        for operation in operations {
+            snapshot.apply(operation).unwrap();
+        }
+        snapshot
+    }
+
+    #[test]
+    fn merge_without_target_changes() {
+        let ancestor = InfraSnapshot::from_iter([track("track", 10.0, "a"), detector("d1", 1.0)]);
+        let source = InfraSnapshot::from_iter([track("track", 20.0, "a"), detector("d2", 2.0)]);
+
+        let merge = InfraMerge::new(&ancestor, &source, &ancestor);
+
+        assert_eq!(merge.conflicts, vec![]);
+        let merged = apply(&ancestor, &merge.operations);
+        assert_eq!(InfraDiff::new(&merged, &source), InfraDiff::default());
+    }
+
+    #[test]
+    fn merge_distinct_fields() {
+        let ancestor = InfraSnapshot::from_iter([track("track", 10.0, "a")]);
+        let source = InfraSnapshot::from_iter([track("track", 20.0, "a")]);
+        let target = InfraSnapshot::from_iter([track("track", 10.0, "b"), detector("d", 1.0)]);
+
+        let merge = InfraMerge::new(&ancestor, &source, &target);
+
+        assert_eq!(merge.conflicts, vec![]);
+        let merged = apply(&target, &merge.operations);
+        let expected = InfraSnapshot::from_iter([track("track", 20.0, "b"), detector("d", 1.0)]);
+        assert_eq!(InfraDiff::new(&merged, &expected), InfraDiff::default());
+    }
+
+    #[test]
+    fn merge_same_changes() {
+        let ancestor = InfraSnapshot::from_iter([track("track", 10.0, "a")]);
+        let source = InfraSnapshot::from_iter([track("track", 20.0, "a"), detector("d", 1.0)]);
+
+        assert_eq!(
+            InfraMerge::new(&ancestor, &source, &source),
+            InfraMerge::default()
+        );
+    }
+
+    #[test]
+    fn merge_conflicting_fields() {
+        let ancestor = InfraSnapshot::from_iter([track("track", 10.0, "a")]);
+        let source = InfraSnapshot::from_iter([track("track", 20.0, "b")]);
+        let target = InfraSnapshot::from_iter([track("track", 30.0, "b")]);
+
+        let merge = InfraMerge::new(&ancestor, &source, &target);
+
+        assert_eq!(merge.operations, vec![]);
+        assert_eq!(
+            merge.conflicts,
+            vec![MergeConflict {
+                obj_type: ObjectType::TrackSection,
+                obj_id: "track".into(),
+                fields: vec!["/length".into()],
+            }]
+        );
+    }
+
+    #[test]
+    fn merge_deletion_of_modified_object() {
+        let ancestor = InfraSnapshot::from_iter([detector("d", 1.0)]);
+        let source = InfraSnapshot::default();
+        let target = InfraSnapshot::from_iter([detector("d", 2.0)]);
+
+        let merge = InfraMerge::new(&ancestor, &source, &target);
+
+        assert_eq!(merge.operations, vec![]);
+        assert_eq!(
+            merge.conflicts,
+            vec![MergeConflict {
+                obj_type: ObjectType::Detector,
+                obj_id: "d".into(),
+                fields: vec![],
+            }]
+        );
+    }
+
+    #[test]
+    fn merge_conflicting_creations() {
+        let ancestor = InfraSnapshot::default();
+        let source = InfraSnapshot::from_iter([detector("d", 1.0)]);
+        let target = InfraSnapshot::from_iter([detector("d", 2.0)]);
+
+        let merge = InfraMerge::new(&ancestor, &source, &target);
+
+        assert_eq!(merge.conflicts.len(), 1);
+        assert_eq!(merge.conflicts[0].fields, vec!["/position".to_string()]);
+        assert_eq!(
+            serde_json::to_value(&merge.conflicts[0]).unwrap(),
+            json!({ "obj_type": "Detector", "obj_id": "d", "fields": ["/position"] })
+        );
+    }
+
+    #[test]
+    fn strict_merge_of_distinct_fields() {
+        let ancestor = InfraSnapshot::from_iter([track("track", 10.0, "a"), detector("d", 1.0)]);
+        let source = InfraSnapshot::from_iter([track("track", 20.0, "a"), detector("d", 2.0)]);
+        let target = InfraSnapshot::from_iter([track("track", 10.0, "b"), detector("d", 1.0)]);
+
+        let merge = InfraMerge::strict(&ancestor, &source, &target);
+
+        assert_eq!(merge.conflicts.len(), 1);
+        assert_eq!(merge.conflicts[0].obj_id, "track");
+        assert_eq!(merge.conflicts[0].fields.len(), 2);
+        let merged = apply(&target, &merge.operations);
+        let expected = InfraSnapshot::from_iter([track("track", 10.0, "b"), detector("d", 2.0)]);
+        assert_eq!(InfraDiff::new(&merged, &expected), InfraDiff::default());
+    }
+
+    #[test]
+    fn overlapping_paths() {
+        assert!(paths_overlap("/length", "/length"));
+        assert!(paths_overlap("/extensions", "/extensions/sncf/line_name"));
+        assert!(paths_overlap("/slopes/1/gradient", "/slopes"));
+        assert!(!paths_overlap("/slopes/1", "/slopes/10"));
+        assert!(!paths_overlap("/length", "/curves"));
+    }
+}
@@ -0,0 +1,66 @@
+use chrono::NaiveDateTime;
+use diesel::delete;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use editoast_derive::ModelV2;
+use editoast_schemas::infra::RailJson;
+
+use crate::error::Result;
+use crate::modelsv2::prelude::*;
+use crate::modelsv2::DbConnection;
+use crate::tables::infra_import::dsl;
+
+/// The latest data imported into an infra from an external source (e.g. OpenStreetMap)
+///
+/// It is the common ancestor of the infra and of the next import from the same source,
+/// which allows to tell the manual editions apart from the changes of the source.
+#[derive(Debug, Clone, ModelV2)]
+#[model(table = crate::tables::infra_import)]
+pub struct InfraImport {
+    pub id: i64,
+    pub infra_id: i64,
+    pub source: String,
+    pub imported: NaiveDateTime,
+    #[model(json)]
+    pub railjson: RailJson,
+}
+
+impl InfraImport {
+    /// Returns the latest import of an infra from the given source
+    pub async fn latest(
+        conn: &mut DbConnection,
+        infra_id: i64,
+        source: &str,
+    ) -> Result<Option<InfraImport>> {
+        Ok(dsl::infra_import
+            .filter(dsl::infra_id.eq(infra_id))
+            .filter(dsl::source.eq(source))
+            .first(conn)
+            .await
+            .optional()?
+            .map(Self::from_row))
+    }
+
+    /// Records an import of an infra, replacing the previous one from the same source
+    pub async fn record(
+        conn: &mut DbConnection,
+        infra_id: i64,
+        source: String,
+        railjson: RailJson,
+    ) -> Result<InfraImport> {
+        delete(
+            dsl::infra_import
+                .filter(dsl::infra_id.eq(infra_id))
+                .filter(dsl::source.eq(&source)),
+        )
+        .execute(conn)
+        .await?;
+        InfraImport::changeset()
+            .infra_id(infra_id)
+            .source(source)
+            .imported(chrono::Utc::now().naive_utc())
+            .railjson(railjson)
+            .create(conn)
+            .await
+    }
+}
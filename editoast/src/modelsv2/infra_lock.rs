@@ -0,0 +1,124 @@
+use chrono::NaiveDateTime;
+use diesel::delete;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use editoast_derive::ModelV2;
+use editoast_schemas::primitives::ObjectRef;
+use serde::Deserialize;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::error::Result;
+use crate::modelsv2::prelude::*;
+use crate::modelsv2::DbConnection;
+use crate::tables::infra_lock::dsl;
+
+editoast_common::schemas! {
+    InfraLock,
+}
+
+/// A soft lock reserving an object of an infra to a user until it expires
+///
+/// Editions of a locked object are rejected unless they are made by the owner of the lock.
+#[derive(Debug, Clone, ModelV2, Serialize, Deserialize, ToSchema)]
+#[model(table = crate::tables::infra_lock)]
+pub struct InfraLock {
+    pub id: i64,
+    pub infra_id: i64,
+    #[model(json)]
+    pub object: ObjectRef,
+    pub owner: String,
+    pub expires: NaiveDateTime,
+}
+
+impl InfraLock {
+    /// Returns the locks of an infra that didn't expire yet
+    pub async fn list_active(conn: &mut DbConnection, infra_id: i64) -> Result<Vec<InfraLock>> {
+        Ok(dsl::infra_lock
+            .filter(dsl::infra_id.eq(infra_id))
+            .filter(dsl::expires.gt(chrono::Utc::now().naive_utc()))
+            .order(dsl::id.asc())
+            .load(conn)
+            .await?
+            .into_iter()
+            .map(Self::from_row)
+            .collect())
+    }
+
+    /// Returns the active locks on the given objects that are not owned by `owner`
+    pub async fn held_by_others(
+        conn: &mut DbConnection,
+        infra_id: i64,
+        objects: &[ObjectRef],
+        owner: Option<&str>,
+    ) -> Result<Vec<InfraLock>> {
+        let locks = Self::list_active(conn, infra_id).await?;
+        Ok(locks
+            .into_iter()
+            .filter(|lock| objects.contains(&lock.object))
+            .filter(|lock| Some(lock.owner.as_str()) != owner)
+            .collect())
+    }
+
+    /// Locks the given objects for `owner` until `expires`
+    ///
+    /// The locks already held by `owner` on these objects are extended.
+    /// The objects must not be locked by someone else (see [InfraLock::held_by_others]).
+    pub async fn acquire(
+        conn: &mut DbConnection,
+        infra_id: i64,
+        objects: Vec<ObjectRef>,
+        owner: String,
+        expires: NaiveDateTime,
+    ) -> Result<Vec<InfraLock>> {
+        // Drop the expired locks and the ones being extended
+        let now = chrono::Utc::now().naive_utc();
+        delete(
+            dsl::infra_lock.filter(dsl::infra_id.eq(infra_id)).filter(
+                dsl::expires.le(now).or(dsl::owner
+                    .eq(&owner)
+                    .and(dsl::object.eq_any(to_values(&objects)?))),
+            ),
+        )
+        .execute(conn)
+        .await?;
+        let mut locks = vec![];
+        for object in objects {
+            let lock = InfraLock::changeset()
+                .infra_id(infra_id)
+                .object(object)
+                .owner(owner.clone())
+                .expires(expires)
+                .create(conn)
+                .await?;
+            locks.push(lock);
+        }
+        Ok(locks)
+    }
+
+    /// Releases the locks held by `owner` on the given objects
+    ///
+    /// Returns the number of released locks.
+    pub async fn release(
+        conn: &mut DbConnection,
+        infra_id: i64,
+        objects: &[ObjectRef],
+        owner: &str,
+    ) -> Result<usize> {
+        Ok(delete(
+            dsl::infra_lock
+                .filter(dsl::infra_id.eq(infra_id))
+                .filter(dsl::owner.eq(owner))
+                .filter(dsl::object.eq_any(to_values(objects)?)),
+        )
+        .execute(conn)
+        .await?)
+    }
+}
+
+fn to_values(objects: &[ObjectRef]) -> Result<Vec<serde_json::Value>> {
+    Ok(objects
+        .iter()
+        .map(serde_json::to_value)
+        .collect::<std::result::Result<_, _>>()?)
+}
@@ -0,0 +1,111 @@
+use chrono::NaiveDateTime;
+use diesel::delete;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use editoast_derive::ModelV2;
+
+use crate::error::Result;
+use crate::infra_cache::operation::Operation;
+use crate::modelsv2::prelude::*;
+use crate::modelsv2::DbConnection;
+use crate::tables::infra_revision::dsl;
+
+/// An entry of the edition history of an infra
+///
+/// Each revision stores a batch of operations applied to the infra along with the
+/// operations reverting it, which are used to undo and redo editions.
+#[derive(Debug, Clone, ModelV2)]
+#[model(table = crate::tables::infra_revision)]
+pub struct InfraRevision {
+    pub id: i64,
+    pub infra_id: i64,
+    /// The version of the infra once the revision is applied
+    pub version: String,
+    pub author: Option<String>,
+    pub created: NaiveDateTime,
+    #[model(json)]
+    pub operations: Vec<Operation>,
+    #[model(json)]
+    pub inverse_operations: Vec<Operation>,
+    pub undone: bool,
+}
+
+impl InfraRevision {
+    /// Records a new revision of an infra
+    ///
+    /// The undone revisions of the infra are discarded since they can't be redone anymore.
+    pub async fn record(
+        conn: &mut DbConnection,
+        infra_id: i64,
+        version: String,
+        author: Option<String>,
+        operations: Vec<Operation>,
+        inverse_operations: Vec<Operation>,
+    ) -> Result<InfraRevision> {
+        delete(dsl::infra_revision.filter(dsl::infra_id.eq(infra_id).and(dsl::undone.eq(true))))
+            .execute(conn)
+            .await?;
+        InfraRevision::changeset()
+            .infra_id(infra_id)
+            .version(version)
+            .author(author)
+            .created(chrono::Utc::now().naive_utc())
+            .operations(operations)
+            .inverse_operations(inverse_operations)
+            .undone(false)
+            .create(conn)
+            .await
+    }
+
+    /// Returns the `count` latest applied revisions of an infra, newest first
+    pub async fn last_applied(
+        conn: &mut DbConnection,
+        infra_id: i64,
+        count: u64,
+    ) -> Result<Vec<InfraRevision>> {
+        InfraRevision::list(
+            conn,
+            SelectionSettings::new()
+                .filter(move || InfraRevision::INFRA_ID.eq(infra_id))
+                .filter(|| InfraRevision::UNDONE.eq(false))
+                .order_by(|| InfraRevision::ID.desc())
+                .limit(count),
+        )
+        .await
+    }
+
+    /// Returns the `count` earliest undone revisions of an infra, oldest first
+    pub async fn first_undone(
+        conn: &mut DbConnection,
+        infra_id: i64,
+        count: u64,
+    ) -> Result<Vec<InfraRevision>> {
+        InfraRevision::list(
+            conn,
+            SelectionSettings::new()
+                .filter(move || InfraRevision::INFRA_ID.eq(infra_id))
+                .filter(|| InfraRevision::UNDONE.eq(true))
+                .order_by(|| InfraRevision::ID.asc())
+                .limit(count),
+        )
+        .await
+    }
+
+    /// Returns the applied revisions of an infra made after the given one, newest first
+    pub async fn applied_after(
+        conn: &mut DbConnection,
+        infra_id: i64,
+        revision_id: i64,
+    ) -> Result<Vec<InfraRevision>> {
+        Ok(dsl::infra_revision
+            .filter(dsl::infra_id.eq(infra_id))
+            .filter(dsl::undone.eq(false))
+            .filter(dsl::id.gt(revision_id))
+            .order(dsl::id.desc())
+            .load(conn)
+            .await?
+            .into_iter()
+            .map(Self::from_row)
+            .collect())
+    }
+}
@@ -4,7 +4,10 @@ pub mod electrical_profiles;
 #[cfg(test)]
 pub mod fixtures;
 pub mod infra;
+pub mod infra_import;
+pub mod infra_lock;
 pub mod infra_objects;
+pub mod infra_revision;
 pub mod light_rolling_stock;
 // We allow unused until models is moved to a separate crate
 pub mod pagination;
@@ -16,9 +19,12 @@ pub mod rolling_stock_image;
 pub mod rolling_stock_livery;
 pub mod rolling_stock_model;
 pub mod scenario;
+pub mod simulation_job;
 pub mod study;
 pub mod timetable;
+pub mod train_pattern;
 pub mod train_schedule;
+pub mod validation_profile;
 pub mod work_schedules;
 
 pub use prelude::*;
@@ -42,7 +48,11 @@ pub use crate::models::PreferredId;
 
 editoast_common::schemas! {
     infra::schemas(),
+    infra_lock::schemas(),
     rolling_stock_model::schemas(),
+    simulation_job::schemas(),
+    validation_profile::schemas(),
+    work_schedules::schemas(),
 }
 
 #[cfg(test)]
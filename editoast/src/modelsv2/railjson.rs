@@ -1,82 +1,330 @@
-use std::sync::Arc;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+use std::io::BufWriter;
+use std::io::Read;
+use std::io::Seek;
+use std::io::Write;
 
 use editoast_derive::EditoastError;
+use editoast_schemas::infra::BufferStop;
+use editoast_schemas::infra::Detector;
+use editoast_schemas::infra::Electrification;
+use editoast_schemas::infra::NeutralSection;
+use editoast_schemas::infra::OperationalPoint;
 use editoast_schemas::infra::RailJson;
-use editoast_schemas::infra::RAILJSON_VERSION;
+use editoast_schemas::infra::RailJsonObjectUpgrader;
+use editoast_schemas::infra::Route;
+use editoast_schemas::infra::Signal;
+use editoast_schemas::infra::SpeedSection;
+use editoast_schemas::infra::Switch;
+use editoast_schemas::infra::SwitchType;
+use editoast_schemas::infra::TrackSection;
+use editoast_schemas::primitives::OSRDTyped;
+use editoast_schemas::primitives::ObjectType;
+use serde::de::DeserializeOwned;
+use serde::de::DeserializeSeed;
+use serde::de::Error as _;
+use serde::de::MapAccess;
+use serde::de::SeqAccess;
+use serde::de::Visitor;
+use serde::Deserializer;
+use serde_json::Value;
+use tokio::sync::mpsc;
 
-use crate::error::InternalError;
 use crate::error::Result;
 use crate::modelsv2::infra_objects::*;
 use crate::modelsv2::prelude::*;
 use crate::modelsv2::DbConnection;
-use crate::modelsv2::DbConnectionPool;
+
+/// The maximum number of objects of a batch inserted at once when importing a railjson
+pub const RAILJSON_BATCH_SIZE: usize = 1000;
 
 #[derive(Debug, thiserror::Error, EditoastError)]
 #[editoast_error(base_id = "railjson")]
 pub enum RailJsonError {
     #[error("Unsupported railjson version '{actual}'. Should be {expected}.")]
     UnsupportedVersion { actual: String, expected: String },
+    #[error("Invalid railjson: {cause}")]
+    Invalid { cause: String },
 }
 
-/// Inserts the content of a RailJson object into the database
-///
-/// All objects are attached to a given infra.
-///
-/// #### `/!\ ATTENTION /!\` On failure this function does NOT rollback the insertions!
-pub async fn persist_railjson(
-    db_pool: Arc<DbConnectionPool>,
-    infra_id: i64,
-    railjson: RailJson,
-) -> Result<()> {
-    macro_rules! persist {
-        ($model:ident, $objects:expr) => {
-            async {
-                let conn = &mut db_pool.get().await.map_err(Into::<InternalError>::into)?;
-                let _ = $model::create_batch::<_, Vec<_>>(
-                    conn,
-                    $model::from_infra_schemas(infra_id, $objects),
-                )
-                .await?;
+macro_rules! railjson_batch {
+    ($($variant:ident($schema:ident, $model:ident, $collection:ident)),* $(,)?) => {
+        /// Objects of the same type of a railjson, persisted together
+        #[derive(Debug, Clone)]
+        pub enum RailJsonBatch {
+            $($variant(Vec<$schema>),)*
+        }
+
+        const RAILJSON_FIELDS: &[&str] = &["version", $(stringify!($collection),)*];
+
+        impl RailJsonBatch {
+            pub fn object_type(&self) -> ObjectType {
+                match self {
+                    $(Self::$variant(_) => $schema::get_type(),)*
+                }
+            }
+
+            pub fn len(&self) -> usize {
+                match self {
+                    $(Self::$variant(objects) => objects.len(),)*
+                }
+            }
+
+            pub fn is_empty(&self) -> bool {
+                self.len() == 0
+            }
+
+            /// Splits the objects of a railjson into batches of at most `batch_size` objects
+            pub fn split(railjson: RailJson, batch_size: usize) -> Vec<Self> {
+                let RailJson {
+                    version: _,
+                    $($collection,)*
+                } = railjson;
+                let mut batches = vec![];
+                $(batches.extend(into_chunks($collection, batch_size).map(Self::$variant));)*
+                batches
+            }
+
+            /// Inserts the objects of the batch, attaching them to the given infra
+            pub async fn persist(self, conn: &mut DbConnection, infra_id: i64) -> Result<()> {
+                match self {
+                    $(Self::$variant(objects) => {
+                        let _ = $model::create_batch::<_, Vec<_>>(
+                            conn,
+                            $model::from_infra_schemas(infra_id, objects),
+                        )
+                        .await?;
+                    })*
+                }
                 Ok(())
             }
+        }
+
+        impl<'de> DeserializeSeed<'de> for CollectionSeed<'_> {
+            type Value = ();
+
+            fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+                match self.collection {
+                    $(stringify!($collection) => deserializer.deserialize_seq(CollectionVisitor {
+                        seed: self,
+                        into_batch: RailJsonBatch::$variant,
+                    }),)*
+                    collection => Err(D::Error::unknown_field(collection, RAILJSON_FIELDS)),
+                }
+            }
+        }
+    };
+}
+
+railjson_batch! {
+    TrackSections(TrackSection, TrackSectionModel, track_sections),
+    BufferStops(BufferStop, BufferStopModel, buffer_stops),
+    Electrifications(Electrification, ElectrificationModel, electrifications),
+    Detectors(Detector, DetectorModel, detectors),
+    OperationalPoints(OperationalPoint, OperationalPointModel, operational_points),
+    Routes(Route, RouteModel, routes),
+    Signals(Signal, SignalModel, signals),
+    Switches(Switch, SwitchModel, switches),
+    SpeedSections(SpeedSection, SpeedSectionModel, speed_sections),
+    SwitchTypes(SwitchType, SwitchTypeModel, extended_switch_types),
+    NeutralSections(NeutralSection, NeutralSectionModel, neutral_sections),
+}
+
+fn into_chunks<T>(objects: Vec<T>, size: usize) -> impl Iterator<Item = Vec<T>> {
+    let mut objects = objects.into_iter().peekable();
+    std::iter::from_fn(move || {
+        objects.peek()?;
+        Some(objects.by_ref().take(size).collect())
+    })
+}
+
+/// Parses a railjson incrementally, sending its objects by batches of at most [RAILJSON_BATCH_SIZE] objects
+///
+/// Objects of older railjson versions are upgraded on the fly. Collections appearing before the
+/// version, as in railjsons with sorted keys, are copied to temporary files until it is read.
+/// Parsing stops at the first error, which is sent as the last message. It also stops as soon
+/// as the receiver is dropped.
+///
+/// This function blocks while reading and waiting for the receiver, it must run in a blocking task.
+pub fn stream_railjson(reader: impl Read, sender: mpsc::Sender<Result<RailJsonBatch>>) {
+    let mut deserializer = serde_json::Deserializer::from_reader(BufReader::new(reader));
+    let result = deserializer
+        .deserialize_map(RailJsonVisitor { sender: &sender })
+        .and_then(|_| deserializer.end());
+    if let Err(error) = result {
+        let error = RailJsonError::Invalid {
+            cause: error.to_string(),
         };
+        // Fails only if the import was aborted, in which case the error doesn't matter anymore
+        let _ = sender.blocking_send(Err(error.into()));
+    }
+}
+
+struct RailJsonVisitor<'a> {
+    sender: &'a mpsc::Sender<Result<RailJsonBatch>>,
+}
+
+impl<'de> Visitor<'de> for RailJsonVisitor<'_> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a railjson object")
     }
 
-    let RailJson {
-        version,
-        track_sections,
-        buffer_stops,
-        electrifications,
-        detectors,
-        operational_points,
-        routes,
-        signals,
-        switches,
-        speed_sections,
-        extended_switch_types,
-        neutral_sections,
-    } = railjson;
-    if version != RAILJSON_VERSION {
-        return Err(RailJsonError::UnsupportedVersion {
-            actual: version,
-            expected: RAILJSON_VERSION.to_string(),
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        let mut upgrader = None;
+        let mut collections = HashSet::new();
+        // Objects can't be upgraded before knowing the version, they are kept on disk until then
+        let mut pending_collections: Vec<(String, File)> = vec![];
+        while let Some(field) = map.next_key::<String>()? {
+            if field == "version" {
+                if upgrader.is_some() {
+                    return Err(A::Error::duplicate_field("version"));
+                }
+                let version = map.next_value::<String>()?;
+                let version_upgrader =
+                    RailJsonObjectUpgrader::new(&version).map_err(A::Error::custom)?;
+                upgrader = Some(version_upgrader);
+                for (collection, mut file) in pending_collections.drain(..) {
+                    file.rewind().map_err(A::Error::custom)?;
+                    let mut deserializer =
+                        serde_json::Deserializer::from_reader(BufReader::new(file));
+                    CollectionSeed {
+                        collection: &collection,
+                        upgrader: version_upgrader,
+                        sender: self.sender,
+                    }
+                    .deserialize(&mut deserializer)
+                    .map_err(A::Error::custom)?;
+                }
+                continue;
+            }
+            if !RAILJSON_FIELDS.contains(&field.as_str()) {
+                return Err(A::Error::unknown_field(&field, RAILJSON_FIELDS));
+            }
+            if !collections.insert(field.clone()) {
+                return Err(A::Error::custom(format!("duplicate field `{field}`")));
+            }
+            match upgrader {
+                Some(upgrader) => map.next_value_seed(CollectionSeed {
+                    collection: &field,
+                    upgrader,
+                    sender: self.sender,
+                })?,
+                None => {
+                    let file = tempfile::tempfile().map_err(A::Error::custom)?;
+                    let file = map.next_value_seed(SpillSeed(BufWriter::new(file)))?;
+                    pending_collections.push((field, file));
+                }
+            }
+        }
+        if upgrader.is_none() {
+            return Err(A::Error::missing_field("version"));
+        }
+        match RAILJSON_FIELDS[1..]
+            .iter()
+            .find(|collection| !collections.contains(**collection))
+        {
+            Some(collection) => Err(A::Error::missing_field(collection)),
+            None => Ok(()),
         }
-        .into());
     }
-    futures::try_join!(
-        persist!(TrackSectionModel, track_sections),
-        persist!(BufferStopModel, buffer_stops),
-        persist!(ElectrificationModel, electrifications),
-        persist!(DetectorModel, detectors),
-        persist!(OperationalPointModel, operational_points),
-        persist!(RouteModel, routes),
-        persist!(SignalModel, signals),
-        persist!(SwitchModel, switches),
-        persist!(SpeedSectionModel, speed_sections),
-        persist!(SwitchTypeModel, extended_switch_types),
-        persist!(NeutralSectionModel, neutral_sections),
-    )
-    .map(|_| ())
+}
+
+/// Copies the array of objects of a collection to a file, one object at a time
+struct SpillSeed(BufWriter<File>);
+
+impl<'de> DeserializeSeed<'de> for SpillSeed {
+    type Value = File;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<File, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for SpillSeed {
+    type Value = File;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an array of objects")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(mut self, mut seq: A) -> Result<File, A::Error> {
+        let mut separator = "";
+        self.0.write_all(b"[").map_err(A::Error::custom)?;
+        while let Some(object) = seq.next_element::<Value>()? {
+            self.0
+                .write_all(separator.as_bytes())
+                .map_err(A::Error::custom)?;
+            serde_json::to_writer(&mut self.0, &object).map_err(A::Error::custom)?;
+            separator = ",";
+        }
+        self.0.write_all(b"]").map_err(A::Error::custom)?;
+        self.0.into_inner().map_err(A::Error::custom)
+    }
+}
+
+/// Parses the array of objects of a collection of a railjson, e.g. `"signals"`
+struct CollectionSeed<'a> {
+    collection: &'a str,
+    upgrader: RailJsonObjectUpgrader,
+    sender: &'a mpsc::Sender<Result<RailJsonBatch>>,
+}
+
+struct CollectionVisitor<'a, T> {
+    seed: CollectionSeed<'a>,
+    into_batch: fn(Vec<T>) -> RailJsonBatch,
+}
+
+impl<T> CollectionVisitor<'_, T> {
+    fn send<E: serde::de::Error>(&self, objects: Vec<T>) -> Result<(), E> {
+        self.seed
+            .sender
+            .blocking_send(Ok((self.into_batch)(objects)))
+            .map_err(|_| E::custom("the railjson import was aborted"))
+    }
+}
+
+impl<'de, T: DeserializeOwned> Visitor<'de> for CollectionVisitor<'_, T> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "an array of {}", self.seed.collection)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        let mut objects = Vec::with_capacity(RAILJSON_BATCH_SIZE);
+        loop {
+            let object = if self.seed.upgrader.is_noop() {
+                seq.next_element::<T>()?
+            } else {
+                let Some(mut object) = seq.next_element::<Value>()? else {
+                    break;
+                };
+                self.seed
+                    .upgrader
+                    .upgrade(self.seed.collection, &mut object);
+                Some(serde_json::from_value(object).map_err(A::Error::custom)?)
+            };
+            let Some(object) = object else {
+                break;
+            };
+            objects.push(object);
+            if objects.len() == RAILJSON_BATCH_SIZE {
+                self.send(std::mem::replace(
+                    &mut objects,
+                    Vec::with_capacity(RAILJSON_BATCH_SIZE),
+                ))?;
+            }
+        }
+        if !objects.is_empty() {
+            self.send(objects)?;
+        }
+        Ok(())
+    }
 }
 
 pub async fn find_all_schemas<T, C>(conn: &mut DbConnection, infra_id: i64) -> Result<C>
@@ -90,3 +338,100 @@ where
         .map(Into::into)
         .collect())
 }
+
+#[cfg(test)]
+mod tests {
+    use editoast_schemas::infra::downgrade_railjson;
+    use editoast_schemas::infra::RailJson;
+    use editoast_schemas::infra::Signal;
+    use editoast_schemas::infra::TrackSection;
+    use editoast_schemas::primitives::ObjectType;
+    use pretty_assertions::assert_eq;
+    use tokio::sync::mpsc;
+
+    use super::stream_railjson;
+    use super::RailJsonBatch;
+    use super::RAILJSON_BATCH_SIZE;
+    use crate::error::Result;
+
+    fn stream_batches(railjson: String) -> Vec<Result<RailJsonBatch>> {
+        let (sender, mut receiver) = mpsc::channel(16);
+        stream_railjson(railjson.as_bytes(), sender);
+        std::iter::from_fn(|| receiver.try_recv().ok()).collect()
+    }
+
+    #[test]
+    fn stream_railjson_by_batches() {
+        let railjson = RailJson {
+            track_sections: vec![TrackSection::default(); RAILJSON_BATCH_SIZE + 1],
+            signals: vec![Signal::default(); 2],
+            ..Default::default()
+        };
+        let batches = stream_batches(serde_json::to_string(&railjson).unwrap())
+            .into_iter()
+            .map(|batch| {
+                let batch = batch.expect("the railjson should be valid");
+                (batch.object_type(), batch.len())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            batches,
+            vec![
+                (ObjectType::TrackSection, RAILJSON_BATCH_SIZE),
+                (ObjectType::TrackSection, 1),
+                (ObjectType::Signal, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn stream_older_railjson() {
+        let railjson = RailJson {
+            track_sections: vec![TrackSection::default(); 2],
+            ..Default::default()
+        };
+        let mut older_railjson = serde_json::to_value(&railjson).unwrap();
+        downgrade_railjson(&mut older_railjson, "3.4.6").unwrap();
+        // The keys of the payload are sorted, the version coming after the objects
+        let batches = stream_batches(older_railjson.to_string());
+        assert_eq!(batches.len(), 1);
+        let Ok(RailJsonBatch::TrackSections(track_sections)) = &batches[0] else {
+            panic!("expected a batch of track sections");
+        };
+        assert_eq!(track_sections, &railjson.track_sections);
+    }
+
+    #[test]
+    fn stream_invalid_railjson() {
+        let railjson = RailJson {
+            track_sections: vec![TrackSection::default()],
+            ..Default::default()
+        };
+        let mut railjson = serde_json::to_value(&railjson).unwrap();
+        railjson["track_sections"][0]["unknown_field"] = true.into();
+        let batches = stream_batches(railjson.to_string());
+        assert!(batches.last().unwrap().is_err());
+    }
+
+    #[test]
+    fn split_railjson() {
+        let railjson = RailJson {
+            track_sections: vec![TrackSection::default(); 5],
+            signals: vec![Signal::default()],
+            ..Default::default()
+        };
+        let batches = RailJsonBatch::split(railjson, 2)
+            .iter()
+            .map(|batch| (batch.object_type(), batch.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            batches,
+            vec![
+                (ObjectType::TrackSection, 2),
+                (ObjectType::TrackSection, 2),
+                (ObjectType::TrackSection, 1),
+                (ObjectType::Signal, 1),
+            ]
+        );
+    }
+}
@@ -0,0 +1,115 @@
+use chrono::NaiveDateTime;
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use editoast_derive::ModelV2;
+use serde::Deserialize;
+use serde::Serialize;
+use strum::FromRepr;
+use utoipa::ToSchema;
+
+use crate::error::Result;
+use crate::modelsv2::prelude::*;
+use crate::modelsv2::DbConnection;
+use crate::views::v2::train_schedule::SimulationSummaryResult;
+
+editoast_common::schemas! {
+    SimulationStatus,
+}
+
+/// A simulation of all the trains of a timetable on an infra, computed in the background
+///
+/// The summary of each train is persisted in a [SimulationJobTrain] as soon as it is computed.
+#[derive(Debug, Clone, ModelV2)]
+#[model(table = crate::tables::simulation_job)]
+pub struct SimulationJob {
+    pub id: i64,
+    pub timetable_id: i64,
+    pub infra_id: i64,
+    pub creation_date: NaiveDateTime,
+    /// The last time the runner of the job showed it was still running
+    pub heartbeat: NaiveDateTime,
+}
+
+impl SimulationJob {
+    /// Refreshes the heartbeat of a job
+    ///
+    /// Returns whether the job still exists.
+    pub async fn beat(conn: &mut DbConnection, job_id: i64) -> Result<bool> {
+        use crate::tables::simulation_job::dsl;
+        let updated = diesel::update(dsl::simulation_job.find(job_id))
+            .set(dsl::heartbeat.eq(Utc::now().naive_utc()))
+            .execute(conn)
+            .await?;
+        Ok(updated > 0)
+    }
+
+    /// Claims the jobs with pending trains whose heartbeat is older than `stale_before`
+    ///
+    /// The heartbeat of the claimed jobs is refreshed by the same query, so that a stale job
+    /// is only claimed once.
+    pub async fn claim_stale(
+        conn: &mut DbConnection,
+        stale_before: NaiveDateTime,
+    ) -> Result<Vec<SimulationJob>> {
+        use crate::tables::simulation_job::dsl;
+        use crate::tables::simulation_job_train::dsl as train_dsl;
+        let pending_jobs = train_dsl::simulation_job_train
+            .filter(train_dsl::status.eq(SimulationStatus::Pending as i16))
+            .select(train_dsl::job_id);
+        let stale_jobs = dsl::simulation_job
+            .filter(dsl::heartbeat.lt(stale_before))
+            .filter(dsl::id.eq_any(pending_jobs));
+        Ok(diesel::update(stale_jobs)
+            .set(dsl::heartbeat.eq(Utc::now().naive_utc()))
+            .get_results(conn)
+            .await?
+            .into_iter()
+            .map(Self::from_row)
+            .collect())
+    }
+}
+
+#[derive(
+    Debug, Default, Clone, Copy, Serialize, Deserialize, FromRepr, ToSchema, PartialEq, Eq, Hash,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum SimulationStatus {
+    /// The train hasn't been simulated yet
+    #[default]
+    Pending,
+    /// The train has been simulated successfully
+    Done,
+    /// The simulation of the train failed
+    Failed,
+}
+
+/// The simulation of a train by a [SimulationJob]
+#[derive(Debug, Clone, ModelV2)]
+#[model(table = crate::tables::simulation_job_train)]
+pub struct SimulationJobTrain {
+    pub id: i64,
+    pub job_id: i64,
+    pub train_schedule_id: i64,
+    #[model(to_enum)]
+    pub status: SimulationStatus,
+    /// Missing while the train is pending, or when its simulation failed unexpectedly
+    #[model(json)]
+    pub summary: Option<SimulationSummaryResult>,
+}
+
+impl SimulationJobTrain {
+    /// Marks the trains of a job that are still pending as failed
+    ///
+    /// Returns the number of trains marked as failed.
+    pub async fn fail_pending(conn: &mut DbConnection, job_id: i64) -> Result<usize> {
+        use crate::tables::simulation_job_train::dsl;
+        let pending = dsl::simulation_job_train
+            .filter(dsl::job_id.eq(job_id))
+            .filter(dsl::status.eq(SimulationStatus::Pending as i16));
+        Ok(diesel::update(pending)
+            .set(SimulationJobTrain::changeset().status(SimulationStatus::Failed))
+            .execute(conn)
+            .await?)
+    }
+}
@@ -0,0 +1,48 @@
+use std::collections::HashMap;
+
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use editoast_derive::ModelV2;
+use editoast_schemas::train_schedule::Recurrence;
+use editoast_schemas::train_schedule::TrainScheduleBase;
+
+use crate::error::Result;
+use crate::modelsv2::DbConnection;
+use crate::tables::train_schedule_v2::dsl;
+
+/// A periodic train, materialized as one train schedule per departure of its recurrence
+///
+/// The `start_time` of the template is ignored, each occurrence departing at one of
+/// the departure times of the recurrence.
+#[derive(Debug, Clone, ModelV2)]
+#[model(table = crate::tables::train_pattern)]
+pub struct TrainPattern {
+    pub id: i64,
+    pub timetable_id: i64,
+    #[model(json)]
+    pub template: TrainScheduleBase,
+    #[model(json)]
+    pub recurrence: Recurrence,
+}
+
+impl TrainPattern {
+    /// Returns the ids of the occurrences of the given train patterns, by departure time
+    pub async fn occurrence_ids(
+        conn: &mut DbConnection,
+        pattern_ids: &[i64],
+    ) -> Result<HashMap<i64, Vec<i64>>> {
+        let occurrences: Vec<(Option<i64>, i64)> = dsl::train_schedule_v2
+            .filter(dsl::pattern_id.eq_any(pattern_ids))
+            .order(dsl::start_time)
+            .select((dsl::pattern_id, dsl::id))
+            .load(conn)
+            .await?;
+        let mut ids: HashMap<_, _> = pattern_ids.iter().map(|id| (*id, vec![])).collect();
+        for (pattern_id, id) in occurrences {
+            if let Some(occurrences) = pattern_id.and_then(|pattern_id| ids.get_mut(&pattern_id)) {
+                occurrences.push(id);
+            }
+        }
+        Ok(ids)
+    }
+}
@@ -34,4 +34,6 @@ pub struct TrainSchedule {
     pub power_restrictions: Vec<PowerRestrictionItem>,
     #[model(json)]
     pub options: TrainScheduleOptions,
+    /// The train pattern this train schedule is an occurrence of
+    pub pattern_id: Option<i64>,
 }
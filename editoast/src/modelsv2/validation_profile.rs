@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+
+use editoast_derive::ModelV2;
+use serde::Deserialize;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::generated_data::infra_error::InfraError;
+use crate::generated_data::infra_error::InfraErrorTypeLabel;
+
+editoast_common::schemas! {
+    ValidationProfile,
+    ValidationRule,
+    Severity,
+}
+
+/// A named set of rules customizing which infra errors are reported and with which severity
+///
+/// Error types missing from the rules keep their default behaviour.
+#[derive(Debug, Clone, ModelV2, Serialize, Deserialize, ToSchema)]
+#[model(table = crate::tables::validation_profile)]
+pub struct ValidationProfile {
+    pub id: i64,
+    pub name: String,
+    #[model(json)]
+    pub rules: HashMap<InfraErrorTypeLabel, ValidationRule>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ValidationRule {
+    /// Whether the errors of this type are reported
+    #[serde(default = "default_enabled")]
+    #[schema(default = true)]
+    pub enabled: bool,
+    /// Overrides the default severity of the errors of this type
+    #[serde(default)]
+    pub severity: Option<Severity>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl ValidationProfile {
+    /// Returns whether the errors of the given type are reported
+    pub fn is_enabled(&self, error_type: InfraErrorTypeLabel) -> bool {
+        self.rules
+            .get(&error_type)
+            .map_or(true, |rule| rule.enabled)
+    }
+
+    /// Returns the error types that are not reported
+    pub fn disabled_types(&self) -> Vec<InfraErrorTypeLabel> {
+        self.rules
+            .iter()
+            .filter(|(_, rule)| !rule.enabled)
+            .map(|(error_type, _)| *error_type)
+            .collect()
+    }
+
+    /// Returns the enabled error types whose severity is forced to the given one
+    pub fn types_with_severity(&self, severity: Severity) -> Vec<InfraErrorTypeLabel> {
+        self.rules
+            .iter()
+            .filter(|(_, rule)| rule.enabled && rule.severity == Some(severity))
+            .map(|(error_type, _)| *error_type)
+            .collect()
+    }
+
+    /// Applies the profile to an error
+    ///
+    /// Returns `None` if the error type is disabled, otherwise the error with its severity overridden.
+    pub fn apply(&self, mut error: InfraError) -> Option<InfraError> {
+        let error_type = InfraErrorTypeLabel::from(&error.sub_type);
+        let rule = match self.rules.get(&error_type) {
+            Some(rule) if !rule.enabled => return None,
+            Some(rule) => rule,
+            None => return Some(error),
+        };
+        if let Some(severity) = rule.severity {
+            error.is_warning = severity == Severity::Warning;
+        }
+        Some(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::Severity;
+    use super::ValidationProfile;
+    use super::ValidationRule;
+    use crate::generated_data::infra_error::InfraError;
+    use crate::generated_data::infra_error::InfraErrorTypeLabel;
+    use crate::infra_cache::tests::create_buffer_stop_cache;
+    use crate::infra_cache::tests::create_track_section_cache;
+    use editoast_schemas::infra::Endpoint;
+
+    fn profile(
+        rules: impl IntoIterator<Item = (InfraErrorTypeLabel, ValidationRule)>,
+    ) -> ValidationProfile {
+        ValidationProfile {
+            id: 0,
+            name: "profile".to_owned(),
+            rules: HashMap::from_iter(rules),
+        }
+    }
+
+    #[test]
+    fn rules_deserialization() {
+        let rules: HashMap<InfraErrorTypeLabel, ValidationRule> = serde_json::from_str(
+            r#"{"missing_buffer_stop": {"severity": "error"}, "empty_object": {"enabled": false}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            rules[&InfraErrorTypeLabel::MissingBufferStop],
+            ValidationRule {
+                enabled: true,
+                severity: Some(Severity::Error)
+            }
+        );
+        assert!(!rules[&InfraErrorTypeLabel::EmptyObject].enabled);
+    }
+
+    #[test]
+    fn apply_severity_override() {
+        let profile = profile([(
+            InfraErrorTypeLabel::MissingBufferStop,
+            ValidationRule {
+                enabled: true,
+                severity: Some(Severity::Error),
+            },
+        )]);
+        let track = create_track_section_cache("A", 500.);
+        let error = InfraError::new_missing_buffer_stop(&track, Endpoint::Begin);
+        assert!(error.is_warning);
+        let error = profile.apply(error).unwrap();
+        assert!(!error.is_warning);
+    }
+
+    #[test]
+    fn apply_disabled_type() {
+        let profile = profile([(
+            InfraErrorTypeLabel::OutOfRange,
+            ValidationRule {
+                enabled: false,
+                severity: None,
+            },
+        )]);
+        let buffer_stop = create_buffer_stop_cache("BF", "A", 530.);
+        let error = InfraError::new_out_of_range(&buffer_stop, "position", 530., [0., 500.]);
+        assert!(profile.apply(error).is_none());
+        assert!(!profile.is_enabled(InfraErrorTypeLabel::OutOfRange));
+        assert!(profile.is_enabled(InfraErrorTypeLabel::EmptyObject));
+        assert_eq!(
+            profile.disabled_types(),
+            vec![InfraErrorTypeLabel::OutOfRange]
+        );
+    }
+}
@@ -1,4 +1,6 @@
 use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
 use editoast_derive::ModelV2;
 use editoast_schemas::infra::TrackRange;
 use strum::FromRepr;
@@ -7,7 +9,16 @@ use serde::Deserialize;
 use serde::Serialize;
 use utoipa::ToSchema;
 
-#[derive(Debug, Clone, ModelV2)]
+use crate::error::Result;
+use crate::modelsv2::prelude::*;
+use crate::modelsv2::DbConnection;
+
+editoast_common::schemas! {
+    WorkScheduleGroup,
+    WorkSchedule,
+}
+
+#[derive(Debug, Clone, ModelV2, Serialize, Deserialize, ToSchema)]
 #[model(table = crate::tables::work_schedule_group)]
 pub struct WorkScheduleGroup {
     pub id: i64,
@@ -23,7 +34,7 @@ pub enum WorkScheduleType {
     Track,
 }
 
-#[derive(Debug, Default, Clone, ModelV2)]
+#[derive(Debug, Default, Clone, ModelV2, Serialize, Deserialize, ToSchema)]
 #[model(table = crate::tables::work_schedule)]
 pub struct WorkSchedule {
     pub id: i64,
@@ -33,6 +44,36 @@ pub struct WorkSchedule {
     pub track_ranges: Vec<TrackRange>,
     pub obj_id: String,
     #[model(to_enum)]
+    #[schema(inline)]
     pub work_schedule_type: WorkScheduleType,
     pub work_schedule_group_id: i64,
 }
+
+impl WorkSchedule {
+    /// Returns the work schedules overlapping a time window, by start date
+    pub async fn list_in_time_window(
+        conn: &mut DbConnection,
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+    ) -> Result<Vec<WorkSchedule>> {
+        use crate::tables::work_schedule::dsl;
+        Ok(dsl::work_schedule
+            .filter(dsl::start_date_time.lt(end))
+            .filter(dsl::end_date_time.gt(start))
+            .order((dsl::start_date_time.asc(), dsl::id.asc()))
+            .load(conn)
+            .await?
+            .into_iter()
+            .map(Self::from_row)
+            .collect())
+    }
+
+    /// Returns true if one of the track ranges of the work schedule overlaps one of the given ones
+    pub fn intersects(&self, track_ranges: &[TrackRange]) -> bool {
+        self.track_ranges.iter().any(|range| {
+            track_ranges.iter().any(|other| {
+                range.track == other.track && range.begin < other.end && other.begin < range.end
+            })
+        })
+    }
+}
@@ -42,6 +42,8 @@ diesel::table! {
         locked -> Bool,
         created -> Timestamptz,
         modified -> Timestamptz,
+        forked_from -> Nullable<Int8>,
+        fork_revision_id -> Nullable<Int8>,
     }
 }
 
@@ -212,6 +214,34 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    use diesel::sql_types::*;
+    use postgis_diesel::sql_types::*;
+
+    infra_import (id) {
+        id -> Int8,
+        infra_id -> Int8,
+        #[max_length = 255]
+        source -> Varchar,
+        imported -> Timestamptz,
+        railjson -> Jsonb,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use postgis_diesel::sql_types::*;
+
+    infra_lock (id) {
+        id -> Int8,
+        infra_id -> Int8,
+        object -> Jsonb,
+        #[max_length = 255]
+        owner -> Varchar,
+        expires -> Timestamptz,
+    }
+}
+
 diesel::table! {
     use diesel::sql_types::*;
     use postgis_diesel::sql_types::*;
@@ -355,6 +385,24 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    use diesel::sql_types::*;
+    use postgis_diesel::sql_types::*;
+
+    infra_revision (id) {
+        id -> Int8,
+        infra_id -> Int8,
+        #[max_length = 40]
+        version -> Varchar,
+        #[max_length = 255]
+        author -> Nullable<Varchar>,
+        created -> Timestamptz,
+        operations -> Jsonb,
+        inverse_operations -> Jsonb,
+        undone -> Bool,
+    }
+}
+
 diesel::table! {
     use diesel::sql_types::*;
     use postgis_diesel::sql_types::*;
@@ -593,6 +641,32 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    use diesel::sql_types::*;
+    use postgis_diesel::sql_types::*;
+
+    simulation_job (id) {
+        id -> Int8,
+        timetable_id -> Int8,
+        infra_id -> Int8,
+        creation_date -> Timestamptz,
+        heartbeat -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use postgis_diesel::sql_types::*;
+
+    simulation_job_train (id) {
+        id -> Int8,
+        job_id -> Int8,
+        train_schedule_id -> Int8,
+        status -> Int2,
+        summary -> Jsonb,
+    }
+}
+
 diesel::table! {
     use diesel::sql_types::*;
     use postgis_diesel::sql_types::*;
@@ -643,6 +717,18 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    use diesel::sql_types::*;
+    use postgis_diesel::sql_types::*;
+
+    train_pattern (id) {
+        id -> Int8,
+        timetable_id -> Int8,
+        template -> Jsonb,
+        recurrence -> Jsonb,
+    }
+}
+
 diesel::table! {
     use diesel::sql_types::*;
     use postgis_diesel::sql_types::*;
@@ -694,6 +780,19 @@ diesel::table! {
         speed_limit_tag -> Nullable<Varchar>,
         power_restrictions -> Jsonb,
         options -> Jsonb,
+        pattern_id -> Nullable<Int8>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use postgis_diesel::sql_types::*;
+
+    validation_profile (id) {
+        id -> Int8,
+        #[max_length = 255]
+        name -> Varchar,
+        rules -> Jsonb,
     }
 }
 
@@ -737,6 +836,8 @@ diesel::joinable!(infra_layer_signal -> infra (infra_id));
 diesel::joinable!(infra_layer_speed_section -> infra (infra_id));
 diesel::joinable!(infra_layer_switch -> infra (infra_id));
 diesel::joinable!(infra_layer_track_section -> infra (infra_id));
+diesel::joinable!(infra_import -> infra (infra_id));
+diesel::joinable!(infra_lock -> infra (infra_id));
 diesel::joinable!(infra_object_buffer_stop -> infra (infra_id));
 diesel::joinable!(infra_object_detector -> infra (infra_id));
 diesel::joinable!(infra_object_electrification -> infra (infra_id));
@@ -748,6 +849,7 @@ diesel::joinable!(infra_object_signal -> infra (infra_id));
 diesel::joinable!(infra_object_speed_section -> infra (infra_id));
 diesel::joinable!(infra_object_switch -> infra (infra_id));
 diesel::joinable!(infra_object_track_section -> infra (infra_id));
+diesel::joinable!(infra_revision -> infra (infra_id));
 diesel::joinable!(pathfinding -> infra (infra_id));
 diesel::joinable!(project -> document (image_id));
 diesel::joinable!(rolling_stock_livery -> document (compound_image_id));
@@ -766,13 +868,19 @@ diesel::joinable!(search_project -> project (id));
 diesel::joinable!(search_scenario -> scenario (id));
 diesel::joinable!(search_signal -> infra_object_signal (id));
 diesel::joinable!(search_study -> study (id));
+diesel::joinable!(simulation_job -> infra (infra_id));
+diesel::joinable!(simulation_job -> timetable_v2 (timetable_id));
+diesel::joinable!(simulation_job_train -> simulation_job (job_id));
+diesel::joinable!(simulation_job_train -> train_schedule_v2 (train_schedule_id));
 diesel::joinable!(simulation_output -> train_schedule (train_schedule_id));
 diesel::joinable!(study -> project (project_id));
 diesel::joinable!(timetable_v2 -> electrical_profile_set (electrical_profile_set_id));
+diesel::joinable!(train_pattern -> timetable_v2 (timetable_id));
 diesel::joinable!(train_schedule -> pathfinding (path_id));
 diesel::joinable!(train_schedule -> rolling_stock (rolling_stock_id));
 diesel::joinable!(train_schedule -> timetable (timetable_id));
 diesel::joinable!(train_schedule_v2 -> timetable_v2 (timetable_id));
+diesel::joinable!(train_schedule_v2 -> train_pattern (pattern_id));
 diesel::joinable!(work_schedule -> work_schedule_group (work_schedule_group_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
@@ -791,6 +899,8 @@ diesel::allow_tables_to_appear_in_same_query!(
     infra_layer_speed_section,
     infra_layer_switch,
     infra_layer_track_section,
+    infra_import,
+    infra_lock,
     infra_object_buffer_stop,
     infra_object_detector,
     infra_object_electrification,
@@ -802,6 +912,7 @@ diesel::allow_tables_to_appear_in_same_query!(
     infra_object_speed_section,
     infra_object_switch,
     infra_object_track_section,
+    infra_revision,
     pathfinding,
     project,
     rolling_stock,
@@ -815,12 +926,16 @@ diesel::allow_tables_to_appear_in_same_query!(
     search_signal,
     search_study,
     search_track,
+    simulation_job,
+    simulation_job_train,
     simulation_output,
     study,
     timetable,
     timetable_v2,
+    train_pattern,
     train_schedule,
     train_schedule_v2,
+    validation_profile,
     work_schedule,
     work_schedule_group,
 );
@@ -0,0 +1,226 @@
+//! Export of simulated train schedules to GTFS feeds
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use chrono::Duration;
+use editoast_schemas::infra::OperationalPoint;
+use editoast_schemas::infra::TrackOffset;
+use editoast_schemas::train_schedule::PathItemLocation;
+use geos::Geom;
+use tracing::warn;
+
+use super::gtfs;
+use super::Result;
+use super::TimetableExchangeError;
+use crate::core::v2::pathfinding::PathfindingResult;
+use crate::core::v2::pathfinding::PathfindingResultSuccess;
+use crate::core::v2::simulation::SimulationResponse;
+use crate::core::CoreClient;
+use crate::modelsv2::prelude::*;
+use crate::modelsv2::train_schedule::TrainSchedule;
+use crate::modelsv2::DbConnection;
+use crate::modelsv2::Infra;
+use crate::modelsv2::OperationalPointModel;
+use crate::modelsv2::TrackSectionModel;
+use crate::views::v2::path::pathfinding_from_train;
+use crate::views::v2::train_schedule::train_simulation;
+use crate::RedisClient;
+
+/// Simulates the train schedules and writes their stops, with the simulated times, as a GTFS feed
+///
+/// A trip stops at the origin and the destination of its train, and wherever the train halts.
+/// The trains which can't be simulated are skipped.
+pub async fn export_gtfs(
+    conn: &mut DbConnection,
+    redis_client: Arc<RedisClient>,
+    core_client: Arc<CoreClient>,
+    infra: &Infra,
+    train_schedules: Vec<TrainSchedule>,
+    dir: &Path,
+) -> Result<()> {
+    let mut redis_conn = redis_client.get_connection().await?;
+    let mut stops: HashMap<String, gtfs::Stop> = HashMap::new();
+    let mut trips = vec![];
+    for train in train_schedules {
+        let simulation = train_simulation(
+            conn,
+            redis_client.clone(),
+            core_client.clone(),
+            &train,
+            infra,
+        )
+        .await?;
+        let pathfinding = pathfinding_from_train(
+            conn,
+            &mut redis_conn,
+            core_client.clone(),
+            infra,
+            train.clone(),
+        )
+        .await?;
+        let (
+            SimulationResponse::Success { final_output, .. },
+            PathfindingResult::Success(PathfindingResultSuccess {
+                path_items_positions,
+                ..
+            }),
+        ) = (simulation, pathfinding)
+        else {
+            warn!(
+                "Train '{}' (id: {}) could not be simulated, it is not exported",
+                train.train_name, train.id
+            );
+            continue;
+        };
+
+        let stop_durations: HashMap<_, _> = train
+            .schedule
+            .iter()
+            .filter_map(|item| Some((&item.at, *item.stop_for.as_deref()?)))
+            .collect();
+        let last = train.path.len() - 1;
+        let mut stop_times = vec![];
+        for (index, (path_item, position)) in
+            train.path.iter().zip(path_items_positions).enumerate()
+        {
+            let stop_for = stop_durations.get(&path_item.id).copied();
+            if index != 0 && index != last && stop_for.is_none() {
+                continue;
+            }
+            let stop = match stops.entry(gtfs_stop_id(&path_item.location)) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    let stop = gtfs_stop(conn, infra.id, entry.key(), &path_item.location).await?;
+                    entry.insert(stop)
+                }
+            };
+            let arrival = train.start_time
+                + Duration::milliseconds(final_output.report_train.time_at(position) as i64);
+            stop_times.push(gtfs::StopTime {
+                stop_id: stop.stop_id.clone(),
+                arrival,
+                departure: arrival + stop_for.unwrap_or_else(Duration::zero),
+            });
+        }
+        trips.push(gtfs::ExportedTrip {
+            trip_id: train.id.to_string(),
+            name: train.train_name,
+            stop_times,
+        });
+    }
+
+    let mut stops: Vec<_> = stops.into_values().collect();
+    stops.sort_by(|a, b| a.stop_id.cmp(&b.stop_id));
+    std::fs::create_dir_all(dir)?;
+    gtfs::write_gtfs(dir, &stops, &trips)
+}
+
+/// Returns the GTFS stop id of a path item location
+fn gtfs_stop_id(location: &PathItemLocation) -> String {
+    match location {
+        PathItemLocation::TrackOffset(TrackOffset { track, offset }) => format!("{track}+{offset}"),
+        PathItemLocation::OperationalPointId { operational_point } => operational_point.to_string(),
+        PathItemLocation::OperationalPointDescription {
+            trigram,
+            secondary_code,
+        } => match secondary_code {
+            Some(secondary_code) => format!("{trigram}-{secondary_code}"),
+            None => trigram.to_string(),
+        },
+        PathItemLocation::OperationalPointUic {
+            uic,
+            secondary_code,
+        } => match secondary_code {
+            Some(secondary_code) => format!("{uic}-{secondary_code}"),
+            None => uic.to_string(),
+        },
+    }
+}
+
+/// Builds the GTFS stop of a path item location, named and placed after its operational point
+async fn gtfs_stop(
+    conn: &mut DbConnection,
+    infra_id: i64,
+    stop_id: &str,
+    location: &PathItemLocation,
+) -> Result<gtfs::Stop> {
+    let (stop_code, operational_point) = match location {
+        PathItemLocation::TrackOffset(_) => (None, None),
+        PathItemLocation::OperationalPointId { operational_point } => {
+            let operational_point =
+                OperationalPointModel::retrieve(conn, (infra_id, operational_point.to_string()))
+                    .await?;
+            let uic = operational_point
+                .as_ref()
+                .and_then(|op| op.extensions.identifier.as_ref())
+                .map(|identifier| identifier.uic.to_string());
+            (uic, operational_point)
+        }
+        PathItemLocation::OperationalPointDescription {
+            trigram,
+            secondary_code,
+        } => {
+            let operational_points = OperationalPointModel::retrieve_from_trigrams(
+                conn,
+                infra_id,
+                &[trigram.to_string()],
+            )
+            .await?;
+            let operational_point = operational_points.into_iter().find(|op| {
+                let sncf = op.extensions.sncf.as_ref();
+                secondary_code.is_none() || sncf.map(|sncf| &sncf.ch) == secondary_code.as_ref()
+            });
+            (Some(trigram.to_string()), operational_point)
+        }
+        PathItemLocation::OperationalPointUic {
+            uic,
+            secondary_code,
+        } => {
+            let operational_points =
+                OperationalPointModel::retrieve_from_uic(conn, infra_id, &[*uic as i64]).await?;
+            let operational_point = operational_points.into_iter().find(|op| {
+                let sncf = op.extensions.sncf.as_ref();
+                secondary_code.is_none() || sncf.map(|sncf| &sncf.ch) == secondary_code.as_ref()
+            });
+            (Some(uic.to_string()), operational_point)
+        }
+    };
+
+    let track_offset = match (location, &operational_point) {
+        (PathItemLocation::TrackOffset(track_offset), _) => track_offset.clone(),
+        (_, Some(operational_point)) => OperationalPoint::track_offset(operational_point)
+            .into_iter()
+            .next()
+            .ok_or_else(|| TimetableExchangeError::OperationalPointWithoutParts(stop_id.into()))?,
+        (_, None) => {
+            return Err(TimetableExchangeError::OperationalPointNotFound(
+                stop_id.into(),
+            ))
+        }
+    };
+    let Some(track) =
+        TrackSectionModel::retrieve(conn, (infra_id, track_offset.track.to_string())).await?
+    else {
+        return Err(TimetableExchangeError::TrackSectionNotFound(
+            track_offset.track.to_string(),
+        ));
+    };
+    let normalized_offset = (track_offset.offset as f64 / 1000.0 / track.length).clamp(0.0, 1.0);
+    let point = geos::Geometry::try_from(&track.geo)?.interpolate_normalized(normalized_offset)?;
+
+    let stop_name = operational_point
+        .as_ref()
+        .and_then(|op| op.extensions.identifier.as_ref())
+        .map(|identifier| identifier.name.as_ref().to_owned())
+        .unwrap_or_else(|| stop_id.to_owned());
+    Ok(gtfs::Stop {
+        stop_id: stop_id.to_owned(),
+        stop_code,
+        stop_name,
+        stop_lat: point.get_y()?,
+        stop_lon: point.get_x()?,
+    })
+}
@@ -0,0 +1,570 @@
+//! Reading and writing of [GTFS](https://gtfs.org/schedule/reference/) feeds
+//!
+//! Feeds are read from and written to directories holding the extracted `.txt` files.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fs::File;
+use std::path::Path;
+
+use chrono::DateTime;
+use chrono::Datelike;
+use chrono::Duration;
+use chrono::FixedOffset;
+use chrono::NaiveDate;
+use chrono::TimeZone;
+use chrono::Utc;
+use chrono::Weekday;
+use serde::Deserialize;
+use serde::Serialize;
+
+use super::Result;
+use super::StopCode;
+use super::TimetableExchangeError;
+use super::Trip;
+use super::TripStop;
+
+#[derive(Debug, Deserialize)]
+struct GtfsStop {
+    stop_id: String,
+    #[serde(default)]
+    stop_code: String,
+    #[serde(default)]
+    parent_station: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GtfsTrip {
+    trip_id: String,
+    service_id: String,
+    #[serde(default)]
+    trip_short_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GtfsStopTime {
+    trip_id: String,
+    #[serde(default)]
+    arrival_time: String,
+    #[serde(default)]
+    departure_time: String,
+    stop_id: String,
+    stop_sequence: u32,
+    #[serde(default)]
+    pickup_type: Option<u8>,
+    #[serde(default)]
+    drop_off_type: Option<u8>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GtfsCalendar {
+    service_id: String,
+    monday: u8,
+    tuesday: u8,
+    wednesday: u8,
+    thursday: u8,
+    friday: u8,
+    saturday: u8,
+    sunday: u8,
+    start_date: String,
+    end_date: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GtfsCalendarDate {
+    service_id: String,
+    date: String,
+    exception_type: u8,
+}
+
+fn read_file<T: for<'de> Deserialize<'de>>(dir: &Path, name: &str) -> Result<Vec<T>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_path(dir.join(name))?;
+    Ok(reader
+        .deserialize()
+        .collect::<std::result::Result<_, _>>()?)
+}
+
+/// Like [read_file] but returns an empty list if the file doesn't exist
+fn read_optional_file<T: for<'de> Deserialize<'de>>(dir: &Path, name: &str) -> Result<Vec<T>> {
+    if dir.join(name).exists() {
+        read_file(dir, name)
+    } else {
+        Ok(vec![])
+    }
+}
+
+fn parse_date(date: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(date, "%Y%m%d")
+        .map_err(|_| TimetableExchangeError::InvalidTime(date.to_owned()))
+}
+
+/// Parses a GTFS time (`H:MM:SS`), which is relative to the start of the service day
+/// and can exceed 24 hours
+fn parse_time(time: &str) -> Result<Option<Duration>> {
+    if time.is_empty() {
+        return Ok(None);
+    }
+    let invalid = || TimetableExchangeError::InvalidTime(time.to_owned());
+    let parts: Vec<i64> = time
+        .split(':')
+        .map(|part| part.parse().map_err(|_| invalid()))
+        .collect::<Result<_>>()?;
+    let [hours, minutes, seconds] = parts[..] else {
+        return Err(invalid());
+    };
+    Ok(Some(
+        Duration::hours(hours) + Duration::minutes(minutes) + Duration::seconds(seconds),
+    ))
+}
+
+fn format_time(time: Duration) -> String {
+    let seconds = time.num_seconds();
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// Returns the services running on a given date
+fn services_running_on(dir: &Path, date: NaiveDate) -> Result<HashSet<String>> {
+    let mut services = HashSet::new();
+    for calendar in read_optional_file::<GtfsCalendar>(dir, "calendar.txt")? {
+        let runs = match date.weekday() {
+            Weekday::Mon => calendar.monday,
+            Weekday::Tue => calendar.tuesday,
+            Weekday::Wed => calendar.wednesday,
+            Weekday::Thu => calendar.thursday,
+            Weekday::Fri => calendar.friday,
+            Weekday::Sat => calendar.saturday,
+            Weekday::Sun => calendar.sunday,
+        } == 1;
+        if runs
+            && parse_date(&calendar.start_date)? <= date
+            && date <= parse_date(&calendar.end_date)?
+        {
+            services.insert(calendar.service_id);
+        }
+    }
+    for calendar_date in read_optional_file::<GtfsCalendarDate>(dir, "calendar_dates.txt")? {
+        if parse_date(&calendar_date.date)? != date {
+            continue;
+        }
+        match calendar_date.exception_type {
+            1 => services.insert(calendar_date.service_id),
+            _ => services.remove(&calendar_date.service_id),
+        };
+    }
+    Ok(services)
+}
+
+/// Returns the code of a stop: its `stop_code`, the UIC code its `stop_id` ends with
+/// (e.g. `StopPoint:OCETrain TER-87686006`) or the code of its parent station
+///
+/// Only 7 or 8 trailing digits are read as a UIC code, so that ids such as `stop_12` don't
+/// get matched with unrelated operational points.
+fn stop_code(stops: &HashMap<String, GtfsStop>, stop_id: &str) -> Option<StopCode> {
+    let stop = stops.get(stop_id)?;
+    let trailing_digits = stop
+        .stop_id
+        .rsplit(|c: char| !c.is_ascii_digit())
+        .next()
+        .unwrap_or_default();
+    let uic = Some(trailing_digits)
+        .filter(|digits| (7..=8).contains(&digits.len()))
+        .and_then(|digits| digits.parse().ok());
+    StopCode::parse(&stop.stop_code)
+        .or_else(|| uic.map(StopCode::Uic))
+        .or_else(|| stop_code(stops, &stop.parent_station))
+}
+
+/// Reads the trips of a GTFS feed running on a given date
+///
+/// The times of the feed are read with the given offset from UTC.
+pub fn read_gtfs(dir: &Path, date: NaiveDate, offset: FixedOffset) -> Result<Vec<Trip>> {
+    let services = services_running_on(dir, date)?;
+    let trips: Vec<GtfsTrip> = read_file::<GtfsTrip>(dir, "trips.txt")?
+        .into_iter()
+        .filter(|trip| services.contains(&trip.service_id))
+        .collect();
+    let stops: HashMap<_, _> = read_file::<GtfsStop>(dir, "stops.txt")?
+        .into_iter()
+        .map(|stop| (stop.stop_id.clone(), stop))
+        .collect();
+    let mut stop_times: HashMap<String, Vec<GtfsStopTime>> = HashMap::new();
+    for stop_time in read_file::<GtfsStopTime>(dir, "stop_times.txt")? {
+        stop_times
+            .entry(stop_time.trip_id.clone())
+            .or_default()
+            .push(stop_time);
+    }
+
+    let service_day = offset
+        .from_local_datetime(&date.and_hms_opt(0, 0, 0).unwrap())
+        .unwrap()
+        .with_timezone(&Utc);
+    let at = |time: &str| -> Result<Option<DateTime<Utc>>> {
+        Ok(parse_time(time)?.map(|time| service_day + time))
+    };
+    let mut result = vec![];
+    for trip in trips {
+        let mut trip_stop_times = stop_times.remove(&trip.trip_id).unwrap_or_default();
+        trip_stop_times.sort_by_key(|stop_time| stop_time.stop_sequence);
+        let stops = trip_stop_times
+            .into_iter()
+            .map(|stop_time| {
+                Ok(TripStop {
+                    code: stop_code(&stops, &stop_time.stop_id),
+                    stop_id: stop_time.stop_id,
+                    arrival: at(&stop_time.arrival_time)?,
+                    departure: at(&stop_time.departure_time)?,
+                    halts: stop_time.pickup_type != Some(1) || stop_time.drop_off_type != Some(1),
+                })
+            })
+            .collect::<Result<_>>()?;
+        let name = if trip.trip_short_name.is_empty() {
+            trip.trip_id
+        } else {
+            trip.trip_short_name
+        };
+        result.push(Trip { name, stops });
+    }
+    Ok(result)
+}
+
+/// A stop of an exported feed
+#[derive(Debug, Clone, Serialize)]
+pub struct Stop {
+    pub stop_id: String,
+    pub stop_code: Option<String>,
+    pub stop_name: String,
+    pub stop_lat: f64,
+    pub stop_lon: f64,
+}
+
+/// A stop of an exported trip
+#[derive(Debug, Clone)]
+pub struct StopTime {
+    pub stop_id: String,
+    pub arrival: DateTime<Utc>,
+    pub departure: DateTime<Utc>,
+}
+
+/// A trip of an exported feed
+#[derive(Debug, Clone)]
+pub struct ExportedTrip {
+    pub trip_id: String,
+    pub name: String,
+    pub stop_times: Vec<StopTime>,
+}
+
+#[derive(Serialize)]
+struct GtfsAgency {
+    agency_id: &'static str,
+    agency_name: &'static str,
+    agency_url: &'static str,
+    agency_timezone: &'static str,
+}
+
+#[derive(Serialize)]
+struct GtfsRoute<'a> {
+    route_id: &'a str,
+    agency_id: &'static str,
+    route_short_name: &'a str,
+    route_type: u8,
+}
+
+#[derive(Serialize)]
+struct GtfsExportedTrip<'a> {
+    route_id: &'a str,
+    service_id: String,
+    trip_id: &'a str,
+    trip_short_name: &'a str,
+}
+
+#[derive(Serialize)]
+struct GtfsExportedStopTime<'a> {
+    trip_id: &'a str,
+    arrival_time: String,
+    departure_time: String,
+    stop_id: &'a str,
+    stop_sequence: usize,
+}
+
+#[derive(Serialize)]
+struct GtfsExportedCalendarDate {
+    service_id: String,
+    date: String,
+    exception_type: u8,
+}
+
+fn write_file<T: Serialize>(
+    dir: &Path,
+    name: &str,
+    rows: impl IntoIterator<Item = T>,
+) -> Result<()> {
+    let mut writer = csv::Writer::from_writer(File::create(dir.join(name))?);
+    for row in rows {
+        writer.serialize(row)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Writes a GTFS feed in a directory, with a rail route per trip
+///
+/// The times are in UTC: each trip runs on the service day of its first departure.
+pub fn write_gtfs(dir: &Path, stops: &[Stop], trips: &[ExportedTrip]) -> Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let service_day = |trip: &ExportedTrip| {
+        trip.stop_times
+            .first()
+            .map(|stop_time| stop_time.departure.date_naive())
+            .unwrap_or_default()
+    };
+
+    write_file(
+        dir,
+        "agency.txt",
+        [GtfsAgency {
+            agency_id: "osrd",
+            agency_name: "OSRD",
+            agency_url: "https://osrd.fr",
+            agency_timezone: "Etc/UTC",
+        }],
+    )?;
+    write_file(dir, "stops.txt", stops)?;
+    write_file(
+        dir,
+        "routes.txt",
+        trips.iter().map(|trip| GtfsRoute {
+            route_id: &trip.trip_id,
+            agency_id: "osrd",
+            route_short_name: &trip.name,
+            // Rail
+            route_type: 2,
+        }),
+    )?;
+    write_file(
+        dir,
+        "trips.txt",
+        trips.iter().map(|trip| GtfsExportedTrip {
+            route_id: &trip.trip_id,
+            service_id: service_day(trip).format("%Y%m%d").to_string(),
+            trip_id: &trip.trip_id,
+            trip_short_name: &trip.name,
+        }),
+    )?;
+    let service_days: HashSet<NaiveDate> = trips.iter().map(service_day).collect();
+    let mut service_days: Vec<_> = service_days.into_iter().collect();
+    service_days.sort();
+    write_file(
+        dir,
+        "calendar_dates.txt",
+        service_days
+            .into_iter()
+            .map(|day| GtfsExportedCalendarDate {
+                service_id: day.format("%Y%m%d").to_string(),
+                date: day.format("%Y%m%d").to_string(),
+                exception_type: 1,
+            }),
+    )?;
+    write_file(
+        dir,
+        "stop_times.txt",
+        trips.iter().flat_map(|trip| {
+            let start = service_day(trip).and_hms_opt(0, 0, 0).unwrap().and_utc();
+            trip.stop_times
+                .iter()
+                .enumerate()
+                .map(move |(index, stop_time)| GtfsExportedStopTime {
+                    trip_id: &trip.trip_id,
+                    arrival_time: format_time(stop_time.arrival - start),
+                    departure_time: format_time(stop_time.departure - start),
+                    stop_id: &stop_time.stop_id,
+                    stop_sequence: index + 1,
+                })
+        }),
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn write_feed(dir: &Path) {
+        let files = [
+            (
+                "stops.txt",
+                "stop_id,stop_code,stop_name,parent_station\n\
+                 StopArea:OCE87686006,,Paris Gare de Lyon,\n\
+                 StopPoint:OCETrain TER-87686006,,Paris Gare de Lyon,StopArea:OCE87686006\n\
+                 MAS,MAS,Maisons-Alfort,\n\
+                 LYO,,Lyon,StopArea:LYO\n\
+                 StopArea:LYO,LYO,Lyon,\n",
+            ),
+            (
+                "trips.txt",
+                "route_id,service_id,trip_id,trip_short_name\n\
+                 r,weekdays,t1,1234\n\
+                 r,sundays,t2,5678\n\
+                 r,weekdays,t3,\n",
+            ),
+            (
+                "stop_times.txt",
+                "trip_id,arrival_time,departure_time,stop_id,stop_sequence,pickup_type,drop_off_type\n\
+                 t1,23:50:00,23:55:00,MAS,2,1,1\n\
+                 t1,,23:40:00,StopPoint:OCETrain TER-87686006,1,,\n\
+                 t1,24:30:00,,LYO,3,,\n\
+                 t2,10:00:00,10:00:00,MAS,1,,\n\
+                 t2,11:00:00,11:00:00,LYO,2,,\n\
+                 t3,9:00:00,9:00:00,MAS,1,,\n\
+                 t3,10:00:00,10:00:00,LYO,2,,\n",
+            ),
+            (
+                "calendar.txt",
+                "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date\n\
+                 weekdays,1,1,1,1,1,0,0,20240101,20241231\n\
+                 sundays,0,0,0,0,0,0,1,20240101,20241231\n",
+            ),
+            (
+                "calendar_dates.txt",
+                "service_id,date,exception_type\n\
+                 weekdays,20240701,2\n\
+                 sundays,20240701,1\n",
+            ),
+        ];
+        for (name, content) in files {
+            fs::write(dir.join(name), content).unwrap();
+        }
+    }
+
+    #[test]
+    fn stop_code_from_stop_id() {
+        let stops: HashMap<_, _> = ["StopPoint:OCETrain TER-87686006", "stop_12"]
+            .map(|stop_id| {
+                let stop = GtfsStop {
+                    stop_id: stop_id.to_owned(),
+                    stop_code: String::new(),
+                    parent_station: String::new(),
+                };
+                (stop_id.to_owned(), stop)
+            })
+            .into_iter()
+            .collect();
+        assert_eq!(
+            stop_code(&stops, "StopPoint:OCETrain TER-87686006"),
+            Some(StopCode::Uic(87686006))
+        );
+        assert_eq!(stop_code(&stops, "stop_12"), None);
+    }
+
+    #[test]
+    fn read_trips() {
+        let dir = tempfile::tempdir().unwrap();
+        write_feed(dir.path());
+        let offset = FixedOffset::east_opt(2 * 3600).unwrap();
+        let at =
+            |day, hour, minute| Some(Utc.with_ymd_and_hms(2024, 7, day, hour, minute, 0).unwrap());
+
+        // Tuesday
+        let trips = read_gtfs(
+            dir.path(),
+            NaiveDate::from_ymd_opt(2024, 7, 2).unwrap(),
+            offset,
+        )
+        .unwrap();
+        let names: Vec<_> = trips.iter().map(|trip| trip.name.as_str()).collect();
+        assert_eq!(names, vec!["1234", "t3"]);
+        assert_eq!(
+            trips[0].stops,
+            vec![
+                TripStop {
+                    stop_id: "StopPoint:OCETrain TER-87686006".into(),
+                    code: Some(StopCode::Uic(87686006)),
+                    arrival: None,
+                    departure: at(2, 21, 40),
+                    halts: true,
+                },
+                TripStop {
+                    stop_id: "MAS".into(),
+                    code: Some(StopCode::Trigram("MAS".into())),
+                    arrival: at(2, 21, 50),
+                    departure: at(2, 21, 55),
+                    halts: false,
+                },
+                TripStop {
+                    stop_id: "LYO".into(),
+                    code: Some(StopCode::Trigram("LYO".into())),
+                    arrival: at(2, 22, 30),
+                    departure: None,
+                    halts: true,
+                },
+            ]
+        );
+
+        // Monday, when the calendar is swapped
+        let trips = read_gtfs(
+            dir.path(),
+            NaiveDate::from_ymd_opt(2024, 7, 1).unwrap(),
+            offset,
+        )
+        .unwrap();
+        let names: Vec<_> = trips.iter().map(|trip| trip.name.as_str()).collect();
+        assert_eq!(names, vec!["5678"]);
+    }
+
+    #[test]
+    fn write_and_read_trips() {
+        let dir = tempfile::tempdir().unwrap();
+        let at = |day, hour, minute| Utc.with_ymd_and_hms(2024, 7, day, hour, minute, 0).unwrap();
+        let stop = |id: &str| Stop {
+            stop_id: id.to_owned(),
+            stop_code: Some(id.to_owned()),
+            stop_name: id.to_owned(),
+            stop_lat: 48.8,
+            stop_lon: 2.3,
+        };
+        let trip = ExportedTrip {
+            trip_id: "1".to_owned(),
+            name: "1234".to_owned(),
+            stop_times: vec![
+                StopTime {
+                    stop_id: "PNO".to_owned(),
+                    arrival: at(1, 23, 40),
+                    departure: at(1, 23, 40),
+                },
+                StopTime {
+                    stop_id: "LYO".to_owned(),
+                    arrival: at(2, 1, 30),
+                    departure: at(2, 1, 30),
+                },
+            ],
+        };
+        write_gtfs(dir.path(), &[stop("PNO"), stop("LYO")], &[trip]).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.path().join("stop_times.txt")).unwrap(),
+            "trip_id,arrival_time,departure_time,stop_id,stop_sequence\n\
+             1,23:40:00,23:40:00,PNO,1\n\
+             1,25:30:00,25:30:00,LYO,2\n"
+        );
+
+        let trips = read_gtfs(
+            dir.path(),
+            NaiveDate::from_ymd_opt(2024, 7, 1).unwrap(),
+            FixedOffset::east_opt(0).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(trips.len(), 1);
+        assert_eq!(trips[0].name, "1234");
+        assert_eq!(trips[0].stops[1].arrival, Some(at(2, 1, 30)));
+    }
+}
@@ -0,0 +1,81 @@
+//! Import of the trips of exchanged timetables as train schedules of an infra
+
+use std::collections::HashSet;
+
+use editoast_schemas::train_schedule::TrainScheduleBase;
+use tracing::warn;
+
+use super::Result;
+use super::StopCode;
+use super::Trip;
+use crate::modelsv2::DbConnection;
+use crate::modelsv2::OperationalPointModel;
+
+/// Converts trips into train schedules running on an infra
+///
+/// The trips calling at a stop which matches no operational point of the infra are skipped.
+pub async fn import_trips(
+    conn: &mut DbConnection,
+    infra_id: i64,
+    trips: Vec<Trip>,
+    rolling_stock_name: &str,
+) -> Result<Vec<TrainScheduleBase>> {
+    let codes: HashSet<_> = trips
+        .iter()
+        .flat_map(|trip| &trip.stops)
+        .filter_map(|stop| stop.code.clone())
+        .collect();
+    let uics: Vec<_> = codes
+        .iter()
+        .filter_map(|code| match code {
+            StopCode::Uic(uic) => Some(*uic as i64),
+            StopCode::Trigram(_) => None,
+        })
+        .collect();
+    let trigrams: Vec<_> = codes
+        .iter()
+        .filter_map(|code| match code {
+            StopCode::Trigram(trigram) => Some(trigram.clone()),
+            StopCode::Uic(_) => None,
+        })
+        .collect();
+    let mut known_codes: HashSet<_> =
+        OperationalPointModel::retrieve_from_uic(conn, infra_id, &uics)
+            .await?
+            .into_iter()
+            .filter_map(|op| Some(StopCode::Uic(op.extensions.identifier.as_ref()?.uic as u32)))
+            .collect();
+    known_codes.extend(
+        OperationalPointModel::retrieve_from_trigrams(conn, infra_id, &trigrams)
+            .await?
+            .into_iter()
+            .filter_map(|op| {
+                Some(StopCode::Trigram(
+                    op.extensions.sncf.as_ref()?.trigram.clone(),
+                ))
+            }),
+    );
+
+    let mut train_schedules = vec![];
+    for trip in trips {
+        let unknown_stops: Vec<_> = trip
+            .stops
+            .iter()
+            .filter(|stop| matches!(&stop.code, Some(code) if !known_codes.contains(code)))
+            .map(|stop| stop.stop_id.as_str())
+            .collect();
+        if !unknown_stops.is_empty() {
+            warn!(
+                "Trip '{}' skipped, its stops {} match no operational point",
+                trip.name,
+                unknown_stops.join(", ")
+            );
+            continue;
+        }
+        match trip.into_train_schedule(rolling_stock_name) {
+            Ok(train_schedule) => train_schedules.push(train_schedule),
+            Err(e) => warn!("Trip skipped: {e}"),
+        }
+    }
+    Ok(train_schedules)
+}
@@ -0,0 +1,283 @@
+//! Exchange of timetables with other tools, in the GTFS and NeTEx formats
+//!
+//! Both importers read the trips running on a given day into [Trip]s, whose stops are
+//! identified by the UIC code or the trigram of an operational point. These trips are then
+//! imported as train schedules by [import_trips], while [export_gtfs] writes simulated train
+//! schedules to a GTFS feed.
+
+mod export;
+pub mod gtfs;
+mod import;
+pub mod netex;
+
+pub use export::export_gtfs;
+pub use import::import_trips;
+
+use chrono::DateTime;
+use chrono::Duration;
+use chrono::Utc;
+use editoast_schemas::primitives::PositiveDuration;
+use editoast_schemas::train_schedule::PathItem;
+use editoast_schemas::train_schedule::PathItemLocation;
+use editoast_schemas::train_schedule::ScheduleItem;
+use editoast_schemas::train_schedule::TrainScheduleBase;
+use thiserror::Error;
+
+use crate::error::InternalError;
+
+#[derive(Debug, Error)]
+pub enum TimetableExchangeError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Csv(#[from] csv::Error),
+    #[error(transparent)]
+    Xml(#[from] roxmltree::Error),
+    #[error(transparent)]
+    Internal(#[from] InternalError),
+    #[error(transparent)]
+    Redis(#[from] redis::RedisError),
+    #[error(transparent)]
+    Geos(#[from] geos::Error),
+    #[error("Invalid time '{0}'")]
+    InvalidTime(String),
+    #[error("Stop '{stop_id}' of trip '{trip}' has no UIC code or trigram")]
+    MissingStopCode { trip: String, stop_id: String },
+    #[error("Trip '{0}' has less than two stops")]
+    TooFewStops(String),
+    #[error("Trip '{0}' has no departure time")]
+    MissingDepartureTime(String),
+    #[error("The times of trip '{0}' go backwards")]
+    TimesGoingBackwards(String),
+    #[error("Operational point not found: {0}")]
+    OperationalPointNotFound(String),
+    #[error("Operational point without parts: {0}")]
+    OperationalPointWithoutParts(String),
+    #[error("Track section not found: {0}")]
+    TrackSectionNotFound(String),
+}
+
+pub type Result<T> = std::result::Result<T, TimetableExchangeError>;
+
+/// How the stop of a trip is identified among the operational points of an infra
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum StopCode {
+    Uic(u32),
+    Trigram(String),
+}
+
+impl StopCode {
+    /// Reads a stop code: digits are a UIC code, anything else a trigram
+    pub fn parse(code: &str) -> Option<StopCode> {
+        let code = code.trim();
+        if code.is_empty() {
+            return None;
+        }
+        Some(match code.parse() {
+            Ok(uic) => StopCode::Uic(uic),
+            Err(_) => StopCode::Trigram(code.to_owned()),
+        })
+    }
+
+    pub fn location(&self) -> PathItemLocation {
+        match self {
+            StopCode::Uic(uic) => PathItemLocation::OperationalPointUic {
+                uic: *uic,
+                secondary_code: None,
+            },
+            StopCode::Trigram(trigram) => PathItemLocation::OperationalPointDescription {
+                trigram: trigram.clone().into(),
+                secondary_code: None,
+            },
+        }
+    }
+}
+
+/// A stop of a trip
+#[derive(Debug, Clone, PartialEq)]
+pub struct TripStop {
+    /// The identifier of the stop in the exchanged timetable
+    pub stop_id: String,
+    pub code: Option<StopCode>,
+    pub arrival: Option<DateTime<Utc>>,
+    pub departure: Option<DateTime<Utc>>,
+    /// False if the train only passes by the stop
+    pub halts: bool,
+}
+
+/// A trip read from an exchanged timetable
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trip {
+    pub name: String,
+    pub stops: Vec<TripStop>,
+}
+
+impl Trip {
+    /// Converts the trip into a train schedule, departing at the time of its first stop
+    ///
+    /// The arrival times at the following stops become the schedule of the train, along
+    /// with the duration of the intermediate halts.
+    pub fn into_train_schedule(self, rolling_stock_name: &str) -> Result<TrainScheduleBase> {
+        let Trip { name, stops } = self;
+        if stops.len() < 2 {
+            return Err(TimetableExchangeError::TooFewStops(name));
+        }
+        let Some(start_time) = stops[0].departure.or(stops[0].arrival) else {
+            return Err(TimetableExchangeError::MissingDepartureTime(name));
+        };
+        let duration = |duration: Duration| {
+            PositiveDuration::try_from(duration)
+                .map_err(|_| TimetableExchangeError::TimesGoingBackwards(name.clone()))
+        };
+
+        let last = stops.len() - 1;
+        let mut path = vec![];
+        let mut schedule = vec![];
+        for (index, stop) in stops.iter().enumerate() {
+            let Some(code) = &stop.code else {
+                return Err(TimetableExchangeError::MissingStopCode {
+                    trip: name,
+                    stop_id: stop.stop_id.clone(),
+                });
+            };
+            let id = format!("stop-{index}");
+            path.push(PathItem {
+                id: id.clone().into(),
+                deleted: false,
+                location: code.location(),
+            });
+            if index == 0 {
+                continue;
+            }
+            let arrival = stop.arrival.or(stop.departure);
+            let stop_for = match (arrival, stop.departure) {
+                _ if !stop.halts || index == last => None,
+                (Some(arrival), Some(departure)) => Some(duration(departure - arrival)?),
+                _ => Some(duration(Duration::zero())?),
+            };
+            let arrival = arrival
+                .map(|arrival| duration(arrival - start_time))
+                .transpose()?;
+            if arrival.is_some() || stop_for.is_some() {
+                schedule.push(ScheduleItem {
+                    at: id.into(),
+                    arrival,
+                    stop_for,
+                    ..Default::default()
+                });
+            }
+        }
+
+        Ok(TrainScheduleBase {
+            train_name: name,
+            rolling_stock_name: rolling_stock_name.to_owned(),
+            start_time,
+            path,
+            schedule,
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn stop(code: &str, arrival: Option<u32>, departure: Option<u32>) -> TripStop {
+        let at = |minute| Utc.with_ymd_and_hms(2024, 7, 1, 8, minute, 0).unwrap();
+        TripStop {
+            stop_id: code.to_owned(),
+            code: StopCode::parse(code),
+            arrival: arrival.map(at),
+            departure: departure.map(at),
+            halts: true,
+        }
+    }
+
+    #[test]
+    fn stop_code() {
+        assert_eq!(StopCode::parse("87686006"), Some(StopCode::Uic(87686006)));
+        assert_eq!(
+            StopCode::parse(" PNO "),
+            Some(StopCode::Trigram("PNO".into()))
+        );
+        assert_eq!(StopCode::parse(""), None);
+    }
+
+    #[test]
+    fn trip_into_train_schedule() {
+        let trip = Trip {
+            name: "1234".into(),
+            stops: vec![
+                stop("PNO", None, Some(0)),
+                stop("87686006", Some(10), Some(12)),
+                TripStop {
+                    halts: false,
+                    ..stop("MAS", Some(20), Some(20))
+                },
+                stop("LYO", Some(30), None),
+            ],
+        };
+        let train = trip.into_train_schedule("R2D2").unwrap();
+        assert_eq!(train.train_name, "1234");
+        assert_eq!(train.rolling_stock_name, "R2D2");
+        assert_eq!(
+            train.start_time,
+            Utc.with_ymd_and_hms(2024, 7, 1, 8, 0, 0).unwrap()
+        );
+        assert_eq!(train.path.len(), 4);
+        assert_eq!(
+            train.path[1].location,
+            PathItemLocation::OperationalPointUic {
+                uic: 87686006,
+                secondary_code: None
+            }
+        );
+        let schedule: Vec<_> = train
+            .schedule
+            .iter()
+            .map(|item| {
+                (
+                    item.at.0.as_str(),
+                    item.arrival.as_ref().map(|d| d.num_minutes()),
+                    item.stop_for.as_ref().map(|d| d.num_minutes()),
+                )
+            })
+            .collect();
+        assert_eq!(
+            schedule,
+            vec![
+                ("stop-1", Some(10), Some(2)),
+                ("stop-2", Some(20), None),
+                ("stop-3", Some(30), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn trip_with_missing_stop_code() {
+        let trip = Trip {
+            name: "1234".into(),
+            stops: vec![stop("PNO", None, Some(0)), stop("", Some(10), None)],
+        };
+        assert!(matches!(
+            trip.into_train_schedule("R2D2"),
+            Err(TimetableExchangeError::MissingStopCode { .. })
+        ));
+    }
+
+    #[test]
+    fn trip_with_times_going_backwards() {
+        let trip = Trip {
+            name: "1234".into(),
+            stops: vec![stop("PNO", None, Some(10)), stop("LYO", Some(0), None)],
+        };
+        assert!(matches!(
+            trip.into_train_schedule("R2D2"),
+            Err(TimetableExchangeError::TimesGoingBackwards(_))
+        ));
+    }
+}
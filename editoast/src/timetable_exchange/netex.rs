@@ -0,0 +1,500 @@
+//! Reading of [NeTEx](https://netex-cen.eu/) timetables
+//!
+//! Only the service journeys and the stops they call at are read:
+//! - stops are identified by the `UIC` or `trigram` key of their `keyList`, or by their
+//!   `PrivateCode`, on the scheduled stop point or the quay or stop place it is assigned to,
+//! - the service journeys run on the dates of their day types, as given by the properties of
+//!   the day types and their assignments to dates, operating days or operating periods;
+//!   the journeys without any day type are read as running on the given date.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use chrono::DateTime;
+use chrono::Datelike;
+use chrono::Duration;
+use chrono::FixedOffset;
+use chrono::NaiveDate;
+use chrono::NaiveTime;
+use chrono::TimeZone;
+use chrono::Utc;
+use chrono::Weekday;
+use roxmltree::Document;
+use roxmltree::Node;
+
+use super::Result;
+use super::StopCode;
+use super::TimetableExchangeError;
+use super::Trip;
+use super::TripStop;
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children()
+        .find(|child| child.tag_name().name() == name)
+}
+
+fn child_text<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    child(node, name)
+        .and_then(|child| child.text())
+        .map(str::trim)
+}
+
+fn child_ref<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    child(node, name).and_then(|child| child.attribute("ref"))
+}
+
+fn elements<'a, 'input>(
+    document: &'a Document<'input>,
+    name: &'a str,
+) -> impl Iterator<Item = Node<'a, 'input>> {
+    document
+        .descendants()
+        .filter(move |node| node.tag_name().name() == name)
+}
+
+/// Returns the code given to a stop by its `keyList` or `PrivateCode`
+fn own_stop_code(node: Node) -> Option<StopCode> {
+    let key_values = child(node, "keyList")
+        .into_iter()
+        .flat_map(|key_list| key_list.children())
+        .filter(|key_value| key_value.tag_name().name() == "KeyValue");
+    for key_value in key_values {
+        let key = child_text(key_value, "Key").unwrap_or_default();
+        let value = child_text(key_value, "Value").unwrap_or_default();
+        if key.eq_ignore_ascii_case("uic") {
+            if let Ok(uic) = value.parse() {
+                return Some(StopCode::Uic(uic));
+            }
+        } else if key.eq_ignore_ascii_case("trigram") && !value.is_empty() {
+            return Some(StopCode::Trigram(value.to_owned()));
+        }
+    }
+    child_text(node, "PrivateCode").and_then(StopCode::parse)
+}
+
+/// Returns the code of a quay or a stop place, falling back on the stop place it is part of
+fn place_code(node: Node) -> Option<StopCode> {
+    node.ancestors()
+        .filter(|node| matches!(node.tag_name().name(), "Quay" | "StopPlace"))
+        .find_map(own_stop_code)
+}
+
+/// Returns the code of each scheduled stop point
+fn scheduled_stop_point_codes(document: &Document) -> HashMap<String, StopCode> {
+    let places: HashMap<_, _> = elements(document, "Quay")
+        .chain(elements(document, "StopPlace"))
+        .filter_map(|node| Some((node.attribute("id")?, node)))
+        .collect();
+    let mut codes: HashMap<String, StopCode> = elements(document, "PassengerStopAssignment")
+        .filter_map(|assignment| {
+            let stop_point = child_ref(assignment, "ScheduledStopPointRef")?;
+            let place = child_ref(assignment, "QuayRef")
+                .or_else(|| child_ref(assignment, "StopPlaceRef"))?;
+            Some((stop_point.to_owned(), place_code(*places.get(place)?)?))
+        })
+        .collect();
+    for stop_point in elements(document, "ScheduledStopPoint") {
+        if let (Some(id), Some(code)) = (stop_point.attribute("id"), own_stop_code(stop_point)) {
+            codes.insert(id.to_owned(), code);
+        }
+    }
+    codes
+}
+
+/// Parses a NeTEx time (`HH:MM:SS`) shifted by a number of days
+fn parse_time(time: &str, day_offset: Option<&str>) -> Result<Duration> {
+    let invalid = || TimetableExchangeError::InvalidTime(time.to_owned());
+    let day_offset: i64 = day_offset.unwrap_or("0").parse().map_err(|_| invalid())?;
+    let time = NaiveTime::parse_from_str(time, "%H:%M:%S").map_err(|_| invalid())?;
+    Ok(Duration::days(day_offset) + (time - NaiveTime::MIN))
+}
+
+/// Parses a NeTEx date, or the date of a NeTEx date time (`YYYY-MM-DDTHH:MM:SS`)
+fn parse_date(date: &str) -> Result<NaiveDate> {
+    let invalid = || TimetableExchangeError::InvalidTime(date.to_owned());
+    let day = date.get(..10).ok_or_else(invalid)?;
+    NaiveDate::parse_from_str(day, "%Y-%m-%d").map_err(|_| invalid())
+}
+
+/// Returns whether a `DaysOfWeek` list (e.g. `Monday Tuesday` or `Weekdays`) includes a date
+fn days_of_week_include(days_of_week: &str, date: NaiveDate) -> bool {
+    let weekday = date.weekday();
+    let weekend = matches!(weekday, Weekday::Sat | Weekday::Sun);
+    days_of_week.split_whitespace().any(|day| match day {
+        "Everyday" => true,
+        "Weekdays" => !weekend,
+        "Weekend" => weekend,
+        day => day.parse::<Weekday>().is_ok_and(|day| day == weekday),
+    })
+}
+
+/// Returns whether the properties of a day type allow it to run on a date
+///
+/// A day type without any `DaysOfWeek` property runs on any day.
+fn day_type_allows(day_type: Node, date: NaiveDate) -> bool {
+    let mut days_of_week = child(day_type, "properties")
+        .into_iter()
+        .flat_map(|properties| properties.children())
+        .filter(|property| property.tag_name().name() == "PropertyOfDay")
+        .filter_map(|property| child_text(property, "DaysOfWeek"))
+        .peekable();
+    days_of_week.peek().is_none() || days_of_week.any(|days| days_of_week_include(days, date))
+}
+
+/// Returns whether an operating period includes a date
+///
+/// The days of a `UicOperatingPeriod` are further restricted by its `ValidDayBits`.
+fn operating_period_includes(period: Node, date: NaiveDate) -> Result<bool> {
+    let (Some(from), Some(to)) = (child_text(period, "FromDate"), child_text(period, "ToDate"))
+    else {
+        return Ok(false);
+    };
+    let from = parse_date(from)?;
+    if date < from || parse_date(to)? < date {
+        return Ok(false);
+    }
+    Ok(match child_text(period, "ValidDayBits") {
+        Some(bits) => bits.chars().nth((date - from).num_days() as usize) == Some('1'),
+        None => true,
+    })
+}
+
+/// Returns the ids of the day types running on a given date
+///
+/// A day type runs on the dates, operating days and operating periods it is assigned to,
+/// restricted to the days of week of its properties for operating periods. An assignment
+/// whose `isAvailable` is false excludes its dates instead. A day type without any
+/// assignment runs on the days of week of its properties.
+fn day_types_running_on(document: &Document, date: NaiveDate) -> Result<HashSet<String>> {
+    let day_types: HashMap<_, _> = elements(document, "DayType")
+        .filter_map(|node| Some((node.attribute("id")?, node)))
+        .collect();
+    let operating_periods: HashMap<_, _> = elements(document, "OperatingPeriod")
+        .chain(elements(document, "UicOperatingPeriod"))
+        .filter_map(|node| Some((node.attribute("id")?, node)))
+        .collect();
+    let operating_days = elements(document, "OperatingDay")
+        .filter_map(|node| Some((node.attribute("id")?, child_text(node, "CalendarDate")?)))
+        .map(|(id, day)| Ok((id, parse_date(day)?)))
+        .collect::<Result<HashMap<_, _>>>()?;
+
+    let mut assigned = HashSet::new();
+    let mut running = HashSet::new();
+    let mut excluded = HashSet::new();
+    for assignment in elements(document, "DayTypeAssignment") {
+        let Some(day_type_id) = child_ref(assignment, "DayTypeRef") else {
+            continue;
+        };
+        assigned.insert(day_type_id);
+        let period = child_ref(assignment, "OperatingPeriodRef")
+            .or_else(|| child_ref(assignment, "UicOperatingPeriodRef"))
+            .and_then(|period| operating_periods.get(period));
+        let applies = if let Some(day) = child_text(assignment, "Date") {
+            parse_date(day)? == date
+        } else if let Some(day) = child_ref(assignment, "OperatingDayRef") {
+            operating_days.get(day) == Some(&date)
+        } else if let Some(period) = period {
+            operating_period_includes(*period, date)?
+                && day_types
+                    .get(day_type_id)
+                    .is_some_and(|day_type| day_type_allows(*day_type, date))
+        } else {
+            false
+        };
+        if !applies {
+            continue;
+        }
+        if child_text(assignment, "isAvailable") == Some("false") {
+            excluded.insert(day_type_id);
+        } else {
+            running.insert(day_type_id);
+        }
+    }
+    for (id, day_type) in &day_types {
+        if !assigned.contains(id) && day_type_allows(*day_type, date) {
+            running.insert(*id);
+        }
+    }
+    Ok(running
+        .difference(&excluded)
+        .map(|id| id.to_string())
+        .collect())
+}
+
+/// Reads the service journeys of a NeTEx document as trips running on a given date
+///
+/// The times of the document are read with the given offset from UTC.
+pub fn read_netex(xml: &str, date: NaiveDate, offset: FixedOffset) -> Result<Vec<Trip>> {
+    let document = Document::parse(xml)?;
+    let stop_point_codes = scheduled_stop_point_codes(&document);
+    // The scheduled stop point of each stop point in journey pattern, and whether trains halt there
+    let stop_points_in_pattern: HashMap<&str, (&str, bool)> =
+        elements(&document, "StopPointInJourneyPattern")
+            .filter_map(|node| {
+                let halts = child_text(node, "ForAlighting") != Some("false")
+                    || child_text(node, "ForBoarding") != Some("false");
+                Some((
+                    node.attribute("id")?,
+                    (child_ref(node, "ScheduledStopPointRef")?, halts),
+                ))
+            })
+            .collect();
+
+    let service_day = offset
+        .from_local_datetime(&date.and_hms_opt(0, 0, 0).unwrap())
+        .unwrap()
+        .with_timezone(&Utc);
+    let at = |node: Node, time: &str, day_offset: &str| -> Result<Option<DateTime<Utc>>> {
+        child_text(node, time)
+            .map(|time| Ok(service_day + parse_time(time, child_text(node, day_offset))?))
+            .transpose()
+    };
+    let day_types = day_types_running_on(&document, date)?;
+    let mut trips = vec![];
+    for journey in elements(&document, "ServiceJourney") {
+        let mut journey_day_types = child(journey, "dayTypes")
+            .into_iter()
+            .flat_map(|day_types| day_types.children())
+            .filter(|node| node.tag_name().name() == "DayTypeRef")
+            .filter_map(|node| node.attribute("ref"))
+            .peekable();
+        if journey_day_types.peek().is_some()
+            && !journey_day_types.any(|day_type| day_types.contains(day_type))
+        {
+            continue;
+        }
+        let name = child_text(journey, "Name")
+            .or_else(|| child_text(journey, "PublicCode"))
+            .or_else(|| child_text(journey, "PrivateCode"))
+            .or_else(|| journey.attribute("id"))
+            .unwrap_or_default()
+            .to_owned();
+        let passing_times = child(journey, "passingTimes")
+            .into_iter()
+            .flat_map(|passing_times| passing_times.children())
+            .filter(|node| node.tag_name().name() == "TimetabledPassingTime");
+        let mut stops = vec![];
+        for passing_time in passing_times {
+            let stop_point_ref =
+                child_ref(passing_time, "StopPointInJourneyPatternRef").unwrap_or_default();
+            let (stop_id, halts) = stop_points_in_pattern
+                .get(stop_point_ref)
+                .copied()
+                .unwrap_or((stop_point_ref, true));
+            stops.push(TripStop {
+                stop_id: stop_id.to_owned(),
+                code: stop_point_codes.get(stop_id).cloned(),
+                arrival: at(passing_time, "ArrivalTime", "ArrivalDayOffset")?,
+                departure: at(passing_time, "DepartureTime", "DepartureDayOffset")?,
+                halts,
+            });
+        }
+        trips.push(Trip { name, stops });
+    }
+    Ok(trips)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    const NETEX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<PublicationDelivery xmlns="http://www.netex.org.uk/netex" version="1.1">
+  <dataObjects>
+    <SiteFrame id="site" version="1">
+      <stopPlaces>
+        <StopPlace id="sp:PNO" version="1">
+          <keyList>
+            <KeyValue><Key>UIC</Key><Value>87686006</Value></KeyValue>
+          </keyList>
+          <quays>
+            <Quay id="q:PNO:A" version="1"/>
+          </quays>
+        </StopPlace>
+      </stopPlaces>
+    </SiteFrame>
+    <ServiceFrame id="service" version="1">
+      <scheduledStopPoints>
+        <ScheduledStopPoint id="ssp:PNO" version="1"/>
+        <ScheduledStopPoint id="ssp:MAS" version="1">
+          <PrivateCode>MAS</PrivateCode>
+        </ScheduledStopPoint>
+        <ScheduledStopPoint id="ssp:LYO" version="1">
+          <keyList>
+            <KeyValue><Key>trigram</Key><Value>LYO</Value></KeyValue>
+          </keyList>
+        </ScheduledStopPoint>
+      </scheduledStopPoints>
+      <stopAssignments>
+        <PassengerStopAssignment id="psa:PNO" version="1" order="1">
+          <ScheduledStopPointRef ref="ssp:PNO"/>
+          <QuayRef ref="q:PNO:A"/>
+        </PassengerStopAssignment>
+      </stopAssignments>
+      <journeyPatterns>
+        <ServiceJourneyPattern id="sjp" version="1">
+          <pointsInSequence>
+            <StopPointInJourneyPattern id="spijp:1" version="1" order="1">
+              <ScheduledStopPointRef ref="ssp:PNO"/>
+            </StopPointInJourneyPattern>
+            <StopPointInJourneyPattern id="spijp:2" version="1" order="2">
+              <ScheduledStopPointRef ref="ssp:MAS"/>
+              <ForAlighting>false</ForAlighting>
+              <ForBoarding>false</ForBoarding>
+            </StopPointInJourneyPattern>
+            <StopPointInJourneyPattern id="spijp:3" version="1" order="3">
+              <ScheduledStopPointRef ref="ssp:LYO"/>
+            </StopPointInJourneyPattern>
+          </pointsInSequence>
+        </ServiceJourneyPattern>
+      </journeyPatterns>
+    </ServiceFrame>
+    <TimetableFrame id="timetable" version="1">
+      <vehicleJourneys>
+        <ServiceJourney id="sj:1" version="1">
+          <Name>1234</Name>
+          <ServiceJourneyPatternRef ref="sjp"/>
+          <passingTimes>
+            <TimetabledPassingTime version="1">
+              <StopPointInJourneyPatternRef ref="spijp:1"/>
+              <DepartureTime>23:40:00</DepartureTime>
+            </TimetabledPassingTime>
+            <TimetabledPassingTime version="1">
+              <StopPointInJourneyPatternRef ref="spijp:2"/>
+              <ArrivalTime>23:50:00</ArrivalTime>
+              <DepartureTime>23:55:00</DepartureTime>
+            </TimetabledPassingTime>
+            <TimetabledPassingTime version="1">
+              <StopPointInJourneyPatternRef ref="spijp:3"/>
+              <ArrivalTime>00:30:00</ArrivalTime>
+              <ArrivalDayOffset>1</ArrivalDayOffset>
+            </TimetabledPassingTime>
+          </passingTimes>
+        </ServiceJourney>
+      </vehicleJourneys>
+    </TimetableFrame>
+  </dataObjects>
+</PublicationDelivery>
+"#;
+
+    #[test]
+    fn read_service_journeys() {
+        let trips = read_netex(
+            NETEX,
+            NaiveDate::from_ymd_opt(2024, 7, 1).unwrap(),
+            FixedOffset::east_opt(2 * 3600).unwrap(),
+        )
+        .unwrap();
+        let at =
+            |day, hour, minute| Some(Utc.with_ymd_and_hms(2024, 7, day, hour, minute, 0).unwrap());
+        assert_eq!(
+            trips,
+            vec![Trip {
+                name: "1234".into(),
+                stops: vec![
+                    TripStop {
+                        stop_id: "ssp:PNO".into(),
+                        code: Some(StopCode::Uic(87686006)),
+                        arrival: None,
+                        departure: at(1, 21, 40),
+                        halts: true,
+                    },
+                    TripStop {
+                        stop_id: "ssp:MAS".into(),
+                        code: Some(StopCode::Trigram("MAS".into())),
+                        arrival: at(1, 21, 50),
+                        departure: at(1, 21, 55),
+                        halts: false,
+                    },
+                    TripStop {
+                        stop_id: "ssp:LYO".into(),
+                        code: Some(StopCode::Trigram("LYO".into())),
+                        arrival: at(1, 22, 30),
+                        departure: None,
+                        halts: true,
+                    },
+                ],
+            }]
+        );
+    }
+
+    #[test]
+    fn read_stop_without_code() {
+        // The stop place of the first stop isn't assigned anymore
+        let netex = NETEX.replace(r#"<QuayRef ref="q:PNO:A"/>"#, "");
+        let trips = read_netex(
+            &netex,
+            NaiveDate::from_ymd_opt(2024, 7, 1).unwrap(),
+            FixedOffset::east_opt(0).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(trips[0].stops[0].stop_id, "ssp:PNO");
+        assert_eq!(trips[0].stops[0].code, None);
+    }
+
+    #[test]
+    fn read_service_journeys_running_on_date() {
+        // The journey runs on weekdays in July, except on the 2nd
+        let netex = NETEX
+            .replace(
+                r#"<ServiceJourneyPatternRef ref="sjp"/>"#,
+                r#"<ServiceJourneyPatternRef ref="sjp"/>
+          <dayTypes><DayTypeRef ref="dt:weekdays"/></dayTypes>"#,
+            )
+            .replace(
+                "  </dataObjects>",
+                r#"    <ServiceCalendarFrame id="calendar" version="1">
+      <dayTypes>
+        <DayType id="dt:weekdays" version="1">
+          <properties>
+            <PropertyOfDay><DaysOfWeek>Weekdays</DaysOfWeek></PropertyOfDay>
+          </properties>
+        </DayType>
+      </dayTypes>
+      <operatingPeriods>
+        <OperatingPeriod id="op:july" version="1">
+          <FromDate>2024-07-01T00:00:00</FromDate>
+          <ToDate>2024-07-31T00:00:00</ToDate>
+        </OperatingPeriod>
+      </operatingPeriods>
+      <dayTypeAssignments>
+        <DayTypeAssignment id="dta:1" version="1" order="1">
+          <OperatingPeriodRef ref="op:july"/>
+          <DayTypeRef ref="dt:weekdays"/>
+        </DayTypeAssignment>
+        <DayTypeAssignment id="dta:2" version="1" order="2">
+          <Date>2024-07-02</Date>
+          <DayTypeRef ref="dt:weekdays"/>
+          <isAvailable>false</isAvailable>
+        </DayTypeAssignment>
+      </dayTypeAssignments>
+    </ServiceCalendarFrame>
+  </dataObjects>"#,
+            );
+        let trip_count = |day| {
+            read_netex(
+                &netex,
+                NaiveDate::from_ymd_opt(2024, 7, day).unwrap(),
+                FixedOffset::east_opt(0).unwrap(),
+            )
+            .unwrap()
+            .len()
+        };
+        // Monday
+        assert_eq!(trip_count(1), 1);
+        // Excluded Tuesday
+        assert_eq!(trip_count(2), 0);
+        // Saturday
+        assert_eq!(trip_count(6), 0);
+        // Thursday out of the operating period
+        let trips = read_netex(
+            &netex,
+            NaiveDate::from_ymd_opt(2024, 8, 1).unwrap(),
+            FixedOffset::east_opt(0).unwrap(),
+        )
+        .unwrap();
+        assert!(trips.is_empty());
+    }
+}
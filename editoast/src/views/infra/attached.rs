@@ -1,15 +1,27 @@
 use std::collections::HashMap;
 
 use actix_web::get;
+use actix_web::post;
 use actix_web::web::Data;
 use actix_web::web::Json;
 use actix_web::web::Path;
 use chashmap::CHashMap;
 use editoast_derive::EditoastError;
+use editoast_schemas::infra::ApplicableDirectionsTrackRange;
+use json_patch::Patch;
+use json_patch::PatchOperation;
+use json_patch::RemoveOperation;
+use json_patch::ReplaceOperation;
 use serde_derive::Deserialize;
+use serde_json::json;
 use thiserror::Error;
+use utoipa::ToSchema;
 
 use crate::error::Result;
+use crate::infra_cache::object_cache::OperationalPointPartCache;
+use crate::infra_cache::operation::DeleteOperation;
+use crate::infra_cache::operation::Operation;
+use crate::infra_cache::operation::UpdateOperation;
 use crate::infra_cache::InfraCache;
 use crate::modelsv2::prelude::*;
 use crate::modelsv2::DbConnectionPoolV2;
@@ -17,7 +29,13 @@ use crate::modelsv2::Infra;
 use crate::views::infra::InfraApiError;
 use editoast_schemas::primitives::ObjectType;
 
-crate::routes! { attached }
+crate::routes! { attached, reposition }
+
+editoast_common::schemas! {
+    RepositionRequest,
+    PositionTransform,
+    OutOfRange,
+}
 
 /// Objects types that can be attached to a track
 const ATTACHED_OBJECTS_TYPES: &[ObjectType] = &[
@@ -36,6 +54,9 @@ enum AttachedError {
     #[error("Track '{track_id}' not found")]
     #[editoast_error(status = 404)]
     TrackNotFound { track_id: String },
+    #[error("Positions can't be rescaled from a length of {from_length} meters")]
+    #[editoast_error(status = 400)]
+    InvalidRescaleLength { from_length: f64 },
 }
 
 #[derive(utoipa::IntoParams, Deserialize)]
@@ -95,6 +116,284 @@ async fn attached(
     Ok(Json(res))
 }
 
+/// How the positions of the objects attached to a track are transformed
+#[derive(Debug, Clone, Copy, Deserialize, ToSchema)]
+#[serde(tag = "type", deny_unknown_fields)]
+enum PositionTransform {
+    /// Adds `offset` meters to every position
+    Shift { offset: f64 },
+    /// Scales the positions from a track of `from_length` meters to the current length of the track
+    Rescale { from_length: f64 },
+}
+
+/// What happens to the objects falling outside of the track once moved
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, ToSchema)]
+enum OutOfRange {
+    /// The objects are moved to the nearest end of the track, the bounds of ranges as well
+    #[default]
+    Clamp,
+    /// The objects are deleted, operational point parts and ranges are removed from their object
+    ///
+    /// Operational points, speed sections and electrifications left without any part or range
+    /// are deleted.
+    Drop,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+struct RepositionRequest {
+    transform: PositionTransform,
+    #[serde(default)]
+    out_of_range: OutOfRange,
+}
+
+/// Compute the operations moving all objects attached to a given track
+///
+/// Shifts or rescales the positions of the signals, detectors, buffer stops, operational point
+/// parts and the ranges of speed sections and electrifications located on the track, typically
+/// after its length was corrected. Ranges are cut at the ends of the track. With `Drop`, the
+/// ranges left empty are removed: speed sections and electrifications left without any range are
+/// deleted, as are operational points left without any part.
+///
+/// The operations are not applied: they can be previewed or applied with the edition endpoints.
+#[utoipa::path(
+    tag = "infra",
+    params(InfraAttachedParams),
+    request_body = RepositionRequest,
+    responses(
+        (status = 200, body = Vec<Operation>, description = "The operations moving the attached objects"),
+        (status = 400, description = "Invalid position transformation"),
+        (status = 404, description = "The infra or the track was not found"),
+    ),
+)]
+#[post("/attached/{track_id}/reposition")]
+async fn reposition(
+    params: Path<InfraAttachedParams>,
+    request: Json<RepositionRequest>,
+    infra_caches: Data<CHashMap<i64, InfraCache>>,
+    db_pool: Data<DbConnectionPoolV2>,
+) -> Result<Json<Vec<Operation>>> {
+    let InfraAttachedParams { infra_id, track_id } = params.into_inner();
+    let RepositionRequest {
+        transform,
+        out_of_range,
+    } = request.into_inner();
+    if let PositionTransform::Rescale { from_length } = transform {
+        if from_length <= 0.0 {
+            return Err(AttachedError::InvalidRescaleLength { from_length }.into());
+        }
+    }
+    let mut conn = db_pool.get().await?;
+    let infra =
+        Infra::retrieve_or_fail(&mut conn, infra_id, || InfraApiError::NotFound { infra_id })
+            .await?;
+    let infra_cache = InfraCache::get_or_load(&mut conn, &infra_caches, &infra).await?;
+    let Ok(track) = infra_cache.get_track_section(&track_id) else {
+        return Err(AttachedError::TrackNotFound { track_id }.into());
+    };
+    let track_reposition = Reposition {
+        track_id: &track_id,
+        length: track.length,
+        transform,
+        out_of_range,
+    };
+    Ok(Json(track_reposition.operations(&infra_cache)))
+}
+
+/// The move of the objects attached to a track
+struct Reposition<'a> {
+    track_id: &'a str,
+    /// The current length of the track
+    length: f64,
+    transform: PositionTransform,
+    out_of_range: OutOfRange,
+}
+
+impl Reposition<'_> {
+    /// Returns the transformed position, which may fall outside of the track
+    fn transform(&self, position: f64) -> f64 {
+        match self.transform {
+            PositionTransform::Shift { offset } => position + offset,
+            PositionTransform::Rescale { from_length } => position * self.length / from_length,
+        }
+    }
+
+    /// Returns the new position, or `None` if the object is dropped
+    fn position(&self, position: f64) -> Option<f64> {
+        let position = self.transform(position);
+        match self.out_of_range {
+            _ if (0.0..=self.length).contains(&position) => Some(position),
+            OutOfRange::Clamp => Some(position.clamp(0.0, self.length)),
+            OutOfRange::Drop => None,
+        }
+    }
+
+    /// Returns the new range, cut at the ends of the track, or `None` if the range is dropped
+    ///
+    /// With [OutOfRange::Drop], ranges are dropped when nothing is left of them.
+    fn range(&self, begin: f64, end: f64) -> Option<(f64, f64)> {
+        let cut = |position| self.transform(position).clamp(0.0, self.length);
+        let (begin, end) = (cut(begin), cut(end));
+        match self.out_of_range {
+            OutOfRange::Drop if begin >= end => None,
+            _ => Some((begin, end)),
+        }
+    }
+
+    /// Computes the operations moving all objects attached to the track
+    fn operations(&self, infra_cache: &InfraCache) -> Vec<Operation> {
+        let track_id = self.track_id.to_owned();
+        let mut operations = vec![];
+        for obj_type in ATTACHED_OBJECTS_TYPES {
+            for obj in infra_cache.get_track_refs_type(&track_id, *obj_type) {
+                let operation = match obj_type {
+                    ObjectType::Signal => {
+                        let signal = infra_cache.get_signal(&obj.obj_id).unwrap();
+                        self.point_operation(*obj_type, &obj.obj_id, signal.position)
+                    }
+                    ObjectType::Detector => {
+                        let detector = infra_cache.get_detector(&obj.obj_id).unwrap();
+                        self.point_operation(*obj_type, &obj.obj_id, detector.position)
+                    }
+                    ObjectType::BufferStop => {
+                        let buffer_stop = infra_cache.get_buffer_stop(&obj.obj_id).unwrap();
+                        self.point_operation(*obj_type, &obj.obj_id, buffer_stop.position)
+                    }
+                    ObjectType::OperationalPoint => {
+                        let operational_point =
+                            infra_cache.get_operational_point(&obj.obj_id).unwrap();
+                        self.operational_point_operation(&obj.obj_id, &operational_point.parts)
+                    }
+                    ObjectType::SpeedSection => {
+                        let speed_section = infra_cache.get_speed_section(&obj.obj_id).unwrap();
+                        self.ranges_operation(*obj_type, &obj.obj_id, &speed_section.track_ranges)
+                    }
+                    ObjectType::Electrification => {
+                        let electrification = infra_cache.get_electrification(&obj.obj_id).unwrap();
+                        self.ranges_operation(*obj_type, &obj.obj_id, &electrification.track_ranges)
+                    }
+                    // Switches are attached to track endpoints
+                    _ => None,
+                };
+                operations.extend(operation);
+            }
+        }
+        operations
+    }
+
+    fn point_operation(
+        &self,
+        obj_type: ObjectType,
+        obj_id: &str,
+        position: f64,
+    ) -> Option<Operation> {
+        match self.position(position) {
+            Some(new_position) if new_position == position => None,
+            Some(new_position) => update_operation(
+                obj_type,
+                obj_id,
+                vec![PatchOperation::Replace(ReplaceOperation {
+                    path: "/position".parse().unwrap(),
+                    value: json!(new_position),
+                })],
+            ),
+            None => Some(Operation::Delete(DeleteOperation {
+                obj_type,
+                obj_id: obj_id.to_owned(),
+            })),
+        }
+    }
+
+    fn operational_point_operation(
+        &self,
+        obj_id: &str,
+        parts: &[OperationalPointPartCache],
+    ) -> Option<Operation> {
+        let mut patch = vec![];
+        let mut dropped_parts = 0;
+        // Parts are removed from the last one so that the indices remain valid
+        for (index, part) in parts.iter().enumerate().rev() {
+            if part.track.as_str() != self.track_id {
+                continue;
+            }
+            patch.push(match self.position(part.position) {
+                Some(position) if position == part.position => continue,
+                Some(position) => PatchOperation::Replace(ReplaceOperation {
+                    path: format!("/parts/{index}/position").parse().unwrap(),
+                    value: json!(position),
+                }),
+                None => {
+                    dropped_parts += 1;
+                    PatchOperation::Remove(RemoveOperation {
+                        path: format!("/parts/{index}").parse().unwrap(),
+                    })
+                }
+            });
+        }
+        // An operational point without any part left is deleted
+        if dropped_parts == parts.len() {
+            return Some(Operation::Delete(DeleteOperation {
+                obj_type: ObjectType::OperationalPoint,
+                obj_id: obj_id.to_owned(),
+            }));
+        }
+        update_operation(ObjectType::OperationalPoint, obj_id, patch)
+    }
+
+    fn ranges_operation(
+        &self,
+        obj_type: ObjectType,
+        obj_id: &str,
+        track_ranges: &[ApplicableDirectionsTrackRange],
+    ) -> Option<Operation> {
+        let new_track_ranges = track_ranges
+            .iter()
+            .filter_map(|range| {
+                if range.track.as_str() != self.track_id {
+                    return Some(range.clone());
+                }
+                let (begin, end) = self.range(range.begin, range.end)?;
+                Some(ApplicableDirectionsTrackRange {
+                    begin,
+                    end,
+                    ..range.clone()
+                })
+            })
+            .collect::<Vec<_>>();
+        if new_track_ranges.is_empty() {
+            return Some(Operation::Delete(DeleteOperation {
+                obj_type,
+                obj_id: obj_id.to_owned(),
+            }));
+        }
+        if new_track_ranges == track_ranges {
+            return None;
+        }
+        update_operation(
+            obj_type,
+            obj_id,
+            vec![PatchOperation::Replace(ReplaceOperation {
+                path: "/track_ranges".parse().unwrap(),
+                value: json!(new_track_ranges),
+            })],
+        )
+    }
+}
+
+fn update_operation(
+    obj_type: ObjectType,
+    obj_id: &str,
+    patch: Vec<PatchOperation>,
+) -> Option<Operation> {
+    (!patch.is_empty()).then(|| {
+        Operation::Update(UpdateOperation {
+            obj_type,
+            obj_id: obj_id.to_owned(),
+            railjson_patch: Patch(patch),
+        })
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
@@ -112,6 +411,14 @@ mod tests {
     use editoast_schemas::infra::TrackSection;
     use editoast_schemas::primitives::OSRDIdentified;
     use editoast_schemas::primitives::ObjectType;
+    use serde_json::json;
+
+    use super::*;
+    use crate::infra_cache::tests::create_detector_cache;
+    use crate::infra_cache::tests::create_electrification_cache;
+    use crate::infra_cache::tests::create_operational_point_cache;
+    use crate::infra_cache::tests::create_signal_cache;
+    use crate::infra_cache::tests::create_track_section_cache;
 
     #[rstest]
     async fn get_attached_detector() {
@@ -149,4 +456,245 @@ mod tests {
             call_and_read_body_json(&app.service, req).await;
         assert_eq!(response.get(&ObjectType::Detector).unwrap().len(), 1);
     }
+
+    #[rstest]
+    async fn reposition_attached_detector() {
+        let app = TestAppBuilder::default_app();
+        let pool = app.db_pool();
+        let empty_infra = Infra::changeset()
+            .name("test_infra".to_owned())
+            .last_railjson_version()
+            .create(pool.get_ok().deref_mut())
+            .await
+            .expect("Failed to create infra");
+        let track = TrackSection::default().into();
+        apply_create_operation(&track, empty_infra.id, pool.get_ok().deref_mut())
+            .await
+            .expect("Failed to create track object");
+        let detector = Detector {
+            track: track.get_id().clone().into(),
+            position: 50.0,
+            ..Default::default()
+        }
+        .into();
+        apply_create_operation(&detector, empty_infra.id, pool.get_ok().deref_mut())
+            .await
+            .expect("Failed to create detector object");
+
+        let req = TestRequest::post()
+            .uri(
+                format!(
+                    "/infra/{}/attached/{}/reposition",
+                    empty_infra.id,
+                    track.get_id()
+                )
+                .as_str(),
+            )
+            .set_json(json!({
+                "transform": { "type": "Shift", "offset": 60.0 },
+                "out_of_range": "Drop",
+            }))
+            .to_request();
+
+        let operations: Vec<Operation> = call_and_read_body_json(&app.service, req).await;
+        assert_eq!(
+            operations,
+            vec![Operation::Delete(DeleteOperation {
+                obj_type: ObjectType::Detector,
+                obj_id: detector.get_id().clone(),
+            })]
+        );
+    }
+
+    fn infra_cache() -> InfraCache {
+        let mut infra_cache = InfraCache::default();
+        infra_cache
+            .add(create_track_section_cache("A", 100.0))
+            .unwrap();
+        infra_cache
+            .add(create_signal_cache("signal", "A", 90.0))
+            .unwrap();
+        infra_cache
+            .add(create_detector_cache("detector", "A", 10.0))
+            .unwrap();
+        infra_cache
+            .add(create_operational_point_cache("op", "A", 40.0))
+            .unwrap();
+        infra_cache
+            .add(create_electrification_cache(
+                "electrification",
+                vec![("A", 50.0, 100.0)],
+            ))
+            .unwrap();
+        infra_cache
+    }
+
+    fn position_update(obj_type: ObjectType, obj_id: &str, path: &str, value: f64) -> Operation {
+        Operation::Update(UpdateOperation {
+            obj_type,
+            obj_id: obj_id.to_owned(),
+            railjson_patch: Patch(vec![PatchOperation::Replace(ReplaceOperation {
+                path: path.parse().unwrap(),
+                value: json!(value),
+            })]),
+        })
+    }
+
+    #[test]
+    fn reposition_shift_and_clamp() {
+        let infra_cache = infra_cache();
+        let track_reposition = Reposition {
+            track_id: "A",
+            length: 100.0,
+            transform: PositionTransform::Shift { offset: 20.0 },
+            out_of_range: OutOfRange::Clamp,
+        };
+
+        let operations = track_reposition.operations(&infra_cache);
+
+        assert!(operations.contains(&position_update(
+            ObjectType::Signal,
+            "signal",
+            "/position",
+            100.0
+        )));
+        assert!(operations.contains(&position_update(
+            ObjectType::Detector,
+            "detector",
+            "/position",
+            30.0
+        )));
+        assert!(operations.contains(&position_update(
+            ObjectType::OperationalPoint,
+            "op",
+            "/parts/0/position",
+            60.0
+        )));
+        assert_eq!(track_reposition.range(50.0, 100.0), Some((70.0, 100.0)));
+        assert_eq!(operations.len(), 4);
+    }
+
+    #[test]
+    fn reposition_clamp_ranges() {
+        let mut infra_cache = InfraCache::default();
+        infra_cache
+            .add(create_track_section_cache("A", 100.0))
+            .unwrap();
+        infra_cache
+            .add(create_electrification_cache(
+                "electrification",
+                vec![("A", 10.0, 40.0), ("A", 50.0, 100.0)],
+            ))
+            .unwrap();
+        let track_reposition = Reposition {
+            track_id: "A",
+            length: 100.0,
+            transform: PositionTransform::Shift { offset: -60.0 },
+            out_of_range: OutOfRange::Clamp,
+        };
+
+        let operations = track_reposition.operations(&infra_cache);
+
+        // The range partly out of the track is cut, the other one is moved to its beginning
+        let track_ranges = create_electrification_cache(
+            "electrification",
+            vec![("A", 0.0, 0.0), ("A", 0.0, 40.0)],
+        )
+        .track_ranges;
+        assert_eq!(
+            operations,
+            vec![Operation::Update(UpdateOperation {
+                obj_type: ObjectType::Electrification,
+                obj_id: "electrification".into(),
+                railjson_patch: Patch(vec![PatchOperation::Replace(ReplaceOperation {
+                    path: "/track_ranges".parse().unwrap(),
+                    value: json!(track_ranges),
+                })]),
+            })]
+        );
+    }
+
+    #[test]
+    fn reposition_shift_and_drop() {
+        let infra_cache = infra_cache();
+        let track_reposition = Reposition {
+            track_id: "A",
+            length: 100.0,
+            transform: PositionTransform::Shift { offset: -50.0 },
+            out_of_range: OutOfRange::Drop,
+        };
+
+        let operations = track_reposition.operations(&infra_cache);
+
+        assert!(operations.contains(&Operation::Delete(DeleteOperation {
+            obj_type: ObjectType::Detector,
+            obj_id: "detector".into(),
+        })));
+        // The operational point lost its only part
+        assert!(operations.contains(&Operation::Delete(DeleteOperation {
+            obj_type: ObjectType::OperationalPoint,
+            obj_id: "op".into(),
+        })));
+        // The electrification range is cut at the beginning of the track
+        assert_eq!(track_reposition.range(50.0, 100.0), Some((0.0, 50.0)));
+        assert_eq!(track_reposition.range(10.0, 40.0), None);
+    }
+
+    #[test]
+    fn reposition_drop_operational_point_part() {
+        let mut infra_cache = InfraCache::default();
+        infra_cache
+            .add(create_track_section_cache("A", 100.0))
+            .unwrap();
+        infra_cache
+            .add(create_track_section_cache("B", 100.0))
+            .unwrap();
+        let mut operational_point = create_operational_point_cache("op", "A", 40.0);
+        operational_point.parts.push(OperationalPointPartCache {
+            track: "B".into(),
+            position: 40.0,
+        });
+        infra_cache.add(operational_point).unwrap();
+        let track_reposition = Reposition {
+            track_id: "A",
+            length: 100.0,
+            transform: PositionTransform::Shift { offset: -50.0 },
+            out_of_range: OutOfRange::Drop,
+        };
+
+        let operations = track_reposition.operations(&infra_cache);
+
+        // The part on the other track is kept
+        assert_eq!(
+            operations,
+            vec![Operation::Update(UpdateOperation {
+                obj_type: ObjectType::OperationalPoint,
+                obj_id: "op".into(),
+                railjson_patch: Patch(vec![PatchOperation::Remove(RemoveOperation {
+                    path: "/parts/0".parse().unwrap(),
+                })]),
+            })]
+        );
+    }
+
+    #[test]
+    fn reposition_rescale() {
+        let infra_cache = infra_cache();
+        let track_reposition = Reposition {
+            track_id: "A",
+            length: 100.0,
+            transform: PositionTransform::Rescale { from_length: 200.0 },
+            out_of_range: OutOfRange::Clamp,
+        };
+
+        let operations = track_reposition.operations(&infra_cache);
+
+        assert!(operations.contains(&position_update(
+            ObjectType::Signal,
+            "signal",
+            "/position",
+            45.0
+        )));
+        assert_eq!(track_reposition.range(50.0, 100.0), Some((25.0, 50.0)));
+    }
 }
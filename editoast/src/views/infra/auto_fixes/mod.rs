@@ -1,21 +1,34 @@
 use std::collections::hash_map::Entry;
 use std::collections::hash_map::HashMap;
+use std::collections::HashSet;
 
 use actix_web::get;
+use actix_web::http::header::ETag;
+use actix_web::http::header::EntityTag;
+use actix_web::post;
 use actix_web::web::Data;
 use actix_web::web::Json as WebJson;
 use actix_web::web::Path;
+use actix_web::web::Query;
+use actix_web::CustomizeResponder;
+use actix_web::Responder as _;
 use chashmap::CHashMap;
 use editoast_derive::EditoastError;
 use itertools::Itertools as _;
+use serde::Deserialize;
+use serde::Serialize;
+use sha1::Digest;
+use sha1::Sha1;
 use thiserror::Error;
 use tracing::debug;
 use tracing::error;
+use utoipa::ToSchema;
 
 use crate::error::InternalError;
 use crate::error::Result;
 use crate::generated_data::generate_infra_errors;
 use crate::generated_data::infra_error::InfraError;
+use crate::generated_data::infra_error::InfraErrorType;
 use crate::infra_cache::operation::patch_infra_object;
 use crate::infra_cache::operation::CacheOperation;
 use crate::infra_cache::operation::DeleteOperation;
@@ -23,12 +36,22 @@ use crate::infra_cache::operation::Operation;
 use crate::infra_cache::operation::UpdateOperation;
 use crate::infra_cache::InfraCache;
 use crate::infra_cache::ObjectCache;
+use crate::map;
+use crate::map::MapLayers;
 use crate::modelsv2::prelude::*;
+use crate::modelsv2::validation_profile::ValidationProfile;
+use crate::modelsv2::DbConnection;
 use crate::modelsv2::DbConnectionPool;
 use crate::modelsv2::Infra;
 use crate::views::infra::InfraApiError;
 use crate::views::infra::InfraIdParam;
+use crate::views::params::ExpectedVersion;
+use crate::views::params::RemoteUser;
+use crate::views::validation_profiles::ValidationProfileError;
+use crate::RedisClient;
 use editoast_schemas::infra::InfraObject;
+use editoast_schemas::infra::TrackEndpoint;
+use editoast_schemas::primitives::BoundingBox;
 use editoast_schemas::primitives::OSRDIdentified as _;
 use editoast_schemas::primitives::OSRDObject;
 use editoast_schemas::primitives::ObjectRef;
@@ -46,21 +69,77 @@ mod track_section;
 
 const MAX_AUTO_FIXES_ITERATIONS: u8 = 5;
 
-type Fix = (Operation, CacheOperation);
+/// An operation fixing an infra object, along with the infra errors it resolves
+#[derive(Debug, Clone)]
+struct Fix {
+    operation: Operation,
+    cache_operation: CacheOperation,
+    errors: Vec<InfraError>,
+}
+
+impl Fix {
+    fn new(operation: Operation, cache_operation: CacheOperation, errors: Vec<InfraError>) -> Self {
+        Self {
+            operation,
+            cache_operation,
+            errors,
+        }
+    }
+
+    /// Sha1 of the errors resolved by the fix
+    ///
+    /// It only depends on the errors so that the same fix gets the same id when recomputed.
+    fn id(&self) -> String {
+        let mut errors = self
+            .errors
+            .iter()
+            .map(|error| serde_json::to_string(error).unwrap())
+            .collect_vec();
+        errors.sort();
+        let mut hasher = Sha1::new();
+        for error in errors {
+            hasher.update(error.as_bytes());
+        }
+        format!("{:x}", hasher.finalize())
+    }
+}
 
-fn new_ref_fix_delete_pair(object: &impl OSRDObject) -> (ObjectRef, Fix) {
+fn new_ref_fix_delete_pair(object: &impl OSRDObject, error: &InfraError) -> (ObjectRef, Fix) {
     let operation = Operation::Delete(DeleteOperation::from(object.get_ref()));
     let cache_operation = CacheOperation::Delete(object.get_ref());
-    (object.get_ref(), (operation, cache_operation))
+    (
+        object.get_ref(),
+        Fix::new(operation, cache_operation, vec![error.clone()]),
+    )
 }
 
-fn new_ref_fix_create_pair(object: InfraObject) -> (ObjectRef, Fix) {
+fn new_ref_fix_create_pair(object: InfraObject, error: &InfraError) -> (ObjectRef, Fix) {
     let object_ref = object.get_ref();
     let operation = Operation::Create(Box::new(object.clone()));
     let cache_operation = CacheOperation::Create(ObjectCache::from(object));
-    (object_ref, (operation, cache_operation))
+    (
+        object_ref,
+        Fix::new(operation, cache_operation, vec![error.clone()]),
+    )
 }
 
+/// Collects the fixes of an object's errors, merging the errors of identical fixes on the same object
+fn collect_fixes(fixes: impl Iterator<Item = (ObjectRef, Fix)>) -> HashMap<ObjectRef, Fix> {
+    let mut collected: HashMap<ObjectRef, Fix> = HashMap::new();
+    for (object_ref, fix) in fixes {
+        match collected.entry(object_ref) {
+            Entry::Occupied(mut entry) => entry.get_mut().errors.extend(fix.errors),
+            Entry::Vacant(entry) => {
+                entry.insert(fix);
+            }
+        }
+    }
+    collected
+}
+
+/// For each snapped dangling track endpoint, the endpoint it snaps to and the error of the latter
+type Snaps = HashMap<TrackEndpoint, (TrackEndpoint, InfraError)>;
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum OrderedOperation {
     RemoveTrackRef { track_refs: usize },
@@ -71,41 +150,305 @@ pub enum OrderedOperation {
 crate::routes! {
     "/auto_fixes" => {
         list_auto_fixes,
+        preview_auto_fixes,
+        apply_auto_fixes,
     },
 }
 
+editoast_common::schemas! {
+    AutoFix,
+    ObjectAutoFixes,
+    AutoFixesSelection,
+}
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+struct AutoFixesQueryParams {
+    /// Only the errors reported by this validation profile are fixed
+    validation_profile_id: Option<i64>,
+    /// Distance (in meters) under which two dangling track endpoints are linked together
+    /// instead of getting a buffer stop each. No endpoint is linked when missing.
+    snap_tolerance: Option<f64>,
+}
+
+/// A fix proposed for an infra object
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+struct AutoFix {
+    /// Identifies the fix when applying a selection of fixes
+    id: String,
+    operation: Operation,
+    /// The infra errors resolved by the fix
+    errors: Vec<InfraError>,
+}
+
+/// The fixes proposed for the errors of an infra object
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+struct ObjectAutoFixes {
+    /// The object the fixed errors are reported on
+    object: ObjectRef,
+    fixes: Vec<AutoFix>,
+}
+
+/// The fixes to apply
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+struct AutoFixesSelection {
+    /// Ids of the fixes, as returned by the preview
+    fix_ids: Vec<String>,
+}
+
+async fn retrieve_profile(
+    conn: &mut DbConnection,
+    validation_profile_id: Option<i64>,
+) -> Result<Option<ValidationProfile>> {
+    let Some(validation_profile_id) = validation_profile_id else {
+        return Ok(None);
+    };
+    let profile = ValidationProfile::retrieve_or_fail(conn, validation_profile_id, || {
+        ValidationProfileError::NotFound {
+            validation_profile_id,
+        }
+    })
+    .await?;
+    Ok(Some(profile))
+}
+
 /// Retrieve a list of operations to fix infra issues
+///
+/// The version of the infra the fixes are computed for is returned in the `ETag` header.
+/// Sending it back in the `If-Match` header when applying the fixes ensures the infra
+/// wasn't edited in the meantime.
 #[utoipa::path(
     tag = "infra",
-    params(InfraIdParam),
+    params(InfraIdParam, AutoFixesQueryParams),
     responses(
-        (status = 200, description = "The list of suggested operations", body = Vec<Operation>)
+        (
+            status = 200,
+            description = "The list of suggested operations",
+            body = Vec<Operation>,
+            headers(("ETag" = String, description = "The version of the infra")),
+        )
     )
 )]
 #[get("")]
 async fn list_auto_fixes(
     infra: Path<i64>,
+    Query(AutoFixesQueryParams {
+        validation_profile_id,
+        snap_tolerance,
+    }): Query<AutoFixesQueryParams>,
     infra_caches: Data<CHashMap<i64, InfraCache>>,
     db_pool: Data<DbConnectionPool>,
-) -> Result<WebJson<Vec<Operation>>> {
+) -> Result<CustomizeResponder<WebJson<Vec<Operation>>>> {
     let infra_id = infra.into_inner();
     let mut conn = db_pool.get().await?;
     let infra =
         Infra::retrieve_or_fail(&mut conn, infra_id, || InfraApiError::NotFound { infra_id })
             .await?;
+    let profile = retrieve_profile(&mut conn, validation_profile_id).await?;
 
     // accepting the early release of ReadGuard as it's anyway released when sending the suggestions (so before edit)
     let mut infra_cache_clone = InfraCache::get_or_load(&mut conn, &infra_caches, &infra)
         .await?
         .clone();
 
+    let fixes = compute_fixes(
+        &mut infra_cache_clone,
+        profile.as_ref(),
+        snap_tolerance,
+        |_| true,
+    )
+    .await?;
+    let operations = fixes.into_iter().map(|fix| fix.operation).collect();
+    Ok(WebJson(operations)
+        .customize()
+        .insert_header(ETag(EntityTag::new_strong(infra.version))))
+}
+
+/// Retrieve the fixes of infra issues, grouped by the object the issues are reported on
+///
+/// Each fix comes with the errors it resolves and an id used to apply a selection of fixes.
+/// The version of the infra the fixes are computed for is returned in the `ETag` header.
+#[utoipa::path(
+    tag = "infra",
+    params(InfraIdParam, AutoFixesQueryParams),
+    responses(
+        (
+            status = 200,
+            description = "The suggested fixes by object",
+            body = Vec<ObjectAutoFixes>,
+            headers(("ETag" = String, description = "The version of the infra")),
+        )
+    )
+)]
+#[get("/preview")]
+async fn preview_auto_fixes(
+    infra: Path<i64>,
+    Query(AutoFixesQueryParams {
+        validation_profile_id,
+        snap_tolerance,
+    }): Query<AutoFixesQueryParams>,
+    infra_caches: Data<CHashMap<i64, InfraCache>>,
+    db_pool: Data<DbConnectionPool>,
+) -> Result<CustomizeResponder<WebJson<Vec<ObjectAutoFixes>>>> {
+    let infra_id = infra.into_inner();
+    let mut conn = db_pool.get().await?;
+    let infra =
+        Infra::retrieve_or_fail(&mut conn, infra_id, || InfraApiError::NotFound { infra_id })
+            .await?;
+    let profile = retrieve_profile(&mut conn, validation_profile_id).await?;
+
+    let mut infra_cache_clone = InfraCache::get_or_load(&mut conn, &infra_caches, &infra)
+        .await?
+        .clone();
+
+    let fixes = compute_fixes(
+        &mut infra_cache_clone,
+        profile.as_ref(),
+        snap_tolerance,
+        |_| true,
+    )
+    .await?;
+    let object_fixes = fixes
+        .into_iter()
+        .map(|fix| {
+            let object = fix.errors[0].get_ref();
+            let fix = AutoFix {
+                id: fix.id(),
+                operation: fix.operation,
+                errors: fix.errors,
+            };
+            (object, fix)
+        })
+        .into_group_map()
+        .into_iter()
+        .map(|(object, mut fixes)| {
+            fixes.sort_by(|a, b| a.id.cmp(&b.id));
+            ObjectAutoFixes { object, fixes }
+        })
+        .sorted_by(|a, b| {
+            (a.object.obj_type.to_string(), &a.object.obj_id)
+                .cmp(&(b.object.obj_type.to_string(), &b.object.obj_id))
+        })
+        .collect();
+    Ok(WebJson(object_fixes)
+        .customize()
+        .insert_header(ETag(EntityTag::new_strong(infra.version))))
+}
+
+/// Apply a selection of the fixes returned by the preview
+///
+/// The fixes are recomputed and only the selected ones are applied, as a regular edition.
+/// The edition can be made conditional to the version of the infra with the `If-Match` header.
+#[utoipa::path(
+    tag = "infra",
+    params(InfraIdParam, AutoFixesQueryParams),
+    request_body = AutoFixesSelection,
+    responses(
+        (
+            status = 200,
+            body = Vec<InfraObject>,
+            description = "The result of the applied operations",
+            headers(("ETag" = String, description = "The new version of the infra")),
+        ),
+        (status = 400, description = "Some selected fixes don't exist anymore"),
+        (status = 409, description = "Some fixed objects are locked by other users"),
+        (status = 412, description = "The infra doesn't match the version given in the `If-Match` header"),
+    )
+)]
+#[post("/apply")]
+#[allow(clippy::too_many_arguments)]
+async fn apply_auto_fixes(
+    infra: Path<i64>,
+    Query(AutoFixesQueryParams {
+        validation_profile_id,
+        snap_tolerance,
+    }): Query<AutoFixesQueryParams>,
+    WebJson(AutoFixesSelection { fix_ids }): WebJson<AutoFixesSelection>,
+    infra_caches: Data<CHashMap<i64, InfraCache>>,
+    db_pool: Data<DbConnectionPool>,
+    redis_client: Data<RedisClient>,
+    map_layers: Data<MapLayers>,
+    RemoteUser(author): RemoteUser,
+    ExpectedVersion(expected_version): ExpectedVersion,
+) -> Result<CustomizeResponder<WebJson<Vec<InfraObject>>>> {
+    let infra_id = infra.into_inner();
+    let mut conn = db_pool.get().await?;
+    let mut infra =
+        Infra::retrieve_or_fail(&mut conn, infra_id, || InfraApiError::NotFound { infra_id })
+            .await?;
+    let profile = retrieve_profile(&mut conn, validation_profile_id).await?;
+    let mut infra_cache = InfraCache::get_or_load_mut(&mut conn, &infra_caches, &infra).await?;
+
+    let fix_ids: HashSet<String> = fix_ids.into_iter().collect();
+    let mut infra_cache_clone = infra_cache.clone();
+    let fixes = compute_fixes(
+        &mut infra_cache_clone,
+        profile.as_ref(),
+        snap_tolerance,
+        |fix| fix_ids.contains(&fix.id()),
+    )
+    .await?;
+    let found_ids: HashSet<String> = fixes.iter().map(Fix::id).collect();
+    let unknown_ids = fix_ids
+        .difference(&found_ids)
+        .cloned()
+        .sorted()
+        .collect_vec();
+    if !unknown_ids.is_empty() {
+        return Err(AutoFixesEditoastError::UnknownFixes {
+            fix_ids: unknown_ids,
+        }
+        .into());
+    }
+
+    let operations = fixes.into_iter().map(|fix| fix.operation).collect_vec();
+    let operation_results = super::edition::apply_edit(
+        &mut conn,
+        &mut infra,
+        &operations,
+        &mut infra_cache,
+        author,
+        expected_version,
+    )
+    .await?;
+
+    let mut conn = redis_client.get_connection().await?;
+    map::invalidate_all(
+        &mut conn,
+        &map_layers.layers.keys().cloned().collect(),
+        infra_id,
+    )
+    .await?;
+
+    Ok(WebJson(operation_results)
+        .customize()
+        .insert_header(ETag(EntityTag::new_strong(infra.version))))
+}
+
+/// Computes the fixes of the infra errors, iterating until the fixes don't raise any new error
+///
+/// Only the fixes accepted by `select` are applied to the cache, returned and taken into
+/// account in the following iterations.
+async fn compute_fixes(
+    infra_cache: &mut InfraCache,
+    profile: Option<&ValidationProfile>,
+    snap_tolerance: Option<f64>,
+    select: impl Fn(&Fix) -> bool,
+) -> Result<Vec<Fix>> {
     let mut fixes = vec![];
     for _ in 0..MAX_AUTO_FIXES_ITERATIONS {
-        let infra_errors = generate_infra_errors(&infra_cache_clone).await;
-        let new_fixes = fix_infra(&mut infra_cache_clone, infra_errors)?;
+        let mut infra_errors = generate_infra_errors(infra_cache).await;
+        if let Some(profile) = profile {
+            infra_errors = infra_errors
+                .into_iter()
+                .filter_map(|error| profile.apply(error))
+                .collect();
+        }
+        let new_fixes = fix_infra(infra_cache, infra_errors, snap_tolerance, &select)?;
         if new_fixes.is_empty() {
             // Every possible error is fixed
-            return Ok(WebJson(fixes));
+            return Ok(fixes);
         }
         fixes.extend(new_fixes);
     }
@@ -119,7 +462,13 @@ async fn list_auto_fixes(
 fn fix_infra(
     infra_cache: &mut InfraCache,
     infra_errors: Vec<InfraError>,
-) -> Result<Vec<Operation>> {
+    snap_tolerance: Option<f64>,
+    select: impl Fn(&Fix) -> bool,
+) -> Result<Vec<Fix>> {
+    let snaps = match snap_tolerance {
+        Some(tolerance) => snap_dangling_endpoints(infra_cache, &infra_errors, tolerance),
+        None => Snaps::default(),
+    };
     let mut fixes: HashMap<ObjectRef, Fix> = HashMap::new();
     for (object_ref, errors) in &infra_errors.into_iter().chunk_by(OSRDObject::get_ref) {
         let fixes_for_object_errors = match object_ref.obj_type {
@@ -127,7 +476,7 @@ fn fix_infra(
                 let track_section = infra_cache
                     .get_track_section(&object_ref.obj_id)
                     .map_err(|e| AutoFixesEditoastError::MissingErrorObject { source: e })?;
-                track_section::fix_track_section(track_section, errors)
+                track_section::fix_track_section(track_section, errors, &snaps)
             }
             ObjectType::Signal => {
                 let signal = infra_cache
@@ -192,7 +541,7 @@ fn fix_infra(
                     Entry::Occupied(entry) => {
                         return Err(AutoFixesEditoastError::ConflictingFixesOnSameObject {
                             object: entry.key().clone(),
-                            fixes: vec![entry.get().0.clone(), fix.0],
+                            fixes: vec![entry.get().operation.clone(), fix.operation],
                         })
                     }
                     Entry::Vacant(entry) => entry.insert(fix),
@@ -201,12 +550,68 @@ fn fix_infra(
             },
         )?;
     }
-    let (operations, cache_operations): (Vec<Operation>, Vec<CacheOperation>) =
-        fixes.into_values().unzip();
+    let fixes = fixes.into_values().filter(select).collect_vec();
+    let cache_operations = fixes
+        .iter()
+        .map(|fix| fix.cache_operation.clone())
+        .collect_vec();
     infra_cache
         .apply_operations(&cache_operations)
         .map_err(|source| AutoFixesEditoastError::FixTrialFailure { source })?;
-    Ok(operations)
+    Ok(fixes)
+}
+
+/// Pairs the dangling track endpoints that are each other's nearest one within the tolerance (in meters)
+///
+/// Endpoints of the same track section are never paired.
+fn snap_dangling_endpoints(
+    infra_cache: &InfraCache,
+    infra_errors: &[InfraError],
+    tolerance: f64,
+) -> Snaps {
+    let dangling_endpoints = infra_errors
+        .iter()
+        .filter_map(|error| {
+            let InfraErrorType::MissingBufferStop { endpoint } = error.get_sub_type() else {
+                return None;
+            };
+            let track = infra_cache
+                .track_sections()
+                .get(error.get_id())?
+                .unwrap_track_section();
+            let track_endpoint = TrackEndpoint::new(error.get_id(), *endpoint);
+            Some((track_endpoint, track.get_endpoint_geo(*endpoint), error))
+        })
+        .collect_vec();
+    let nearest = |index: usize| {
+        let (track_endpoint, geo, _) = &dangling_endpoints[index];
+        dangling_endpoints
+            .iter()
+            .enumerate()
+            .filter(|(_, (other, _, _))| other.track != track_endpoint.track)
+            .map(|(other_index, (_, other_geo, _))| {
+                let distance = BoundingBox(*geo, *other_geo).diagonal_length();
+                (other_index, distance)
+            })
+            .filter(|(_, distance)| *distance <= tolerance)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(other_index, _)| other_index)
+    };
+    let mut snaps = Snaps::new();
+    for (index, (track_endpoint, _, _)) in dangling_endpoints.iter().enumerate() {
+        let Some(other_index) = nearest(index) else {
+            continue;
+        };
+        if nearest(other_index) != Some(index) {
+            continue;
+        }
+        let (other, _, other_error) = &dangling_endpoints[other_index];
+        snaps.insert(
+            track_endpoint.clone(),
+            (other.clone(), (*other_error).clone()),
+        );
+    }
+    snaps
 }
 
 // 'reduce_operation' needs to produce an `Option` since combining two existing `Operation`
@@ -312,6 +717,9 @@ pub enum AutoFixesEditoastError {
     #[error("Failed to find the error's object")]
     #[editoast_error(status = 500)]
     MissingErrorObject { source: InternalError },
+    #[error("Some selected fixes could not be found")]
+    #[editoast_error(status = 400)]
+    UnknownFixes { fix_ids: Vec<String> },
 }
 
 #[cfg(test)]
@@ -332,16 +740,21 @@ mod tests {
     use crate::fixtures::tests::empty_infra;
     use crate::fixtures::tests::small_infra;
     use crate::generated_data::infra_error::InfraErrorType;
+    use crate::generated_data::infra_error::InfraErrorTypeLabel;
     use crate::infra_cache::object_cache::BufferStopCache;
     use crate::infra_cache::object_cache::DetectorCache;
     use crate::infra_cache::object_cache::SignalCache;
+    use crate::infra_cache::object_cache::TrackSectionCache;
     use crate::infra_cache::operation::DeleteOperation;
     use crate::infra_cache::operation::Operation;
+    use crate::infra_cache::tests::create_track_section_cache;
     use crate::infra_cache::InfraCacheEditoastError;
+    use crate::modelsv2::validation_profile::ValidationRule;
     use crate::views::infra::errors::query_errors;
     use crate::views::tests::create_test_service;
     use editoast_schemas::infra::ApplicableDirectionsTrackRange;
     use editoast_schemas::infra::Detector;
+    use editoast_schemas::infra::Direction;
     use editoast_schemas::infra::Electrification;
     use editoast_schemas::infra::Endpoint;
     use editoast_schemas::infra::InfraObject;
@@ -400,11 +813,13 @@ mod tests {
         let conn = &mut db_pool().get().await.unwrap();
         force_refresh(&mut small_infra).await;
 
-        // Check the only initial issues are "overlapping_speed_sections" warnings
+        // Check the only initial issues are "overlapping_speed_sections" and "electrification_gap" warnings
         let (infra_errors_before_all, before_all_count) = query_errors(conn, &small_infra).await;
-        assert!(infra_errors_before_all
-            .iter()
-            .all(|e| matches!(e.sub_type, InfraErrorType::OverlappingSpeedSections { .. })));
+        assert!(infra_errors_before_all.iter().all(|e| matches!(
+            e.sub_type,
+            InfraErrorType::OverlappingSpeedSections { .. }
+                | InfraErrorType::ElectrificationGap { .. }
+        )));
 
         // Remove a track
         let deletion = Operation::Delete(DeleteOperation {
@@ -478,9 +893,65 @@ mod tests {
         })));
     }
 
+    #[rstest::rstest]
+    async fn test_no_fix_for_disabled_error_type() {
+        let app = create_test_service().await;
+        let small_infra = small_infra(db_pool()).await;
+        let small_infra_id = small_infra.id();
+        let conn = &mut db_pool().get().await.unwrap();
+        let profile = ValidationProfile::changeset()
+            .name(format!("no_invalid_reference_{small_infra_id}"))
+            .rules(HashMap::from([(
+                InfraErrorTypeLabel::InvalidReference,
+                ValidationRule {
+                    enabled: false,
+                    severity: None,
+                },
+            )]))
+            .create(conn)
+            .await
+            .unwrap();
+        // Remove a buffer stop
+        let deletion = Operation::Delete(DeleteOperation {
+            obj_id: "buffer_stop.4".to_string(),
+            obj_type: ObjectType::BufferStop,
+        });
+        let req_del = TestRequest::post()
+            .uri(format!("/infra/{small_infra_id}/").as_str())
+            .set_json(json!([deletion]))
+            .to_request();
+        assert_eq!(call_service(&app, req_del).await.status(), StatusCode::OK);
+
+        let request = TestRequest::get()
+            .uri(
+                format!(
+                    "/infra/{small_infra_id}/auto_fixes?validation_profile_id={}",
+                    profile.id
+                )
+                .as_str(),
+            )
+            .to_request();
+        let response = call_service(&app, request).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let operations: Vec<Operation> = read_body_json(response).await;
+        assert!(!operations.contains(&Operation::Delete(DeleteOperation {
+            obj_id: "rt.DE0->buffer_stop.4".to_string(),
+            obj_type: ObjectType::Route,
+        })));
+        profile.delete(conn).await.unwrap();
+    }
+
     #[test]
     fn test_invalid_ref_signal_fix() {
-        let signal = SignalCache::new("SA0".to_string(), "TA1".to_string(), 0.0, vec![]);
+        let signal = SignalCache::new(
+            "SA0".to_string(),
+            "TA1".to_string(),
+            0.0,
+            Direction::StartToStop,
+            400.0,
+            vec![],
+        );
         let error = InfraError::new_invalid_reference(
             &signal,
             "track",
@@ -489,8 +960,8 @@ mod tests {
 
         let mut infra_cache = InfraCache::default();
         infra_cache.add(signal.clone()).unwrap();
-        let operations = fix_infra(&mut infra_cache, vec![error]).unwrap();
-        let operation = operations.first().unwrap();
+        let operations = fix_infra(&mut infra_cache, vec![error], None, |_| true).unwrap();
+        let operation = &operations.first().unwrap().operation;
         assert_eq!(
             operation,
             &Operation::Delete(DeleteOperation {
@@ -503,7 +974,14 @@ mod tests {
 
     #[rstest::rstest]
     async fn test_wrong_invalid_ref_signal_fix() {
-        let signal = SignalCache::new("SA0".to_string(), "TA1".to_string(), 0.0, vec![]);
+        let signal = SignalCache::new(
+            "SA0".to_string(),
+            "TA1".to_string(),
+            0.0,
+            Direction::StartToStop,
+            400.0,
+            vec![],
+        );
         let error = InfraError::new_invalid_reference(
             &signal,
             "track",
@@ -511,7 +989,7 @@ mod tests {
         );
 
         let mut infra_cache = InfraCache::default();
-        let error = fix_infra(&mut infra_cache, vec![error]).unwrap_err();
+        let error = fix_infra(&mut infra_cache, vec![error], None, |_| true).unwrap_err();
         assert_eq!(
             error,
             AutoFixesEditoastError::MissingErrorObject {
@@ -551,8 +1029,8 @@ mod tests {
         infra_cache.add(route.clone()).unwrap();
 
         // Delete the route: the entry point doesn't exist.
-        let operations = fix_infra(&mut infra_cache, vec![error]).unwrap();
-        let operation = operations.first().unwrap();
+        let operations = fix_infra(&mut infra_cache, vec![error], None, |_| true).unwrap();
+        let operation = &operations.first().unwrap().operation;
         assert_eq!(
             operation,
             &Operation::Delete(DeleteOperation {
@@ -588,7 +1066,7 @@ mod tests {
             .unwrap();
 
         // Error: the route is not in the cache.
-        let error = fix_infra(&mut infra_cache, vec![error]).unwrap_err();
+        let error = fix_infra(&mut infra_cache, vec![error], None, |_| true).unwrap_err();
         assert_eq!(
             error,
             AutoFixesEditoastError::MissingErrorObject {
@@ -628,8 +1106,8 @@ mod tests {
         infra_cache.add(route.clone()).unwrap();
 
         // Delete the route: the exit point doesn't exist.
-        let operations = fix_infra(&mut infra_cache, vec![error]).unwrap();
-        let operation = operations.first().unwrap();
+        let operations = fix_infra(&mut infra_cache, vec![error], None, |_| true).unwrap();
+        let operation = &operations.first().unwrap().operation;
         assert_eq!(
             operation,
             &Operation::Delete(DeleteOperation {
@@ -676,7 +1154,7 @@ mod tests {
         infra_cache.add(route).unwrap();
 
         // Don't delete the route: entry and exit points are fine.
-        let operations = fix_infra(&mut infra_cache, vec![error]).unwrap();
+        let operations = fix_infra(&mut infra_cache, vec![error], None, |_| true).unwrap();
         assert!(operations.is_empty());
     }
 
@@ -986,4 +1464,87 @@ mod tests {
         positions.sort_by(|a, b| a.partial_cmp(b).unwrap());
         assert_eq!(positions, vec![0., 1_000.0]);
     }
+
+    #[test]
+    fn snap_dangling_track_endpoints() {
+        let mut infra_cache = InfraCache::default();
+        let track_e = TrackSectionCache {
+            begin_geo: (0., 0.),
+            end_geo: (0.01, 0.),
+            ..create_track_section_cache("E", 1_000.)
+        };
+        let track_f = TrackSectionCache {
+            begin_geo: (0.01001, 0.),
+            end_geo: (0.02, 0.),
+            ..create_track_section_cache("F", 1_000.)
+        };
+        infra_cache.add(track_e.clone()).unwrap();
+        infra_cache.add(track_f.clone()).unwrap();
+        let errors = vec![
+            InfraError::new_missing_buffer_stop(&track_e, Endpoint::Begin),
+            InfraError::new_missing_buffer_stop(&track_e, Endpoint::End),
+            InfraError::new_missing_buffer_stop(&track_f, Endpoint::Begin),
+            InfraError::new_missing_buffer_stop(&track_f, Endpoint::End),
+        ];
+
+        let fixes = fix_infra(&mut infra_cache, errors, Some(5.), |_| true).unwrap();
+
+        assert_eq!(fixes.len(), 3);
+        let link = fixes
+            .iter()
+            .find(|fix| matches!(&fix.operation, Operation::Create(object) if object.get_type() == ObjectType::Switch))
+            .expect("the close endpoints should be linked");
+        assert_eq!(link.errors.len(), 2);
+        assert_eq!(infra_cache.switches().len(), 1);
+        assert_eq!(infra_cache.buffer_stops().len(), 2);
+    }
+
+    #[rstest::rstest]
+    async fn preview_and_apply_selected_fixes() {
+        let app = create_test_service().await;
+        let empty_infra = empty_infra(db_pool()).await;
+        let empty_infra_id = empty_infra.id();
+
+        let track: InfraObject = TrackSection {
+            id: "track_with_no_buffer_stops".into(),
+            length: 1_000.0,
+            ..Default::default()
+        }
+        .into();
+        let req_create = get_create_operation_request(track.clone(), empty_infra_id);
+        assert_eq!(
+            call_service(&app, req_create).await.status(),
+            StatusCode::OK
+        );
+
+        let request = TestRequest::get()
+            .uri(format!("/infra/{empty_infra_id}/auto_fixes/preview").as_str())
+            .to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let object_fixes: Vec<ObjectAutoFixes> = read_body_json(response).await;
+        assert_eq!(object_fixes.len(), 1);
+        assert_eq!(object_fixes[0].object, track.get_ref());
+        assert_eq!(object_fixes[0].fixes.len(), 2);
+        let selected_fix = &object_fixes[0].fixes[0];
+        assert_eq!(selected_fix.errors.len(), 1);
+
+        let request = TestRequest::post()
+            .uri(format!("/infra/{empty_infra_id}/auto_fixes/apply").as_str())
+            .set_json(json!({ "fix_ids": [selected_fix.id] }))
+            .to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let objects: Vec<InfraObject> = read_body_json(response).await;
+        assert_eq!(objects.len(), 1);
+        assert_eq!(objects[0].get_type(), ObjectType::BufferStop);
+
+        // The applied fix is not proposed anymore
+        let request = TestRequest::post()
+            .uri(format!("/infra/{empty_infra_id}/auto_fixes/apply").as_str())
+            .set_json(json!({ "fix_ids": [selected_fix.id] }))
+            .to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
 }
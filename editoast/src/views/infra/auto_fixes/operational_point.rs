@@ -37,21 +37,28 @@ pub fn fix_operational_point(
     errors: impl Iterator<Item = InfraError>,
 ) -> HashMap<ObjectRef, Fix> {
     let mut new_op = operational_point.clone();
+    let mut fixed_errors = vec![];
     let operation = errors
-        .filter_map(|infra_error| match infra_error.get_sub_type() {
-            InfraErrorType::EmptyObject => Some(OrderedOperation::Delete),
-            InfraErrorType::InvalidReference { reference }
-                if reference.obj_type == ObjectType::TrackSection =>
-            {
-                new_op
-                    .parts
-                    .retain(|part| part.track.as_str() != reference.obj_id);
-                invalid_reference_to_ordered_operation(operational_point, reference)
-            }
-            _ => {
-                debug!("error not (yet) fixable for '{}'", infra_error.get_type());
-                None
+        .filter_map(|infra_error| {
+            let ordered_operation = match infra_error.get_sub_type() {
+                InfraErrorType::EmptyObject => Some(OrderedOperation::Delete),
+                InfraErrorType::InvalidReference { reference }
+                    if reference.obj_type == ObjectType::TrackSection =>
+                {
+                    new_op
+                        .parts
+                        .retain(|part| part.track.as_str() != reference.obj_id);
+                    invalid_reference_to_ordered_operation(operational_point, reference)
+                }
+                _ => {
+                    debug!("error not (yet) fixable for '{}'", infra_error.get_type());
+                    None
+                }
+            };
+            if ordered_operation.is_some() {
+                fixed_errors.push(infra_error);
             }
+            ordered_operation
         })
         .unique()
         // Need to invert the ordering because removing from the front would invalidate other indexes
@@ -78,7 +85,10 @@ pub fn fix_operational_point(
                 Operation::Delete(_) => CacheOperation::Delete(operational_point.get_ref()),
                 Operation::Create(_) => panic!("We should not create new operational points"),
             };
-            (operational_point.get_ref(), (operation, cache_operation))
+            (
+                operational_point.get_ref(),
+                Fix::new(operation, cache_operation, fixed_errors),
+            )
         })
         .into_iter()
         .collect()
@@ -94,6 +104,7 @@ mod tests {
     use crate::infra_cache::operation::CacheOperation;
     use crate::infra_cache::operation::Operation;
     use crate::infra_cache::ObjectCache;
+    use crate::views::infra::auto_fixes::Fix;
     use editoast_schemas::primitives::Identifier;
     use editoast_schemas::primitives::OSRDObject as _;
     use editoast_schemas::primitives::ObjectRef;
@@ -134,7 +145,11 @@ mod tests {
 
         assert_eq!(operations.len(), 1);
 
-        let (operation, cache_operation) = operations.get(&op_cache.get_ref()).unwrap();
+        let Fix {
+            operation,
+            cache_operation,
+            ..
+        } = operations.get(&op_cache.get_ref()).unwrap();
         let Operation::Update(update_operation) = operation else {
             panic!("not an `Operation::Update`");
         };
@@ -188,7 +203,11 @@ mod tests {
 
         assert_eq!(operations.len(), 1);
 
-        let (operation, cache_operation) = operations.get(&op_cache.get_ref()).unwrap();
+        let Fix {
+            operation,
+            cache_operation,
+            ..
+        } = operations.get(&op_cache.get_ref()).unwrap();
         let Operation::Delete(delete_operation) = operation else {
             panic!("not an `Operation::Delete`");
         };
@@ -16,26 +16,25 @@ pub fn fix_route(
     route: &Route,
     errors: impl Iterator<Item = InfraError>,
 ) -> HashMap<ObjectRef, Fix> {
-    errors
-        .filter_map(|infra_error| match infra_error.get_sub_type() {
-            InfraErrorType::InvalidReference { reference }
-                if matches!(
-                    reference.obj_type,
-                    ObjectType::BufferStop | ObjectType::Detector
-                ) =>
+    let fixes = errors.filter_map(|infra_error| match infra_error.get_sub_type() {
+        InfraErrorType::InvalidReference { reference }
+            if matches!(
+                reference.obj_type,
+                ObjectType::BufferStop | ObjectType::Detector
+            ) =>
+        {
+            if reference.obj_id.eq(route.entry_point.get_id())
+                || reference.obj_id.eq(route.exit_point.get_id())
             {
-                if reference.obj_id.eq(route.entry_point.get_id())
-                    || reference.obj_id.eq(route.exit_point.get_id())
-                {
-                    Some(new_ref_fix_delete_pair(route))
-                } else {
-                    None
-                }
-            }
-            _ => {
-                debug!("error not (yet) fixable for '{}'", infra_error.get_type());
+                Some(new_ref_fix_delete_pair(route, &infra_error))
+            } else {
                 None
             }
-        })
-        .collect()
+        }
+        _ => {
+            debug!("error not (yet) fixable for '{}'", infra_error.get_type());
+            None
+        }
+    });
+    super::collect_fixes(fixes)
 }
@@ -1,12 +1,17 @@
 use std::collections::HashMap;
 
 use tracing::debug;
+use uuid::Uuid;
 
+use super::new_ref_fix_create_pair;
 use super::new_ref_fix_delete_pair;
 use super::Fix;
 use crate::generated_data::infra_error::InfraError;
 use crate::generated_data::infra_error::InfraErrorType;
 use crate::infra_cache::object_cache::SignalCache;
+use editoast_schemas::infra::Detector;
+use editoast_schemas::infra::InfraObject;
+use editoast_schemas::primitives::Identifier;
 use editoast_schemas::primitives::OSRDObject as _;
 use editoast_schemas::primitives::ObjectRef;
 use editoast_schemas::primitives::ObjectType;
@@ -15,18 +20,64 @@ pub fn fix_signal(
     signal: &SignalCache,
     errors: impl Iterator<Item = InfraError>,
 ) -> HashMap<ObjectRef, Fix> {
-    errors
-        .filter_map(|infra_error| match infra_error.get_sub_type() {
-            InfraErrorType::OutOfRange { .. } => Some(new_ref_fix_delete_pair(signal)),
-            InfraErrorType::InvalidReference { reference }
-                if reference.obj_type == ObjectType::TrackSection =>
-            {
-                Some(new_ref_fix_delete_pair(signal))
-            }
-            _ => {
-                debug!("error not (yet) fixable for '{}'", infra_error.get_type());
-                None
-            }
-        })
-        .collect()
+    let fixes = errors.filter_map(|infra_error| match infra_error.get_sub_type() {
+        InfraErrorType::OutOfRange { .. } => Some(new_ref_fix_delete_pair(signal, &infra_error)),
+        InfraErrorType::InvalidReference { reference }
+            if reference.obj_type == ObjectType::TrackSection =>
+        {
+            Some(new_ref_fix_delete_pair(signal, &infra_error))
+        }
+        InfraErrorType::MissingSignalDetector => {
+            let detector = InfraObject::Detector {
+                railjson: Detector {
+                    id: Identifier::from(Uuid::new_v4()),
+                    track: signal.track.clone().into(),
+                    position: signal.position,
+                    ..Default::default()
+                },
+            };
+            Some(new_ref_fix_create_pair(detector, &infra_error))
+        }
+        _ => {
+            debug!("error not (yet) fixable for '{}'", infra_error.get_type());
+            None
+        }
+    });
+    super::collect_fixes(fixes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infra_cache::operation::CacheOperation;
+    use crate::infra_cache::operation::Operation;
+    use crate::infra_cache::tests::create_signal_cache;
+    use crate::infra_cache::ObjectCache;
+
+    #[test]
+    fn missing_signal_detector() {
+        let signal = create_signal_cache("S", "A", 120.);
+        let errors = vec![InfraError::new_missing_signal_detector(&signal)];
+        let fixes = fix_signal(&signal, errors.clone().into_iter());
+
+        assert_eq!(fixes.len(), 1);
+        let Fix {
+            operation,
+            cache_operation,
+            errors: fixed_errors,
+        } = fixes.into_values().next().unwrap();
+        assert_eq!(fixed_errors, errors);
+        let Operation::Create(railjson) = operation else {
+            panic!("expecting an `Operation::Create(_)`");
+        };
+        let InfraObject::Detector { railjson: detector } = *railjson else {
+            panic!("expecting a `InfraObject::Detector {{ .. }}`")
+        };
+        assert_eq!(detector.track.as_str(), "A");
+        assert_eq!(detector.position, 120.);
+        assert!(matches!(
+            cache_operation,
+            CacheOperation::Create(ObjectCache::Detector(_))
+        ));
+    }
 }
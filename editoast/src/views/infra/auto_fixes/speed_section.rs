@@ -36,16 +36,23 @@ pub fn fix_speed_section(
     speed_section: &SpeedSection,
     errors: impl Iterator<Item = InfraError>,
 ) -> HashMap<ObjectRef, Fix> {
+    let mut fixed_errors = vec![];
     let operation = errors
-        .filter_map(|infra_error| match infra_error.get_sub_type() {
-            InfraErrorType::EmptyObject => Some(OrderedOperation::Delete),
-            InfraErrorType::InvalidReference { reference } => {
-                invalid_reference_to_ordered_operation(speed_section, reference)
-            }
-            _ => {
-                debug!("error not (yet) fixable for '{}'", infra_error.get_type());
-                None
+        .filter_map(|infra_error| {
+            let ordered_operation = match infra_error.get_sub_type() {
+                InfraErrorType::EmptyObject => Some(OrderedOperation::Delete),
+                InfraErrorType::InvalidReference { reference } => {
+                    invalid_reference_to_ordered_operation(speed_section, reference)
+                }
+                _ => {
+                    debug!("error not (yet) fixable for '{}'", infra_error.get_type());
+                    None
+                }
+            };
+            if ordered_operation.is_some() {
+                fixed_errors.push(infra_error);
             }
+            ordered_operation
         })
         .unique()
         // Need to invert the ordering because removing from the front would invalidate other indexes
@@ -79,7 +86,7 @@ pub fn fix_speed_section(
                     return None;
                 }
             };
-            Some((speed_section.get_ref(), (operation, cache_operation)))
+            Some((speed_section.get_ref(), Fix::new(operation, cache_operation, fixed_errors)))
         })
         .into_iter()
         .collect()
@@ -93,6 +100,7 @@ mod tests {
     use crate::infra_cache::operation::CacheOperation;
     use crate::infra_cache::operation::Operation;
     use crate::infra_cache::ObjectCache;
+    use crate::views::infra::auto_fixes::Fix;
     use editoast_schemas::infra::ApplicableDirections;
     use editoast_schemas::infra::ApplicableDirectionsTrackRange;
     use editoast_schemas::infra::SpeedSection;
@@ -145,7 +153,12 @@ mod tests {
 
         assert_eq!(operations.len(), 1);
 
-        let (operation, cache_operation) = operations.get(&speed_section_cache.get_ref()).unwrap();
+        let Fix {
+            operation,
+            cache_operation,
+            errors,
+        } = operations.get(&speed_section_cache.get_ref()).unwrap();
+        assert_eq!(errors.len(), 2);
         let Operation::Update(update_operation) = operation else {
             panic!("not an `Operation::Update`");
         };
@@ -207,7 +220,11 @@ mod tests {
 
         assert_eq!(operations.len(), 1);
 
-        let (operation, cache_operation) = operations.get(&speed_section_cache.get_ref()).unwrap();
+        let Fix {
+            operation,
+            cache_operation,
+            ..
+        } = operations.get(&speed_section_cache.get_ref()).unwrap();
         let Operation::Delete(delete_operation) = operation else {
             panic!("not an `Operation::Delete`");
         };
@@ -15,18 +15,17 @@ pub fn fix_switch(
     switch: &SwitchCache,
     errors: impl Iterator<Item = InfraError>,
 ) -> HashMap<ObjectRef, Fix> {
-    errors
-        .filter_map(|infra_error| match infra_error.get_sub_type() {
-            InfraErrorType::InvalidSwitchPorts => Some(new_ref_fix_delete_pair(switch)),
-            InfraErrorType::InvalidReference { reference }
-                if reference.obj_type == ObjectType::TrackSection =>
-            {
-                Some(new_ref_fix_delete_pair(switch))
-            }
-            _ => {
-                debug!("error not (yet) fixable for '{}'", infra_error.get_type());
-                None
-            }
-        })
-        .collect()
+    let fixes = errors.filter_map(|infra_error| match infra_error.get_sub_type() {
+        InfraErrorType::InvalidSwitchPorts => Some(new_ref_fix_delete_pair(switch, &infra_error)),
+        InfraErrorType::InvalidReference { reference }
+            if reference.obj_type == ObjectType::TrackSection =>
+        {
+            Some(new_ref_fix_delete_pair(switch, &infra_error))
+        }
+        _ => {
+            debug!("error not (yet) fixable for '{}'", infra_error.get_type());
+            None
+        }
+    });
+    super::collect_fixes(fixes)
 }
@@ -5,45 +5,82 @@ use uuid::Uuid;
 
 use super::new_ref_fix_create_pair;
 use super::Fix;
+use super::Snaps;
 use crate::generated_data::infra_error::InfraError;
 use crate::generated_data::infra_error::InfraErrorType;
 use crate::infra_cache::object_cache::TrackSectionCache;
 use editoast_schemas::infra::BufferStop;
 use editoast_schemas::infra::Endpoint;
 use editoast_schemas::infra::InfraObject;
+use editoast_schemas::infra::Switch;
+use editoast_schemas::infra::TrackEndpoint;
 use editoast_schemas::primitives::Identifier;
 use editoast_schemas::primitives::OSRDIdentified as _;
 use editoast_schemas::primitives::OSRDObject as _;
 use editoast_schemas::primitives::ObjectRef;
 
+/// Fixes the errors of a track section
+///
+/// A missing buffer stop is fixed by linking the endpoint to the one it snaps to if any,
+/// and by adding a buffer stop otherwise.
 pub fn fix_track_section(
     track_section: &TrackSectionCache,
     errors: impl Iterator<Item = InfraError>,
+    snaps: &Snaps,
 ) -> HashMap<ObjectRef, Fix> {
-    errors
-        .filter_map(|infra_error| match infra_error.get_sub_type() {
-            InfraErrorType::MissingBufferStop { endpoint } => {
-                let track_id = infra_error.get_id();
-                let position = match endpoint {
-                    Endpoint::Begin => 0.0,
-                    Endpoint::End => track_section.length,
-                };
-                let buffer_stop = InfraObject::BufferStop {
-                    railjson: (BufferStop {
-                        id: Identifier::from(Uuid::new_v4()),
-                        track: track_id.to_string().into(),
-                        position,
-                        ..Default::default()
-                    }),
-                };
-                Some(new_ref_fix_create_pair(buffer_stop))
+    let fixes = errors.filter_map(|infra_error| match infra_error.get_sub_type() {
+        InfraErrorType::MissingBufferStop { endpoint } => {
+            let track_id = infra_error.get_id();
+            let track_endpoint = TrackEndpoint::new(track_id, *endpoint);
+            match snaps.get(&track_endpoint) {
+                // Both snapped endpoints are fixed by a single link, created from the first one
+                Some((other, _)) if endpoint_key(other) < endpoint_key(&track_endpoint) => None,
+                Some((other, other_error)) => {
+                    let link = InfraObject::Switch {
+                        railjson: Switch {
+                            id: Identifier::from(Uuid::new_v4()),
+                            switch_type: Identifier::from("link"),
+                            ports: HashMap::from([
+                                (Identifier::from("A"), track_endpoint.clone()),
+                                (Identifier::from("B"), other.clone()),
+                            ]),
+                            ..Default::default()
+                        },
+                    };
+                    let (object_ref, mut fix) = new_ref_fix_create_pair(link, &infra_error);
+                    fix.errors.push(other_error.clone());
+                    Some((object_ref, fix))
+                }
+                None => {
+                    let position = match endpoint {
+                        Endpoint::Begin => 0.0,
+                        Endpoint::End => track_section.length,
+                    };
+                    let buffer_stop = InfraObject::BufferStop {
+                        railjson: (BufferStop {
+                            id: Identifier::from(Uuid::new_v4()),
+                            track: track_id.to_string().into(),
+                            position,
+                            ..Default::default()
+                        }),
+                    };
+                    Some(new_ref_fix_create_pair(buffer_stop, &infra_error))
+                }
             }
-            _ => {
-                debug!("error not (yet) fixable for '{}'", infra_error.get_type());
-                None
-            }
-        })
-        .collect()
+        }
+        _ => {
+            debug!("error not (yet) fixable for '{}'", infra_error.get_type());
+            None
+        }
+    });
+    super::collect_fixes(fixes)
+}
+
+fn endpoint_key(track_endpoint: &TrackEndpoint) -> (&str, bool) {
+    (
+        track_endpoint.track.as_str(),
+        track_endpoint.endpoint == Endpoint::End,
+    )
 }
 
 #[cfg(test)]
@@ -69,11 +106,17 @@ mod tests {
         )];
         let operations = fix_track_section(
             &TrackSectionCache::from(track_section.clone()),
-            errors.into_iter(),
+            errors.clone().into_iter(),
+            &Snaps::default(),
         );
 
         assert_eq!(operations.len(), 1);
-        let (operation, cache_operation) = operations.into_values().next().unwrap();
+        let Fix {
+            operation,
+            cache_operation,
+            errors: fixed_errors,
+        } = operations.into_values().next().unwrap();
+        assert_eq!(fixed_errors, errors);
         let Operation::Create(railjson) = operation else {
             panic!("expecting an `Operation::Create(_)`");
         };
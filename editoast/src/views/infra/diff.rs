@@ -0,0 +1,181 @@
+use actix_web::get;
+use actix_web::web::Data;
+use actix_web::web::Json;
+use actix_web::web::Path;
+use actix_web::web::Query;
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+use crate::error::Result;
+use crate::infra_cache::operation::Operation;
+use crate::modelsv2::infra::InfraDiff;
+use crate::modelsv2::prelude::*;
+use crate::modelsv2::DbConnectionPool;
+use crate::modelsv2::Infra;
+use crate::views::infra::InfraApiError;
+
+crate::routes! {
+    "/diff/{other_infra_id}" => {
+        diff,
+        "/operations" => {
+            diff_operations,
+        },
+    },
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[allow(unused)]
+struct DiffPathParam {
+    /// The base infra ID
+    infra_id: i64,
+    /// The ID of the infra compared to the base infra
+    other_infra_id: i64,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct DiffQueryParams {
+    /// Compare the base infra as it was right after this revision
+    revision: Option<i64>,
+    /// Compare the other infra as it was right after this revision
+    other_revision: Option<i64>,
+}
+
+/// Compare the objects of two infras
+///
+/// The differences are expressed from the base infra to the compared one.
+#[utoipa::path(
+    tag = "infra",
+    params(DiffPathParam, DiffQueryParams),
+    responses(
+        (status = 200, description = "The differences between both infras", body = InfraDiff),
+        (status = 404, description = "Infra or revision not found"),
+    ),
+)]
+#[get("")]
+async fn diff(
+    path: Path<DiffPathParam>,
+    params: Query<DiffQueryParams>,
+    db_pool: Data<DbConnectionPool>,
+) -> Result<Json<InfraDiff>> {
+    Ok(Json(
+        compute_diff(&db_pool, path.into_inner(), params.into_inner()).await?,
+    ))
+}
+
+/// Compare the objects of two infras as a batch of operations
+///
+/// The operations turn the base infra into the compared one. They can be applied
+/// to another infra with the `POST /infra/ID` endpoint.
+#[utoipa::path(
+    tag = "infra",
+    params(DiffPathParam, DiffQueryParams),
+    responses(
+        (status = 200, description = "The operations turning the base infra into the compared one", body = Vec<Operation>),
+        (status = 404, description = "Infra or revision not found"),
+    ),
+)]
+#[get("")]
+async fn diff_operations(
+    path: Path<DiffPathParam>,
+    params: Query<DiffQueryParams>,
+    db_pool: Data<DbConnectionPool>,
+) -> Result<Json<Vec<Operation>>> {
+    let infra_diff = compute_diff(&db_pool, path.into_inner(), params.into_inner()).await?;
+    Ok(Json(infra_diff.into_operations()))
+}
+
+async fn compute_diff(
+    db_pool: &DbConnectionPool,
+    DiffPathParam {
+        infra_id,
+        other_infra_id,
+    }: DiffPathParam,
+    DiffQueryParams {
+        revision,
+        other_revision,
+    }: DiffQueryParams,
+) -> Result<InfraDiff> {
+    let conn = &mut db_pool.get().await?;
+    let infra =
+        Infra::retrieve_or_fail(conn, infra_id, || InfraApiError::NotFound { infra_id }).await?;
+    let other_infra = Infra::retrieve_or_fail(conn, other_infra_id, || InfraApiError::NotFound {
+        infra_id: other_infra_id,
+    })
+    .await?;
+    let base = infra.snapshot(conn, revision).await?;
+    let other = other_infra.snapshot(conn, other_revision).await?;
+    Ok(InfraDiff::new(&base, &other))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use actix_web::test::call_and_read_body_json;
+    use actix_web::test::call_service;
+    use actix_web::test::TestRequest;
+    use rstest::*;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::fixtures::tests::db_pool;
+    use crate::fixtures::tests::small_infra;
+    use crate::fixtures::tests::IntoFixture;
+    use crate::views::tests::create_test_service;
+
+    #[rstest]
+    async fn diff_cloned_infra(db_pool: Arc<DbConnectionPool>) {
+        let app = create_test_service().await;
+        let small_infra = small_infra(db_pool.clone()).await;
+        let small_infra_id = small_infra.id();
+
+        let req = TestRequest::post()
+            .uri(format!("/infra/{small_infra_id}/clone/?name=cloned_infra").as_str())
+            .to_request();
+        let clone_id: i64 = call_and_read_body_json(&app, req).await;
+        let _clone = Infra::retrieve(&mut db_pool.get().await.unwrap(), clone_id)
+            .await
+            .unwrap()
+            .expect("infra was not cloned")
+            .into_fixture(db_pool);
+
+        let req = TestRequest::get()
+            .uri(format!("/infra/{small_infra_id}/diff/{clone_id}").as_str())
+            .to_request();
+        let infra_diff: InfraDiff = call_and_read_body_json(&app, req).await;
+        assert_eq!(infra_diff, InfraDiff::default());
+
+        let req = TestRequest::post()
+            .uri(format!("/infra/{clone_id}/").as_str())
+            .set_json(json!([{
+                "operation_type": "UPDATE",
+                "obj_type": "TrackSection",
+                "obj_id": "TA0",
+                "railjson_patch": [{ "op": "replace", "path": "/length", "value": 1234.0 }],
+            }]))
+            .to_request();
+        assert_eq!(call_service(&app, req).await.status(), StatusCode::OK);
+
+        let req = TestRequest::get()
+            .uri(format!("/infra/{small_infra_id}/diff/{clone_id}/operations").as_str())
+            .to_request();
+        let operations: Vec<Operation> = call_and_read_body_json(&app, req).await;
+        assert_eq!(operations.len(), 1);
+        assert!(matches!(&operations[0], Operation::Update(update) if update.obj_id == "TA0"));
+    }
+
+    #[rstest]
+    async fn diff_with_unknown_revision() {
+        let app = create_test_service().await;
+        let small_infra = small_infra(db_pool()).await;
+
+        let req = TestRequest::get()
+            .uri(format!("/infra/{0}/diff/{0}?revision=-1", small_infra.id()).as_str())
+            .to_request();
+        assert_eq!(
+            call_service(&app, req).await.status(),
+            StatusCode::NOT_FOUND
+        );
+    }
+}
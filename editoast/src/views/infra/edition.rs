@@ -1,13 +1,20 @@
+use actix_web::http::header::ETag;
+use actix_web::http::header::EntityTag;
 use actix_web::post;
 use actix_web::web::Data;
 use actix_web::web::Json;
 use actix_web::web::Path;
+use actix_web::CustomizeResponder;
+use actix_web::Responder as _;
 use chashmap::CHashMap;
 use editoast_derive::EditoastError;
 use editoast_schemas::infra::ApplicableDirectionsTrackRange;
+use editoast_schemas::infra::Curve;
 use editoast_schemas::infra::DirectionalTrackRange;
 use editoast_schemas::infra::Endpoint;
+use editoast_schemas::infra::LoadingGaugeLimit;
 use editoast_schemas::infra::Sign;
+use editoast_schemas::infra::Slope;
 use editoast_schemas::infra::Switch;
 use editoast_schemas::infra::TrackEndpoint;
 use editoast_schemas::infra::TrackOffset;
@@ -15,18 +22,30 @@ use editoast_schemas::infra::TrackSection;
 use editoast_schemas::primitives::Identifier;
 use editoast_schemas::primitives::OSRDIdentified;
 use editoast_schemas::primitives::ObjectType;
+use geos::geojson::Geometry;
+use geos::geojson::Value::LineString;
 use itertools::Itertools;
 use json_patch::{AddOperation, Patch, PatchOperation, RemoveOperation, ReplaceOperation};
+use serde::Deserialize;
+use serde::Serialize;
 use serde_json::json;
 use std::collections::HashMap;
+use std::collections::HashSet;
 use thiserror::Error;
 use tracing::error;
 use tracing::info;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
+use super::locks::LockError;
+use crate::error::InternalError;
 use crate::error::Result;
 use crate::generated_data;
+use crate::generated_data::generate_infra_errors;
+use crate::generated_data::infra_error::InfraError;
 use crate::infra_cache::object_cache::OperationalPointPartCache;
+use crate::infra_cache::operation::load_object_data;
+use crate::infra_cache::operation::simulate_operations;
 use crate::infra_cache::operation::CacheOperation;
 use crate::infra_cache::operation::DeleteOperation;
 use crate::infra_cache::operation::Operation;
@@ -35,18 +54,29 @@ use crate::infra_cache::InfraCache;
 use crate::infra_cache::ObjectCache;
 use crate::map;
 use crate::map::MapLayers;
+use crate::modelsv2::infra_lock::InfraLock;
+use crate::modelsv2::infra_revision::InfraRevision;
 use crate::modelsv2::prelude::*;
 use crate::modelsv2::DbConnection;
 use crate::modelsv2::DbConnectionPool;
 use crate::modelsv2::Infra;
 use crate::views::infra::InfraApiError;
 use crate::views::infra::InfraIdParam;
+use crate::views::params::ExpectedVersion;
+use crate::views::params::RemoteUser;
 use crate::RedisClient;
 use editoast_schemas::infra::InfraObject;
 
 crate::routes! {
     edit,
+    preview,
     split_track_section,
+    merge_track_sections,
+}
+
+editoast_common::schemas! {
+    EditionPreview,
+    TrackSectionsToMerge,
 }
 
 /// Edit the content of an infrastructure
@@ -59,15 +89,26 @@ crate::routes! {
 ///
 /// After editing the object, the generated cartographic layers are invalidated and
 /// regenerated. The edition step fails if the regeneration fails.
+///
+/// The edition can be made conditional to the version of the infra with the `If-Match` header.
+/// Edited objects locked by other users are rejected.
 #[utoipa::path(
     tag = "infra",
     params(InfraIdParam),
     request_body = Vec<Operation>,
     responses(
-        (status = 200, body = Vec<InfraObject>, description = "The result of the operations")
+        (
+            status = 200,
+            body = Vec<InfraObject>,
+            description = "The result of the operations",
+            headers(("ETag" = String, description = "The new version of the infra")),
+        ),
+        (status = 409, description = "Some edited objects are locked by other users"),
+        (status = 412, description = "The infra doesn't match the version given in the `If-Match` header"),
     )
 )]
 #[post("")]
+#[allow(clippy::too_many_arguments)]
 pub async fn edit<'a>(
     infra: Path<InfraIdParam>,
     operations: Json<Vec<Operation>>,
@@ -75,16 +116,24 @@ pub async fn edit<'a>(
     infra_caches: Data<CHashMap<i64, InfraCache>>,
     redis_client: Data<RedisClient>,
     map_layers: Data<MapLayers>,
-) -> Result<Json<Vec<InfraObject>>> {
+    RemoteUser(author): RemoteUser,
+    ExpectedVersion(expected_version): ExpectedVersion,
+) -> Result<CustomizeResponder<Json<Vec<InfraObject>>>> {
     let infra_id = infra.infra_id;
     let mut conn = db_pool.get().await?;
-    // TODO: lock for update
     let mut infra =
         Infra::retrieve_or_fail(&mut conn, infra_id, || InfraApiError::NotFound { infra_id })
             .await?;
     let mut infra_cache = InfraCache::get_or_load_mut(&mut conn, &infra_caches, &infra).await?;
-    let operation_results =
-        apply_edit(&mut conn, &mut infra, &operations, &mut infra_cache).await?;
+    let operation_results = apply_edit(
+        &mut conn,
+        &mut infra,
+        &operations,
+        &mut infra_cache,
+        author,
+        expected_version,
+    )
+    .await?;
 
     let mut conn = redis_client.get_connection().await?;
     map::invalidate_all(
@@ -94,7 +143,83 @@ pub async fn edit<'a>(
     )
     .await?;
 
-    Ok(Json(operation_results))
+    Ok(Json(operation_results)
+        .customize()
+        .insert_header(ETag(EntityTag::new_strong(infra.version))))
+}
+
+/// The outcome of an edition batch that was not applied
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+struct EditionPreview {
+    /// The objects resulting from the operations
+    objects: Vec<InfraObject>,
+    /// The infra errors introduced by the operations
+    new_errors: Vec<InfraError>,
+    /// The infra errors fixed by the operations
+    fixed_errors: Vec<InfraError>,
+}
+
+/// Preview the outcome of an edition batch
+///
+/// The operations are checked the same way as for an edition, but are only applied to a
+/// scratch copy of the infra cache: nothing is persisted and the infra version is left untouched.
+#[utoipa::path(
+    tag = "infra",
+    params(InfraIdParam),
+    request_body = Vec<Operation>,
+    responses(
+        (status = 200, body = EditionPreview, description = "The outcome of the operations"),
+        (status = 404, description = "The infra or an object targeted by the operations was not found"),
+    )
+)]
+#[post("/preview")]
+async fn preview(
+    infra: Path<InfraIdParam>,
+    operations: Json<Vec<Operation>>,
+    db_pool: Data<DbConnectionPool>,
+    infra_caches: Data<CHashMap<i64, InfraCache>>,
+) -> Result<Json<EditionPreview>> {
+    let infra_id = infra.infra_id;
+    let conn = &mut db_pool.get().await?;
+    let infra =
+        Infra::retrieve_or_fail(conn, infra_id, || InfraApiError::NotFound { infra_id }).await?;
+    let (objects, cache_operations) = simulate_operations(&operations, infra_id, conn).await?;
+
+    let mut scratch_cache = InfraCache::get_or_load(conn, &infra_caches, &infra)
+        .await?
+        .clone();
+    let errors_before = generate_infra_errors(&scratch_cache).await;
+    scratch_cache.apply_operations(&cache_operations)?;
+    let errors_after = generate_infra_errors(&scratch_cache).await;
+    let (new_errors, fixed_errors) = compare_errors(errors_before, errors_after);
+    Ok(Json(EditionPreview {
+        objects,
+        new_errors,
+        fixed_errors,
+    }))
+}
+
+/// Returns the errors only found after an edition, and the ones only found before
+fn compare_errors(
+    before: Vec<InfraError>,
+    after: Vec<InfraError>,
+) -> (Vec<InfraError>, Vec<InfraError>) {
+    // Infra errors can't be hashed, compare their serialization instead
+    let keys = |errors: &[InfraError]| -> HashSet<String> {
+        errors
+            .iter()
+            .map(|error| serde_json::to_string(error).expect("infra errors are serializable"))
+            .collect()
+    };
+    let keys_before = keys(&before);
+    let keys_after = keys(&after);
+    let only_in = |errors: Vec<InfraError>, other_keys: &HashSet<String>| {
+        errors
+            .into_iter()
+            .filter(|error| !other_keys.contains(&serde_json::to_string(error).unwrap()))
+            .collect()
+    };
+    (only_in(after, &keys_before), only_in(before, &keys_after))
 }
 
 #[utoipa::path(
@@ -106,6 +231,7 @@ pub async fn edit<'a>(
     ),
 )]
 #[post("/split_track_section")]
+#[allow(clippy::too_many_arguments)]
 pub async fn split_track_section<'a>(
     infra: Path<i64>,
     payload: Json<TrackOffset>,
@@ -113,6 +239,8 @@ pub async fn split_track_section<'a>(
     infra_caches: Data<CHashMap<i64, InfraCache>>,
     redis_client: Data<RedisClient>,
     map_layers: Data<MapLayers>,
+    RemoteUser(author): RemoteUser,
+    ExpectedVersion(expected_version): ExpectedVersion,
 ) -> Result<Json<Vec<String>>> {
     let payload = payload.into_inner();
     let infra_id = infra.into_inner();
@@ -306,7 +434,15 @@ pub async fn split_track_section<'a>(
     }));
 
     // Apply operations
-    apply_edit(conn, &mut infra, &operations, &mut infra_cache).await?;
+    apply_edit(
+        conn,
+        &mut infra,
+        &operations,
+        &mut infra_cache,
+        author,
+        expected_version,
+    )
+    .await?;
     let mut conn = redis_client.get_connection().await?;
     map::invalidate_all(
         &mut conn,
@@ -809,71 +945,691 @@ fn get_splitted_patch_operations_for_ranges(
     patch_operations
 }
 
-async fn apply_edit(
+#[derive(Debug, Deserialize, ToSchema)]
+struct TrackSectionsToMerge {
+    #[schema(inline)]
+    first_track: Identifier,
+    #[schema(inline)]
+    second_track: Identifier,
+}
+
+/// Merge two adjacent track sections into a new one
+///
+/// The track sections must either be linked by a `link` switch, or share an endpoint which is
+/// not connected to any switch. They must also have the same direction, but can be given in any order.
+///
+/// The merged track section concatenates their geometries, slopes, curves and loading gauge limits,
+/// and keeps the extensions of the upstream track section. The objects located on the merged
+/// track sections are moved to the new one, and the link switch is deleted along with its
+/// references in routes.
+#[utoipa::path(
+    tag = "infra",
+    params(InfraIdParam),
+    request_body = TrackSectionsToMerge,
+    responses(
+        (status = 200, body = inline(String), description = "ID of the merged track section"),
+        (status = 400, description = "The track sections are not adjacent or have opposite directions"),
+        (status = 404, description = "The infra or a track section was not found"),
+    ),
+)]
+#[post("/merge_track_sections")]
+#[allow(clippy::too_many_arguments)]
+async fn merge_track_sections(
+    infra: Path<InfraIdParam>,
+    payload: Json<TrackSectionsToMerge>,
+    db_pool: Data<DbConnectionPool>,
+    infra_caches: Data<CHashMap<i64, InfraCache>>,
+    redis_client: Data<RedisClient>,
+    map_layers: Data<MapLayers>,
+    RemoteUser(author): RemoteUser,
+    ExpectedVersion(expected_version): ExpectedVersion,
+) -> Result<Json<String>> {
+    let infra_id = infra.infra_id;
+    let TrackSectionsToMerge {
+        first_track,
+        second_track,
+    } = payload.into_inner();
+    info!(
+        first_track = first_track.as_str(),
+        second_track = second_track.as_str(),
+        "Merging track sections"
+    );
+    let conn = &mut db_pool.get().await?;
+
+    let mut infra =
+        Infra::retrieve_or_fail(conn, infra_id, || InfraApiError::NotFound { infra_id }).await?;
+    let mut infra_cache = InfraCache::get_or_load_mut(conn, &infra_caches, &infra).await?;
+
+    let first_track = load_track_section(conn, infra_id, &first_track).await?;
+    let second_track = load_track_section(conn, infra_id, &second_track).await?;
+    let track_merge = TrackSectionsMerge::new(infra_id, &infra_cache, first_track, second_track)?;
+    let operations = track_merge.operations(&infra_cache);
+
+    apply_edit(
+        conn,
+        &mut infra,
+        &operations,
+        &mut infra_cache,
+        author,
+        expected_version,
+    )
+    .await?;
+    let mut conn = redis_client.get_connection().await?;
+    map::invalidate_all(
+        &mut conn,
+        &map_layers.layers.keys().cloned().collect(),
+        infra_id,
+    )
+    .await?;
+
+    Ok(Json(track_merge.merged_id.to_string()))
+}
+
+async fn load_track_section(
+    conn: &mut DbConnection,
+    infra_id: i64,
+    track_id: &str,
+) -> Result<TrackSection> {
+    let data = load_object_data(infra_id, ObjectType::TrackSection, track_id, conn).await?;
+    Ok(serde_json::from_value(data)?)
+}
+
+/// Two adjacent track sections being merged
+#[derive(Debug)]
+struct TrackSectionsMerge {
+    upstream: TrackSection,
+    downstream: TrackSection,
+    /// The ID of the switch linking both track sections, if any
+    link: Option<String>,
+    merged_id: Identifier,
+}
+
+impl TrackSectionsMerge {
+    /// Checks that two track sections can be merged and sorts them in their direction
+    fn new(
+        infra_id: i64,
+        infra_cache: &InfraCache,
+        track: TrackSection,
+        other: TrackSection,
+    ) -> Result<Self> {
+        let tracksection_ids = vec![track.id.to_string(), other.id.to_string()];
+        let not_adjacent = || EditionError::MergeTrackSectionsNotAdjacent {
+            infra_id,
+            tracksection_ids: tracksection_ids.clone(),
+        };
+        if track.id == other.id {
+            return Err(not_adjacent().into());
+        }
+
+        let switches = [&track.id, &other.id]
+            .into_iter()
+            .flat_map(|track_id| infra_cache.get_track_refs_type(track_id, ObjectType::Switch))
+            .unique()
+            .map(|switch| infra_cache.get_switch(&switch.obj_id))
+            .collect::<Result<Vec<_>>>()?;
+        let link = switches.iter().find(|switch| {
+            switch.switch_type == "link"
+                && switch.ports.len() == 2
+                && switch.ports.values().any(|port| port.track == track.id)
+                && switch.ports.values().any(|port| port.track == other.id)
+        });
+
+        // Find the endpoints connecting both track sections
+        let (track_endpoint, other_endpoint) = match link {
+            Some(link) => {
+                let endpoint = |track_id: &Identifier| {
+                    link.ports
+                        .values()
+                        .find(|port| &port.track == track_id)
+                        .map(|port| port.endpoint)
+                        .expect("the link connects both track sections")
+                };
+                (endpoint(&track.id), endpoint(&other.id))
+            }
+            None => {
+                let track_geo = line_string_coordinates(&track);
+                let other_geo = line_string_coordinates(&other);
+                let touch = |position: Option<&Vec<f64>>, other_position: Option<&Vec<f64>>| {
+                    position.is_some() && position == other_position
+                };
+                if touch(track_geo.last(), other_geo.first()) {
+                    (Endpoint::End, Endpoint::Begin)
+                } else if touch(track_geo.first(), other_geo.last()) {
+                    (Endpoint::Begin, Endpoint::End)
+                } else if touch(track_geo.first(), other_geo.first()) {
+                    (Endpoint::Begin, Endpoint::Begin)
+                } else if touch(track_geo.last(), other_geo.last()) {
+                    (Endpoint::End, Endpoint::End)
+                } else {
+                    return Err(not_adjacent().into());
+                }
+            }
+        };
+
+        // Any other switch on the junction would be disconnected by the merge
+        let junction = [
+            TrackEndpoint {
+                track: track.id.clone(),
+                endpoint: track_endpoint,
+            },
+            TrackEndpoint {
+                track: other.id.clone(),
+                endpoint: other_endpoint,
+            },
+        ];
+        let link_id = link.map(|link| link.obj_id.clone());
+        if switches
+            .iter()
+            .filter(|switch| Some(&switch.obj_id) != link_id.as_ref())
+            .flat_map(|switch| switch.ports.values())
+            .any(|port| junction.contains(port))
+        {
+            return Err(not_adjacent().into());
+        }
+
+        let (upstream, downstream) = match (track_endpoint, other_endpoint) {
+            (Endpoint::End, Endpoint::Begin) => (track, other),
+            (Endpoint::Begin, Endpoint::End) => (other, track),
+            _ => {
+                return Err(EditionError::MergeTrackSectionsOppositeDirections {
+                    infra_id,
+                    tracksection_ids,
+                }
+                .into())
+            }
+        };
+        Ok(Self {
+            upstream,
+            downstream,
+            link: link_id,
+            merged_id: Identifier::from(Uuid::new_v4()),
+        })
+    }
+
+    /// Returns the offset of a merged track section on the new one
+    fn offset(&self, track: &str) -> Option<f64> {
+        if track == self.upstream.id.as_str() {
+            Some(0.0)
+        } else if track == self.downstream.id.as_str() {
+            Some(self.upstream.length)
+        } else {
+            None
+        }
+    }
+
+    /// Builds the track section resulting from the merge
+    fn merged(&self) -> TrackSection {
+        let offset = self.upstream.length;
+        let mut geo = line_string_coordinates(&self.upstream).to_vec();
+        let downstream_geo = line_string_coordinates(&self.downstream);
+        // Don't duplicate the junction point
+        let skip = usize::from(geo.last().is_some() && geo.last() == downstream_geo.first());
+        geo.extend(downstream_geo.iter().skip(skip).cloned());
+        TrackSection {
+            id: self.merged_id.clone(),
+            length: self.upstream.length + self.downstream.length,
+            slopes: self
+                .upstream
+                .slopes
+                .iter()
+                .cloned()
+                .chain(self.downstream.slopes.iter().map(|slope| Slope {
+                    begin: slope.begin + offset,
+                    end: slope.end + offset,
+                    ..slope.clone()
+                }))
+                .collect(),
+            curves: self
+                .upstream
+                .curves
+                .iter()
+                .cloned()
+                .chain(self.downstream.curves.iter().map(|curve| Curve {
+                    begin: curve.begin + offset,
+                    end: curve.end + offset,
+                    ..curve.clone()
+                }))
+                .collect(),
+            loading_gauge_limits: self
+                .upstream
+                .loading_gauge_limits
+                .iter()
+                .cloned()
+                .chain(
+                    self.downstream
+                        .loading_gauge_limits
+                        .iter()
+                        .map(|limit| LoadingGaugeLimit {
+                            begin: limit.begin + offset,
+                            end: limit.end + offset,
+                            ..limit.clone()
+                        }),
+                )
+                .collect(),
+            geo: Geometry::new(LineString(geo)),
+            extensions: self.upstream.extensions.clone(),
+        }
+    }
+
+    /// Computes the operations creating the merged track section, moving the objects
+    /// located on the merged track sections and deleting the obsolete objects
+    fn operations(&self, infra_cache: &InfraCache) -> Vec<Operation> {
+        let mut operations = vec![Operation::Create(Box::new(InfraObject::TrackSection {
+            railjson: self.merged(),
+        }))];
+
+        let impacted = [&self.upstream.id, &self.downstream.id]
+            .into_iter()
+            .filter_map(|track_id| infra_cache.track_sections_refs.get(track_id.as_str()))
+            .flatten()
+            .unique();
+        for obj in impacted {
+            let patch_operations = match obj.obj_type {
+                ObjectType::Signal => {
+                    let signal = infra_cache.get_signal(&obj.obj_id).unwrap();
+                    self.location_patch("", &signal.track, signal.position)
+                }
+                ObjectType::Detector => {
+                    let detector = infra_cache.get_detector(&obj.obj_id).unwrap();
+                    self.location_patch("", &detector.track, detector.position)
+                }
+                ObjectType::BufferStop => {
+                    let buffer_stop = infra_cache.get_buffer_stop(&obj.obj_id).unwrap();
+                    self.location_patch("", &buffer_stop.track, buffer_stop.position)
+                }
+                ObjectType::Switch if self.link.as_ref() == Some(&obj.obj_id) => continue,
+                ObjectType::Switch => {
+                    let switch = infra_cache.get_switch(&obj.obj_id).unwrap();
+                    // The endpoints of the merged track sections left are the ones of the new one
+                    switch
+                        .ports
+                        .iter()
+                        .filter(|(_, port)| self.offset(&port.track).is_some())
+                        .map(|(key, _)| {
+                            PatchOperation::Replace(ReplaceOperation {
+                                path: format!("/ports/{}/track", key).parse().unwrap(),
+                                value: json!(self.merged_id),
+                            })
+                        })
+                        .collect()
+                }
+                ObjectType::Electrification => {
+                    let electrification = infra_cache.get_electrification(&obj.obj_id).unwrap();
+                    vec![PatchOperation::Replace(ReplaceOperation {
+                        path: "/track_ranges".parse().unwrap(),
+                        value: json!(self.applicable_ranges(&electrification.track_ranges)),
+                    })]
+                }
+                ObjectType::SpeedSection => {
+                    let speed_section = infra_cache.get_speed_section(&obj.obj_id).unwrap();
+                    let mut patch_operations = vec![PatchOperation::Replace(ReplaceOperation {
+                        path: "/track_ranges".parse().unwrap(),
+                        value: json!(self.applicable_ranges(&speed_section.track_ranges)),
+                    })];
+                    if let Some(psl) = &speed_section.extensions.psl_sncf {
+                        patch_operations.extend(self.sign_patch("/extensions/psl_sncf/z", psl.z()));
+                        for (index, sign) in psl.announcement().iter().enumerate() {
+                            patch_operations.extend(self.sign_patch(
+                                &format!("/extensions/psl_sncf/announcement/{}", index),
+                                sign,
+                            ));
+                        }
+                        for (index, sign) in psl.r().iter().enumerate() {
+                            patch_operations.extend(
+                                self.sign_patch(&format!("/extensions/psl_sncf/r/{}", index), sign),
+                            );
+                        }
+                    }
+                    patch_operations
+                }
+                ObjectType::NeutralSection => {
+                    let neutral_section = infra_cache.get_neutral_section(&obj.obj_id).unwrap();
+                    let mut patch_operations = vec![
+                        PatchOperation::Replace(ReplaceOperation {
+                            path: "/track_ranges".parse().unwrap(),
+                            value: json!(self.directional_ranges(&neutral_section.track_ranges)),
+                        }),
+                        PatchOperation::Replace(ReplaceOperation {
+                            path: "/announcement_track_ranges".parse().unwrap(),
+                            value: json!(
+                                self.directional_ranges(&neutral_section.announcement_track_ranges)
+                            ),
+                        }),
+                    ];
+                    if let Some(neutral) = &neutral_section.extensions.neutral_sncf {
+                        patch_operations
+                            .extend(self.sign_patch("/extensions/neutral_sncf/exe", &neutral.exe));
+                        for (name, signs) in [
+                            ("announcement", &neutral.announcement),
+                            ("end", &neutral.end),
+                            ("rev", &neutral.rev),
+                        ] {
+                            for (index, sign) in signs.iter().enumerate() {
+                                patch_operations.extend(self.sign_patch(
+                                    &format!("/extensions/neutral_sncf/{}/{}", name, index),
+                                    sign,
+                                ));
+                            }
+                        }
+                    }
+                    patch_operations
+                }
+                ObjectType::OperationalPoint => {
+                    let operational_point = infra_cache.get_operational_point(&obj.obj_id).unwrap();
+                    operational_point
+                        .parts
+                        .iter()
+                        .enumerate()
+                        .flat_map(|(index, part)| {
+                            self.location_patch(
+                                &format!("/parts/{}", index),
+                                &part.track,
+                                part.position,
+                            )
+                        })
+                        .collect()
+                }
+                // Routes don't reference track sections
+                ObjectType::Route => continue,
+                // Track sections and switch types don't depend on track sections
+                ObjectType::TrackSection | ObjectType::SwitchType => continue,
+            };
+            if !patch_operations.is_empty() {
+                operations.push(Operation::Update(UpdateOperation {
+                    obj_type: obj.obj_type,
+                    obj_id: obj.obj_id.clone(),
+                    railjson_patch: Patch(patch_operations),
+                }));
+            }
+        }
+
+        if let Some(link) = &self.link {
+            // Routes can't go through the deleted link anymore
+            for route in infra_cache.routes().values() {
+                let route = route.unwrap_route();
+                if route
+                    .switches_directions
+                    .contains_key(&Identifier::from(link.as_str()))
+                {
+                    let key = link.replace('~', "~0").replace('/', "~1");
+                    operations.push(Operation::Update(UpdateOperation {
+                        obj_type: ObjectType::Route,
+                        obj_id: route.id.to_string(),
+                        railjson_patch: Patch(vec![PatchOperation::Remove(RemoveOperation {
+                            path: format!("/switches_directions/{}", key).parse().unwrap(),
+                        })]),
+                    }));
+                }
+            }
+            operations.push(Operation::Delete(DeleteOperation {
+                obj_type: ObjectType::Switch,
+                obj_id: link.clone(),
+            }));
+        }
+        for track in [&self.upstream, &self.downstream] {
+            operations.push(Operation::Delete(DeleteOperation {
+                obj_type: ObjectType::TrackSection,
+                obj_id: track.id.to_string(),
+            }));
+        }
+        operations
+    }
+
+    /// Moves a location (`<path>/track` and `<path>/position`) to the merged track section
+    fn location_patch(&self, path: &str, track: &str, position: f64) -> Vec<PatchOperation> {
+        let Some(offset) = self.offset(track) else {
+            return vec![];
+        };
+        vec![
+            PatchOperation::Replace(ReplaceOperation {
+                path: format!("{}/track", path).parse().unwrap(),
+                value: json!(self.merged_id),
+            }),
+            PatchOperation::Replace(ReplaceOperation {
+                path: format!("{}/position", path).parse().unwrap(),
+                value: json!(position + offset),
+            }),
+        ]
+    }
+
+    fn sign_patch(&self, path: &str, sign: &Sign) -> Vec<PatchOperation> {
+        self.location_patch(path, &sign.track, sign.position)
+    }
+
+    /// Moves track ranges to the merged track section, joining the ones meeting at the junction
+    fn applicable_ranges(
+        &self,
+        ranges: &[ApplicableDirectionsTrackRange],
+    ) -> Vec<ApplicableDirectionsTrackRange> {
+        ranges
+            .iter()
+            .map(|range| match self.offset(&range.track) {
+                Some(offset) => ApplicableDirectionsTrackRange {
+                    track: self.merged_id.clone(),
+                    begin: range.begin + offset,
+                    end: range.end + offset,
+                    ..range.clone()
+                },
+                None => range.clone(),
+            })
+            .coalesce(|range, next| {
+                if range.track == self.merged_id
+                    && next.track == self.merged_id
+                    && range.end == next.begin
+                    && range.applicable_directions == next.applicable_directions
+                {
+                    Ok(ApplicableDirectionsTrackRange {
+                        end: next.end,
+                        ..range
+                    })
+                } else {
+                    Err((range, next))
+                }
+            })
+            .collect()
+    }
+
+    /// Same as [TrackSectionsMerge::applicable_ranges] for `DirectionalTrackRange`
+    fn directional_ranges(&self, ranges: &[DirectionalTrackRange]) -> Vec<DirectionalTrackRange> {
+        ranges
+            .iter()
+            .map(|range| match self.offset(&range.track) {
+                Some(offset) => DirectionalTrackRange {
+                    track: self.merged_id.clone(),
+                    begin: range.begin + offset,
+                    end: range.end + offset,
+                    ..range.clone()
+                },
+                None => range.clone(),
+            })
+            .coalesce(|range, next| {
+                if range.track == self.merged_id
+                    && next.track == self.merged_id
+                    && range.end == next.begin
+                    && range.direction == next.direction
+                {
+                    Ok(DirectionalTrackRange {
+                        end: next.end,
+                        ..range
+                    })
+                } else {
+                    Err((range, next))
+                }
+            })
+            .collect()
+    }
+}
+
+/// Returns the coordinates of the geometry of a track section
+fn line_string_coordinates(track: &TrackSection) -> &[Vec<f64>] {
+    match &track.geo.value {
+        LineString(coordinates) => coordinates,
+        _ => &[],
+    }
+}
+
+pub(super) async fn apply_edit(
     connection: &mut DbConnection,
     infra: &mut Infra,
     operations: &[Operation],
     infra_cache: &mut InfraCache,
+    author: Option<String>,
+    expected_version: Option<Vec<String>>,
+) -> Result<Vec<InfraObject>> {
+    // Apply modifications in one transaction, the cache being updated only once it's committed
+    let mut scratch_cache = infra_cache.clone();
+    let scratch = &mut scratch_cache;
+    let infra_id = infra.id;
+    let railjsons = connection
+        .build_transaction()
+        .run::<_, InternalError, _>(|conn| {
+            Box::pin(async move {
+                // Concurrent editions of the infra wait for this one to be done
+                *infra = Infra::retrieve_for_update(conn, infra_id)
+                    .await?
+                    .ok_or(InfraApiError::NotFound { infra_id })?;
+                check_edition(
+                    conn,
+                    infra,
+                    operations,
+                    author.as_deref(),
+                    expected_version.as_deref(),
+                )
+                .await?;
+                apply_edit_in_transaction(conn, infra, operations, scratch, author).await
+            })
+        })
+        .await?;
+    *infra_cache = scratch_cache;
+    Ok(railjsons)
+}
+
+/// Applies an edition like [apply_edit], on a connection already in a transaction
+///
+/// It allows to commit the edition along with other changes. The infra must have been locked
+/// for update and the edition checked with [check_edition]. The given infra cache is updated
+/// even if the transaction is rolled back: it should be a copy, swapped in after the commit.
+pub(super) async fn apply_edit_in_transaction(
+    conn: &mut DbConnection,
+    infra: &mut Infra,
+    operations: &[Operation],
+    infra_cache: &mut InfraCache,
+    author: Option<String>,
 ) -> Result<Vec<InfraObject>> {
     let infra_id = infra.id;
-    // Check if the infra is locked
+    let (railjsons, mut inverse_operations) =
+        apply_operations(conn, infra, operations, infra_cache).await?;
+
+    // Record the revision in the edition history
+    inverse_operations.reverse();
+    InfraRevision::record(
+        conn,
+        infra_id,
+        infra.version.clone(),
+        author,
+        operations.to_vec(),
+        inverse_operations,
+    )
+    .await?;
+
+    Ok(railjsons)
+}
+
+/// Checks that an edition can be applied to the current state of the infra
+///
+/// The infra must not be locked, must still be at one of the expected versions if any, and
+/// the edited objects must not be locked by other users.
+pub(super) async fn check_edition(
+    conn: &mut DbConnection,
+    infra: &Infra,
+    operations: &[Operation],
+    author: Option<&str>,
+    expected_version: Option<&[String]>,
+) -> Result<()> {
+    let infra_id = infra.id;
     if infra.locked {
         return Err(EditionError::InfraIsLocked { infra_id }.into());
     }
+    if let Some(expected_version) = expected_version {
+        if !expected_version.contains(&infra.version) {
+            return Err(EditionError::StaleVersion {
+                infra_id,
+                expected_version: expected_version.join(", "),
+                version: infra.version.clone(),
+            }
+            .into());
+        }
+    }
+    let objects = operations.iter().map(Operation::object_ref).collect_vec();
+    let locks = InfraLock::held_by_others(conn, infra_id, &objects, author).await?;
+    if !locks.is_empty() {
+        return Err(LockError::ObjectsLocked { infra_id, locks }.into());
+    }
+    Ok(())
+}
 
-    // Apply modifications in one transaction
-    connection
-        .build_transaction()
-        .run(|conn| {
-            Box::pin(async {
-                let mut railjsons = vec![];
-                let mut cache_operations = vec![];
-                for operation in operations {
-                    let railjson = operation.apply(infra_id, conn).await?;
-                    match (operation, railjson) {
-                        (Operation::Create(_), Some(railjson)) => {
-                            railjsons.push(railjson.clone());
-                            cache_operations
-                                .push(CacheOperation::Create(ObjectCache::from(railjson)));
-                        }
-                        (Operation::Update(_), Some(railjson)) => {
-                            railjsons.push(railjson.clone());
-                            cache_operations
-                                .push(CacheOperation::Update(ObjectCache::from(railjson)));
-                        }
-                        (Operation::Delete(delete_operation), _) => {
-                            cache_operations
-                                .push(CacheOperation::Delete(delete_operation.clone().into()));
-                        }
-                        _ => unreachable!("CREATE and UPDATE always produce a RailJSON"),
-                    }
-                }
+/// Applies a batch of operations to an infra and updates its cache and generated data
+///
+/// Returns the resulting objects along with the operations reverting each operation of the batch.
+/// This function must be called within a transaction, with a copy of the infra cache that is
+/// only kept if the transaction is committed.
+pub(super) async fn apply_operations(
+    conn: &mut DbConnection,
+    infra: &mut Infra,
+    operations: &[Operation],
+    infra_cache: &mut InfraCache,
+) -> Result<(Vec<InfraObject>, Vec<Operation>)> {
+    let infra_id = infra.id;
+    let mut railjsons = vec![];
+    let mut inverse_operations = vec![];
+    let mut cache_operations = vec![];
+    for operation in operations {
+        let (railjson, inverse_operation) = operation.apply_and_invert(infra_id, conn).await?;
+        inverse_operations.push(inverse_operation);
+        match (operation, railjson) {
+            (Operation::Create(_), Some(railjson)) => {
+                railjsons.push(railjson.clone());
+                cache_operations.push(CacheOperation::Create(ObjectCache::from(railjson)));
+            }
+            (Operation::Update(_), Some(railjson)) => {
+                railjsons.push(railjson.clone());
+                cache_operations.push(CacheOperation::Update(ObjectCache::from(railjson)));
+            }
+            (Operation::Delete(delete_operation), _) => {
+                cache_operations.push(CacheOperation::Delete(delete_operation.clone().into()));
+            }
+            _ => unreachable!("CREATE and UPDATE always produce a RailJSON"),
+        }
+    }
 
-                // Bump version
-                infra.bump_version(conn).await?;
-                // Apply operations to infra cache
-                infra_cache.apply_operations(&cache_operations)?;
+    // Bump version
+    infra.bump_version(conn).await?;
+    // Apply operations to infra cache
+    infra_cache.apply_operations(&cache_operations)?;
 
-                // Refresh layers if needed
-                generated_data::update_all(conn, infra_id, &cache_operations, infra_cache)
-                    .await
-                    .expect("Update generated data failed");
+    // Refresh layers if needed
+    generated_data::update_all(conn, infra_id, &cache_operations, infra_cache).await?;
 
-                // Bump infra generated version to the infra version
-                infra.bump_generated_version(conn).await?;
+    // Bump infra generated version to the infra version
+    infra.bump_generated_version(conn).await?;
 
-                Ok(railjsons)
-            })
-        })
-        .await
+    Ok((railjsons, inverse_operations))
 }
 
 #[derive(Debug, Clone, Error, EditoastError)]
 #[editoast_error(base_id = "infra:edition")]
-enum EditionError {
+pub(super) enum EditionError {
     #[error("Infra {infra_id} is locked")]
     InfraIsLocked { infra_id: i64 },
 
+    #[error("Infra {infra_id} was edited since version {expected_version}, its current version is {version}")]
+    #[editoast_error(status = 412)]
+    StaleVersion {
+        infra_id: i64,
+        expected_version: String,
+        version: String,
+    },
+
     #[error("Invalid split offset for track section '{tracksection_id}' in infra '{infra_id}'. Expected a value between 0 and {tracksection_length} meters")]
     #[editoast_error(status = 400)]
     SplitTrackSectionBadOffset {
@@ -881,6 +1637,20 @@ enum EditionError {
         tracksection_id: String,
         tracksection_length: f64,
     },
+
+    #[error("Track sections {tracksection_ids:?} of infra '{infra_id}' can't be merged since they are not only connected to each other")]
+    #[editoast_error(status = 400)]
+    MergeTrackSectionsNotAdjacent {
+        infra_id: i64,
+        tracksection_ids: Vec<String>,
+    },
+
+    #[error("Track sections {tracksection_ids:?} of infra '{infra_id}' can't be merged since they have opposite directions")]
+    #[editoast_error(status = 400)]
+    MergeTrackSectionsOppositeDirections {
+        infra_id: i64,
+        tracksection_ids: Vec<String>,
+    },
 }
 
 #[cfg(test)]
@@ -889,14 +1659,21 @@ pub mod tests {
     use actix_web::test::call_and_read_body_json;
     use actix_web::test::call_service;
     use actix_web::test::TestRequest;
+    use editoast_schemas::primitives::ObjectRef;
     use rstest::*;
     use serde_json::Value as JsonValue;
 
     use super::*;
+    use crate::error::EditoastError as _;
     use crate::fixtures::tests::db_pool;
     use crate::fixtures::tests::small_infra;
     use crate::generated_data::infra_error::InfraError;
     use crate::generated_data::infra_error::InfraErrorType;
+    use crate::infra_cache::tests::create_electrification_cache;
+    use crate::infra_cache::tests::create_signal_cache;
+    use crate::infra_cache::tests::create_switch_cache_link;
+    use crate::infra_cache::tests::create_track_endpoint;
+    use crate::infra_cache::tests::create_track_section_cache;
     use crate::views::infra::errors::query_errors;
     use crate::views::tests::create_test_service;
 
@@ -1005,6 +1782,190 @@ pub mod tests {
         assert_eq!(errors_without_routes.len() - init_errors.len(), 0);
     }
 
+    #[rstest]
+    async fn merge_track_sections_should_revert_split() {
+        let pg_db_pool = db_pool();
+        let conn = &mut pg_db_pool.get().await.unwrap();
+        let small_infra = small_infra(pg_db_pool.clone()).await;
+        let infra_id = small_infra.id();
+        let app = create_test_service().await;
+        let req_refresh = TestRequest::post()
+            .uri(format!("/infra/refresh/?infras={infra_id}&force=true").as_str())
+            .to_request();
+        call_service(&app, req_refresh).await;
+        let (init_errors, _) = query_errors(conn, &small_infra).await;
+
+        let req = TestRequest::post()
+            .uri(format!("/infra/{infra_id}/split_track_section").as_str())
+            .set_json(json!({ "track": "TA0", "offset": 1000000 }))
+            .to_request();
+        let splitted: Vec<String> = call_and_read_body_json(&app, req).await;
+
+        // The track sections can be given in any order
+        let req = TestRequest::post()
+            .uri(format!("/infra/{infra_id}/merge_track_sections").as_str())
+            .set_json(json!({ "first_track": splitted[1], "second_track": splitted[0] }))
+            .to_request();
+        let merged: String = call_and_read_body_json(&app, req).await;
+
+        let infra_cache = InfraCache::load(conn, &small_infra.model).await.unwrap();
+        assert_eq!(
+            infra_cache.get_track_section(&merged).unwrap().length,
+            2000.0
+        );
+        assert!(infra_cache.get_track_section(&splitted[0]).is_err());
+        assert!(infra_cache.switches().values().all(|switch| switch
+            .unwrap_switch()
+            .ports
+            .values()
+            .all(|port| !splitted.contains(&port.track.to_string()))));
+        let (errors, _) = query_errors(conn, &small_infra).await;
+        let errors_without_routes = errors
+            .into_iter()
+            .filter(|e| {
+                !matches!(
+                    e.sub_type,
+                    InfraErrorType::MissingRoute | InfraErrorType::InvalidRoute
+                )
+            })
+            .count();
+        assert_eq!(errors_without_routes, init_errors.len());
+    }
+
+    #[rstest]
+    async fn merge_track_sections_connected_by_a_point_switch() {
+        let small_infra = small_infra(db_pool()).await;
+        let app = create_test_service().await;
+
+        let req = TestRequest::post()
+            .uri(format!("/infra/{}/merge_track_sections", small_infra.id()).as_str())
+            .set_json(json!({ "first_track": "TA0", "second_track": "TA1" }))
+            .to_request();
+        let res = call_service(&app, req).await;
+
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    fn track_section(id: &str, length: f64, geo: Vec<Vec<f64>>) -> TrackSection {
+        TrackSection {
+            id: id.into(),
+            length,
+            slopes: vec![Slope {
+                gradient: 5.0,
+                begin: 0.0,
+                end: length,
+            }],
+            geo: Geometry::new(LineString(geo)),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn merge_track_sections_sharing_an_endpoint() {
+        let upstream = track_section("A", 100.0, vec![vec![0.0, 0.0], vec![1.0, 0.0]]);
+        let downstream = track_section("B", 50.0, vec![vec![1.0, 0.0], vec![2.0, 0.0]]);
+
+        let track_merge =
+            TrackSectionsMerge::new(0, &InfraCache::default(), downstream, upstream).unwrap();
+        let merged = track_merge.merged();
+
+        assert_eq!(merged.length, 150.0);
+        assert_eq!(
+            merged.geo,
+            Geometry::new(LineString(vec![
+                vec![0.0, 0.0],
+                vec![1.0, 0.0],
+                vec![2.0, 0.0]
+            ]))
+        );
+        assert_eq!(
+            merged.slopes[1],
+            Slope {
+                gradient: 5.0,
+                begin: 100.0,
+                end: 150.0,
+            }
+        );
+    }
+
+    #[test]
+    fn merge_track_sections_with_opposite_directions() {
+        let track = track_section("A", 100.0, vec![vec![0.0, 0.0], vec![1.0, 0.0]]);
+        let other = track_section("B", 50.0, vec![vec![2.0, 0.0], vec![1.0, 0.0]]);
+
+        let error = TrackSectionsMerge::new(0, &InfraCache::default(), track, other).unwrap_err();
+
+        assert_eq!(
+            error.get_type(),
+            EditionError::MergeTrackSectionsOppositeDirections {
+                infra_id: 0,
+                tracksection_ids: vec![],
+            }
+            .get_type()
+        );
+    }
+
+    #[test]
+    fn merge_linked_track_sections_moves_objects() {
+        let mut infra_cache = InfraCache::default();
+        infra_cache
+            .add(create_track_section_cache("A", 100.0))
+            .unwrap();
+        infra_cache
+            .add(create_track_section_cache("B", 50.0))
+            .unwrap();
+        infra_cache
+            .add(create_switch_cache_link(
+                "link".into(),
+                ("A", create_track_endpoint(Endpoint::End, "A")),
+                ("B", create_track_endpoint(Endpoint::Begin, "B")),
+                "link".into(),
+            ))
+            .unwrap();
+        infra_cache
+            .add(create_signal_cache("signal", "B", 20.0))
+            .unwrap();
+        infra_cache
+            .add(create_electrification_cache(
+                "electrification",
+                vec![("A", 50.0, 100.0), ("B", 0.0, 50.0)],
+            ))
+            .unwrap();
+        // The geometries don't need to touch when the track sections are linked
+        let track = track_section("A", 100.0, vec![vec![0.0, 0.0], vec![1.0, 0.0]]);
+        let other = track_section("B", 50.0, vec![vec![1.1, 0.0], vec![2.0, 0.0]]);
+
+        let track_merge = TrackSectionsMerge::new(0, &infra_cache, track, other).unwrap();
+        let operations = track_merge.operations(&infra_cache);
+
+        assert!(operations.contains(&Operation::Update(UpdateOperation {
+            obj_type: ObjectType::Signal,
+            obj_id: "signal".into(),
+            railjson_patch: Patch(vec![
+                PatchOperation::Replace(ReplaceOperation {
+                    path: "/track".parse().unwrap(),
+                    value: json!(track_merge.merged_id),
+                }),
+                PatchOperation::Replace(ReplaceOperation {
+                    path: "/position".parse().unwrap(),
+                    value: json!(120.0),
+                }),
+            ]),
+        })));
+        let electrification = infra_cache.get_electrification("electrification").unwrap();
+        let track_ranges = track_merge.applicable_ranges(&electrification.track_ranges);
+        assert_eq!(track_ranges.len(), 1);
+        assert_eq!((track_ranges[0].begin, track_ranges[0].end), (50.0, 150.0));
+        let deleted = operations
+            .iter()
+            .filter_map(|operation| match operation {
+                Operation::Delete(delete) => Some(delete.obj_id.as_str()),
+                _ => None,
+            })
+            .collect_vec();
+        assert_eq!(deleted, vec!["link", "A", "B"]);
+    }
+
     #[rstest]
     async fn apply_edit_transaction_should_work() {
         // Init
@@ -1029,10 +1990,16 @@ pub mod tests {
             }),
         ]
         .to_vec();
-        let result: Vec<InfraObject> =
-            apply_edit(conn, &mut small_infra.model, &operations, &mut infra_cache)
-                .await
-                .unwrap();
+        let result: Vec<InfraObject> = apply_edit(
+            conn,
+            &mut small_infra.model,
+            &operations,
+            &mut infra_cache,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
 
         // Check that the updated track has the new length
         assert_eq!(1234.0, result[0].get_data()["length"]);
@@ -1075,7 +2042,15 @@ pub mod tests {
             }),
         ]
         .to_vec();
-        let result = apply_edit(conn, &mut small_infra.model, &operations, &mut infra_cache).await;
+        let result = apply_edit(
+            conn,
+            &mut small_infra.model,
+            &operations,
+            &mut infra_cache,
+            None,
+            None,
+        )
+        .await;
 
         // Check that we have an error
         assert!(result.is_err());
@@ -1088,4 +2063,183 @@ pub mod tests {
         let res: Vec<JsonValue> = call_and_read_body_json(&app, req).await;
         assert_eq!(2000.0, res[0]["railjson"]["length"])
     }
+
+    fn edit_length_request(infra_id: i64) -> TestRequest {
+        TestRequest::post()
+            .uri(format!("/infra/{infra_id}/").as_str())
+            .set_json(json!([{
+                "operation_type": "UPDATE",
+                "obj_type": "TrackSection",
+                "obj_id": "TA0",
+                "railjson_patch": [{ "op": "replace", "path": "/length", "value": 1234.0 }],
+            }]))
+    }
+
+    #[rstest]
+    async fn edit_with_expected_version() {
+        let app = create_test_service().await;
+        let small_infra = small_infra(db_pool()).await;
+        let infra_id = small_infra.id();
+        let version = small_infra.model.version.clone();
+
+        let req = edit_length_request(infra_id)
+            .insert_header(("If-Match", format!("\"{version}\"")))
+            .to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_ne!(res.headers().get("ETag").unwrap(), version.as_str());
+        let etag = res
+            .headers()
+            .get("ETag")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_owned();
+
+        // The version was bumped by the previous edition
+        let req = edit_length_request(infra_id)
+            .insert_header(("If-Match", version.as_str()))
+            .to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::PRECONDITION_FAILED);
+
+        // Weak tags never match
+        let req = edit_length_request(infra_id)
+            .insert_header(("If-Match", format!("W/{etag}")))
+            .to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::PRECONDITION_FAILED);
+
+        // Any of the listed versions may match
+        let req = edit_length_request(infra_id)
+            .insert_header(("If-Match", format!("\"stale\", {etag}")))
+            .to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let req = edit_length_request(infra_id)
+            .insert_header(("If-Match", "*"))
+            .to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[rstest]
+    async fn edit_object_locked_by_another_user() {
+        let app = create_test_service().await;
+        let small_infra = small_infra(db_pool()).await;
+        let infra_id = small_infra.id();
+        let req = TestRequest::post()
+            .uri(format!("/infra/{infra_id}/locks").as_str())
+            .insert_header(("x-remote-user", "alice"))
+            .set_json(json!({
+                "objects": [{ "obj_type": "TrackSection", "obj_id": "TA0" }],
+            }))
+            .to_request();
+        assert_eq!(call_service(&app, req).await.status(), StatusCode::OK);
+
+        let req = edit_length_request(infra_id)
+            .insert_header(("x-remote-user", "bob"))
+            .to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+
+        let req = edit_length_request(infra_id)
+            .insert_header(("x-remote-user", "alice"))
+            .to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[rstest]
+    async fn preview_edition_should_not_persist() {
+        let app = create_test_service().await;
+        let small_infra = small_infra(db_pool()).await;
+        let infra_id = small_infra.id();
+
+        let req = TestRequest::post()
+            .uri(format!("/infra/{infra_id}/preview").as_str())
+            .set_json(json!([
+                {
+                    "operation_type": "UPDATE",
+                    "obj_type": "TrackSection",
+                    "obj_id": "TA0",
+                    "railjson_patch": [{ "op": "replace", "path": "/length", "value": 1234.0 }],
+                },
+                {
+                    "operation_type": "DELETE",
+                    "obj_type": "TrackSection",
+                    "obj_id": "TA0",
+                },
+            ]))
+            .to_request();
+        let edition_preview: EditionPreview = call_and_read_body_json(&app, req).await;
+        assert_eq!(edition_preview.objects.len(), 1);
+        assert_eq!(1234.0, edition_preview.objects[0].get_data()["length"]);
+        assert!(edition_preview.new_errors.iter().any(|error| error.sub_type
+            == InfraErrorType::InvalidReference {
+                reference: ObjectRef::new(ObjectType::TrackSection, "TA0"),
+            }));
+
+        let req = TestRequest::post()
+            .uri(format!("/infra/{infra_id}/objects/TrackSection").as_str())
+            .set_json(json!(["TA0"]))
+            .to_request();
+        let res: Vec<JsonValue> = call_and_read_body_json(&app, req).await;
+        assert_eq!(2000.0, res[0]["railjson"]["length"]);
+    }
+
+    #[rstest]
+    async fn preview_edition_of_unknown_object() {
+        let app = create_test_service().await;
+        let small_infra = small_infra(db_pool()).await;
+
+        let req = TestRequest::post()
+            .uri(format!("/infra/{}/preview", small_infra.id()).as_str())
+            .set_json(json!([{
+                "operation_type": "DELETE",
+                "obj_type": "TrackSection",
+                "obj_id": "UNKNOWN",
+            }]))
+            .to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[rstest]
+    async fn preview_creation_of_existing_object() {
+        let app = create_test_service().await;
+        let small_infra = small_infra(db_pool()).await;
+
+        let req = TestRequest::post()
+            .uri(format!("/infra/{}/preview", small_infra.id()).as_str())
+            .set_json(json!([{
+                "operation_type": "CREATE",
+                "obj_type": "BufferStop",
+                "railjson": {
+                    "id": "buffer_stop.0",
+                    "track": "TA0",
+                    "position": 10.0,
+                },
+            }]))
+            .to_request();
+        let error: InternalError = call_and_read_body_json(&app, req).await;
+        assert_eq!(
+            error.error_type,
+            "editoast:cache_operation:DuplicateIdsProvided"
+        );
+    }
+
+    #[test]
+    fn compare_errors_before_and_after_edition() {
+        let kept = InfraError::new_missing_route(&"kept");
+        let fixed = InfraError::new_missing_route(&"fixed");
+        let new = InfraError::new_node_endpoint_not_unique(&"new");
+
+        let (new_errors, fixed_errors) =
+            compare_errors(vec![kept.clone(), fixed.clone()], vec![new.clone(), kept]);
+
+        assert_eq!(new_errors, vec![new]);
+        assert_eq!(fixed_errors, vec![fixed]);
+    }
 }
@@ -17,11 +17,13 @@ use crate::generated_data::infra_error::InfraError;
 use crate::generated_data::infra_error::InfraErrorTypeLabel;
 use crate::modelsv2::infra::errors::Level;
 use crate::modelsv2::prelude::*;
+use crate::modelsv2::validation_profile::ValidationProfile;
 use crate::modelsv2::DbConnectionPoolV2;
 use crate::modelsv2::Infra;
 use crate::views::infra::InfraIdParam;
 use crate::views::pagination::PaginationQueryParam;
 use crate::views::pagination::PaginationStats;
+use crate::views::validation_profiles::ValidationProfileError;
 
 use super::InfraApiError;
 
@@ -47,6 +49,8 @@ struct ErrorListQueryParams {
     /// Filter errors and warnings related to a given object
     #[param(value_type = Option<String>)]
     object_id: Option<Identifier>,
+    /// The validation profile deciding which errors are reported and their severity
+    validation_profile_id: Option<i64>,
 }
 
 #[derive(Serialize, utoipa::ToSchema)]
@@ -81,6 +85,7 @@ async fn list_errors(
         level,
         error_type,
         object_id,
+        validation_profile_id,
     }): Query<ErrorListQueryParams>,
 ) -> Result<WebJson<ErrorListResponse>> {
     let (page, page_size) = pagination_params
@@ -100,9 +105,28 @@ async fn list_errors(
         infra_id: infra.infra_id,
     })
     .await?;
+    let profile = match validation_profile_id {
+        Some(validation_profile_id) => Some(
+            ValidationProfile::retrieve_or_fail(conn, validation_profile_id, || {
+                ValidationProfileError::NotFound {
+                    validation_profile_id,
+                }
+            })
+            .await?,
+        ),
+        None => None,
+    };
 
     let (results, total_count) = infra
-        .get_paginated_errors(conn, level, error_type, object_id, page, page_size)
+        .get_paginated_errors(
+            conn,
+            profile.as_ref(),
+            level,
+            error_type,
+            object_id,
+            page,
+            page_size,
+        )
         .await?;
     let results = results
         .into_iter()
@@ -125,7 +149,7 @@ pub(in crate::views) async fn query_errors(
     infra: &Infra,
 ) -> (Vec<InfraError>, u64) {
     infra
-        .get_paginated_errors(conn, Level::All, None, None, 1, 10000)
+        .get_paginated_errors(conn, None, Level::All, None, None, 1, 10000)
         .await
         .expect("errors should be fetched successfully")
 }
@@ -163,4 +187,23 @@ mod tests {
         let response = call_service(&app.service, req).await;
         assert_eq!(response.status(), StatusCode::OK);
     }
+
+    #[rstest]
+    async fn list_errors_unexisting_validation_profile() {
+        let app = TestAppBuilder::default_app();
+        let db_pool = app.db_pool();
+        let empty_infra = create_empty_infra(db_pool.get_ok().deref_mut()).await;
+
+        let req = TestRequest::get()
+            .uri(
+                format!(
+                    "/infra/{}/errors?validation_profile_id=-666",
+                    empty_infra.id
+                )
+                .as_str(),
+            )
+            .to_request();
+        let response = call_service(&app.service, req).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
 }
@@ -0,0 +1,596 @@
+use actix_web::get;
+use actix_web::http::header::ETag;
+use actix_web::http::header::EntityTag;
+use actix_web::post;
+use actix_web::web::Data;
+use actix_web::web::Json;
+use actix_web::web::Path;
+use actix_web::web::Query;
+use actix_web::CustomizeResponder;
+use actix_web::CustomizeResponder;
+use actix_web::Responder as _;
+use actix_web::Responder;
+use chashmap::CHashMap;
+use chrono::NaiveDateTime;
+use editoast_derive::EditoastError;
+use serde::Deserialize;
+use serde::Serialize;
+use thiserror::Error;
+use utoipa::IntoParams;
+use utoipa::ToSchema;
+
+use super::edition::apply_operations;
+use super::edition::check_edition;
+use crate::error::InternalError;
+use crate::error::Result;
+use crate::infra_cache::operation::Operation;
+use crate::infra_cache::InfraCache;
+use crate::map;
+use crate::map::MapLayers;
+use crate::modelsv2::infra_revision::InfraRevision;
+use crate::modelsv2::prelude::*;
+use crate::modelsv2::DbConnection;
+use crate::modelsv2::DbConnectionPool;
+use crate::modelsv2::Infra;
+use crate::views::infra::InfraApiError;
+use crate::views::infra::InfraIdParam;
+use crate::views::pagination::PaginatedList as _;
+use crate::views::pagination::PaginationQueryParam;
+use crate::views::pagination::PaginationStats;
+use crate::views::params::ExpectedVersion;
+use crate::views::params::RemoteUser;
+use crate::RedisClient;
+
+crate::routes! {
+    "/history" => {
+        list,
+        undo,
+        redo,
+        "/{revision_id}" => {
+            get,
+        },
+    },
+}
+
+editoast_common::schemas! {
+    InfraRevisionSummary,
+    InfraRevisionDetails,
+}
+
+#[derive(Debug, Error, EditoastError)]
+#[editoast_error(base_id = "infra:history")]
+enum HistoryError {
+    #[error("Revision '{revision_id}' could not be found in infra '{infra_id}'")]
+    #[editoast_error(status = 404)]
+    RevisionNotFound { infra_id: i64, revision_id: i64 },
+    #[error("Cannot undo {count} revisions of infra '{infra_id}', only {available} are available")]
+    #[editoast_error(status = 400)]
+    NotEnoughRevisionsToUndo {
+        infra_id: i64,
+        count: u64,
+        available: usize,
+    },
+    #[error("Cannot redo {count} revisions of infra '{infra_id}', only {available} are available")]
+    #[editoast_error(status = 400)]
+    NotEnoughRevisionsToRedo {
+        infra_id: i64,
+        count: u64,
+        available: usize,
+    },
+}
+
+/// A revision of an infra, without its operations
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+struct InfraRevisionSummary {
+    id: i64,
+    /// The version of the infra once the revision was applied
+    version: String,
+    /// The user who made the edition, if known
+    author: Option<String>,
+    created: NaiveDateTime,
+    /// Whether the revision is currently undone
+    undone: bool,
+    /// The number of operations of the revision
+    operation_count: usize,
+}
+
+impl From<InfraRevision> for InfraRevisionSummary {
+    fn from(revision: InfraRevision) -> Self {
+        Self {
+            id: revision.id,
+            version: revision.version,
+            author: revision.author,
+            created: revision.created,
+            undone: revision.undone,
+            operation_count: revision.operations.len(),
+        }
+    }
+}
+
+/// A revision of an infra along with its operations
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+struct InfraRevisionDetails {
+    id: i64,
+    /// The version of the infra once the revision was applied
+    version: String,
+    /// The user who made the edition, if known
+    author: Option<String>,
+    created: NaiveDateTime,
+    /// Whether the revision is currently undone
+    undone: bool,
+    /// The operations of the edition batch
+    operations: Vec<Operation>,
+    /// The operations reverting the edition batch, in order of application
+    inverse_operations: Vec<Operation>,
+}
+
+impl From<InfraRevision> for InfraRevisionDetails {
+    fn from(revision: InfraRevision) -> Self {
+        Self {
+            id: revision.id,
+            version: revision.version,
+            author: revision.author,
+            created: revision.created,
+            undone: revision.undone,
+            operations: revision.operations,
+            inverse_operations: revision.inverse_operations,
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct InfraHistoryResponse {
+    #[serde(flatten)]
+    stats: PaginationStats,
+    results: Vec<InfraRevisionSummary>,
+}
+
+/// Lists the edition history of an infra, latest revisions first
+#[utoipa::path(
+    tag = "infra",
+    params(InfraIdParam, PaginationQueryParam),
+    responses(
+        (status = 200, description = "The revisions of the infra, paginated", body = inline(InfraHistoryResponse)),
+        (status = 404, description = "Infra ID not found"),
+    ),
+)]
+#[get("")]
+async fn list(
+    infra: Path<InfraIdParam>,
+    pagination_params: Query<PaginationQueryParam>,
+    db_pool: Data<DbConnectionPool>,
+) -> Result<Json<InfraHistoryResponse>> {
+    let infra_id = infra.infra_id;
+    let conn = &mut db_pool.get().await?;
+    Infra::retrieve_or_fail(conn, infra_id, || InfraApiError::NotFound { infra_id }).await?;
+
+    let settings = pagination_params
+        .validate(1000)?
+        .warn_page_size(100)
+        .into_selection_settings()
+        .filter(move || InfraRevision::INFRA_ID.eq(infra_id))
+        .order_by(|| InfraRevision::ID.desc());
+    let (revisions, stats) = InfraRevision::list_paginated(conn, settings).await?;
+    Ok(Json(InfraHistoryResponse {
+        stats,
+        results: revisions.into_iter().map(Into::into).collect(),
+    }))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[allow(unused)]
+struct RevisionIdParam {
+    /// An existing infra ID
+    infra_id: i64,
+    /// A revision ID of the infra
+    revision_id: i64,
+}
+
+/// Retrieve a revision of an infra along with its operations
+#[utoipa::path(
+    tag = "infra",
+    params(RevisionIdParam),
+    responses(
+        (status = 200, description = "The revision", body = InfraRevisionDetails),
+        (status = 404, description = "Revision not found"),
+    ),
+)]
+#[get("")]
+async fn get(
+    path: Path<RevisionIdParam>,
+    db_pool: Data<DbConnectionPool>,
+) -> Result<Json<InfraRevisionDetails>> {
+    let RevisionIdParam {
+        infra_id,
+        revision_id,
+    } = path.into_inner();
+    let conn = &mut db_pool.get().await?;
+    let not_found = || HistoryError::RevisionNotFound {
+        infra_id,
+        revision_id,
+    };
+    let revision = InfraRevision::retrieve_or_fail(conn, revision_id, not_found).await?;
+    if revision.infra_id != infra_id {
+        return Err(not_found().into());
+    }
+    Ok(Json(revision.into()))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct RevisionCountParam {
+    /// The number of revisions to undo or redo
+    #[serde(default = "default_revision_count")]
+    #[param(minimum = 1, default = 1)]
+    count: u64,
+}
+
+fn default_revision_count() -> u64 {
+    1
+}
+
+/// Undo the latest editions of an infra
+///
+/// The inverse operations of the latest applied revisions are applied, newest revision first.
+/// The undone revisions can be redone until a new edition is made.
+/// Like editions, undoing can be made conditional to the version of the infra with the `If-Match`
+/// header, and fails if the reverted objects are locked by other users.
+#[utoipa::path(
+    tag = "infra",
+    params(InfraIdParam, RevisionCountParam),
+    responses(
+        (
+            status = 200,
+            body = Vec<InfraRevisionSummary>,
+            headers(("ETag" = String, description = "The new version of the infra")),
+            description = "The undone revisions"
+        ),
+        (status = 400, description = "Not enough revisions to undo"),
+        (status = 404, description = "Infra ID not found"),
+        (status = 409, description = "Some reverted objects are locked by other users"),
+        (status = 412, description = "The infra doesn't match the version given in the `If-Match` header"),
+    ),
+)]
+#[post("/undo")]
+#[allow(clippy::too_many_arguments)]
+async fn undo(
+    infra: Path<InfraIdParam>,
+    params: Query<RevisionCountParam>,
+    db_pool: Data<DbConnectionPool>,
+    infra_caches: Data<CHashMap<i64, InfraCache>>,
+    redis_client: Data<RedisClient>,
+    map_layers: Data<MapLayers>,
+    RemoteUser(author): RemoteUser,
+    ExpectedVersion(expected_version): ExpectedVersion,
+) -> Result<CustomizeResponder<Json<Vec<InfraRevisionSummary>>>> {
+    replay_latest_revisions(
+        infra.infra_id,
+        params.count,
+        true,
+        &db_pool,
+        &infra_caches,
+        &redis_client,
+        &map_layers,
+        author,
+        expected_version,
+    )
+    .await
+}
+
+/// Redo the latest undone editions of an infra
+///
+/// The operations of the undone revisions are applied again, oldest revision first.
+/// Like editions, redoing can be made conditional to the version of the infra with the `If-Match`
+/// header, and fails if the edited objects are locked by other users.
+#[utoipa::path(
+    tag = "infra",
+    params(InfraIdParam, RevisionCountParam),
+    responses(
+        (
+            status = 200,
+            body = Vec<InfraRevisionSummary>,
+            headers(("ETag" = String, description = "The new version of the infra")),
+            description = "The redone revisions"
+        ),
+        (status = 400, description = "Not enough revisions to redo"),
+        (status = 404, description = "Infra ID not found"),
+        (status = 409, description = "Some edited objects are locked by other users"),
+        (status = 412, description = "The infra doesn't match the version given in the `If-Match` header"),
+    ),
+)]
+#[post("/redo")]
+#[allow(clippy::too_many_arguments)]
+async fn redo(
+    infra: Path<InfraIdParam>,
+    params: Query<RevisionCountParam>,
+    db_pool: Data<DbConnectionPool>,
+    infra_caches: Data<CHashMap<i64, InfraCache>>,
+    redis_client: Data<RedisClient>,
+    map_layers: Data<MapLayers>,
+    RemoteUser(author): RemoteUser,
+    ExpectedVersion(expected_version): ExpectedVersion,
+) -> Result<CustomizeResponder<Json<Vec<InfraRevisionSummary>>>> {
+    replay_latest_revisions(
+        infra.infra_id,
+        params.count,
+        false,
+        &db_pool,
+        &infra_caches,
+        &redis_client,
+        &map_layers,
+        author,
+        expected_version,
+    )
+    .await
+}
+
+/// Undo the `count` latest applied revisions when `undone` is true, redo the `count` first
+/// undone revisions otherwise
+#[allow(clippy::too_many_arguments)]
+async fn replay_latest_revisions(
+    infra_id: i64,
+    count: u64,
+    undone: bool,
+    db_pool: &DbConnectionPool,
+    infra_caches: &CHashMap<i64, InfraCache>,
+    redis_client: &RedisClient,
+    map_layers: &MapLayers,
+    author: Option<String>,
+    expected_version: Option<Vec<String>>,
+) -> Result<CustomizeResponder<Json<Vec<InfraRevisionSummary>>>> {
+    let conn = &mut db_pool.get().await?;
+    let mut infra =
+        Infra::retrieve_or_fail(conn, infra_id, || InfraApiError::NotFound { infra_id }).await?;
+    // Concurrent editions of the infra wait for the cache to be released
+    let mut infra_cache = InfraCache::get_or_load_mut(conn, infra_caches, &infra).await?;
+    let revisions = replay_revisions(
+        conn,
+        &mut infra,
+        &mut infra_cache,
+        count,
+        undone,
+        author,
+        expected_version,
+    )
+    .await?;
+
+    let mut redis_conn = redis_client.get_connection().await?;
+    map::invalidate_all(
+        &mut redis_conn,
+        &map_layers.layers.keys().cloned().collect(),
+        infra_id,
+    )
+    .await?;
+
+    let revisions = revisions.into_iter().map(Into::into).collect::<Vec<_>>();
+    Ok(Json(revisions)
+        .customize()
+        .insert_header(ETag(EntityTag::new_strong(infra.version))))
+}
+
+/// Undo or redo revisions in one transaction
+///
+/// The revisions are selected once the infra is locked for update, so that concurrent undos or
+/// redos can't pick the same ones. They are applied to a copy of the infra cache, which
+/// replaces it once the transaction is committed.
+async fn replay_revisions(
+    connection: &mut DbConnection,
+    infra: &mut Infra,
+    infra_cache: &mut InfraCache,
+    count: u64,
+    undone: bool,
+    author: Option<String>,
+    expected_version: Option<Vec<String>>,
+) -> Result<Vec<InfraRevision>> {
+    let infra_id = infra.id;
+    let mut scratch_cache = infra_cache.clone();
+    let scratch = &mut scratch_cache;
+    let replayed = connection
+        .build_transaction()
+        .run::<_, InternalError, _>(|conn| {
+            Box::pin(async move {
+                *infra = Infra::retrieve_for_update(conn, infra_id)
+                    .await?
+                    .ok_or(InfraApiError::NotFound { infra_id })?;
+                let revisions = if undone {
+                    InfraRevision::last_applied(conn, infra_id, count).await?
+                } else {
+                    InfraRevision::first_undone(conn, infra_id, count).await?
+                };
+                if (revisions.len() as u64) < count {
+                    let available = revisions.len();
+                    return Err(if undone {
+                        HistoryError::NotEnoughRevisionsToUndo {
+                            infra_id,
+                            count,
+                            available,
+                        }
+                    } else {
+                        HistoryError::NotEnoughRevisionsToRedo {
+                            infra_id,
+                            count,
+                            available,
+                        }
+                    }
+                    .into());
+                }
+                let operations = |revision: &InfraRevision| {
+                    if undone {
+                        revision.inverse_operations.clone()
+                    } else {
+                        revision.operations.clone()
+                    }
+                };
+                let all_operations = revisions.iter().flat_map(operations).collect::<Vec<_>>();
+                check_edition(
+                    conn,
+                    infra,
+                    &all_operations,
+                    author.as_deref(),
+                    expected_version.as_deref(),
+                )
+                .await?;
+
+                let mut replayed = Vec::with_capacity(revisions.len());
+                for mut revision in revisions {
+                    apply_operations(conn, infra, &operations(&revision), scratch).await?;
+                    revision.patch().undone(undone).apply(conn).await?;
+                    replayed.push(revision);
+                }
+                Ok(replayed)
+            })
+        })
+        .await?;
+    *infra_cache = scratch_cache;
+    Ok(replayed)
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use actix_web::test::call_and_read_body_json;
+    use actix_web::test::call_service;
+    use actix_web::test::TestRequest;
+    use json_patch::Patch;
+    use json_patch::PatchOperation;
+    use json_patch::ReplaceOperation;
+    use rstest::*;
+    use serde_json::json;
+    use serde_json::Value as JsonValue;
+
+    use super::*;
+    use crate::fixtures::tests::db_pool;
+    use crate::fixtures::tests::small_infra;
+    use crate::infra_cache::operation::UpdateOperation;
+    use crate::views::tests::create_test_service;
+    use editoast_schemas::primitives::ObjectType;
+
+    fn edit_length_request(infra_id: i64, length: f64) -> TestRequest {
+        let operation = Operation::Update(UpdateOperation {
+            obj_type: ObjectType::TrackSection,
+            obj_id: "TA0".to_string(),
+            railjson_patch: Patch(vec![PatchOperation::Replace(ReplaceOperation {
+                path: "/length".to_string().parse().unwrap(),
+                value: json!(length),
+            })]),
+        });
+        TestRequest::post()
+            .uri(format!("/infra/{infra_id}/").as_str())
+            .insert_header(("x-remote-user", "provider/user"))
+            .set_json(json!([operation]))
+    }
+
+    fn track_length_request(infra_id: i64) -> TestRequest {
+        TestRequest::post()
+            .uri(format!("/infra/{infra_id}/objects/TrackSection").as_str())
+            .set_json(json!(["TA0"]))
+    }
+
+    #[rstest]
+    async fn edition_is_recorded_in_history() {
+        let app = create_test_service().await;
+        let small_infra = small_infra(db_pool()).await;
+        let infra_id = small_infra.id();
+
+        let res = call_service(&app, edit_length_request(infra_id, 1234.0).to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let req = TestRequest::get()
+            .uri(format!("/infra/{infra_id}/history").as_str())
+            .to_request();
+        let history: JsonValue = call_and_read_body_json(&app, req).await;
+        assert_eq!(history["count"], 1);
+        assert_eq!(history["results"][0]["author"], "provider/user");
+        assert_eq!(history["results"][0]["operation_count"], 1);
+        assert_eq!(history["results"][0]["undone"], false);
+
+        let revision_id = history["results"][0]["id"].as_i64().unwrap();
+        let req = TestRequest::get()
+            .uri(format!("/infra/{infra_id}/history/{revision_id}").as_str())
+            .to_request();
+        let revision: InfraRevisionDetails = call_and_read_body_json(&app, req).await;
+        assert_eq!(revision.operations.len(), 1);
+        assert_eq!(revision.inverse_operations.len(), 1);
+    }
+
+    #[rstest]
+    async fn undo_and_redo_edition() {
+        let app = create_test_service().await;
+        let small_infra = small_infra(db_pool()).await;
+        let infra_id = small_infra.id();
+        call_service(&app, edit_length_request(infra_id, 1234.0).to_request()).await;
+
+        let req = TestRequest::post()
+            .uri(format!("/infra/{infra_id}/history/undo").as_str())
+            .to_request();
+        let undone: Vec<InfraRevisionSummary> = call_and_read_body_json(&app, req).await;
+        assert_eq!(undone.len(), 1);
+        assert!(undone[0].undone);
+        let tracks: Vec<JsonValue> =
+            call_and_read_body_json(&app, track_length_request(infra_id).to_request()).await;
+        assert_eq!(tracks[0]["railjson"]["length"], 2000.0);
+
+        let req = TestRequest::post()
+            .uri(format!("/infra/{infra_id}/history/redo").as_str())
+            .to_request();
+        let redone: Vec<InfraRevisionSummary> = call_and_read_body_json(&app, req).await;
+        assert_eq!(redone.len(), 1);
+        assert!(!redone[0].undone);
+        let tracks: Vec<JsonValue> =
+            call_and_read_body_json(&app, track_length_request(infra_id).to_request()).await;
+        assert_eq!(tracks[0]["railjson"]["length"], 1234.0);
+    }
+
+    #[rstest]
+    async fn new_edition_discards_undone_revisions() {
+        let app = create_test_service().await;
+        let small_infra = small_infra(db_pool()).await;
+        let infra_id = small_infra.id();
+        call_service(&app, edit_length_request(infra_id, 1234.0).to_request()).await;
+        let req = TestRequest::post()
+            .uri(format!("/infra/{infra_id}/history/undo").as_str())
+            .to_request();
+        call_service(&app, req).await;
+        call_service(&app, edit_length_request(infra_id, 4321.0).to_request()).await;
+
+        let req = TestRequest::post()
+            .uri(format!("/infra/{infra_id}/history/redo").as_str())
+            .to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[rstest]
+    async fn undo_with_stale_version() {
+        let app = create_test_service().await;
+        let small_infra = small_infra(db_pool()).await;
+        let infra_id = small_infra.id();
+        let version = small_infra.model.version.clone();
+        call_service(&app, edit_length_request(infra_id, 1234.0).to_request()).await;
+
+        // The version was bumped by the edition
+        let req = TestRequest::post()
+            .uri(format!("/infra/{infra_id}/history/undo").as_str())
+            .insert_header(("If-Match", format!("\"{version}\"")))
+            .to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::PRECONDITION_FAILED);
+        let tracks: Vec<JsonValue> =
+            call_and_read_body_json(&app, track_length_request(infra_id).to_request()).await;
+        assert_eq!(tracks[0]["railjson"]["length"], 1234.0);
+    }
+
+    #[rstest]
+    async fn undo_too_many_revisions() {
+        let app = create_test_service().await;
+        let small_infra = small_infra(db_pool()).await;
+        let infra_id = small_infra.id();
+        call_service(&app, edit_length_request(infra_id, 1234.0).to_request()).await;
+
+        let req = TestRequest::post()
+            .uri(format!("/infra/{infra_id}/history/undo?count=2").as_str())
+            .to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,275 @@
+use std::collections::HashSet;
+
+use actix_web::get;
+use actix_web::post;
+use actix_web::web::Data;
+use actix_web::web::Json;
+use actix_web::web::Path;
+use chrono::Duration;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::AsyncConnection;
+use editoast_derive::EditoastError;
+use editoast_schemas::primitives::ObjectRef;
+use serde::Deserialize;
+use serde::Serialize;
+use thiserror::Error;
+use utoipa::ToSchema;
+
+use crate::error::InternalError;
+use crate::error::Result;
+use crate::modelsv2::infra_lock::InfraLock;
+use crate::modelsv2::prelude::*;
+use crate::modelsv2::DbConnectionPool;
+use crate::modelsv2::Infra;
+use crate::views::infra::InfraApiError;
+use crate::views::infra::InfraIdParam;
+use crate::views::params::RemoteUser;
+
+crate::routes! {
+    "/locks" => {
+        list,
+        acquire,
+        "/release" => {
+            release,
+        },
+    },
+}
+
+editoast_common::schemas! {
+    LockRequest,
+    ReleaseRequest,
+}
+
+/// The longest duration of a lock, in seconds
+const MAX_LOCK_DURATION: u64 = 24 * 60 * 60;
+
+#[derive(Debug, Error, EditoastError)]
+#[editoast_error(base_id = "infra:locks")]
+pub(super) enum LockError {
+    #[error("Locking objects requires an identified user")]
+    #[editoast_error(status = 401)]
+    AnonymousUser,
+    #[error("Locks can't last more than {max_duration} seconds")]
+    #[editoast_error(status = 400)]
+    DurationTooLong { max_duration: u64 },
+    #[error("{} objects of infra '{infra_id}' are locked by other users", locks.len())]
+    #[editoast_error(status = 409)]
+    ObjectsLocked {
+        infra_id: i64,
+        locks: Vec<InfraLock>,
+    },
+}
+
+fn default_lock_duration() -> u64 {
+    15 * 60
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+struct LockRequest {
+    objects: Vec<ObjectRef>,
+    /// How long the objects stay locked, in seconds
+    #[serde(default = "default_lock_duration")]
+    #[schema(default = default_lock_duration, maximum = 86400)]
+    duration: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+struct ReleaseRequest {
+    objects: Vec<ObjectRef>,
+}
+
+/// List the active locks of an infra
+#[utoipa::path(
+    tag = "infra",
+    params(InfraIdParam),
+    responses(
+        (status = 200, description = "The active locks", body = Vec<InfraLock>),
+        (status = 404, description = "Infra ID not found"),
+    ),
+)]
+#[get("")]
+async fn list(
+    infra: Path<InfraIdParam>,
+    db_pool: Data<DbConnectionPool>,
+) -> Result<Json<Vec<InfraLock>>> {
+    let infra_id = infra.infra_id;
+    let conn = &mut db_pool.get().await?;
+    Infra::retrieve_or_fail(conn, infra_id, || InfraApiError::NotFound { infra_id }).await?;
+    Ok(Json(InfraLock::list_active(conn, infra_id).await?))
+}
+
+/// Lock objects of an infra
+///
+/// Until the locks expire, the objects can only be edited by the user who locked them.
+/// Locking objects already locked by the same user extends their locks.
+#[utoipa::path(
+    tag = "infra",
+    params(InfraIdParam),
+    request_body = LockRequest,
+    responses(
+        (status = 200, description = "The acquired locks", body = Vec<InfraLock>),
+        (status = 401, description = "The user is not identified"),
+        (status = 404, description = "Infra ID not found"),
+        (status = 409, description = "Some objects are locked by other users"),
+    ),
+)]
+#[post("")]
+async fn acquire(
+    infra: Path<InfraIdParam>,
+    request: Json<LockRequest>,
+    db_pool: Data<DbConnectionPool>,
+    RemoteUser(owner): RemoteUser,
+) -> Result<Json<Vec<InfraLock>>> {
+    let infra_id = infra.infra_id;
+    let LockRequest { objects, duration } = request.into_inner();
+    let owner = owner.ok_or(LockError::AnonymousUser)?;
+    if duration > MAX_LOCK_DURATION {
+        return Err(LockError::DurationTooLong {
+            max_duration: MAX_LOCK_DURATION,
+        }
+        .into());
+    }
+    let objects = objects
+        .into_iter()
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+    let expires = chrono::Utc::now().naive_utc() + Duration::seconds(duration as i64);
+
+    let conn = &mut db_pool.get().await?;
+    Infra::retrieve_or_fail(conn, infra_id, || InfraApiError::NotFound { infra_id }).await?;
+    let result = conn
+        .transaction(|conn| {
+            let objects = objects.clone();
+            let owner = owner.clone();
+            async move {
+                let locks =
+                    InfraLock::held_by_others(conn, infra_id, &objects, Some(&owner)).await?;
+                if !locks.is_empty() {
+                    return Err(LockError::ObjectsLocked { infra_id, locks }.into());
+                }
+                InfraLock::acquire(conn, infra_id, objects, owner, expires).await
+            }
+            .scope_boxed()
+        })
+        .await;
+    match result {
+        // Another user locked some of the objects concurrently
+        Err(error) if is_lock_conflict(&error) => {
+            let locks = InfraLock::held_by_others(conn, infra_id, &objects, Some(&owner)).await?;
+            Err(LockError::ObjectsLocked { infra_id, locks }.into())
+        }
+        result => Ok(Json(result?)),
+    }
+}
+
+fn is_lock_conflict(error: &InternalError) -> bool {
+    error.message.contains(
+        r#"duplicate key value violates unique constraint "infra_lock_infra_id_object_key""#,
+    )
+}
+
+/// Release locks held on objects of an infra
+///
+/// Only the locks of the current user are released.
+#[utoipa::path(
+    tag = "infra",
+    params(InfraIdParam),
+    request_body = ReleaseRequest,
+    responses(
+        (status = 204, description = "The locks were released"),
+        (status = 401, description = "The user is not identified"),
+        (status = 404, description = "Infra ID not found"),
+    ),
+)]
+#[post("")]
+async fn release(
+    infra: Path<InfraIdParam>,
+    request: Json<ReleaseRequest>,
+    db_pool: Data<DbConnectionPool>,
+    RemoteUser(owner): RemoteUser,
+) -> Result<actix_web::HttpResponse> {
+    let infra_id = infra.infra_id;
+    let owner = owner.ok_or(LockError::AnonymousUser)?;
+    let conn = &mut db_pool.get().await?;
+    Infra::retrieve_or_fail(conn, infra_id, || InfraApiError::NotFound { infra_id }).await?;
+    InfraLock::release(conn, infra_id, &request.objects, &owner).await?;
+    Ok(actix_web::HttpResponse::NoContent().finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use actix_web::test::call_and_read_body_json;
+    use actix_web::test::call_service;
+    use actix_web::test::TestRequest;
+    use editoast_schemas::primitives::ObjectType;
+    use rstest::*;
+    use serde_json::json;
+
+    use super::*;
+    use crate::fixtures::tests::db_pool;
+    use crate::fixtures::tests::small_infra;
+    use crate::views::tests::create_test_service;
+
+    fn lock_request(infra_id: i64, user: &str) -> TestRequest {
+        TestRequest::post()
+            .uri(format!("/infra/{infra_id}/locks").as_str())
+            .insert_header(("x-remote-user", user))
+            .set_json(json!({
+                "objects": [{ "obj_type": "TrackSection", "obj_id": "TA0" }],
+            }))
+    }
+
+    #[rstest]
+    async fn acquire_and_release_locks() {
+        let app = create_test_service().await;
+        let small_infra = small_infra(db_pool()).await;
+        let infra_id = small_infra.id();
+
+        let locks: Vec<InfraLock> =
+            call_and_read_body_json(&app, lock_request(infra_id, "alice").to_request()).await;
+        assert_eq!(locks.len(), 1);
+        assert_eq!(locks[0].owner, "alice");
+        assert_eq!(
+            locks[0].object,
+            ObjectRef::new(ObjectType::TrackSection, "TA0")
+        );
+
+        let res = call_service(&app, lock_request(infra_id, "bob").to_request()).await;
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+
+        let req = TestRequest::post()
+            .uri(format!("/infra/{infra_id}/locks/release").as_str())
+            .insert_header(("x-remote-user", "alice"))
+            .set_json(json!({
+                "objects": [{ "obj_type": "TrackSection", "obj_id": "TA0" }],
+            }))
+            .to_request();
+        assert_eq!(
+            call_service(&app, req).await.status(),
+            StatusCode::NO_CONTENT
+        );
+
+        let req = TestRequest::get()
+            .uri(format!("/infra/{infra_id}/locks").as_str())
+            .to_request();
+        let locks: Vec<InfraLock> = call_and_read_body_json(&app, req).await;
+        assert!(locks.is_empty());
+    }
+
+    #[rstest]
+    async fn anonymous_users_cannot_lock() {
+        let app = create_test_service().await;
+        let small_infra = small_infra(db_pool()).await;
+
+        let req = TestRequest::post()
+            .uri(format!("/infra/{}/locks", small_infra.id()).as_str())
+            .set_json(json!({ "objects": [] }))
+            .to_request();
+        assert_eq!(
+            call_service(&app, req).await.status(),
+            StatusCode::UNAUTHORIZED
+        );
+    }
+}
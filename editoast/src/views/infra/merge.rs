@@ -0,0 +1,305 @@
+use actix_web::post;
+use actix_web::web::Data;
+use actix_web::web::Json;
+use actix_web::web::Path;
+use actix_web::web::Query;
+use chashmap::CHashMap;
+use editoast_derive::EditoastError;
+use serde::Deserialize;
+use serde::Serialize;
+use thiserror::Error;
+use utoipa::IntoParams;
+use utoipa::ToSchema;
+
+use super::edition::apply_edit;
+use crate::error::Result;
+use crate::generated_data::generate_infra_errors;
+use crate::generated_data::infra_error::InfraError;
+use crate::infra_cache::operation::Operation;
+use crate::infra_cache::InfraCache;
+use crate::map;
+use crate::map::MapLayers;
+use crate::modelsv2::infra::InfraMerge;
+use crate::modelsv2::infra::MergeConflict;
+use crate::modelsv2::prelude::*;
+use crate::modelsv2::DbConnectionPool;
+use crate::modelsv2::Infra;
+use crate::views::infra::InfraApiError;
+use crate::views::params::ExpectedVersion;
+use crate::views::params::RemoteUser;
+use crate::RedisClient;
+
+crate::routes! {
+    "/merge/{source_infra_id}" => {
+        merge,
+    },
+}
+
+editoast_common::schemas! {
+    InfraMergeResult,
+}
+
+#[derive(Debug, Error, EditoastError)]
+#[editoast_error(base_id = "infra:merge")]
+enum MergeError {
+    #[error("Infra '{source_infra_id}' cannot be merged into infra '{infra_id}' because of {} conflicting objects", conflicts.len())]
+    #[editoast_error(status = 409)]
+    Conflicts {
+        infra_id: i64,
+        source_infra_id: i64,
+        conflicts: Vec<MergeConflict>,
+    },
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[allow(unused)]
+struct MergePathParam {
+    /// The ID of the infra receiving the changes
+    infra_id: i64,
+    /// The ID of the infra whose changes are merged
+    source_infra_id: i64,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct MergeQueryParams {
+    /// Only compute the result of the merge without applying it
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+struct InfraMergeResult {
+    /// The operations applied to the target infra
+    operations: Vec<Operation>,
+    /// The objects changed on both sides, whose source changes are not merged
+    conflicts: Vec<MergeConflict>,
+    /// The errors of the merged infra, only computed on dry runs
+    #[schema(required)]
+    errors: Option<Vec<InfraError>>,
+}
+
+/// Merge the changes made on an infra since it was cloned into another infra
+///
+/// One of the infras must have been cloned from the other. The changes recorded in the
+/// edition history of the source infra since the clone are compared to the changes of the
+/// target infra. An object modified on both sides is in conflict unless
+/// the changes concern distinct fields.
+///
+/// With `dry_run`, nothing is applied and the errors the merged infra would have are
+/// returned along with the conflicts. Otherwise the merge is rejected if there is any
+/// conflict, and is recorded as a new revision of the target infra.
+#[utoipa::path(
+    tag = "infra",
+    params(MergePathParam, MergeQueryParams),
+    responses(
+        (status = 200, description = "The result of the merge", body = InfraMergeResult),
+        (status = 400, description = "The infras are not related by a clone"),
+        (status = 404, description = "Infra not found"),
+        (status = 409, description = "The changes of both infras conflict"),
+    ),
+)]
+#[post("")]
+#[allow(clippy::too_many_arguments)]
+async fn merge(
+    path: Path<MergePathParam>,
+    params: Query<MergeQueryParams>,
+    db_pool: Data<DbConnectionPool>,
+    infra_caches: Data<CHashMap<i64, InfraCache>>,
+    redis_client: Data<RedisClient>,
+    map_layers: Data<MapLayers>,
+    RemoteUser(author): RemoteUser,
+    ExpectedVersion(expected_version): ExpectedVersion,
+) -> Result<Json<InfraMergeResult>> {
+    let MergePathParam {
+        infra_id,
+        source_infra_id,
+    } = path.into_inner();
+    let conn = &mut db_pool.get().await?;
+    let mut infra =
+        Infra::retrieve_or_fail(conn, infra_id, || InfraApiError::NotFound { infra_id }).await?;
+    let source_infra = Infra::retrieve_or_fail(conn, source_infra_id, || InfraApiError::NotFound {
+        infra_id: source_infra_id,
+    })
+    .await?;
+
+    let ancestor = source_infra.fork_snapshot(conn, &infra).await?;
+    let source = source_infra.snapshot(conn, None).await?;
+    let mut target = infra.snapshot(conn, None).await?;
+    let InfraMerge {
+        operations,
+        conflicts,
+    } = InfraMerge::new(&ancestor, &source, &target);
+
+    if params.dry_run {
+        for operation in &operations {
+            target.apply(operation)?;
+        }
+        let errors = generate_infra_errors(&target.infra_cache()?).await;
+        return Ok(Json(InfraMergeResult {
+            operations,
+            conflicts,
+            errors: Some(errors),
+        }));
+    }
+
+    if !conflicts.is_empty() {
+        return Err(MergeError::Conflicts {
+            infra_id,
+            source_infra_id,
+            conflicts,
+        }
+        .into());
+    }
+    if !operations.is_empty() {
+        let mut infra_cache = InfraCache::get_or_load_mut(conn, &infra_caches, &infra).await?;
+        apply_edit(
+            conn,
+            &mut infra,
+            &operations,
+            &mut infra_cache,
+            author,
+            expected_version,
+        )
+        .await?;
+        let mut redis_conn = redis_client.get_connection().await?;
+        map::invalidate_all(
+            &mut redis_conn,
+            &map_layers.layers.keys().cloned().collect(),
+            infra_id,
+        )
+        .await?;
+    }
+    Ok(Json(InfraMergeResult {
+        operations,
+        conflicts,
+        errors: None,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_http::Request;
+    use actix_web::dev::Service;
+    use actix_web::dev::ServiceResponse;
+    use actix_web::http::StatusCode;
+    use actix_web::test::call_and_read_body_json;
+    use actix_web::test::call_service;
+    use actix_web::test::TestRequest;
+    use rstest::*;
+    use serde_json::json;
+    use serde_json::Value as JsonValue;
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::error::InternalError;
+    use crate::fixtures::tests::db_pool;
+    use crate::fixtures::tests::small_infra;
+    use crate::fixtures::tests::IntoFixture;
+    use crate::fixtures::tests::TestFixture;
+    use crate::views::tests::create_test_service;
+
+    async fn clone_infra(
+        app: &impl Service<Request, Response = ServiceResponse, Error = actix_web::Error>,
+        db_pool: Arc<DbConnectionPool>,
+        infra_id: i64,
+    ) -> TestFixture<Infra> {
+        let req = TestRequest::post()
+            .uri(format!("/infra/{infra_id}/clone/?name=variant").as_str())
+            .to_request();
+        let clone_id: i64 = call_and_read_body_json(app, req).await;
+        Infra::retrieve(&mut db_pool.get().await.unwrap(), clone_id)
+            .await
+            .unwrap()
+            .expect("infra was not cloned")
+            .into_fixture(db_pool)
+    }
+
+    fn edit_request(infra_id: i64, path: &str, value: JsonValue) -> TestRequest {
+        TestRequest::post()
+            .uri(format!("/infra/{infra_id}/").as_str())
+            .set_json(json!([{
+                "operation_type": "UPDATE",
+                "obj_type": "TrackSection",
+                "obj_id": "TA0",
+                "railjson_patch": [{ "op": "replace", "path": path, "value": value }],
+            }]))
+    }
+
+    #[rstest]
+    async fn merge_variant(db_pool: Arc<DbConnectionPool>) {
+        let app = create_test_service().await;
+        let small_infra = small_infra(db_pool.clone()).await;
+        let infra_id = small_infra.id();
+        let variant = clone_infra(&app, db_pool, infra_id).await;
+        let variant_id = variant.id();
+        let req = edit_request(variant_id, "/extensions/sncf/track_name", json!("V2")).to_request();
+        assert_eq!(call_service(&app, req).await.status(), StatusCode::OK);
+
+        let req = TestRequest::post()
+            .uri(format!("/infra/{infra_id}/merge/{variant_id}?dry_run=true").as_str())
+            .to_request();
+        let result: InfraMergeResult = call_and_read_body_json(&app, req).await;
+        assert_eq!(result.operations.len(), 1);
+        assert!(result.conflicts.is_empty());
+        assert_eq!(result.errors, Some(vec![]));
+
+        let req = TestRequest::post()
+            .uri(format!("/infra/{infra_id}/merge/{variant_id}").as_str())
+            .to_request();
+        let result: InfraMergeResult = call_and_read_body_json(&app, req).await;
+        assert_eq!(result.operations.len(), 1);
+
+        let req = TestRequest::get()
+            .uri(format!("/infra/{infra_id}/diff/{variant_id}").as_str())
+            .to_request();
+        let infra_diff: JsonValue = call_and_read_body_json(&app, req).await;
+        assert_eq!(infra_diff["modified"], json!([]));
+    }
+
+    #[rstest]
+    async fn merge_conflicting_variant(db_pool: Arc<DbConnectionPool>) {
+        let app = create_test_service().await;
+        let small_infra = small_infra(db_pool.clone()).await;
+        let infra_id = small_infra.id();
+        let variant = clone_infra(&app, db_pool, infra_id).await;
+        let variant_id = variant.id();
+        call_service(
+            &app,
+            edit_request(variant_id, "/length", json!(1234.0)).to_request(),
+        )
+        .await;
+        call_service(
+            &app,
+            edit_request(infra_id, "/length", json!(4321.0)).to_request(),
+        )
+        .await;
+
+        let req = TestRequest::post()
+            .uri(format!("/infra/{infra_id}/merge/{variant_id}?dry_run=true").as_str())
+            .to_request();
+        let result: InfraMergeResult = call_and_read_body_json(&app, req).await;
+        assert!(result.operations.is_empty());
+        assert_eq!(result.conflicts.len(), 1);
+        assert_eq!(result.conflicts[0].obj_id, "TA0");
+        assert_eq!(result.conflicts[0].fields, vec!["/length".to_string()]);
+
+        let req = TestRequest::post()
+            .uri(format!("/infra/{infra_id}/merge/{variant_id}").as_str())
+            .to_request();
+        assert_eq!(call_service(&app, req).await.status(), StatusCode::CONFLICT);
+    }
+
+    #[rstest]
+    async fn merge_unrelated_infras(db_pool: Arc<DbConnectionPool>) {
+        let app = create_test_service().await;
+        let infra = small_infra(db_pool.clone()).await;
+        let other_infra = small_infra(db_pool).await;
+
+        let req = TestRequest::post()
+            .uri(format!("/infra/{}/merge/{}", infra.id(), other_infra.id()).as_str())
+            .to_request();
+        let error: InternalError = call_and_read_body_json(&app, req).await;
+        assert_eq!(error.error_type, "editoast:infra:diff:UnrelatedInfras");
+    }
+}
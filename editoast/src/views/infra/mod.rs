@@ -1,11 +1,17 @@
 mod attached;
 mod auto_fixes;
+mod diff;
 mod edition;
 mod errors;
+mod history;
 mod lines;
+mod locks;
+mod merge;
 mod objects;
 mod pathfinding;
 mod railjson;
+mod railml;
+mod reimport;
 mod routes;
 
 use actix_web::delete;
@@ -64,8 +70,13 @@ crate::routes! {
                 auto_fixes::routes(),
                 pathfinding::routes(),
                 attached::routes(),
+                diff::routes(),
                 edition::routes(),
                 errors::routes(),
+                history::routes(),
+                merge::routes(),
+                reimport::routes(),
+                locks::routes(),
             ),
             get,
             load,
@@ -83,11 +94,20 @@ crate::routes! {
         refresh,
         get_all_voltages,
         railjson::routes(),
+        railml::routes(),
     },
 }
 
 editoast_common::schemas! {
+    attached::schemas(),
     pathfinding::schemas(),
+    edition::schemas(),
+    history::schemas(),
+    locks::schemas(),
+    merge::schemas(),
+    railjson::schemas(),
+    railml::schemas(),
+    reimport::schemas(),
     InfraState,
     InfraWithState,
 }
@@ -101,6 +121,7 @@ pub fn infra_routes() -> impl HttpServiceFactory {
             refresh,
             get_all_voltages,
             railjson::railjson_routes(),
+            railml::railml_routes(),
         ))
         .service(
             scope("/{infra_id}")
@@ -126,6 +147,11 @@ pub fn infra_routes() -> impl HttpServiceFactory {
                     attached::routes(),
                     lines::routes(),
                     auto_fixes::routes(),
+                    diff::routes(),
+                    history::routes(),
+                    merge::routes(),
+                    reimport::routes(),
+                    locks::routes(),
                 )),
         )
 }
@@ -1,24 +1,35 @@
+use std::collections::HashSet;
+use std::io;
+use std::io::Read;
+
 use actix_web::dev::HttpServiceFactory;
 use actix_web::get;
 use actix_web::http::header::ContentType;
 use actix_web::post;
 use actix_web::services;
+use actix_web::web::Bytes;
 use actix_web::web::Data;
 use actix_web::web::Json;
 use actix_web::web::Path;
+use actix_web::web::Payload;
 use actix_web::web::Query;
 use actix_web::HttpResponse;
 use actix_web::Responder;
 use chashmap::CHashMap;
 use editoast_derive::EditoastError;
+use editoast_schemas::infra::downgrade_railjson;
+use editoast_schemas::infra::extract_railjson;
 use editoast_schemas::infra::RailJson;
-use editoast_schemas::infra::RAILJSON_VERSION;
+use editoast_schemas::infra::RailJsonMigrationError;
+use editoast_schemas::primitives::BoundingBox;
 use enum_map::EnumMap;
 use futures::future::try_join_all;
+use futures::StreamExt;
 use serde::Deserialize;
 use serde::Serialize;
 use strum::IntoEnumIterator;
 use thiserror::Error;
+use tokio::sync::mpsc;
 use utoipa::IntoParams;
 use utoipa::ToSchema;
 
@@ -33,45 +44,166 @@ use editoast_schemas::primitives::ObjectType;
 
 /// Return `/infra/<infra_id>/railjson` routes
 pub fn railjson_routes() -> impl HttpServiceFactory {
-    services![get_railjson, post_railjson]
+    services![get_railjson, post_railjson_extract, post_railjson]
 }
 
 crate::routes! {
     get_railjson,
+    post_railjson_extract,
     post_railjson,
 }
 
+editoast_common::schemas! {
+    RailjsonExtractForm,
+}
+
 #[derive(Debug, Error, EditoastError)]
 #[editoast_error(base_id = "infra:railjson")]
 enum ListErrorsRailjson {
     #[error("Wrong Railjson version provided")]
     WrongRailjsonVersionProvided,
+    #[error("Invalid railjson: {cause}")]
+    InvalidRailjson { cause: String },
+    #[error(
+        "Neither a bounding box nor line codes were given to select the exported track sections"
+    )]
+    EmptyExtractSelection,
+}
+
+impl From<RailJsonMigrationError> for ListErrorsRailjson {
+    fn from(error: RailJsonMigrationError) -> Self {
+        match error {
+            RailJsonMigrationError::MissingVersion => Self::InvalidRailjson {
+                cause: error.to_string(),
+            },
+            RailJsonMigrationError::UnsupportedVersion(_)
+            | RailJsonMigrationError::NewerTargetVersion { .. } => {
+                Self::WrongRailjsonVersionProvided
+            }
+        }
+    }
+}
+
+/// Represents the query parameters for a `GET /infra/{infra_id}/railjson` request
+#[derive(Debug, Clone, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct GetRailjsonQueryParams {
+    /// An older supported RailJSON version to export the infra to. Defaults to the current one.
+    version: Option<String>,
 }
 
 /// Serialize an infra
 #[utoipa::path(
     tag = "infra",
-    params(InfraIdParam),
+    params(InfraIdParam, GetRailjsonQueryParams),
     responses(
         (status = 200,  description = "The infra in railjson format", body = RailJson),
+        (status = 400, description = "The requested railjson version is not supported"),
         (status = 404, description = "The infra was not found"),
     )
 )]
 #[get("/{infra_id}/railjson")]
 async fn get_railjson(
     infra: Path<InfraIdParam>,
+    Query(GetRailjsonQueryParams { version }): Query<GetRailjsonQueryParams>,
+    db_pool: Data<DbConnectionPool>,
+) -> Result<impl Responder> {
+    let infra_id = infra.infra_id;
+    let conn = &mut db_pool.get().await?;
+    let infra_meta =
+        Infra::retrieve_or_fail(conn, infra_id, || InfraApiError::NotFound { infra_id }).await?;
+
+    let railjson = railjson_payload(&db_pool, &infra_meta).await?;
+
+    // The infra is only deserialized when it must be converted to an older version
+    let railjson = match version {
+        Some(version) if version != infra_meta.railjson_version => {
+            let mut railjson: serde_json::Value = serde_json::from_str(&railjson)?;
+            downgrade_railjson(&mut railjson, &version).map_err(ListErrorsRailjson::from)?;
+            railjson.to_string()
+        }
+        _ => railjson,
+    };
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .append_header(("x-infra-version", infra_meta.version))
+        .body(railjson))
+}
+
+/// The track sections of an infra to export, a track section being selected if it matches any criterion
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+struct RailjsonExtractForm {
+    /// Selects the track sections intersecting this bounding box
+    #[serde(default)]
+    bbox: Option<BoundingBox>,
+    /// Selects the track sections of these lines
+    #[serde(default)]
+    line_codes: HashSet<i32>,
+}
+
+/// Serialize a part of an infra
+///
+/// The selected track sections are exported with all the objects attached to them.
+/// The switches connecting them to other track sections are dropped along with the routes
+/// crossing them, and the track endpoints they leave are closed with generated buffer stops.
+#[utoipa::path(
+    tag = "infra",
+    params(InfraIdParam, GetRailjsonQueryParams),
+    request_body = RailjsonExtractForm,
+    responses(
+        (status = 200,  description = "The extracted part of the infra in railjson format", body = RailJson),
+        (status = 400, description = "No track section selection was given or the requested railjson version is not supported"),
+        (status = 404, description = "The infra was not found"),
+    )
+)]
+#[post("/{infra_id}/railjson/extract")]
+async fn post_railjson_extract(
+    infra: Path<InfraIdParam>,
+    Query(GetRailjsonQueryParams { version }): Query<GetRailjsonQueryParams>,
+    Json(selection): Json<RailjsonExtractForm>,
     db_pool: Data<DbConnectionPool>,
 ) -> Result<impl Responder> {
+    if selection.bbox.is_none() && selection.line_codes.is_empty() {
+        return Err(ListErrorsRailjson::EmptyExtractSelection.into());
+    }
     let infra_id = infra.infra_id;
     let conn = &mut db_pool.get().await?;
     let infra_meta =
         Infra::retrieve_or_fail(conn, infra_id, || InfraApiError::NotFound { infra_id }).await?;
 
+    let railjson: RailJson = serde_json::from_str(&railjson_payload(&db_pool, &infra_meta).await?)?;
+    let railjson = extract_railjson(railjson, |track| {
+        let in_bbox = selection
+            .bbox
+            .as_ref()
+            .map_or(false, |bbox| track.intersects(bbox));
+        let on_lines = track
+            .extensions
+            .sncf
+            .as_ref()
+            .map_or(false, |sncf| selection.line_codes.contains(&sncf.line_code));
+        in_bbox || on_lines
+    });
+
+    let mut railjson = serde_json::to_value(railjson)?;
+    if let Some(version) = version.filter(|version| *version != infra_meta.railjson_version) {
+        downgrade_railjson(&mut railjson, &version).map_err(ListErrorsRailjson::from)?;
+    }
+
+    Ok(HttpResponse::Ok()
+        .append_header(("x-infra-version", infra_meta.version))
+        .json(railjson))
+}
+
+/// Builds the railjson of an infra
+pub(super) async fn railjson_payload(db_pool: &DbConnectionPool, infra: &Infra) -> Result<String> {
     let futures: Vec<_> = ObjectType::iter()
         .map(|object_type| (object_type, db_pool.get()))
         .map(|(object_type, conn_future)| async move {
             let conn = &mut conn_future.await?;
-            let railjson_data = Infra::get_railjson(conn, infra_id, &object_type).await?;
+            let railjson_data = Infra::get_railjson(conn, infra.id, &object_type).await?;
             let result: Result<_> = Ok((object_type, railjson_data));
             result
         })
@@ -93,7 +225,7 @@ async fn get_railjson(
         .collect();
 
     // Here we avoid the deserialization of the whole RailJson object
-    let railjson = format!(
+    Ok(format!(
         r#"{{
             "version": "{version}",
             "track_sections": {track_sections},
@@ -108,7 +240,7 @@ async fn get_railjson(
             "electrifications": {electrifications},
             "neutral_sections": {neutral_sections}
         }}"#,
-        version = infra_meta.railjson_version,
+        version = infra.railjson_version,
         track_sections = res[ObjectType::TrackSection],
         signals = res[ObjectType::Signal],
         speed_sections = res[ObjectType::SpeedSection],
@@ -120,12 +252,7 @@ async fn get_railjson(
         operational_points = res[ObjectType::OperationalPoint],
         electrifications = res[ObjectType::Electrification],
         neutral_sections = res[ObjectType::NeutralSection]
-    );
-
-    Ok(HttpResponse::Ok()
-        .content_type(ContentType::json())
-        .append_header(("x-infra-version", infra_meta.version))
-        .body(railjson))
+    ))
 }
 
 /// Represents the query parameters for a `POST /infra/railjson` request
@@ -144,33 +271,45 @@ struct PostRailjsonResponse {
 }
 
 /// Import an infra from railjson
+///
+/// Infras in an older supported version of the format are upgraded to the current one.
+/// The railjson is imported while it is received.
 #[utoipa::path(
     tag = "infra",
     params(PostRailjsonQueryParams),
     request_body = RailJson,
     responses(
         (status = 201,  description = "The imported infra id", body = inline(PostRailjsonResponse)),
+        (status = 400, description = "The railjson is invalid or its version is not supported"),
         (status = 404, description = "The infra was not found"),
     )
 )]
 #[post("/railjson")]
 async fn post_railjson(
     params: Query<PostRailjsonQueryParams>,
-    railjson: Json<RailJson>,
+    mut payload: Payload,
     db_pool: Data<DbConnectionPool>,
     infra_caches: Data<CHashMap<i64, InfraCache>>,
 ) -> Result<Json<PostRailjsonResponse>> {
-    if railjson.version != RAILJSON_VERSION {
-        return Err(ListErrorsRailjson::WrongRailjsonVersionProvided.into());
-    }
-    let railjson = railjson.into_inner();
-
     let db_pool = db_pool.into_inner();
-    let mut infra = Infra::changeset()
+    // Bounds the number of chunks received ahead of the parsing
+    let (sender, receiver) = mpsc::channel(16);
+    let receive_payload = async move {
+        while let Some(chunk) = payload.next().await {
+            let chunk = chunk.map_err(|error| io::Error::other(error.to_string()));
+            let failed = chunk.is_err();
+            // The import stops reading on its first error
+            if sender.send(chunk).await.is_err() || failed {
+                break;
+            }
+        }
+    };
+    let import = Infra::changeset()
         .name(params.name.clone())
         .last_railjson_version()
-        .persist(railjson, db_pool.clone())
-        .await?;
+        .persist_stream(PayloadReader::new(receiver), db_pool.clone(), |_, _| {});
+    let (_, infra) = futures::join!(receive_payload, import);
+    let mut infra = infra?;
     let infra_id = infra.id;
 
     let mut conn = db_pool.get().await?;
@@ -186,6 +325,35 @@ async fn post_railjson(
     Ok(Json(PostRailjsonResponse { infra: infra.id }))
 }
 
+/// Reads the chunks of a request body, blocking while waiting for them
+struct PayloadReader {
+    chunks: mpsc::Receiver<io::Result<Bytes>>,
+    chunk: Bytes,
+}
+
+impl PayloadReader {
+    fn new(chunks: mpsc::Receiver<io::Result<Bytes>>) -> Self {
+        Self {
+            chunks,
+            chunk: Bytes::new(),
+        }
+    }
+}
+
+impl Read for PayloadReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.chunk.is_empty() {
+            match self.chunks.blocking_recv() {
+                Some(chunk) => self.chunk = chunk?,
+                None => return Ok(0),
+            }
+        }
+        let read = buf.len().min(self.chunk.len());
+        buf[..read].copy_from_slice(&self.chunk.split_to(read));
+        Ok(read)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use actix_http::StatusCode;
@@ -202,6 +370,11 @@ mod tests {
     use crate::views::infra::tests::create_object_request;
     use crate::views::tests::create_test_service;
     use editoast_schemas::infra::SwitchType;
+    use editoast_schemas::infra::TrackSection;
+    use editoast_schemas::infra::TrackSectionExtensions;
+    use editoast_schemas::infra::TrackSectionSncfExtension;
+    use editoast_schemas::infra::RAILJSON_VERSION;
+    use serde_json::json;
 
     #[rstest]
     #[serial_test::serial]
@@ -253,4 +426,120 @@ mod tests {
         let conn = &mut db_pool.get().await.unwrap();
         assert!(Infra::delete_static(conn, res.infra).await.unwrap());
     }
+
+    #[rstest]
+    #[serial_test::serial]
+    async fn test_get_railjson_older_version(#[future] empty_infra: TestFixture<Infra>) {
+        let empty_infra = empty_infra.await;
+        let app = create_test_service().await;
+
+        let req = create_object_request(empty_infra.id(), TrackSection::default().into());
+        let response = call_service(&app, req).await;
+        assert!(response.status().is_success());
+
+        let req = actix_test::TestRequest::get()
+            .uri(&format!(
+                "/infra/{}/railjson?version=3.4.11",
+                empty_infra.id()
+            ))
+            .to_request();
+        let response = call_service(&app, req).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let railjson: serde_json::Value = read_body_json(response).await;
+        assert_eq!(railjson["version"], json!("3.4.11"));
+        assert_eq!(
+            railjson["track_sections"][0]["sch"],
+            railjson["track_sections"][0]["geo"]
+        );
+
+        let req = actix_test::TestRequest::get()
+            .uri(&format!(
+                "/infra/{}/railjson?version=1.0.0",
+                empty_infra.id()
+            ))
+            .to_request();
+        let response = call_service(&app, req).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[rstest]
+    #[serial_test::serial]
+    async fn test_post_railjson_extract(#[future] empty_infra: TestFixture<Infra>) {
+        let empty_infra = empty_infra.await;
+        let app = create_test_service().await;
+
+        for (track_id, line_code) in [("track_1", 1), ("track_2", 2)] {
+            let track_section = TrackSection {
+                id: track_id.into(),
+                extensions: TrackSectionExtensions {
+                    sncf: Some(TrackSectionSncfExtension {
+                        line_code,
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            let req = create_object_request(empty_infra.id(), track_section.into());
+            let response = call_service(&app, req).await;
+            assert!(response.status().is_success());
+        }
+
+        let req = actix_test::TestRequest::post()
+            .uri(&format!("/infra/{}/railjson/extract", empty_infra.id()))
+            .set_json(json!({ "line_codes": [1] }))
+            .to_request();
+        let response = call_service(&app, req).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let railjson: RailJson = read_body_json(response).await;
+        assert_eq!(railjson.track_sections.len(), 1);
+        assert_eq!(railjson.track_sections[0].id.0, "track_1");
+
+        let req = actix_test::TestRequest::post()
+            .uri(&format!("/infra/{}/railjson/extract", empty_infra.id()))
+            .set_json(json!({}))
+            .to_request();
+        let response = call_service(&app, req).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[rstest]
+    #[serial_test::serial]
+    async fn test_post_railjson_older_version(db_pool: Arc<DbConnectionPool>) {
+        let app = create_test_service().await;
+
+        let mut railjson = serde_json::to_value(RailJson {
+            track_sections: vec![Default::default()],
+            ..Default::default()
+        })
+        .unwrap();
+        railjson["version"] = json!("3.4.11");
+        railjson["track_sections"][0]["sch"] = railjson["track_sections"][0]["geo"].clone();
+
+        let req = actix_test::TestRequest::post()
+            .uri("/infra/railjson?name=post_railjson_older_version_test")
+            .set_json(&railjson)
+            .to_request();
+        let response = call_service(&app, req).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let res: PostRailjsonResponse = read_body_json(response).await;
+
+        let conn = &mut db_pool.get().await.unwrap();
+        let infra = Infra::retrieve(conn, res.infra).await.unwrap().unwrap();
+        assert_eq!(infra.railjson_version, RAILJSON_VERSION);
+        assert!(Infra::delete_static(conn, res.infra).await.unwrap());
+    }
+
+    #[rstest]
+    async fn test_post_railjson_unsupported_version() {
+        let app = create_test_service().await;
+
+        let railjson = json!({ "version": "2.0.0" });
+        let req = actix_test::TestRequest::post()
+            .uri("/infra/railjson?name=post_railjson_unsupported_version_test")
+            .set_json(&railjson)
+            .to_request();
+        let response = call_service(&app, req).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
 }
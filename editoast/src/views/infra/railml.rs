@@ -0,0 +1,230 @@
+use actix_web::dev::HttpServiceFactory;
+use actix_web::get;
+use actix_web::post;
+use actix_web::services;
+use actix_web::web::Data;
+use actix_web::web::Json;
+use actix_web::web::Path;
+use actix_web::web::Query;
+use actix_web::HttpResponse;
+use actix_web::Responder;
+use chashmap::CHashMap;
+use editoast_derive::EditoastError;
+use editoast_schemas::infra::RailJson;
+use railml::export_railml;
+use railml::parse_railml;
+use railml::RailMlImport;
+use railml::UnmappedElement;
+use serde::Deserialize;
+use serde::Serialize;
+use thiserror::Error;
+use utoipa::IntoParams;
+use utoipa::ToSchema;
+
+use super::railjson::railjson_payload;
+use crate::error::Result;
+use crate::infra_cache::InfraCache;
+use crate::modelsv2::prelude::*;
+use crate::modelsv2::DbConnectionPool;
+use crate::modelsv2::Infra;
+use crate::views::infra::InfraApiError;
+use crate::views::infra::InfraIdParam;
+
+/// Return `/infra/railml` and `/infra/<infra_id>/railml` routes
+pub fn railml_routes() -> impl HttpServiceFactory {
+    services![get_railml, post_railml]
+}
+
+crate::routes! {
+    get_railml,
+    post_railml,
+}
+
+editoast_common::schemas! {
+    UnmappedElement,
+}
+
+#[derive(Debug, Error, EditoastError)]
+#[editoast_error(base_id = "infra:railml")]
+enum RailmlError {
+    #[error("Invalid railML: {cause}")]
+    InvalidRailml { cause: String },
+}
+
+/// Serialize an infra to railML 3.2
+///
+/// Only point switches are exported as `switchIS`, the other switches being exported as net relations.
+/// Routes and neutral sections are not exported.
+#[utoipa::path(
+    tag = "infra",
+    params(InfraIdParam),
+    responses(
+        (status = 200, description = "The infra in railML format", body = String, content_type = "application/xml"),
+        (status = 404, description = "The infra was not found"),
+    )
+)]
+#[get("/{infra_id}/railml")]
+async fn get_railml(
+    infra: Path<InfraIdParam>,
+    db_pool: Data<DbConnectionPool>,
+) -> Result<impl Responder> {
+    let infra_id = infra.infra_id;
+    let conn = &mut db_pool.get().await?;
+    let infra_meta =
+        Infra::retrieve_or_fail(conn, infra_id, || InfraApiError::NotFound { infra_id }).await?;
+
+    let railjson: RailJson = serde_json::from_str(&railjson_payload(&db_pool, &infra_meta).await?)?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/xml")
+        .append_header(("x-infra-version", infra_meta.version))
+        .body(export_railml(&railjson)))
+}
+
+/// Represents the query parameters for a `POST /infra/railml` request
+#[derive(Debug, Clone, Deserialize, IntoParams)]
+struct PostRailmlQueryParams {
+    /// The name of the infrastructure.
+    name: String,
+    /// Flag indicating whether to generate data.
+    #[serde(default)]
+    generate_data: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+struct PostRailmlResponse {
+    pub infra: i64,
+    /// The railML elements which could not be imported
+    pub unmapped: Vec<UnmappedElement>,
+}
+
+/// Import an infra from a railML 3 document
+///
+/// Geometric coordinates are expected to be WGS84 longitudes and latitudes.
+/// The elements which can't be converted are skipped and listed in the response.
+#[utoipa::path(
+    tag = "infra",
+    params(PostRailmlQueryParams),
+    request_body(content = String, content_type = "application/xml"),
+    responses(
+        (status = 200, description = "The imported infra id and the unmapped elements", body = inline(PostRailmlResponse)),
+        (status = 400, description = "The railML document is invalid"),
+    )
+)]
+#[post("/railml")]
+async fn post_railml(
+    params: Query<PostRailmlQueryParams>,
+    railml: String,
+    db_pool: Data<DbConnectionPool>,
+    infra_caches: Data<CHashMap<i64, InfraCache>>,
+) -> Result<Json<PostRailmlResponse>> {
+    let RailMlImport { railjson, unmapped } =
+        parse_railml(&railml).map_err(|e| RailmlError::InvalidRailml {
+            cause: e.to_string(),
+        })?;
+
+    let db_pool = db_pool.into_inner();
+    let mut infra = Infra::changeset()
+        .name(params.name.clone())
+        .last_railjson_version()
+        .persist(railjson, db_pool.clone())
+        .await?;
+    let infra_id = infra.id;
+
+    let mut conn = db_pool.get().await?;
+    infra
+        .bump_version(&mut conn)
+        .await
+        .map_err(|_| InfraApiError::NotFound { infra_id })?;
+    if params.generate_data {
+        let infra_cache = InfraCache::get_or_load(&mut conn, &infra_caches, &infra).await?;
+        infra.refresh(db_pool, true, &infra_cache).await?;
+    }
+
+    Ok(Json(PostRailmlResponse {
+        infra: infra.id,
+        unmapped,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_http::StatusCode;
+    use actix_web::test as actix_test;
+    use actix_web::test::call_service;
+    use actix_web::test::read_body;
+    use actix_web::test::read_body_json;
+    use rstest::*;
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::fixtures::tests::db_pool;
+    use crate::views::tests::create_test_service;
+
+    const RAILML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<railML xmlns="https://www.railml.org/schemas/3.2" version="3.2">
+  <infrastructure id="is">
+    <topology>
+      <netElements>
+        <netElement id="ne_1" length="1000">
+          <associatedPositioningSystem id="aps_1">
+            <intrinsicCoordinate id="ic_1_0" intrinsicCoord="0"><geometricCoordinate positioningSystemRef="gps" x="2.0" y="48.0"/></intrinsicCoordinate>
+            <intrinsicCoordinate id="ic_1_1" intrinsicCoord="1"><geometricCoordinate positioningSystemRef="gps" x="2.01" y="48.0"/></intrinsicCoordinate>
+          </associatedPositioningSystem>
+        </netElement>
+      </netElements>
+    </topology>
+    <functionalInfrastructure>
+      <bufferStops>
+        <bufferStop id="bs_1"><spotLocation id="bs_1_sl" netElementRef="ne_1" intrinsicCoord="0"/></bufferStop>
+      </bufferStops>
+      <levelCrossingsIS>
+        <levelCrossingIS id="lc_1"><spotLocation id="lc_1_sl" netElementRef="ne_1" intrinsicCoord="0.5"/></levelCrossingIS>
+      </levelCrossingsIS>
+    </functionalInfrastructure>
+  </infrastructure>
+</railML>
+"#;
+
+    #[rstest]
+    #[serial_test::serial]
+    async fn test_post_and_get_railml(db_pool: Arc<DbConnectionPool>) {
+        let app = create_test_service().await;
+
+        let req = actix_test::TestRequest::post()
+            .uri("/infra/railml?name=post_railml_test")
+            .insert_header(("content-type", "application/xml"))
+            .set_payload(RAILML)
+            .to_request();
+        let response = call_service(&app, req).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let res: PostRailmlResponse = read_body_json(response).await;
+        assert_eq!(res.unmapped.len(), 1);
+        assert_eq!(res.unmapped[0].id.as_deref(), Some("lc_1"));
+
+        let req = actix_test::TestRequest::get()
+            .uri(&format!("/infra/{}/railml", res.infra))
+            .to_request();
+        let response = call_service(&app, req).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let railml = String::from_utf8(read_body(response).await.to_vec()).unwrap();
+        let import = parse_railml(&railml).unwrap();
+        assert_eq!(import.railjson.track_sections.len(), 1);
+        assert_eq!(import.railjson.buffer_stops.len(), 1);
+
+        let conn = &mut db_pool.get().await.unwrap();
+        assert!(Infra::delete_static(conn, res.infra).await.unwrap());
+    }
+
+    #[rstest]
+    async fn test_post_invalid_railml() {
+        let app = create_test_service().await;
+
+        let req = actix_test::TestRequest::post()
+            .uri("/infra/railml?name=post_invalid_railml_test")
+            .set_payload("<railML>")
+            .to_request();
+        let response = call_service(&app, req).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
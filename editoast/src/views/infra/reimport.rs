@@ -0,0 +1,299 @@
+use actix_web::post;
+use actix_web::web::Data;
+use actix_web::web::Json;
+use actix_web::web::Path;
+use actix_web::web::Query;
+use chashmap::CHashMap;
+use editoast_schemas::infra::RailJson;
+use serde::Deserialize;
+use serde::Serialize;
+use utoipa::IntoParams;
+use utoipa::ToSchema;
+
+use super::edition::apply_edit_in_transaction;
+use super::edition::check_edition;
+use super::InfraIdParam;
+use crate::error::InternalError;
+use crate::error::Result;
+use crate::generated_data::generate_infra_errors;
+use crate::generated_data::infra_error::InfraError;
+use crate::infra_cache::operation::Operation;
+use crate::infra_cache::InfraCache;
+use crate::map;
+use crate::map::MapLayers;
+use crate::modelsv2::infra::InfraMerge;
+use crate::modelsv2::infra::InfraSnapshot;
+use crate::modelsv2::infra::MergeConflict;
+use crate::modelsv2::infra_import::InfraImport;
+use crate::modelsv2::prelude::*;
+use crate::modelsv2::DbConnection;
+use crate::modelsv2::DbConnectionPool;
+use crate::modelsv2::Infra;
+use crate::views::infra::InfraApiError;
+use crate::views::params::ExpectedVersion;
+use crate::views::params::RemoteUser;
+use crate::RedisClient;
+
+crate::routes! {
+    "/reimport" => {
+        reimport,
+    },
+}
+
+editoast_common::schemas! {
+    InfraReimportResult,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ReimportQueryParams {
+    /// The name of the imported data source (e.g. `osm`)
+    source: String,
+    /// Only compute the result of the import without applying it
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+struct InfraReimportResult {
+    /// The operations applied to the infra
+    operations: Vec<Operation>,
+    /// The objects edited since the previous import that the new import would change,
+    /// they are left untouched
+    conflicts: Vec<MergeConflict>,
+    /// The errors of the updated infra, only computed on dry runs
+    #[schema(required)]
+    errors: Option<Vec<InfraError>>,
+}
+
+/// Import an updated version of a data source onto an infra
+///
+/// The objects are matched by identifier with the ones of the previous import from the same
+/// source, or with the objects the infra was created with for the first re-import. Only the
+/// objects left untouched since then are created, updated or deleted: the ones edited in the
+/// meantime are reported as conflicts and kept as they are.
+///
+/// The import is recorded as a new revision of the infra. Like editions, it can be made
+/// conditional to the version of the infra with the `If-Match` header, and fails if the
+/// imported changes target objects locked by other users. With `dry_run`, nothing is applied
+/// and the errors the updated infra would have are returned along with the conflicts.
+#[utoipa::path(
+    tag = "infra",
+    params(InfraIdParam, ReimportQueryParams),
+    request_body = RailJson,
+    responses(
+        (status = 200, description = "The result of the import", body = InfraReimportResult),
+        (status = 404, description = "Infra not found"),
+        (status = 409, description = "Some changed objects are locked by other users"),
+        (status = 412, description = "The infra doesn't match the version given in the `If-Match` header"),
+    ),
+)]
+#[post("")]
+#[allow(clippy::too_many_arguments)]
+async fn reimport(
+    infra: Path<InfraIdParam>,
+    Query(ReimportQueryParams { source, dry_run }): Query<ReimportQueryParams>,
+    Json(railjson): Json<RailJson>,
+    db_pool: Data<DbConnectionPool>,
+    infra_caches: Data<CHashMap<i64, InfraCache>>,
+    redis_client: Data<RedisClient>,
+    map_layers: Data<MapLayers>,
+    RemoteUser(author): RemoteUser,
+    ExpectedVersion(expected_version): ExpectedVersion,
+) -> Result<Json<InfraReimportResult>> {
+    let infra_id = infra.infra_id;
+    let conn = &mut db_pool.get().await?;
+    let mut infra =
+        Infra::retrieve_or_fail(conn, infra_id, || InfraApiError::NotFound { infra_id }).await?;
+    let imported = InfraSnapshot::from_iter(railjson.clone().into_objects());
+
+    if dry_run {
+        let (
+            InfraMerge {
+                operations,
+                conflicts,
+            },
+            mut target,
+        ) = merge_import(conn, &infra, &source, &imported).await?;
+        for operation in &operations {
+            target.apply(operation)?;
+        }
+        let errors = generate_infra_errors(&target.infra_cache()?).await;
+        return Ok(Json(InfraReimportResult {
+            operations,
+            conflicts,
+            errors: Some(errors),
+        }));
+    }
+
+    // Concurrent editions of the infra wait for the cache to be released
+    let mut infra_cache = InfraCache::get_or_load_mut(conn, &infra_caches, &infra).await?;
+    let mut scratch_cache = infra_cache.clone();
+    let scratch = &mut scratch_cache;
+    let infra = &mut infra;
+    let (operations, conflicts) = conn
+        .build_transaction()
+        .run::<_, InternalError, _>(|conn| {
+            Box::pin(async move {
+                // The merge is computed once the infra is locked for update, so that no edition
+                // can happen in between
+                *infra = Infra::retrieve_for_update(conn, infra_id)
+                    .await?
+                    .ok_or(InfraApiError::NotFound { infra_id })?;
+                let (
+                    InfraMerge {
+                        operations,
+                        conflicts,
+                    },
+                    _,
+                ) = merge_import(conn, infra, &source, &imported).await?;
+                check_edition(
+                    conn,
+                    infra,
+                    &operations,
+                    author.as_deref(),
+                    expected_version.as_deref(),
+                )
+                .await?;
+
+                // The conflicting objects are recorded as imported so they aren't reported again
+                // until the source changes them once more
+                InfraImport::record(conn, infra_id, source, railjson).await?;
+                if !operations.is_empty() {
+                    apply_edit_in_transaction(conn, infra, &operations, scratch, author).await?;
+                }
+                Ok((operations, conflicts))
+            })
+        })
+        .await?;
+    *infra_cache = scratch_cache;
+
+    if !operations.is_empty() {
+        let mut redis_conn = redis_client.get_connection().await?;
+        map::invalidate_all(
+            &mut redis_conn,
+            &map_layers.layers.keys().cloned().collect(),
+            infra_id,
+        )
+        .await?;
+    }
+    Ok(Json(InfraReimportResult {
+        operations,
+        conflicts,
+        errors: None,
+    }))
+}
+
+/// Merges the imported objects onto the infra
+///
+/// The common ancestor is the previous import of the same source, or the state the infra was
+/// created with. Returns the merge along with the current state of the infra.
+async fn merge_import(
+    conn: &mut DbConnection,
+    infra: &Infra,
+    source: &str,
+    imported: &InfraSnapshot,
+) -> Result<(InfraMerge, InfraSnapshot)> {
+    let ancestor = match InfraImport::latest(conn, infra.id, source).await? {
+        Some(import) => InfraSnapshot::from_iter(import.railjson.into_objects()),
+        None => infra.initial_snapshot(conn).await?,
+    };
+    let target = infra.snapshot(conn, None).await?;
+    let merge = InfraMerge::strict(&ancestor, imported, &target);
+    Ok((merge, target))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use actix_web::test::call_and_read_body_json;
+    use actix_web::test::call_service;
+    use actix_web::test::TestRequest;
+    use rstest::*;
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::fixtures::tests::db_pool;
+    use crate::fixtures::tests::small_infra;
+    use crate::views::infra::railjson::railjson_payload;
+    use crate::views::tests::create_test_service;
+
+    fn reimport_request(infra_id: i64, railjson: &RailJson) -> TestRequest {
+        TestRequest::post()
+            .uri(format!("/infra/{infra_id}/reimport?source=osm").as_str())
+            .set_json(railjson)
+    }
+
+    #[rstest]
+    async fn reimport_keeps_manual_editions(db_pool: Arc<DbConnectionPool>) {
+        let app = create_test_service().await;
+        let small_infra = small_infra(db_pool.clone()).await;
+        let infra_id = small_infra.id();
+        let railjson: RailJson = serde_json::from_str(
+            &railjson_payload(&db_pool, &small_infra.model)
+                .await
+                .unwrap(),
+        )
+        .unwrap();
+
+        // A dry run of the unchanged source finds nothing to do and no error
+        let req = TestRequest::post()
+            .uri(format!("/infra/{infra_id}/reimport?source=osm&dry_run=true").as_str())
+            .set_json(&railjson)
+            .to_request();
+        let result: InfraReimportResult = call_and_read_body_json(&app, req).await;
+        assert!(result.operations.is_empty());
+        assert!(result.conflicts.is_empty());
+        assert_eq!(result.errors, Some(vec![]));
+
+        // Manual edition of a track section
+        let req = TestRequest::post()
+            .uri(format!("/infra/{infra_id}/").as_str())
+            .set_json(serde_json::json!([{
+                "operation_type": "UPDATE",
+                "obj_type": "TrackSection",
+                "obj_id": "TA0",
+                "railjson_patch": [{ "op": "replace", "path": "/length", "value": 1234.0 }],
+            }]))
+            .to_request();
+        let _: serde_json::Value = call_and_read_body_json(&app, req).await;
+
+        // The source changes the edited track section and another one
+        let mut updated = railjson.clone();
+        for track in &mut updated.track_sections {
+            if track.id.as_str() == "TA0" || track.id.as_str() == "TA1" {
+                track.length += 1.0;
+            }
+        }
+        let result: InfraReimportResult =
+            call_and_read_body_json(&app, reimport_request(infra_id, &updated).to_request()).await;
+        assert_eq!(result.operations.len(), 1);
+        assert_eq!(result.conflicts.len(), 1);
+        assert_eq!(result.conflicts[0].obj_id, "TA0");
+
+        // Importing the same data again changes nothing
+        let result: InfraReimportResult =
+            call_and_read_body_json(&app, reimport_request(infra_id, &updated).to_request()).await;
+        assert!(result.operations.is_empty());
+        assert!(result.conflicts.is_empty());
+    }
+
+    #[rstest]
+    async fn reimport_with_stale_version(db_pool: Arc<DbConnectionPool>) {
+        let app = create_test_service().await;
+        let small_infra = small_infra(db_pool.clone()).await;
+        let infra_id = small_infra.id();
+        let railjson: RailJson = serde_json::from_str(
+            &railjson_payload(&db_pool, &small_infra.model)
+                .await
+                .unwrap(),
+        )
+        .unwrap();
+
+        let req = reimport_request(infra_id, &railjson)
+            .insert_header(("If-Match", "\"stale\""))
+            .to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::PRECONDITION_FAILED);
+    }
+}
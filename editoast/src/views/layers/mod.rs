@@ -12,6 +12,7 @@ use diesel::sql_query;
 use diesel::sql_types::Integer;
 use diesel_async::RunQueryDsl;
 use editoast_derive::EditoastError;
+use editoast_schemas::primitives::BoundingBox;
 use mvt_utils::create_and_fill_mvt_tile;
 use mvt_utils::get_geo_json_sql_query;
 use mvt_utils::GeoJsonAndData;
@@ -25,12 +26,17 @@ use utoipa::ToSchema;
 use crate::client::get_root_url;
 use crate::client::MapLayersConfig;
 use crate::error::Result;
+use crate::map::export_layers;
 use crate::map::get_cache_tile_key;
 use crate::map::get_view_cache_prefix;
+use crate::map::write_geopackage;
 use crate::map::Layer;
 use crate::map::MapLayers;
 use crate::map::Tile;
+use crate::modelsv2::prelude::*;
 use crate::modelsv2::DbConnectionPoolV2;
+use crate::modelsv2::Infra;
+use crate::views::infra::InfraApiError;
 use crate::RedisClient;
 
 crate::routes! {
@@ -41,6 +47,9 @@ crate::routes! {
         "/tile/{layer_slug}/{view_slug}/{z}/{x}/{y}" => {
             cache_and_get_mvt_tile,
         },
+        "/export" => {
+            export_infra_layers,
+        },
     }
 }
 
@@ -57,6 +66,9 @@ enum LayersError {
         view_name: String,
         expected_names: Vec<String>,
     },
+    #[error("Invalid bounding box '{bbox}', expected 'min_lon,min_lat,max_lon,max_lat'")]
+    #[editoast_error(status = 400)]
+    InvalidBoundingBox { bbox: String },
 }
 
 impl LayersError {
@@ -230,6 +242,98 @@ async fn cache_and_get_mvt_tile(
         .body(mvt_bytes))
 }
 
+#[derive(Debug, Clone, Copy, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+enum LayersExportFormat {
+    #[default]
+    Geojson,
+    Gpkg,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ExportLayersParams {
+    infra: i64,
+    /// `geojson` returns the feature collections by layer name, `gpkg` a GeoPackage file
+    #[serde(default)]
+    #[param(inline)]
+    format: LayersExportFormat,
+    /// Only exports the features intersecting this bounding box, formatted as `min_lon,min_lat,max_lon,max_lat`.
+    /// Their geometry is clipped to it.
+    bbox: Option<String>,
+}
+
+fn parse_bbox(bbox: &str) -> Result<BoundingBox> {
+    let invalid_bbox = || LayersError::InvalidBoundingBox {
+        bbox: bbox.to_owned(),
+    };
+    let coordinates = bbox
+        .split(',')
+        .map(|coordinate| coordinate.trim().parse::<f64>())
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|_| invalid_bbox())?;
+    match coordinates[..] {
+        [min_lon, min_lat, max_lon, max_lat] if min_lon <= max_lon && min_lat <= max_lat => {
+            Ok(BoundingBox((min_lon, min_lat), (max_lon, max_lat)))
+        }
+        _ => Err(invalid_bbox().into()),
+    }
+}
+
+/// Exports every map layer of an infra with the flattened properties of their objects
+///
+/// The coordinates are given in WGS84.
+#[utoipa::path(
+    tag = "layers",
+    params(ExportLayersParams),
+    responses(
+        (status = 200, description = "The GeoJSON feature collections by layer name or the GeoPackage file", content(
+            ("application/json" = HashMap<String, Object>),
+            ("application/geopackage+sqlite3" = Vec<u8>),
+        )),
+        (status = 400, description = "The bounding box is invalid"),
+        (status = 404, description = "The infra was not found"),
+    )
+)]
+#[get("")]
+async fn export_infra_layers(
+    params: Query<ExportLayersParams>,
+    map_layers: Data<MapLayers>,
+    db_pool: Data<DbConnectionPoolV2>,
+) -> Result<HttpResponse> {
+    let ExportLayersParams {
+        infra: infra_id,
+        format,
+        bbox,
+    } = params.into_inner();
+    let bbox = bbox.as_deref().map(parse_bbox).transpose()?;
+    let conn = &mut db_pool.get().await?;
+    let infra =
+        Infra::retrieve_or_fail(conn, infra_id, || InfraApiError::NotFound { infra_id }).await?;
+    let layers = export_layers(conn, &map_layers, infra.id, bbox.as_ref()).await?;
+
+    match format {
+        LayersExportFormat::Geojson => Ok(HttpResponse::Ok().json(layers)),
+        LayersExportFormat::Gpkg => {
+            // SQLite and the filesystem are blocking, keep them off the async workers
+            let geopackage = tokio::task::spawn_blocking(move || -> Result<Vec<u8>> {
+                let dir = tempfile::tempdir()?;
+                let path = dir.path().join("layers.gpkg");
+                write_geopackage(&path, &layers)?;
+                Ok(std::fs::read(path)?)
+            })
+            .await??;
+            Ok(HttpResponse::Ok()
+                .content_type("application/geopackage+sqlite3")
+                .append_header((
+                    "Content-Disposition",
+                    format!("attachment; filename=\"infra_{infra_id}_layers.gpkg\""),
+                ))
+                .body(geopackage))
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
@@ -307,4 +411,18 @@ mod tests {
             test_get_query_with_preset_values(expected_root_url).await;
         }
     }
+
+    #[rstest]
+    async fn export_layers_invalid_bbox() {
+        let error: InternalError = LayersError::InvalidBoundingBox {
+            bbox: "0,1,2".to_owned(),
+        }
+        .into();
+        test_get_query(
+            "/layers/export?infra=2&bbox=0,1,2",
+            StatusCode::BAD_REQUEST,
+            to_value(error).unwrap(),
+        )
+        .await;
+    }
 }
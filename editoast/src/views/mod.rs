@@ -19,6 +19,7 @@ pub mod study;
 pub mod timetable;
 pub mod train_schedule;
 pub mod v2;
+pub mod validation_profiles;
 pub mod work_schedules;
 
 #[cfg(test)]
@@ -70,6 +71,7 @@ fn routes_v2() -> Routes<impl HttpServiceFactory> {
         sprites::routes(),
         search::routes(),
         electrical_profiles::routes(),
+        validation_profiles::routes(),
         layers::routes(),
         infra::routes(),
         single_simulation::routes(),
@@ -99,6 +101,7 @@ editoast_common::schemas! {
     rolling_stocks::schemas(),
     light_rolling_stocks::schemas(),
     electrical_profiles::schemas(),
+    validation_profiles::schemas(),
     infra::schemas(),
     single_simulation::schemas(),
     v2::schemas(),
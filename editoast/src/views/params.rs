@@ -28,3 +28,98 @@ where
         Ok(List(res))
     }
 }
+
+/// The user on behalf of whom a request is made
+///
+/// It is read from the `x-remote-user` header set by the gateway. It's `None`
+/// when editoast is reached directly.
+#[derive(Debug, Default, Clone)]
+pub struct RemoteUser(pub Option<String>);
+
+impl actix_web::FromRequest for RemoteUser {
+    type Error = actix_web::Error;
+    type Future = std::future::Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &actix_web::HttpRequest, _: &mut actix_web::dev::Payload) -> Self::Future {
+        let user = req
+            .headers()
+            .get("x-remote-user")
+            .and_then(|value| value.to_str().ok())
+            .map(String::from);
+        std::future::ready(Ok(RemoteUser(user)))
+    }
+}
+
+/// The versions of the infra a request was prepared against
+///
+/// It is read from the `If-Match` header, a comma-separated list of entity tags (RFC 9110).
+/// Unquoted values are accepted. Weak tags (`W/"..."`) are dropped since `If-Match` uses the
+/// strong comparison: they never match. It's `None` when the header is missing or is `*`,
+/// in which case no version check is made.
+#[derive(Debug, Default, Clone)]
+pub struct ExpectedVersion(pub Option<Vec<String>>);
+
+impl ExpectedVersion {
+    fn parse(header: &str) -> Self {
+        let header = header.trim();
+        if header == "*" {
+            return ExpectedVersion(None);
+        }
+        let versions = header
+            .split(',')
+            .map(str::trim)
+            .filter(|tag| !tag.starts_with("W/"))
+            .map(|tag| tag.trim_matches('"').to_owned())
+            .filter(|tag| !tag.is_empty())
+            .collect();
+        ExpectedVersion(Some(versions))
+    }
+}
+
+impl actix_web::FromRequest for ExpectedVersion {
+    type Error = actix_web::Error;
+    type Future = std::future::Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &actix_web::HttpRequest, _: &mut actix_web::dev::Payload) -> Self::Future {
+        let expected_version = req
+            .headers()
+            .get(actix_web::http::header::IF_MATCH)
+            .and_then(|value| value.to_str().ok())
+            .map(ExpectedVersion::parse)
+            .unwrap_or_default();
+        std::future::ready(Ok(expected_version))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ExpectedVersion;
+
+    #[test]
+    fn expected_version_any() {
+        assert_eq!(ExpectedVersion::parse("*").0, None);
+        assert_eq!(ExpectedVersion::parse(" * ").0, None);
+    }
+
+    #[test]
+    fn expected_version_entity_tags() {
+        assert_eq!(ExpectedVersion::parse("3").0, Some(vec!["3".to_owned()]));
+        assert_eq!(
+            ExpectedVersion::parse("\"3\"").0,
+            Some(vec!["3".to_owned()])
+        );
+        assert_eq!(
+            ExpectedVersion::parse("\"1\", \"2\",3").0,
+            Some(vec!["1".to_owned(), "2".to_owned(), "3".to_owned()])
+        );
+    }
+
+    #[test]
+    fn expected_version_weak_tags_never_match() {
+        assert_eq!(ExpectedVersion::parse("W/\"3\"").0, Some(vec![]));
+        assert_eq!(
+            ExpectedVersion::parse("\"1\", W/\"2\"").0,
+            Some(vec!["1".to_owned()])
+        );
+    }
+}
@@ -15,7 +15,7 @@ use crate::error::Result;
 ///
 /// Functions of the [QueryContext] consume [TypedAst]s  as argument(s) and
 /// produce another [TypedAst]. See [QueryContext::def_function].
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum TypedAst {
     Null,
     Boolean(bool),
@@ -123,6 +123,8 @@ pub struct QueryContext {
     pub search_table_name: Option<String>,
     /// Maps a column name to its expected values' type.
     pub columns_type: HashMap<String, TypeSpec>,
+    /// Maps the columns that aren't part of the search table to the table (or alias) holding them
+    pub columns_table: HashMap<String, String>,
 }
 
 impl QueryContext {
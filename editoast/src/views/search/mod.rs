@@ -252,6 +252,8 @@ enum SearchError {
     ObjectType { object_type: String },
     #[error("query has type '{query_type}' but Boolean is expected")]
     QueryAst { query_type: String },
+    #[error("order_by expression has type '{order_type}' but a number or a string is expected")]
+    OrderByAst { order_type: String },
 }
 
 impl SearchConfig {
@@ -268,12 +270,20 @@ impl SearchConfig {
         context.search_table_name = Some(self.table.to_owned());
         // Register known columns with their expected type
         for Criteria {
-            name, data_type, ..
+            name,
+            data_type,
+            table,
+            ..
         } in self.criterias.iter()
         {
             context
                 .columns_type
                 .insert(name.to_string(), data_type.clone());
+            if let Some(table) = table {
+                context
+                    .columns_table
+                    .insert(name.to_string(), table.to_string());
+            }
         }
         context
     }
@@ -304,6 +314,10 @@ pub struct SearchPayload {
     /// The query to run
     #[schema(value_type = SearchQuery)]
     query: JsonValue,
+    /// An expression sorting the results in ascending order, such as the distance to a point
+    #[schema(value_type = Option<SearchQuery>, example = json!(["distance", ["geographic"], ["point", 2.35, 48.85]]))]
+    #[serde(default)]
+    order_by: Option<JsonValue>,
     /// Whether to return the SQL query instead of executing it
     #[serde(default)]
     dry: bool,
@@ -311,6 +325,7 @@ pub struct SearchPayload {
 
 fn create_sql_query(
     query: JsonValue,
+    order_by: Option<JsonValue>,
     search_config: &SearchConfig,
     limit: i64,
     offset: i64,
@@ -330,12 +345,37 @@ fn create_sql_query(
     let result_columns = search_config.result_columns();
     let mut bindings = Default::default();
     let constraints = where_expression.to_sql(&mut bindings);
+    let order = match order_by {
+        Some(order_by) => {
+            let ast = SearchAst::build_ast(order_by)?;
+            let order_type = context.typecheck_search_query(&ast)?;
+            let orderable = TypeSpec::or(
+                AstType::Null,
+                TypeSpec::or(
+                    AstType::Integer,
+                    TypeSpec::or(AstType::Float, AstType::String),
+                ),
+            );
+            if !orderable.is_supertype_spec(&order_type) {
+                return Err(SearchError::OrderByAst {
+                    order_type: order_type.to_string(),
+                }
+                .into());
+            }
+            format!(
+                "ORDER BY {}",
+                context.search_ast_to_sql(&ast)?.to_sql(&mut bindings)
+            )
+        }
+        None => String::new(),
+    };
     let sql_code = format!(
         "WITH _RESULT AS (
             SELECT {result_columns}
             FROM {table}
             {joins}
             WHERE {constraints}
+            {order}
             LIMIT {limit} OFFSET {offset}
         )
         SELECT to_jsonb(_RESULT) AS result
@@ -361,6 +401,7 @@ struct SearchDBResult {
 ///     {
 ///         "object": string,
 ///         "query": query,
+///         "order_by": expression, # default: null
 ///         "dry": boolean, # default: false
 ///     }
 ///
@@ -368,6 +409,8 @@ struct SearchDBResult {
 /// - `object` can be any search object declared in `search.yml`
 /// - `query` is a JSON document which can be deserialized into a [SearchAst].
 ///   Check out examples below.
+/// - `order_by` is an optional expression of the same language, evaluating to a number
+///   or a string, by which the results are sorted in ascending order.
 ///
 /// # Response
 ///
@@ -393,6 +436,10 @@ struct SearchDBResult {
 ///   `["or", ["search", ["name"], "Paris"], ["search", ["name"], "Lyon"]]`
 /// * All railway stations with "Paris" in their name but not PNO :
 ///   `["and", ["search", ["name"], "Paris"], ["not", ["=", ["trigram"], "pno"]]]`
+/// * The signals less than 500 meters away from a point:
+///   `["distance_lt", ["geographic"], ["point", 2.35, 48.85], 500]`,
+///   ordered by `["distance", ["geographic"], ["point", 2.35, 48.85]]`
+/// * The operational points of an area: `["within_bbox", ["geographic"], 2.2, 48.8, 2.5, 48.9]`
 ///
 /// See [SearchAst] for a more detailed view of the query language.
 #[utoipa::path(
@@ -410,13 +457,18 @@ pub async fn search(
     db_pool: Data<DbConnectionPool>,
 ) -> Result<impl Responder> {
     let (page, per_page) = query_params.validate(1000)?.warn_page_size(100).unpack();
-    let Json(SearchPayload { object, query, dry }) = payload;
+    let Json(SearchPayload {
+        object,
+        query,
+        order_by,
+        dry,
+    }) = payload;
     let search_config =
         SearchConfigFinder::find(&object).ok_or_else(|| SearchError::ObjectType {
             object_type: object.to_owned(),
         })?;
     let offset = (page - 1) * per_page;
-    let sql = create_sql_query(query, &search_config, per_page, offset)?;
+    let sql = create_sql_query(query, order_by, &search_config, per_page, offset)?;
 
     if dry {
         let query = diesel::debug_query::<Pg, _>(&sql).to_string();
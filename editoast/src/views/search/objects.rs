@@ -79,7 +79,8 @@ pub(super) struct SearchResultItemTrack {
         data_type = "text",
         sql = "infra_object_operational_point.data->'extensions'->'identifier'->>'name'",
         textual_search,
-    )
+    ),
+    column(name = "geographic", data_type = "geometry", table = "lay")
 )]
 #[allow(unused)]
 /// A search result item for a query with `object = "operationalpoint"`
@@ -159,6 +160,7 @@ pub(super) struct SearchResultItemOperationalPointTrackSections {
         data_type = "integer",
         sql = "(track_section.data->'extensions'->'sncf'->>'line_code')::integer"
     ),
+    column(name = "geographic", data_type = "geometry", table = "lay"),
     joins = "
         INNER JOIN infra_object_signal AS sig ON sig.id = search_signal.id
         INNER JOIN infra_object_track_section AS track_section ON track_section.obj_id = sig.data->>'track' AND track_section.infra_id = sig.infra_id
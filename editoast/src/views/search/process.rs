@@ -55,7 +55,11 @@ impl QueryContext {
             SearchAst::String(s) => Ok(TypedAst::String(s.clone())),
             SearchAst::Column(name) => Ok(TypedAst::Column {
                 name: name.to_owned(),
-                table: self.search_table_name.clone(),
+                table: self
+                    .columns_table
+                    .get(name)
+                    .or(self.search_table_name.as_ref())
+                    .cloned(),
                 spec: self
                     .columns_type
                     .get(name)
@@ -207,9 +211,146 @@ pub fn create_processing_context() -> QueryContext {
         "contains",
         Rc::new(|sub, array| Ok(SqlQuery::infix("<@", sub, array))),
     );
+    def_spatial_functions(&mut context);
     context
 }
 
+/// Adds the functions operating on geometry columns to a context
+///
+/// The geometry columns are in Web Mercator (SRID 3857). The geometries built by the query are
+/// given in WGS84 and transformed to Web Mercator once, so that the spatial index of the compared
+/// column can be used. Distances are computed on the spheroid, in meters.
+///
+/// List of functions:
+/// - point : number -> number -> geometry (longitude then latitude)
+/// - geojson : string -> geometry
+/// - within_bbox : geometry -> number -> number -> number -> number -> bool
+///   (min longitude, min latitude, max longitude, max latitude)
+/// - intersects : geometry -> (geometry | string) -> bool (a string is parsed as GeoJSON)
+/// - distance : geometry -> geometry -> float
+/// - distance_lt : geometry -> geometry -> number -> bool
+fn def_spatial_functions(context: &mut QueryContext) {
+    let number = || TypeSpec::or(AstType::Integer, AstType::Float);
+    // Transforms a WGS84 geometry to Web Mercator
+    let mercator = |geometry: SqlQuery| {
+        SqlQuery::call(
+            "ST_Transform",
+            vec![
+                SqlQuery::call("ST_SetSRID", vec![geometry, 4326_i64.into()]),
+                3857_i64.into(),
+            ],
+        )
+    };
+    let geography = |geometry: SqlQuery| {
+        SqlQuery::cast(
+            SqlQuery::call("ST_Transform", vec![geometry, 4326_i64.into()]),
+            "geography",
+        )
+    };
+    // Web Mercator stretches lengths by 1 / cos(latitude), taken at the center of `geometry`
+    let mercator_length = |meters: SqlQuery, geometry: SqlQuery| {
+        let center = SqlQuery::call(
+            "ST_Transform",
+            vec![
+                SqlQuery::call("ST_Centroid", vec![geometry]),
+                4326_i64.into(),
+            ],
+        );
+        let latitude = SqlQuery::call("radians", vec![SqlQuery::call("ST_Y", vec![center])]);
+        SqlQuery::infix("/", meters, SqlQuery::call("cos", vec![latitude]))
+    };
+    let geojson =
+        move |string: TypedAst| mercator(SqlQuery::call("ST_GeomFromGeoJSON", vec![string]));
+    let intersects = Rc::new(move |mut args: Vec<TypedAst>| -> Result<TypedAst> {
+        let other = match args.pop().unwrap() {
+            TypedAst::String(string) => geojson(TypedAst::String(string)),
+            other => other.into(),
+        };
+        let column = args.pop().unwrap();
+        Ok(SqlQuery::call("ST_Intersects", vec![column.into(), other])
+            .into_typed_ast(AstType::Boolean.into()))
+    });
+
+    context.def_function(
+        "point",
+        number() >> number() >> AstType::Geometry,
+        Rc::new(move |args| {
+            Ok(mercator(SqlQuery::call("ST_MakePoint", args))
+                .into_typed_ast(AstType::Geometry.into()))
+        }),
+    );
+    context.def_function(
+        "geojson",
+        AstType::String >> AstType::Geometry,
+        Rc::new(move |mut args| {
+            Ok(geojson(args.pop().unwrap()).into_typed_ast(AstType::Geometry.into()))
+        }),
+    );
+    context.def_function(
+        "within_bbox",
+        AstType::Geometry >> number() >> number() >> number() >> number() >> AstType::Boolean,
+        Rc::new(move |mut args| {
+            let column = args.remove(0);
+            let bbox = mercator(SqlQuery::call("ST_MakeEnvelope", args));
+            Ok(SqlQuery::call("ST_Within", vec![column.into(), bbox])
+                .into_typed_ast(AstType::Boolean.into()))
+        }),
+    );
+    context.def_function(
+        "intersects",
+        AstType::Geometry >> AstType::Geometry >> AstType::Boolean,
+        intersects.clone(),
+    );
+    context.def_function(
+        "intersects",
+        AstType::Geometry >> AstType::String >> AstType::Boolean,
+        intersects,
+    );
+    context.def_function(
+        "distance",
+        AstType::Geometry >> AstType::Geometry >> AstType::Float,
+        Rc::new(move |args| {
+            let args: Vec<_> = args.into_iter().map(|arg| geography(arg.into())).collect();
+            Ok(SqlQuery::call("ST_Distance", args).into_typed_ast(AstType::Float.into()))
+        }),
+    );
+    context.def_function(
+        "distance_lt",
+        AstType::Geometry >> AstType::Geometry >> number() >> AstType::Boolean,
+        Rc::new(move |args| {
+            let mut args = args.into_iter();
+            let (left, right, meters) = (
+                args.next().unwrap(),
+                args.next().unwrap(),
+                args.next().unwrap(),
+            );
+            // The candidates are first selected in Web Mercator, with the index of the column,
+            // around the geometry built by the query
+            let reference = match right {
+                TypedAst::Column { .. } => left.clone(),
+                _ => right.clone(),
+            };
+            let candidate = SqlQuery::call(
+                "ST_DWithin",
+                vec![
+                    left.clone().into(),
+                    right.clone().into(),
+                    mercator_length(meters.clone().into(), reference.into()),
+                ],
+            );
+            let within = SqlQuery::call(
+                "ST_DWithin",
+                vec![
+                    geography(left.into()),
+                    geography(right.into()),
+                    meters.into(),
+                ],
+            );
+            Ok(SqlQuery::infix("AND", candidate, within).into_typed_ast(AstType::Boolean.into()))
+        }),
+    );
+}
+
 #[cfg(test)]
 mod tests {
     use std::rc::Rc;
@@ -227,6 +368,9 @@ mod tests {
             .insert("trigram".into(), AstType::String.into());
         env.columns_type
             .insert("infra_id".into(), AstType::Integer.into());
+        env.columns_type
+            .insert("geographic".into(), AstType::Geometry.into());
+        env.columns_table.insert("geographic".into(), "lay".into());
         // + : int -> int -> int
         env.def_function_2::<dsl::Integer, dsl::Integer, dsl::Integer>(
             "+",
@@ -455,6 +599,53 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_typecheck_spatial() {
+        assert!(typecheck(json!(["within_bbox", ["geographic"], 0, 0, 1.5, 1])).is_ok());
+        assert!(typecheck(json!([
+            "distance_lt",
+            ["geographic"],
+            ["point", 2.35, 48.85],
+            500
+        ]))
+        .is_ok());
+        assert!(typecheck(json!(["intersects", ["geographic"], "{}"])).is_ok());
+        assert!(typecheck(json!(["within_bbox", ["name"], 0, 0, 1, 1])).is_err());
+        assert!(typecheck(json!(["point", "2.35", 48.85])).is_err());
+    }
+
+    #[test]
+    fn eval_spatial() {
+        assert_eq!(
+            SqlQuery::from(eval(json!(["within_bbox", ["geographic"], 0, 0, 1.5, 1]))).to_string(),
+            "ST_Within((\"lay\".\"geographic\"), (ST_Transform((ST_SetSRID((ST_MakeEnvelope((0), (0), (1.5), (1))), (4326))), (3857))))"
+        );
+        let mut bindings = vec![];
+        let sql = SqlQuery::from(eval(json!([
+            "intersects",
+            ["geographic"],
+            r#"{"type": "Point", "coordinates": [0, 0]}"#
+        ])))
+        .to_sql(&mut bindings);
+        assert_eq!(
+            sql,
+            "ST_Intersects((\"lay\".\"geographic\"), (ST_Transform((ST_SetSRID((ST_GeomFromGeoJSON(($1))), (4326))), (3857))))"
+        );
+        assert_eq!(bindings.len(), 1);
+        // The column is compared as is, so that its spatial index can be used
+        let sql = SqlQuery::from(eval(json!([
+            "distance_lt",
+            ["geographic"],
+            ["point", 2.35, 48.85],
+            500
+        ])))
+        .to_string();
+        assert!(sql.starts_with("(ST_DWithin((\"lay\".\"geographic\"), (ST_Transform((ST_SetSRID((ST_MakePoint((2.35), (48.85))), (4326))), (3857)))"));
+        assert!(sql.contains(
+            ") AND (ST_DWithin(((ST_Transform((\"lay\".\"geographic\"), (4326)))::geography)"
+        ));
+    }
+
     #[test]
     fn test_arity_error() {
         assert!(try_eval(json!(["+", 21])).is_err());
@@ -6,6 +6,8 @@ use super::typing::TypeSpec;
 pub struct Criteria {
     pub name: String,
     pub data_type: TypeSpec,
+    /// The table (or alias) holding the column if it isn't stored in the search table
+    pub table: Option<String>,
     pub migration: Option<CriteriaMigration>,
 }
 
@@ -214,6 +216,7 @@ DROP FUNCTION IF EXISTS {update_trigger_function};
         let cache_columns = self
             .criterias
             .iter()
+            .filter(|c| c.migration.is_some())
             .map(|c| format!("\"{}\"", c.name))
             .collect_vec()
             .join(", ");
@@ -10,7 +10,7 @@ use super::typing::TypeSpec;
 /// and reliable to use (as opposed to multiple string interpolations)
 ///
 /// Also takes care of parenthesizing and providing the strings to interpolate.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum SqlQuery {
     Value(TypedAst),
     Call {
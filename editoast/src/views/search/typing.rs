@@ -48,6 +48,7 @@ pub enum AstType {
     Integer,
     Float,
     String,
+    Geometry,
 }
 
 /// Allows combining [AstType]s in order to express more complex types
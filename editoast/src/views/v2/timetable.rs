@@ -1,6 +1,12 @@
+mod simulation_job;
 pub mod stdcm;
+mod train_pattern;
+mod work_schedule_conflicts;
+
+pub use simulation_job::resume_stale_jobs;
 
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::ops::DerefMut as _;
 
 use actix_web::delete;
@@ -12,6 +18,7 @@ use actix_web::web::Json;
 use actix_web::web::Path;
 use actix_web::web::Query;
 use actix_web::HttpResponse;
+use chrono::Duration;
 use derivative::Derivative;
 use editoast_derive::EditoastError;
 use editoast_schemas::train_schedule::TrainScheduleBase;
@@ -25,6 +32,8 @@ use utoipa::ToSchema;
 use crate::core::v2::conflict_detection::Conflict;
 use crate::core::v2::conflict_detection::ConflictDetectionRequest;
 use crate::core::v2::conflict_detection::TrainRequirements;
+use crate::core::v2::pathfinding::PathfindingResult;
+use crate::core::v2::pathfinding::PathfindingResultSuccess;
 use crate::core::v2::simulation::SimulationResponse;
 use crate::core::AsCoreRequest;
 use crate::error::Result;
@@ -33,17 +42,20 @@ use crate::modelsv2::timetable::Timetable;
 use crate::modelsv2::timetable::TimetableWithTrains;
 use crate::modelsv2::train_schedule::TrainSchedule;
 use crate::modelsv2::train_schedule::TrainScheduleChangeset;
+use crate::modelsv2::work_schedules::WorkSchedule;
 use crate::modelsv2::DbConnectionPoolV2;
 use crate::modelsv2::Infra;
 use crate::views::pagination::PaginatedList;
 use crate::views::pagination::PaginationQueryParam;
 use crate::views::pagination::PaginationStats;
+use crate::views::v2::path::pathfinding_from_train;
 use crate::views::v2::train_schedule::train_simulation_batch;
 use crate::views::v2::train_schedule::TrainScheduleForm;
 use crate::views::v2::train_schedule::TrainScheduleResult;
 use crate::CoreClient;
 use crate::RedisClient;
 use crate::RetrieveBatch;
+use crate::RollingStockModel;
 
 crate::routes! {
     "/v2/timetable" => {
@@ -55,7 +67,9 @@ crate::routes! {
             put,
             conflicts,
             train_schedule,
+            simulation_job::routes(),
             stdcm::routes(),
+            train_pattern::routes(),
         }
     },
 }
@@ -64,7 +78,9 @@ editoast_common::schemas! {
     TimetableForm,
     TimetableResult,
     TimetableDetailedResult,
+    simulation_job::schemas(),
     stdcm::schemas(),
+    train_pattern::schemas(),
 }
 
 #[derive(Debug, Error, EditoastError)]
@@ -310,6 +326,8 @@ pub struct InfraIdQueryParam {
 }
 
 /// Retrieve the list of conflict of the timetable (invalid trains are ignored)
+///
+/// The conflicts between the trains and the work schedules are included.
 #[utoipa::path(
     tag = "timetablev2",
     params(TimetableIdParam, InfraIdQueryParam),
@@ -356,6 +374,7 @@ pub async fn conflicts(
 
     // 2. Build core request
     let mut trains_requirements = HashMap::with_capacity(trains.len());
+    let mut simulated_trains = Vec::with_capacity(trains.len());
     for (train, sim) in trains.into_iter().zip(simulations) {
         let final_output = match sim {
             SimulationResponse::Success { final_output, .. } => final_output,
@@ -369,15 +388,93 @@ pub async fn conflicts(
                 routing_requirements: final_output.routing_requirements,
             },
         );
+        simulated_trains.push((train, final_output.report_train));
     }
     let conflict_detection_request = ConflictDetectionRequest {
         trains_requirements,
     };
 
     // 3. Call core
-    let conflicts = conflict_detection_request.fetch(&core_client).await?;
+    let mut conflicts = conflict_detection_request
+        .fetch(&core_client)
+        .await?
+        .conflicts;
+
+    // 4. Detect the conflicts with the work schedules happening while the trains run
+    let time_span = simulated_trains
+        .iter()
+        .map(|(train, report)| {
+            let duration = report.times.last().copied().unwrap_or_default();
+            let end = train.start_time + Duration::milliseconds(duration as i64);
+            (train.start_time, end)
+        })
+        .reduce(|(start, end), (other_start, other_end)| {
+            (start.min(other_start), end.max(other_end))
+        });
+    let Some((start, end)) = time_span else {
+        return Ok(Json(conflicts));
+    };
+    let work_schedules =
+        WorkSchedule::list_in_time_window(conn, start.naive_utc(), end.naive_utc()).await?;
+    if work_schedules.is_empty() {
+        return Ok(Json(conflicts));
+    }
+    let rolling_stock_names: HashSet<_> = simulated_trains
+        .iter()
+        .map(|(train, _)| train.rolling_stock_name.clone())
+        .collect();
+    let (rolling_stocks, _): (Vec<_>, _) =
+        RollingStockModel::retrieve_batch(conn, rolling_stock_names).await?;
+    let rolling_stock_lengths: HashMap<_, _> = rolling_stocks
+        .into_iter()
+        .map(|rs| (rs.name, (rs.length * 1000.).round() as u64))
+        .collect();
+    let pathfinding_results =
+        simulated_trains
+            .iter()
+            .zip(db_pool.iter_conn())
+            .map(|((train, _), conn)| {
+                let redis_client = redis_client.clone();
+                let core_client = core_client.clone();
+                let infra = &infra;
+                async move {
+                    let mut redis_conn = redis_client.get_connection().await?;
+                    pathfinding_from_train(
+                        conn.await?.deref_mut(),
+                        &mut redis_conn,
+                        core_client,
+                        infra,
+                        train.clone(),
+                    )
+                    .await
+                }
+            });
+    let pathfinding_results = futures::future::try_join_all(pathfinding_results).await?;
+    for ((train, report), pathfinding_result) in simulated_trains.iter().zip(pathfinding_results) {
+        let PathfindingResult::Success(PathfindingResultSuccess {
+            track_section_ranges,
+            ..
+        }) = pathfinding_result
+        else {
+            continue;
+        };
+        // The rolling stock may have been deleted since the simulation
+        let Some(length) = rolling_stock_lengths
+            .get(&train.rolling_stock_name)
+            .copied()
+        else {
+            continue;
+        };
+        conflicts.extend(work_schedule_conflicts::detect_conflicts(
+            train,
+            length,
+            &track_section_ranges,
+            report,
+            &work_schedules,
+        ));
+    }
 
-    Ok(Json(conflicts.conflicts))
+    Ok(Json(conflicts))
 }
 
 #[cfg(test)]
@@ -0,0 +1,856 @@
+use std::collections::HashMap;
+use std::ops::DerefMut as _;
+use std::pin::pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::delete;
+use actix_web::get;
+use actix_web::post;
+use actix_web::web::Data;
+use actix_web::web::Json;
+use actix_web::web::Path;
+use actix_web::web::Query;
+use actix_web::HttpResponse;
+use chrono::NaiveDateTime;
+use chrono::Utc;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::AsyncConnection;
+use editoast_derive::EditoastError;
+use futures::future;
+use futures::future::Either;
+use itertools::Itertools as _;
+use serde::Deserialize;
+use serde::Serialize;
+use thiserror::Error;
+use utoipa::IntoParams;
+use utoipa::ToSchema;
+
+use super::InfraIdQueryParam;
+use super::TimetableError;
+use super::TimetableIdParam;
+use crate::error::InternalError;
+use crate::error::Result;
+use crate::modelsv2::prelude::*;
+use crate::modelsv2::simulation_job::SimulationJob;
+use crate::modelsv2::simulation_job::SimulationJobTrain;
+use crate::modelsv2::simulation_job::SimulationStatus;
+use crate::modelsv2::timetable::TimetableWithTrains;
+use crate::modelsv2::train_schedule::TrainSchedule;
+use crate::modelsv2::DbConnection;
+use crate::modelsv2::DbConnectionPoolV2;
+use crate::modelsv2::Infra;
+use crate::views::pagination::PaginatedList;
+use crate::views::pagination::PaginationQueryParam;
+use crate::views::pagination::PaginationStats;
+use crate::views::v2::train_schedule::train_simulation;
+use crate::views::v2::train_schedule::SimulationSummaryResult;
+use crate::CoreClient;
+use crate::RedisClient;
+
+crate::routes! {
+    "/simulation_job" => {
+        post,
+        "/{job_id}" => {
+            get,
+            delete,
+            results,
+        },
+    },
+}
+
+editoast_common::schemas! {
+    SimulationJobResult,
+    SimulationJobProgress,
+    SimulationJobTrainResult,
+}
+
+/// Number of trains a simulation job simulates concurrently
+const SIMULATION_CHUNK_SIZE: u64 = 32;
+
+/// Period at which running jobs refresh their heartbeat, and stale jobs are looked for
+const SIMULATION_JOB_HEARTBEAT_PERIOD: Duration = Duration::from_secs(10);
+
+/// Number of missed heartbeats after which a job is considered stopped
+const SIMULATION_JOB_MISSED_HEARTBEATS: u32 = 6;
+
+#[derive(Debug, Error, EditoastError)]
+#[editoast_error(base_id = "simulation_job")]
+enum SimulationJobError {
+    #[error("Simulation job '{simulation_job_id}', could not be found")]
+    #[editoast_error(status = 404)]
+    NotFound { simulation_job_id: i64 },
+}
+
+#[derive(IntoParams, Deserialize)]
+struct SimulationJobIdParam {
+    /// A simulation job ID
+    job_id: i64,
+}
+
+#[derive(Deserialize)]
+struct SimulationJobPathParam {
+    id: i64,
+    job_id: i64,
+}
+
+/// The number of trains of a simulation job, by status
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+struct SimulationJobProgress {
+    pending: u64,
+    done: u64,
+    failed: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+struct SimulationJobResult {
+    id: i64,
+    timetable_id: i64,
+    infra_id: i64,
+    creation_date: NaiveDateTime,
+    progress: SimulationJobProgress,
+}
+
+/// The simulation of a train by a simulation job
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+struct SimulationJobTrainResult {
+    train_schedule_id: i64,
+    status: SimulationStatus,
+    /// Missing while the train is pending, or if its simulation failed unexpectedly
+    summary: Option<SimulationSummaryResult>,
+}
+
+impl From<SimulationJobTrain> for SimulationJobTrainResult {
+    fn from(job_train: SimulationJobTrain) -> Self {
+        Self {
+            train_schedule_id: job_train.train_schedule_id,
+            status: job_train.status,
+            summary: job_train.summary,
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct SimulationJobTrainListResponse {
+    #[serde(flatten)]
+    stats: PaginationStats,
+    results: Vec<SimulationJobTrainResult>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+struct SimulationJobResultsQueryParam {
+    /// Only return the trains with this status
+    status: Option<SimulationStatus>,
+}
+
+async fn retrieve_job(
+    conn: &mut DbConnection,
+    timetable_id: i64,
+    simulation_job_id: i64,
+) -> Result<SimulationJob> {
+    let job = SimulationJob::retrieve_or_fail(conn, simulation_job_id, || {
+        SimulationJobError::NotFound { simulation_job_id }
+    })
+    .await?;
+    if job.timetable_id != timetable_id {
+        return Err(SimulationJobError::NotFound { simulation_job_id }.into());
+    }
+    Ok(job)
+}
+
+async fn job_result(conn: &mut DbConnection, job: SimulationJob) -> Result<SimulationJobResult> {
+    let job_id = job.id;
+    let count = |status: SimulationStatus| {
+        SelectionSettings::new()
+            .filter(move || SimulationJobTrain::JOB_ID.eq(job_id))
+            .filter(move || SimulationJobTrain::STATUS.eq(status))
+    };
+    let progress = SimulationJobProgress {
+        pending: SimulationJobTrain::count(conn, count(SimulationStatus::Pending)).await?,
+        done: SimulationJobTrain::count(conn, count(SimulationStatus::Done)).await?,
+        failed: SimulationJobTrain::count(conn, count(SimulationStatus::Failed)).await?,
+    };
+    Ok(SimulationJobResult {
+        id: job.id,
+        timetable_id: job.timetable_id,
+        infra_id: job.infra_id,
+        creation_date: job.creation_date,
+        progress,
+    })
+}
+
+/// Runs a job in the background
+fn spawn_job(
+    db_pool: Arc<DbConnectionPoolV2>,
+    redis_client: Arc<RedisClient>,
+    core_client: Arc<CoreClient>,
+    job: SimulationJob,
+) {
+    let job_id = job.id;
+    let runner = run_job(db_pool, redis_client, core_client, job);
+    tokio::spawn(async move {
+        if let Err(error) = runner.await {
+            tracing::error!(job_id, %error, "Simulation job stopped");
+        }
+    });
+}
+
+/// Resumes the jobs whose runner stopped, for instance because editoast was restarted
+///
+/// A job is stale when its heartbeat wasn't refreshed for [SIMULATION_JOB_MISSED_HEARTBEATS]
+/// periods. Stale jobs are looked for periodically, forever.
+pub async fn resume_stale_jobs(
+    db_pool: Arc<DbConnectionPoolV2>,
+    redis_client: Arc<RedisClient>,
+    core_client: Arc<CoreClient>,
+) {
+    let stale_after = chrono::Duration::from_std(
+        SIMULATION_JOB_HEARTBEAT_PERIOD * SIMULATION_JOB_MISSED_HEARTBEATS,
+    )
+    .expect("the stale job delay should fit in a chrono duration");
+    let mut interval = tokio::time::interval(SIMULATION_JOB_HEARTBEAT_PERIOD);
+    loop {
+        interval.tick().await;
+        let stale_before = Utc::now().naive_utc() - stale_after;
+        let claim = async {
+            SimulationJob::claim_stale(db_pool.get().await?.deref_mut(), stale_before).await
+        };
+        let jobs = match claim.await {
+            Ok(jobs) => jobs,
+            Err(error) => {
+                tracing::error!(%error, "Could not look for stale simulation jobs");
+                continue;
+            }
+        };
+        for job in jobs {
+            tracing::info!(job_id = job.id, "Resuming stale simulation job");
+            spawn_job(
+                db_pool.clone(),
+                redis_client.clone(),
+                core_client.clone(),
+                job,
+            );
+        }
+    }
+}
+
+/// Refreshes the heartbeat of a job until the job is deleted or the returned future is dropped
+async fn keep_alive(db_pool: Arc<DbConnectionPoolV2>, job_id: i64) {
+    let mut interval = tokio::time::interval(SIMULATION_JOB_HEARTBEAT_PERIOD);
+    loop {
+        interval.tick().await;
+        let beat = async { SimulationJob::beat(db_pool.get().await?.deref_mut(), job_id).await };
+        match beat.await {
+            Ok(true) => {}
+            Ok(false) => return,
+            Err(error) => tracing::warn!(job_id, %error, "Could not refresh the job heartbeat"),
+        }
+    }
+}
+
+/// Simulates the pending trains of a job, chunk by chunk
+///
+/// The summary of each train is persisted as soon as its chunk is simulated.
+/// The job stops when no train is pending anymore, including when the job gets deleted.
+/// While it runs, the heartbeat of the job is refreshed so that it isn't resumed by
+/// [resume_stale_jobs]. If the job stops because of an error, its remaining trains are marked
+/// as failed so that the job doesn't look like it is still running.
+async fn run_job(
+    db_pool: Arc<DbConnectionPoolV2>,
+    redis_client: Arc<RedisClient>,
+    core_client: Arc<CoreClient>,
+    job: SimulationJob,
+) -> Result<()> {
+    let job_id = job.id;
+    let simulation = pin!(simulate_pending_trains(
+        db_pool.clone(),
+        redis_client,
+        core_client,
+        job
+    ));
+    let heartbeat = pin!(keep_alive(db_pool.clone(), job_id));
+    let result = match future::select(simulation, heartbeat).await {
+        Either::Left((result, _)) => result,
+        // The job was deleted
+        Either::Right(((), _)) => Ok(()),
+    };
+    let Err(error) = result else {
+        return Ok(());
+    };
+    let fail_pending =
+        async { SimulationJobTrain::fail_pending(db_pool.get().await?.deref_mut(), job_id).await };
+    match fail_pending.await {
+        Ok(failed) => tracing::warn!(job_id, failed, "Pending trains marked as failed"),
+        Err(error) => tracing::error!(job_id, %error, "Could not mark pending trains as failed"),
+    }
+    Err(error)
+}
+
+async fn simulate_pending_trains(
+    db_pool: Arc<DbConnectionPoolV2>,
+    redis_client: Arc<RedisClient>,
+    core_client: Arc<CoreClient>,
+    job: SimulationJob,
+) -> Result<()> {
+    let infra_id = job.infra_id;
+    let infra = Infra::retrieve_or_fail(db_pool.get().await?.deref_mut(), infra_id, || {
+        TimetableError::InfraNotFound { infra_id }
+    })
+    .await?;
+    loop {
+        let job_id = job.id;
+        let pending: Vec<SimulationJobTrain> = SimulationJobTrain::list(
+            db_pool.get().await?.deref_mut(),
+            SelectionSettings::new()
+                .filter(move || SimulationJobTrain::JOB_ID.eq(job_id))
+                .filter(|| SimulationJobTrain::STATUS.eq(SimulationStatus::Pending))
+                .order_by(|| SimulationJobTrain::ID.asc())
+                .limit(SIMULATION_CHUNK_SIZE),
+        )
+        .await?;
+        if pending.is_empty() {
+            return Ok(());
+        }
+
+        let train_ids = pending.iter().map(|job_train| job_train.train_schedule_id);
+        let (trains, _): (Vec<TrainSchedule>, _) =
+            TrainSchedule::retrieve_batch(db_pool.get().await?.deref_mut(), train_ids).await?;
+        let trains: HashMap<_, _> = trains.into_iter().map(|train| (train.id, train)).collect();
+
+        let simulations = pending
+            .into_iter()
+            .zip(db_pool.iter_conn())
+            .map(|(job_train, conn)| {
+                let redis_client = redis_client.clone();
+                let core_client = core_client.clone();
+                let train = trains.get(&job_train.train_schedule_id);
+                let infra = &infra;
+                async move {
+                    let mut conn = conn.await?;
+                    // A train deleted in the meantime is deleted from the job as well
+                    let Some(train) = train else {
+                        return Ok(());
+                    };
+                    let summary = match train_simulation(
+                        conn.deref_mut(),
+                        redis_client,
+                        core_client,
+                        train,
+                        infra,
+                    )
+                    .await
+                    {
+                        Ok(simulation) => Some(SimulationSummaryResult::from(simulation)),
+                        Err(error) => {
+                            tracing::error!(train_id = train.id, %error, "Train simulation failed");
+                            None
+                        }
+                    };
+                    let status = match summary {
+                        Some(SimulationSummaryResult::Success { .. }) => SimulationStatus::Done,
+                        _ => SimulationStatus::Failed,
+                    };
+                    // The job may have been deleted in the meantime, in which case nothing is updated
+                    let _: Option<SimulationJobTrain> = SimulationJobTrain::changeset()
+                        .status(status)
+                        .summary(summary)
+                        .update(conn.deref_mut(), job_train.id)
+                        .await?;
+                    Ok::<_, InternalError>(())
+                }
+            });
+        futures::future::try_join_all(simulations).await?;
+    }
+}
+
+/// Start the simulation of all the trains of a timetable on an infra
+///
+/// The trains are simulated in the background: the returned job can be polled
+/// for progress, and its results fetched page by page as they are computed.
+#[utoipa::path(
+    tag = "timetablev2",
+    params(TimetableIdParam, InfraIdQueryParam),
+    responses(
+        (status = 200, description = "The started simulation job", body = SimulationJobResult),
+        (status = 404, description = "Timetable or infra not found"),
+    )
+)]
+#[post("")]
+async fn post(
+    db_pool: Data<DbConnectionPoolV2>,
+    redis_client: Data<RedisClient>,
+    core_client: Data<CoreClient>,
+    timetable_id: Path<TimetableIdParam>,
+    query: Query<InfraIdQueryParam>,
+) -> Result<Json<SimulationJobResult>> {
+    let timetable_id = timetable_id.id;
+    let infra_id = query.into_inner().infra_id;
+
+    let job = db_pool
+        .get()
+        .await?
+        .transaction::<_, InternalError, _>(|conn| {
+            async move {
+                let timetable = TimetableWithTrains::retrieve_or_fail(conn, timetable_id, || {
+                    TimetableError::NotFound { timetable_id }
+                })
+                .await?;
+                Infra::retrieve_or_fail(conn, infra_id, || TimetableError::InfraNotFound {
+                    infra_id,
+                })
+                .await?;
+                let job = SimulationJob::changeset()
+                    .timetable_id(timetable_id)
+                    .infra_id(infra_id)
+                    .creation_date(Utc::now().naive_utc())
+                    .heartbeat(Utc::now().naive_utc())
+                    .create(conn)
+                    .await?;
+                let job_trains = timetable.train_ids.into_iter().map(|train_id| {
+                    SimulationJobTrain::changeset()
+                        .job_id(job.id)
+                        .train_schedule_id(train_id)
+                        .status(SimulationStatus::Pending)
+                        .summary(None)
+                });
+                let _: Vec<_> = SimulationJobTrain::create_batch(conn, job_trains).await?;
+                Ok(job)
+            }
+            .scope_boxed()
+        })
+        .await?;
+
+    spawn_job(
+        db_pool.clone().into_inner(),
+        redis_client.into_inner(),
+        core_client.into_inner(),
+        job.clone(),
+    );
+
+    Ok(Json(
+        job_result(db_pool.get().await?.deref_mut(), job).await?,
+    ))
+}
+
+/// Retrieve the progress of a simulation job
+#[utoipa::path(
+    tag = "timetablev2",
+    params(TimetableIdParam, SimulationJobIdParam),
+    responses(
+        (status = 200, description = "The simulation job", body = SimulationJobResult),
+        (status = 404, description = "Simulation job not found"),
+    )
+)]
+#[get("")]
+async fn get(
+    db_pool: Data<DbConnectionPoolV2>,
+    path: Path<SimulationJobPathParam>,
+) -> Result<Json<SimulationJobResult>> {
+    let SimulationJobPathParam { id, job_id } = path.into_inner();
+    let conn = &mut db_pool.get().await?;
+    let job = retrieve_job(conn, id, job_id).await?;
+    Ok(Json(job_result(conn, job).await?))
+}
+
+/// Retrieve the paginated simulations of the trains of a job, by train id
+#[utoipa::path(
+    tag = "timetablev2",
+    params(TimetableIdParam, SimulationJobIdParam, PaginationQueryParam, SimulationJobResultsQueryParam),
+    responses(
+        (status = 200, description = "The simulations of the trains", body = inline(SimulationJobTrainListResponse)),
+        (status = 404, description = "Simulation job not found"),
+    )
+)]
+#[get("/results")]
+async fn results(
+    db_pool: Data<DbConnectionPoolV2>,
+    path: Path<SimulationJobPathParam>,
+    pagination_params: Query<PaginationQueryParam>,
+    Query(SimulationJobResultsQueryParam { status }): Query<SimulationJobResultsQueryParam>,
+) -> Result<Json<SimulationJobTrainListResponse>> {
+    let SimulationJobPathParam { id, job_id } = path.into_inner();
+    let conn = &mut db_pool.get().await?;
+    retrieve_job(conn, id, job_id).await?;
+
+    let mut settings = pagination_params
+        .validate(1000)?
+        .warn_page_size(100)
+        .into_selection_settings()
+        .filter(move || SimulationJobTrain::JOB_ID.eq(job_id))
+        .order_by(|| SimulationJobTrain::TRAIN_SCHEDULE_ID.asc());
+    if let Some(status) = status {
+        settings = settings.filter(move || SimulationJobTrain::STATUS.eq(status));
+    }
+    let (results, stats) = SimulationJobTrain::list_paginated(conn, settings).await?;
+    Ok(Json(SimulationJobTrainListResponse {
+        stats,
+        results: results.into_iter().map_into().collect(),
+    }))
+}
+
+/// Delete a simulation job and its results, stopping it if it is still running
+#[utoipa::path(
+    tag = "timetablev2",
+    params(TimetableIdParam, SimulationJobIdParam),
+    responses(
+        (status = 204, description = "The simulation job has been deleted"),
+        (status = 404, description = "Simulation job not found"),
+    )
+)]
+#[delete("")]
+async fn delete(
+    db_pool: Data<DbConnectionPoolV2>,
+    path: Path<SimulationJobPathParam>,
+) -> Result<HttpResponse> {
+    let SimulationJobPathParam { id, job_id } = path.into_inner();
+    let conn = &mut db_pool.get().await?;
+    retrieve_job(conn, id, job_id).await?;
+    // The results are deleted in cascade
+    SimulationJob::delete_static_or_fail(conn, job_id, || SimulationJobError::NotFound {
+        simulation_job_id: job_id,
+    })
+    .await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::DerefMut as _;
+
+    use actix_web::test::TestRequest;
+    use pretty_assertions::assert_eq;
+    use reqwest::StatusCode;
+    use rstest::rstest;
+
+    use editoast_schemas::infra::Direction;
+    use editoast_schemas::infra::TrackSection;
+    use editoast_schemas::train_schedule::TrainScheduleBase;
+    use serde_json::json;
+
+    use super::*;
+    use crate::client::RedisConfig;
+    use crate::core::mocking::MockingClient;
+    use crate::core::v2::pathfinding::PathfindingResult;
+    use crate::core::v2::pathfinding::PathfindingResultSuccess;
+    use crate::core::v2::pathfinding::TrackRange;
+    use crate::core::v2::simulation::CompleteReportTrain;
+    use crate::core::v2::simulation::ElectricalProfiles;
+    use crate::core::v2::simulation::Mrsp;
+    use crate::core::v2::simulation::ReportTrain;
+    use crate::core::v2::simulation::SimulationResponse;
+    use crate::modelsv2::fixtures::create_empty_infra;
+    use crate::modelsv2::fixtures::create_fast_rolling_stock;
+    use crate::modelsv2::fixtures::create_infra_object;
+    use crate::modelsv2::fixtures::create_simple_train_schedule;
+    use crate::modelsv2::fixtures::create_timetable;
+    use crate::modelsv2::train_schedule::TrainScheduleChangeset;
+    use crate::views::test_app::TestAppBuilder;
+    use crate::views::v2::train_schedule::TrainScheduleForm;
+
+    #[rstest]
+    async fn simulation_job_lifecycle() {
+        let app = TestAppBuilder::default_app();
+        let pool = app.db_pool();
+        let timetable = create_timetable(pool.get_ok().deref_mut()).await;
+        let infra = create_empty_infra(pool.get_ok().deref_mut()).await;
+
+        let request = TestRequest::post()
+            .uri(&format!(
+                "/v2/timetable/{}/simulation_job?infra_id={}",
+                timetable.id, infra.id
+            ))
+            .to_request();
+        let job: SimulationJobResult = app.fetch(request).assert_status(StatusCode::OK).json_into();
+        assert_eq!(job.infra_id, infra.id);
+        assert_eq!(job.progress, SimulationJobProgress::default());
+
+        let url = format!("/v2/timetable/{}/simulation_job/{}", timetable.id, job.id);
+        let request = TestRequest::delete().uri(&url).to_request();
+        app.fetch(request).assert_status(StatusCode::NO_CONTENT);
+
+        let request = TestRequest::get().uri(&url).to_request();
+        app.fetch(request).assert_status(StatusCode::NOT_FOUND);
+    }
+
+    #[rstest]
+    async fn simulation_job_infra_not_found() {
+        let app = TestAppBuilder::default_app();
+        let pool = app.db_pool();
+        let timetable = create_timetable(pool.get_ok().deref_mut()).await;
+
+        let request = TestRequest::post()
+            .uri(&format!(
+                "/v2/timetable/{}/simulation_job?infra_id=0",
+                timetable.id
+            ))
+            .to_request();
+        app.fetch(request).assert_status(StatusCode::NOT_FOUND);
+    }
+
+    #[rstest]
+    async fn simulation_job_progress_and_results() {
+        let app = TestAppBuilder::default_app();
+        let pool = app.db_pool();
+        let timetable = create_timetable(pool.get_ok().deref_mut()).await;
+        let infra = create_empty_infra(pool.get_ok().deref_mut()).await;
+        let done_train =
+            create_simple_train_schedule(pool.get_ok().deref_mut(), timetable.id).await;
+        let pending_train =
+            create_simple_train_schedule(pool.get_ok().deref_mut(), timetable.id).await;
+
+        // The job is created without being run
+        let job = SimulationJob::changeset()
+            .timetable_id(timetable.id)
+            .infra_id(infra.id)
+            .creation_date(Utc::now().naive_utc())
+            .heartbeat(Utc::now().naive_utc())
+            .create(pool.get_ok().deref_mut())
+            .await
+            .unwrap();
+        let job_trains = [
+            (done_train.id, SimulationStatus::Done),
+            (pending_train.id, SimulationStatus::Pending),
+        ]
+        .map(|(train_id, status)| {
+            let summary =
+                (status == SimulationStatus::Done).then_some(SimulationSummaryResult::Success {
+                    length: 1000,
+                    time: 60000,
+                    energy_consumption: 0.,
+                    scheduled_points_honored: true,
+                });
+            SimulationJobTrain::changeset()
+                .job_id(job.id)
+                .train_schedule_id(train_id)
+                .status(status)
+                .summary(summary)
+        });
+        let _: Vec<_> = SimulationJobTrain::create_batch(pool.get_ok().deref_mut(), job_trains)
+            .await
+            .unwrap();
+
+        let url = format!("/v2/timetable/{}/simulation_job/{}", timetable.id, job.id);
+        let request = TestRequest::get().uri(&url).to_request();
+        let result: SimulationJobResult =
+            app.fetch(request).assert_status(StatusCode::OK).json_into();
+        assert_eq!(
+            result.progress,
+            SimulationJobProgress {
+                pending: 1,
+                done: 1,
+                failed: 0,
+            }
+        );
+
+        let request = TestRequest::get()
+            .uri(&format!("{url}/results?status=done"))
+            .to_request();
+        let response: serde_json::Value =
+            app.fetch(request).assert_status(StatusCode::OK).json_into();
+        let results: Vec<SimulationJobTrainResult> =
+            serde_json::from_value(response["results"].clone()).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].train_schedule_id, done_train.id);
+        assert!(matches!(
+            results[0].summary,
+            Some(SimulationSummaryResult::Success { length: 1000, .. })
+        ));
+    }
+
+    /// Mocks a core computing a 1km path on the track section `T` and its simulation
+    fn mocked_core() -> MockingClient {
+        let mut core = MockingClient::new();
+        let pathfinding = PathfindingResult::Success(PathfindingResultSuccess {
+            blocks: vec![],
+            routes: vec![],
+            track_section_ranges: vec![TrackRange::new("T", 0, 1_000_000, Direction::StartToStop)],
+            length: 1_000_000,
+            path_items_positions: vec![0, 1_000_000],
+        });
+        core.stub("/v2/pathfinding/blocks")
+            .method(reqwest::Method::POST)
+            .response(StatusCode::OK)
+            .body(serde_json::to_string(&pathfinding).unwrap())
+            .finish();
+        let report_train = ReportTrain {
+            positions: vec![0, 1_000_000],
+            times: vec![0, 60_000],
+            speeds: vec![0., 30.],
+            energy_consumption: 0.,
+            scheduled_points_honored: true,
+        };
+        let simulation = SimulationResponse::Success {
+            base: report_train.clone(),
+            provisional: report_train.clone(),
+            final_output: CompleteReportTrain {
+                report_train,
+                signal_sightings: vec![],
+                zone_updates: vec![],
+                spacing_requirements: vec![],
+                routing_requirements: vec![],
+            },
+            mrsp: Mrsp::default(),
+            electrical_profiles: ElectricalProfiles {
+                boundaries: vec![],
+                values: vec![],
+            },
+        };
+        core.stub("/v2/standalone_simulation")
+            .method(reqwest::Method::POST)
+            .response(StatusCode::OK)
+            .body(serde_json::to_string(&simulation).unwrap())
+            .finish();
+        core
+    }
+
+    #[rstest]
+    async fn simulation_job_run() {
+        let app = TestAppBuilder::new()
+            .db_pool(DbConnectionPoolV2::for_tests())
+            .core_client(mocked_core().into())
+            .build();
+        let pool = app.db_pool();
+        let timetable = create_timetable(pool.get_ok().deref_mut()).await;
+        let infra = create_empty_infra(pool.get_ok().deref_mut()).await;
+        create_infra_object(
+            pool.get_ok().deref_mut(),
+            infra.id,
+            TrackSection {
+                id: "T".into(),
+                length: 1000.,
+                ..Default::default()
+            },
+        )
+        .await;
+        let rolling_stock =
+            create_fast_rolling_stock(pool.get_ok().deref_mut(), "simulation_job_run").await;
+
+        // A train running on the track section, and another whose rolling stock doesn't exist
+        let train_schedule: TrainScheduleBase = serde_json::from_value(json!({
+            "train_name": "simulated",
+            "rolling_stock_name": rolling_stock.name,
+            "start_time": "2024-07-02T08:00:00Z",
+            "path": [
+                { "id": "a", "track": "T", "offset": 0 },
+                { "id": "b", "track": "T", "offset": 1_000_000 },
+            ],
+            "constraint_distribution": "STANDARD",
+        }))
+        .unwrap();
+        let changeset: TrainScheduleChangeset = TrainScheduleForm {
+            timetable_id: Some(timetable.id),
+            train_schedule,
+        }
+        .into();
+        let done_train = changeset.create(pool.get_ok().deref_mut()).await.unwrap();
+        let failed_train =
+            create_simple_train_schedule(pool.get_ok().deref_mut(), timetable.id).await;
+
+        let job = SimulationJob::changeset()
+            .timetable_id(timetable.id)
+            .infra_id(infra.id)
+            .creation_date(Utc::now().naive_utc())
+            .heartbeat(Utc::now().naive_utc())
+            .create(pool.get_ok().deref_mut())
+            .await
+            .unwrap();
+        let job_trains = [done_train.id, failed_train.id].map(|train_id| {
+            SimulationJobTrain::changeset()
+                .job_id(job.id)
+                .train_schedule_id(train_id)
+                .status(SimulationStatus::Pending)
+                .summary(None)
+        });
+        let _: Vec<_> = SimulationJobTrain::create_batch(pool.get_ok().deref_mut(), job_trains)
+            .await
+            .unwrap();
+
+        let redis_client = RedisClient::new(RedisConfig::default()).unwrap();
+        run_job(
+            pool.clone(),
+            Arc::new(redis_client),
+            app.core_client(),
+            job.clone(),
+        )
+        .await
+        .unwrap();
+
+        let job_id = job.id;
+        let job_trains: HashMap<_, _> = SimulationJobTrain::list(
+            pool.get_ok().deref_mut(),
+            SelectionSettings::new().filter(move || SimulationJobTrain::JOB_ID.eq(job_id)),
+        )
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|job_train| (job_train.train_schedule_id, job_train))
+        .collect();
+        let done = &job_trains[&done_train.id];
+        assert_eq!(done.status, SimulationStatus::Done);
+        assert!(matches!(
+            done.summary,
+            Some(SimulationSummaryResult::Success {
+                length: 1_000_000,
+                time: 60_000,
+                ..
+            })
+        ));
+        let failed = &job_trains[&failed_train.id];
+        assert_eq!(failed.status, SimulationStatus::Failed);
+        assert!(matches!(
+            failed.summary,
+            Some(SimulationSummaryResult::RollingStockNotFound { .. })
+        ));
+    }
+
+    #[rstest]
+    async fn stale_simulation_jobs_are_claimed_once() {
+        let app = TestAppBuilder::default_app();
+        let pool = app.db_pool();
+        let timetable = create_timetable(pool.get_ok().deref_mut()).await;
+        let infra = create_empty_infra(pool.get_ok().deref_mut()).await;
+        let train = create_simple_train_schedule(pool.get_ok().deref_mut(), timetable.id).await;
+
+        let now = Utc::now().naive_utc();
+        let stale_job = SimulationJob::changeset()
+            .timetable_id(timetable.id)
+            .infra_id(infra.id)
+            .creation_date(now)
+            .heartbeat(now - chrono::Duration::hours(1))
+            .create(pool.get_ok().deref_mut())
+            .await
+            .unwrap();
+        let running_job = SimulationJob::changeset()
+            .timetable_id(timetable.id)
+            .infra_id(infra.id)
+            .creation_date(now)
+            .heartbeat(now)
+            .create(pool.get_ok().deref_mut())
+            .await
+            .unwrap();
+        let job_trains = [stale_job.id, running_job.id].map(|job_id| {
+            SimulationJobTrain::changeset()
+                .job_id(job_id)
+                .train_schedule_id(train.id)
+                .status(SimulationStatus::Pending)
+                .summary(None)
+        });
+        let _: Vec<_> = SimulationJobTrain::create_batch(pool.get_ok().deref_mut(), job_trains)
+            .await
+            .unwrap();
+
+        let stale_before = now - chrono::Duration::minutes(1);
+        let claimed = SimulationJob::claim_stale(pool.get_ok().deref_mut(), stale_before)
+            .await
+            .unwrap();
+        let claimed_ids = claimed.iter().map(|job| job.id).collect::<Vec<_>>();
+        assert!(claimed_ids.contains(&stale_job.id));
+        assert!(!claimed_ids.contains(&running_job.id));
+
+        // The heartbeat of the claimed job was refreshed
+        let claimed = SimulationJob::claim_stale(pool.get_ok().deref_mut(), stale_before)
+            .await
+            .unwrap();
+        assert!(claimed.iter().all(|job| job.id != stale_job.id));
+    }
+}
@@ -279,6 +279,7 @@ async fn get_maximum_run_time(
         speed_limit_tag: data.speed_limit_tags.clone(),
         power_restrictions: vec![],
         options: Default::default(),
+        pattern_id: None,
     };
 
     let conn = &mut db_pool.clone().get().await?;
@@ -0,0 +1,468 @@
+use std::collections::HashSet;
+
+use actix_web::delete;
+use actix_web::get;
+use actix_web::post;
+use actix_web::put;
+use actix_web::web::Data;
+use actix_web::web::Json;
+use actix_web::web::Path;
+use actix_web::HttpResponse;
+use chrono::DateTime;
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::AsyncConnection;
+use diesel_async::RunQueryDsl;
+use editoast_derive::EditoastError;
+use editoast_schemas::train_schedule::Recurrence;
+use editoast_schemas::train_schedule::TrainScheduleBase;
+use serde::Deserialize;
+use serde::Serialize;
+use thiserror::Error;
+use utoipa::IntoParams;
+use utoipa::ToSchema;
+
+use super::TimetableError;
+use super::TimetableIdParam;
+use crate::error::InternalError;
+use crate::error::Result;
+use crate::modelsv2::prelude::*;
+use crate::modelsv2::timetable::Timetable;
+use crate::modelsv2::train_pattern::TrainPattern;
+use crate::modelsv2::train_schedule::TrainSchedule;
+use crate::modelsv2::train_schedule::TrainScheduleChangeset;
+use crate::modelsv2::DbConnection;
+use crate::modelsv2::DbConnectionPoolV2;
+use crate::views::v2::train_schedule::TrainScheduleForm;
+
+crate::routes! {
+    "/train_pattern" => {
+        post,
+        list,
+        "/{pattern_id}" => {
+            get,
+            put,
+            delete,
+        },
+    },
+}
+
+editoast_common::schemas! {
+    TrainPatternForm,
+    TrainPatternResult,
+}
+
+/// Maximum number of train schedules a train pattern can be materialized into
+const MAX_OCCURRENCES: usize = 5000;
+
+#[derive(Debug, Error, EditoastError)]
+#[editoast_error(base_id = "train_pattern")]
+enum TrainPatternError {
+    #[error("Train pattern '{train_pattern_id}', could not be found")]
+    #[editoast_error(status = 404)]
+    NotFound { train_pattern_id: i64 },
+    #[error("The train pattern would have more than {max} occurrences")]
+    #[editoast_error(status = 400)]
+    TooManyOccurrences { max: usize },
+}
+
+#[derive(IntoParams, Deserialize)]
+struct TrainPatternIdParam {
+    /// A train pattern ID
+    pattern_id: i64,
+}
+
+#[derive(Deserialize)]
+struct TrainPatternPathParam {
+    id: i64,
+    pattern_id: i64,
+}
+
+/// A periodic train, made of a train schedule template and its recurrence
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+struct TrainPatternForm {
+    /// The `start_time` of the template is replaced by the departure time of each occurrence
+    template: TrainScheduleBase,
+    recurrence: Recurrence,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+struct TrainPatternResult {
+    id: i64,
+    timetable_id: i64,
+    template: TrainScheduleBase,
+    recurrence: Recurrence,
+    /// The train schedules materializing the pattern, by departure time
+    train_ids: Vec<i64>,
+}
+
+impl TrainPatternResult {
+    fn new(pattern: TrainPattern, train_ids: Vec<i64>) -> Self {
+        Self {
+            id: pattern.id,
+            timetable_id: pattern.timetable_id,
+            template: pattern.template,
+            recurrence: pattern.recurrence,
+            train_ids,
+        }
+    }
+}
+
+/// Creates, updates and deletes the occurrences of a train pattern to match its template and recurrence
+///
+/// The occurrences are matched by departure time: the ones still running keep their id
+/// and are overwritten with the template.
+async fn materialize(conn: &mut DbConnection, pattern: &TrainPattern) -> Result<Vec<i64>> {
+    use crate::tables::train_schedule_v2::dsl;
+
+    let departures: Vec<DateTime<Utc>> = pattern
+        .recurrence
+        .departures()
+        .take(MAX_OCCURRENCES + 1)
+        .collect();
+    if departures.len() > MAX_OCCURRENCES {
+        return Err(TrainPatternError::TooManyOccurrences {
+            max: MAX_OCCURRENCES,
+        }
+        .into());
+    }
+    let template = || -> TrainScheduleChangeset {
+        TrainScheduleForm {
+            timetable_id: Some(pattern.timetable_id),
+            train_schedule: pattern.template.clone(),
+        }
+        .into()
+    };
+
+    // Remove the occurrences that no longer run
+    diesel::delete(
+        dsl::train_schedule_v2
+            .filter(dsl::pattern_id.eq(pattern.id))
+            .filter(dsl::start_time.ne_all(&departures)),
+    )
+    .execute(conn)
+    .await?;
+
+    // Apply the template to the remaining ones, keeping their departure time
+    let kept: HashSet<DateTime<Utc>> =
+        diesel::update(dsl::train_schedule_v2.filter(dsl::pattern_id.eq(pattern.id)))
+            .set(template().flat_start_time(None))
+            .returning(dsl::start_time)
+            .get_results(conn)
+            .await?
+            .into_iter()
+            .collect();
+
+    // Create the missing ones
+    let changesets: Vec<_> = departures
+        .iter()
+        .filter(|departure| !kept.contains(departure))
+        .map(|departure| {
+            template()
+                .start_time(*departure)
+                .pattern_id(Some(pattern.id))
+        })
+        .collect();
+    let _: Vec<_> = TrainSchedule::create_batch(conn, changesets).await?;
+
+    let mut train_ids = TrainPattern::occurrence_ids(conn, &[pattern.id]).await?;
+    Ok(train_ids.remove(&pattern.id).unwrap_or_default())
+}
+
+async fn retrieve_pattern(
+    conn: &mut DbConnection,
+    timetable_id: i64,
+    train_pattern_id: i64,
+) -> Result<TrainPattern> {
+    let pattern = TrainPattern::retrieve_or_fail(conn, train_pattern_id, || {
+        TrainPatternError::NotFound { train_pattern_id }
+    })
+    .await?;
+    if pattern.timetable_id != timetable_id {
+        return Err(TrainPatternError::NotFound { train_pattern_id }.into());
+    }
+    Ok(pattern)
+}
+
+/// Create a train pattern and the train schedules of its occurrences
+#[utoipa::path(
+    tag = "timetablev2,train_schedulev2",
+    params(TimetableIdParam),
+    request_body = TrainPatternForm,
+    responses(
+        (status = 200, description = "The created train pattern", body = TrainPatternResult),
+        (status = 404, description = "Timetable not found"),
+    )
+)]
+#[post("")]
+async fn post(
+    db_pool: Data<DbConnectionPoolV2>,
+    timetable_id: Path<TimetableIdParam>,
+    data: Json<TrainPatternForm>,
+) -> Result<Json<TrainPatternResult>> {
+    let timetable_id = timetable_id.id;
+    let TrainPatternForm {
+        template,
+        recurrence,
+    } = data.into_inner();
+
+    let result = db_pool
+        .get()
+        .await?
+        .transaction::<_, InternalError, _>(|conn| {
+            async move {
+                Timetable::retrieve_or_fail(conn, timetable_id, || TimetableError::NotFound {
+                    timetable_id,
+                })
+                .await?;
+                let pattern = TrainPattern::changeset()
+                    .timetable_id(timetable_id)
+                    .template(template)
+                    .recurrence(recurrence)
+                    .create(conn)
+                    .await?;
+                let train_ids = materialize(conn, &pattern).await?;
+                Ok(TrainPatternResult::new(pattern, train_ids))
+            }
+            .scope_boxed()
+        })
+        .await?;
+    Ok(Json(result))
+}
+
+/// Retrieve the train patterns of a timetable
+#[utoipa::path(
+    tag = "timetablev2,train_schedulev2",
+    params(TimetableIdParam),
+    responses(
+        (status = 200, description = "The train patterns of the timetable", body = Vec<TrainPatternResult>),
+        (status = 404, description = "Timetable not found"),
+    )
+)]
+#[get("")]
+async fn list(
+    db_pool: Data<DbConnectionPoolV2>,
+    timetable_id: Path<TimetableIdParam>,
+) -> Result<Json<Vec<TrainPatternResult>>> {
+    let timetable_id = timetable_id.id;
+    let conn = &mut db_pool.get().await?;
+    Timetable::retrieve_or_fail(conn, timetable_id, || TimetableError::NotFound {
+        timetable_id,
+    })
+    .await?;
+
+    let patterns: Vec<TrainPattern> = TrainPattern::list(
+        conn,
+        SelectionSettings::new()
+            .filter(move || TrainPattern::TIMETABLE_ID.eq(timetable_id))
+            .order_by(|| TrainPattern::ID.asc()),
+    )
+    .await?;
+    let pattern_ids: Vec<_> = patterns.iter().map(|pattern| pattern.id).collect();
+    let mut train_ids = TrainPattern::occurrence_ids(conn, &pattern_ids).await?;
+    Ok(Json(
+        patterns
+            .into_iter()
+            .map(|pattern| {
+                let ids = train_ids.remove(&pattern.id).unwrap_or_default();
+                TrainPatternResult::new(pattern, ids)
+            })
+            .collect(),
+    ))
+}
+
+/// Retrieve a train pattern
+#[utoipa::path(
+    tag = "timetablev2,train_schedulev2",
+    params(TimetableIdParam, TrainPatternIdParam),
+    responses(
+        (status = 200, description = "The train pattern", body = TrainPatternResult),
+        (status = 404, description = "Train pattern not found"),
+    )
+)]
+#[get("")]
+async fn get(
+    db_pool: Data<DbConnectionPoolV2>,
+    path: Path<TrainPatternPathParam>,
+) -> Result<Json<TrainPatternResult>> {
+    let TrainPatternPathParam { id, pattern_id } = path.into_inner();
+    let conn = &mut db_pool.get().await?;
+    let pattern = retrieve_pattern(conn, id, pattern_id).await?;
+    let mut train_ids = TrainPattern::occurrence_ids(conn, &[pattern_id]).await?;
+    Ok(Json(TrainPatternResult::new(
+        pattern,
+        train_ids.remove(&pattern_id).unwrap_or_default(),
+    )))
+}
+
+/// Update a train pattern and re-materialize its occurrences
+///
+/// The occurrences departing at the same time as before keep their id, and are
+/// overwritten by the template: their own editions are lost.
+#[utoipa::path(
+    tag = "timetablev2,train_schedulev2",
+    params(TimetableIdParam, TrainPatternIdParam),
+    request_body = TrainPatternForm,
+    responses(
+        (status = 200, description = "The updated train pattern", body = TrainPatternResult),
+        (status = 404, description = "Train pattern not found"),
+    )
+)]
+#[put("")]
+async fn put(
+    db_pool: Data<DbConnectionPoolV2>,
+    path: Path<TrainPatternPathParam>,
+    data: Json<TrainPatternForm>,
+) -> Result<Json<TrainPatternResult>> {
+    let TrainPatternPathParam { id, pattern_id } = path.into_inner();
+    let TrainPatternForm {
+        template,
+        recurrence,
+    } = data.into_inner();
+
+    let result = db_pool
+        .get()
+        .await?
+        .transaction::<_, InternalError, _>(|conn| {
+            async move {
+                retrieve_pattern(conn, id, pattern_id).await?;
+                let pattern = TrainPattern::changeset()
+                    .template(template)
+                    .recurrence(recurrence)
+                    .update_or_fail(conn, pattern_id, || TrainPatternError::NotFound {
+                        train_pattern_id: pattern_id,
+                    })
+                    .await?;
+                let train_ids = materialize(conn, &pattern).await?;
+                Ok(TrainPatternResult::new(pattern, train_ids))
+            }
+            .scope_boxed()
+        })
+        .await?;
+    Ok(Json(result))
+}
+
+/// Delete a train pattern and all its occurrences
+#[utoipa::path(
+    tag = "timetablev2,train_schedulev2",
+    params(TimetableIdParam, TrainPatternIdParam),
+    responses(
+        (status = 204, description = "The train pattern and its occurrences have been deleted"),
+        (status = 404, description = "Train pattern not found"),
+    )
+)]
+#[delete("")]
+async fn delete(
+    db_pool: Data<DbConnectionPoolV2>,
+    path: Path<TrainPatternPathParam>,
+) -> Result<HttpResponse> {
+    let TrainPatternPathParam { id, pattern_id } = path.into_inner();
+    let conn = &mut db_pool.get().await?;
+    retrieve_pattern(conn, id, pattern_id).await?;
+    // The occurrences are deleted in cascade
+    TrainPattern::delete_static_or_fail(conn, pattern_id, || TrainPatternError::NotFound {
+        train_pattern_id: pattern_id,
+    })
+    .await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::DerefMut as _;
+
+    use actix_web::test::TestRequest;
+    use pretty_assertions::assert_eq;
+    use reqwest::StatusCode;
+    use rstest::rstest;
+    use serde_json::json;
+
+    use super::*;
+    use crate::modelsv2::fixtures::create_timetable;
+    use crate::modelsv2::fixtures::simple_train_schedule_base;
+    use crate::views::test_app::TestAppBuilder;
+
+    fn pattern_form(last_departure: &str) -> serde_json::Value {
+        json!({
+            "template": simple_train_schedule_base(),
+            "recurrence": {
+                "interval": "PT30M",
+                "first_departure": "2024-06-28T08:00:00Z",
+                "last_departure": last_departure,
+            },
+        })
+    }
+
+    #[rstest]
+    async fn train_pattern_materialization() {
+        let app = TestAppBuilder::default_app();
+        let pool = app.db_pool();
+        let timetable = create_timetable(pool.get_ok().deref_mut()).await;
+
+        let request = TestRequest::post()
+            .uri(&format!("/v2/timetable/{}/train_pattern", timetable.id))
+            .set_json(pattern_form("2024-06-28T10:00:00Z"))
+            .to_request();
+        let pattern: TrainPatternResult =
+            app.fetch(request).assert_status(StatusCode::OK).json_into();
+        assert_eq!(pattern.train_ids.len(), 5);
+
+        let (trains, _): (Vec<TrainSchedule>, _) =
+            TrainSchedule::retrieve_batch(pool.get_ok().deref_mut(), pattern.train_ids.clone())
+                .await
+                .unwrap();
+        assert!(trains
+            .iter()
+            .all(|train| train.pattern_id == Some(pattern.id)));
+
+        // Shorten the service and rename the train
+        let mut form = pattern_form("2024-06-28T09:00:00Z");
+        form["template"]["train_name"] = json!("renamed");
+        let request = TestRequest::put()
+            .uri(&format!(
+                "/v2/timetable/{}/train_pattern/{}",
+                timetable.id, pattern.id
+            ))
+            .set_json(form)
+            .to_request();
+        let updated: TrainPatternResult =
+            app.fetch(request).assert_status(StatusCode::OK).json_into();
+        assert_eq!(updated.train_ids, pattern.train_ids[..3]);
+
+        let (trains, _): (Vec<TrainSchedule>, _) =
+            TrainSchedule::retrieve_batch(pool.get_ok().deref_mut(), pattern.train_ids)
+                .await
+                .unwrap();
+        assert_eq!(trains.len(), 3);
+        assert!(trains.iter().all(|train| train.train_name == "renamed"));
+    }
+
+    #[rstest]
+    async fn train_pattern_delete() {
+        let app = TestAppBuilder::default_app();
+        let pool = app.db_pool();
+        let timetable = create_timetable(pool.get_ok().deref_mut()).await;
+
+        let request = TestRequest::post()
+            .uri(&format!("/v2/timetable/{}/train_pattern", timetable.id))
+            .set_json(pattern_form("2024-06-28T10:00:00Z"))
+            .to_request();
+        let pattern: TrainPatternResult =
+            app.fetch(request).assert_status(StatusCode::OK).json_into();
+
+        let request = TestRequest::delete()
+            .uri(&format!(
+                "/v2/timetable/{}/train_pattern/{}",
+                timetable.id, pattern.id
+            ))
+            .to_request();
+        app.fetch(request).assert_status(StatusCode::NO_CONTENT);
+
+        let (trains, _): (Vec<TrainSchedule>, _) =
+            TrainSchedule::retrieve_batch(pool.get_ok().deref_mut(), pattern.train_ids)
+                .await
+                .unwrap();
+        assert!(trains.is_empty());
+    }
+}
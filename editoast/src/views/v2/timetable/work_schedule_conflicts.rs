@@ -0,0 +1,187 @@
+use chrono::Duration;
+use chrono::TimeZone;
+use chrono::Utc;
+use editoast_schemas::infra::Direction;
+
+use crate::core::v2::conflict_detection::Conflict;
+use crate::core::v2::conflict_detection::ConflictType;
+use crate::core::v2::pathfinding::TrackRange;
+use crate::core::v2::simulation::ReportTrain;
+use crate::modelsv2::train_schedule::TrainSchedule;
+use crate::modelsv2::work_schedules::WorkSchedule;
+
+/// Returns the conflicts between a simulated train and the work schedules
+///
+/// A train conflicts with a work schedule when it occupies one of its track ranges,
+/// from the moment its head enters the range to the moment its tail leaves it,
+/// during the time window of the work schedule.
+pub fn detect_conflicts(
+    train: &TrainSchedule,
+    train_length: u64,
+    path: &[TrackRange],
+    report: &ReportTrain,
+    work_schedules: &[WorkSchedule],
+) -> Vec<Conflict> {
+    let at = |time: u64| train.start_time + Duration::milliseconds(time as i64);
+    work_schedules
+        .iter()
+        .filter_map(|work_schedule| {
+            let start = Utc.from_utc_datetime(&work_schedule.start_date_time);
+            let end = Utc.from_utc_datetime(&work_schedule.end_date_time);
+            let (start_time, end_time) = path_intersections(path, work_schedule)
+                .into_iter()
+                .map(|(begin, end)| {
+                    (
+                        at(report.time_at(begin)),
+                        at(report.time_at(end + train_length)),
+                    )
+                })
+                .filter(|(occupation_start, occupation_end)| {
+                    *occupation_start < end && start < *occupation_end
+                })
+                .map(|(occupation_start, occupation_end)| {
+                    (occupation_start.max(start), occupation_end.min(end))
+                })
+                .reduce(|(start_a, end_a), (start_b, end_b)| {
+                    (start_a.min(start_b), end_a.max(end_b))
+                })?;
+            Some(Conflict {
+                train_ids: vec![train.id],
+                work_schedule_ids: vec![work_schedule.id],
+                start_time,
+                end_time,
+                conflict_type: ConflictType::WorkSchedule,
+            })
+        })
+        .collect()
+}
+
+/// Returns the ranges of positions of the path (in mm) on the track ranges of a work schedule
+fn path_intersections(path: &[TrackRange], work_schedule: &WorkSchedule) -> Vec<(u64, u64)> {
+    let mut intersections = vec![];
+    let mut position = 0;
+    for track_range in path {
+        for range in work_schedule
+            .track_ranges
+            .iter()
+            .filter(|range| range.track == track_range.track_section)
+        {
+            let begin = ((range.begin * 1000.).round() as u64).max(track_range.begin);
+            let end = ((range.end * 1000.).round() as u64).min(track_range.end);
+            if begin >= end {
+                continue;
+            }
+            let (begin, end) = match track_range.direction {
+                Direction::StartToStop => (begin - track_range.begin, end - track_range.begin),
+                Direction::StopToStart => (track_range.end - end, track_range.end - begin),
+            };
+            intersections.push((position + begin, position + end));
+        }
+        position += track_range.length();
+    }
+    intersections
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+    use editoast_schemas::infra::TrackRange as WorkScheduleTrackRange;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn at(time: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(time).unwrap().into()
+    }
+
+    fn work_schedule(
+        id: i64,
+        track: &str,
+        begin: f64,
+        end: f64,
+        start: &str,
+        stop: &str,
+    ) -> WorkSchedule {
+        WorkSchedule {
+            id,
+            start_date_time: at(start).naive_utc(),
+            end_date_time: at(stop).naive_utc(),
+            track_ranges: vec![WorkScheduleTrackRange::new(track, begin, end)],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn train_crossing_work_schedules() {
+        let train = TrainSchedule {
+            id: 1,
+            start_time: at("2024-06-28T08:00:00Z"),
+            ..Default::default()
+        };
+        // 1 km on track A then 1 km backwards on track B, at 10 m/s
+        let path = vec![
+            TrackRange::new("A", 0, 1_000_000, Direction::StartToStop),
+            TrackRange::new("B", 0, 1_000_000, Direction::StopToStart),
+        ];
+        let report = ReportTrain {
+            positions: vec![0, 2_000_000],
+            times: vec![0, 200_000],
+            speeds: vec![10., 10.],
+            energy_consumption: 0.,
+            scheduled_points_honored: true,
+        };
+        let work_schedules = vec![
+            // The 100 m long train is on [900 m, 1000 m] of B from 08:01:40 to 08:02:00
+            work_schedule(
+                10,
+                "B",
+                900.,
+                1000.,
+                "2024-06-28T08:01:00Z",
+                "2024-06-28T08:02:00Z",
+            ),
+            // Already over
+            work_schedule(
+                11,
+                "B",
+                900.,
+                1000.,
+                "2024-06-28T07:00:00Z",
+                "2024-06-28T08:01:00Z",
+            ),
+            // Not on the path of the train
+            work_schedule(
+                12,
+                "C",
+                0.,
+                1000.,
+                "2024-06-28T07:00:00Z",
+                "2024-06-28T09:00:00Z",
+            ),
+        ];
+
+        let conflicts = detect_conflicts(&train, 100_000, &path, &report, &work_schedules);
+        assert_eq!(conflicts.len(), 1);
+        let conflict = &conflicts[0];
+        assert_eq!(conflict.train_ids, vec![1]);
+        assert_eq!(conflict.work_schedule_ids, vec![10]);
+        assert_eq!(conflict.start_time, at("2024-06-28T08:01:40Z"));
+        assert_eq!(conflict.end_time, at("2024-06-28T08:02:00Z"));
+    }
+
+    #[test]
+    fn time_at_position() {
+        let report = ReportTrain {
+            positions: vec![0, 1000, 1000, 3000],
+            times: vec![0, 10, 50, 70],
+            speeds: vec![],
+            energy_consumption: 0.,
+            scheduled_points_honored: true,
+        };
+        assert_eq!(report.time_at(0), 0);
+        assert_eq!(report.time_at(500), 5);
+        assert_eq!(report.time_at(1000), 10);
+        assert_eq!(report.time_at(2000), 60);
+        assert_eq!(report.time_at(5000), 70);
+    }
+}
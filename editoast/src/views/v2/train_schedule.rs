@@ -109,6 +109,9 @@ struct TrainScheduleIdParam {
 pub struct TrainScheduleResult {
     id: i64,
     timetable_id: i64,
+    /// The train pattern the train schedule is an occurrence of
+    #[serde(default)]
+    pattern_id: Option<i64>,
     #[serde(flatten)]
     pub train_schedule: TrainScheduleBase,
 }
@@ -118,6 +121,7 @@ impl From<TrainSchedule> for TrainScheduleResult {
         Self {
             id: value.id,
             timetable_id: value.timetable_id,
+            pattern_id: value.pattern_id,
             train_schedule: TrainScheduleBase {
                 train_name: value.train_name,
                 labels: value.labels.into_iter().flatten().collect(),
@@ -492,9 +496,9 @@ struct SimulationBatchParams {
     ids: Vec<i64>,
 }
 
-#[derive(Debug, Serialize, ToSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(tag = "status", rename_all = "snake_case")]
-enum SimulationSummaryResult {
+pub enum SimulationSummaryResult {
     /// Minimal information on a simulation's result
     Success {
         /// Length of a path in mm
@@ -516,6 +520,40 @@ enum SimulationSummaryResult {
     RollingStockNotFound { rolling_stock_name: String },
 }
 
+impl From<SimulationResponse> for SimulationSummaryResult {
+    fn from(simulation: SimulationResponse) -> Self {
+        match simulation {
+            SimulationResponse::Success { final_output, .. } => {
+                let report = final_output.report_train;
+                SimulationSummaryResult::Success {
+                    length: *report.positions.last().unwrap(),
+                    time: *report.times.last().unwrap(),
+                    energy_consumption: report.energy_consumption,
+                    scheduled_points_honored: report.scheduled_points_honored,
+                }
+            }
+            SimulationResponse::PathfindingFailed { pathfinding_result } => {
+                match pathfinding_result {
+                    PathfindingResult::PathfindingFailed { core_error } => {
+                        SimulationSummaryResult::PathfindingFailed {
+                            error_type: core_error.get_type().into(),
+                        }
+                    }
+                    PathfindingResult::RollingStockNotFound { rolling_stock_name } => {
+                        SimulationSummaryResult::RollingStockNotFound { rolling_stock_name }
+                    }
+                    _ => SimulationSummaryResult::PathfindingNotFound,
+                }
+            }
+            SimulationResponse::SimulationFailed { core_error } => {
+                SimulationSummaryResult::SimulationFailed {
+                    error_type: core_error.get_type().into(),
+                }
+            }
+        }
+    }
+}
+
 /// Associate each train id with its simulation summary response
 /// If the simulation fails, it associates the reason: pathfinding failed or running time failed
 #[utoipa::path(
@@ -566,39 +604,11 @@ pub async fn simulation_summary(
         train_simulation_batch(db_pool.clone(), redis_client, core, &trains, &infra).await?;
 
     // Transform simulations to simulation summary
-    let mut simulation_summaries = HashMap::new();
-    for (train, sim) in trains.iter().zip(simulations) {
-        let simulation_summary_result = match sim {
-            SimulationResponse::Success { final_output, .. } => {
-                let report = final_output.report_train;
-                SimulationSummaryResult::Success {
-                    length: *report.positions.last().unwrap(),
-                    time: *report.times.last().unwrap(),
-                    energy_consumption: report.energy_consumption,
-                    scheduled_points_honored: report.scheduled_points_honored,
-                }
-            }
-            SimulationResponse::PathfindingFailed { pathfinding_result } => {
-                match pathfinding_result {
-                    PathfindingResult::PathfindingFailed { core_error } => {
-                        SimulationSummaryResult::PathfindingFailed {
-                            error_type: core_error.get_type().into(),
-                        }
-                    }
-                    PathfindingResult::RollingStockNotFound { rolling_stock_name } => {
-                        SimulationSummaryResult::RollingStockNotFound { rolling_stock_name }
-                    }
-                    _ => SimulationSummaryResult::PathfindingNotFound,
-                }
-            }
-            SimulationResponse::SimulationFailed { core_error } => {
-                SimulationSummaryResult::SimulationFailed {
-                    error_type: core_error.get_type().into(),
-                }
-            }
-        };
-        simulation_summaries.insert(train.id, simulation_summary_result);
-    }
+    let simulation_summaries = trains
+        .iter()
+        .zip(simulations)
+        .map(|(train, sim)| (train.id, sim.into()))
+        .collect();
 
     Ok(Json(simulation_summaries))
 }
@@ -0,0 +1,289 @@
+use std::collections::HashMap;
+
+use actix_web::delete;
+use actix_web::get;
+use actix_web::post;
+use actix_web::put;
+use actix_web::web::Data;
+use actix_web::web::Json;
+use actix_web::web::Path;
+use actix_web::HttpResponse;
+use editoast_derive::EditoastError;
+use serde::Deserialize;
+use serde::Serialize;
+use thiserror::Error;
+use utoipa::IntoParams;
+use utoipa::ToSchema;
+
+use crate::error::InternalError;
+use crate::error::Result;
+use crate::generated_data::infra_error::InfraErrorTypeLabel;
+use crate::modelsv2::prelude::*;
+use crate::modelsv2::validation_profile::ValidationProfile;
+use crate::modelsv2::validation_profile::ValidationRule;
+use crate::modelsv2::DbConnectionPoolV2;
+
+crate::routes! {
+    "/validation_profiles" => {
+        list,
+        create,
+        "/{validation_profile_id}" => {
+            get,
+            update,
+            delete,
+        }
+    }
+}
+
+editoast_common::schemas! {
+    ValidationProfileForm,
+}
+
+#[derive(Debug, Error, EditoastError)]
+#[editoast_error(base_id = "validation_profile")]
+pub enum ValidationProfileError {
+    #[error("Validation profile '{validation_profile_id}' could not be found")]
+    #[editoast_error(status = 404)]
+    NotFound { validation_profile_id: i64 },
+    #[error("Name '{name}' already used")]
+    #[editoast_error(status = 400)]
+    NameAlreadyUsed { name: String },
+}
+
+fn map_diesel_error(e: InternalError, name: impl AsRef<str>) -> InternalError {
+    if e.message
+        .contains(r#"duplicate key value violates unique constraint "validation_profile_name_key""#)
+    {
+        ValidationProfileError::NameAlreadyUsed {
+            name: name.as_ref().to_string(),
+        }
+        .into()
+    } else {
+        e
+    }
+}
+
+#[derive(IntoParams)]
+#[allow(unused)]
+pub struct ValidationProfileIdParam {
+    validation_profile_id: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+struct ValidationProfileForm {
+    name: String,
+    /// The rules of the profile by error type, the types missing keep their default behaviour
+    #[serde(default)]
+    rules: HashMap<InfraErrorTypeLabel, ValidationRule>,
+}
+
+impl From<ValidationProfileForm> for Changeset<ValidationProfile> {
+    fn from(form: ValidationProfileForm) -> Self {
+        ValidationProfile::changeset()
+            .name(form.name)
+            .rules(form.rules)
+    }
+}
+
+/// Retrieve the list of validation profiles
+#[utoipa::path(
+    tag = "validation_profiles",
+    responses(
+        (status = 200, body = Vec<ValidationProfile>, description = "The list of validation profiles"),
+    )
+)]
+#[get("")]
+async fn list(db_pool: Data<DbConnectionPoolV2>) -> Result<Json<Vec<ValidationProfile>>> {
+    let conn = &mut db_pool.get().await?;
+    let settings = SelectionSettings::new().order_by(|| ValidationProfile::ID.asc());
+    Ok(Json(ValidationProfile::list(conn, settings).await?))
+}
+
+/// Create a validation profile
+#[utoipa::path(
+    tag = "validation_profiles",
+    request_body = ValidationProfileForm,
+    responses(
+        (status = 200, body = ValidationProfile, description = "The created validation profile"),
+    )
+)]
+#[post("")]
+async fn create(
+    db_pool: Data<DbConnectionPoolV2>,
+    data: Json<ValidationProfileForm>,
+) -> Result<Json<ValidationProfile>> {
+    let conn = &mut db_pool.get().await?;
+    let form = data.into_inner();
+    let name = form.name.clone();
+    let profile = <Changeset<ValidationProfile>>::from(form)
+        .create(conn)
+        .await
+        .map_err(|e| map_diesel_error(e, name))?;
+    Ok(Json(profile))
+}
+
+/// Retrieve a validation profile
+#[utoipa::path(
+    tag = "validation_profiles",
+    params(ValidationProfileIdParam),
+    responses(
+        (status = 200, body = ValidationProfile, description = "The requested validation profile"),
+        (status = 404, body = InternalError, description = "The requested validation profile was not found"),
+    )
+)]
+#[get("")]
+async fn get(
+    db_pool: Data<DbConnectionPoolV2>,
+    validation_profile_id: Path<i64>,
+) -> Result<Json<ValidationProfile>> {
+    let validation_profile_id = validation_profile_id.into_inner();
+    let conn = &mut db_pool.get().await?;
+    let profile = ValidationProfile::retrieve_or_fail(conn, validation_profile_id, || {
+        ValidationProfileError::NotFound {
+            validation_profile_id,
+        }
+    })
+    .await?;
+    Ok(Json(profile))
+}
+
+/// Replace the name and the rules of a validation profile
+#[utoipa::path(
+    tag = "validation_profiles",
+    params(ValidationProfileIdParam),
+    request_body = ValidationProfileForm,
+    responses(
+        (status = 200, body = ValidationProfile, description = "The updated validation profile"),
+        (status = 404, body = InternalError, description = "The requested validation profile was not found"),
+    )
+)]
+#[put("")]
+async fn update(
+    db_pool: Data<DbConnectionPoolV2>,
+    validation_profile_id: Path<i64>,
+    data: Json<ValidationProfileForm>,
+) -> Result<Json<ValidationProfile>> {
+    let validation_profile_id = validation_profile_id.into_inner();
+    let conn = &mut db_pool.get().await?;
+    let form = data.into_inner();
+    let name = form.name.clone();
+    let profile = <Changeset<ValidationProfile>>::from(form)
+        .update(conn, validation_profile_id)
+        .await
+        .map_err(|e| map_diesel_error(e, name))?
+        .ok_or(ValidationProfileError::NotFound {
+            validation_profile_id,
+        })?;
+    Ok(Json(profile))
+}
+
+/// Delete a validation profile
+#[utoipa::path(
+    tag = "validation_profiles",
+    params(ValidationProfileIdParam),
+    responses(
+        (status = 204, description = "The validation profile was deleted successfully"),
+        (status = 404, body = InternalError, description = "The requested validation profile was not found"),
+    )
+)]
+#[delete("")]
+async fn delete(
+    db_pool: Data<DbConnectionPoolV2>,
+    validation_profile_id: Path<i64>,
+) -> Result<HttpResponse> {
+    let validation_profile_id = validation_profile_id.into_inner();
+    let conn = &mut db_pool.get().await?;
+    ValidationProfile::delete_static_or_fail(conn, validation_profile_id, || {
+        ValidationProfileError::NotFound {
+            validation_profile_id,
+        }
+    })
+    .await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_http::StatusCode;
+    use actix_web::test::TestRequest;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+    use serde_json::json;
+    use std::ops::DerefMut;
+
+    use super::*;
+    use crate::modelsv2::validation_profile::Severity;
+    use crate::views::test_app::TestAppBuilder;
+
+    #[rstest]
+    async fn validation_profile_crud() {
+        let app = TestAppBuilder::default_app();
+        let pool = app.db_pool();
+        let name = format!("profile_{}", uuid::Uuid::new_v4());
+
+        let request = TestRequest::post()
+            .uri("/validation_profiles")
+            .set_json(json!({
+                "name": name,
+                "rules": {
+                    "missing_buffer_stop": { "severity": "error" },
+                    "odd_buffer_stop_location": { "enabled": false },
+                },
+            }))
+            .to_request();
+        let profile: ValidationProfile =
+            app.fetch(request).assert_status(StatusCode::OK).json_into();
+        assert_eq!(profile.name, name);
+        assert_eq!(
+            profile.rules[&InfraErrorTypeLabel::MissingBufferStop].severity,
+            Some(Severity::Error)
+        );
+
+        let request = TestRequest::get()
+            .uri(&format!("/validation_profiles/{}", profile.id))
+            .to_request();
+        let retrieved: ValidationProfile =
+            app.fetch(request).assert_status(StatusCode::OK).json_into();
+        assert_eq!(retrieved.rules, profile.rules);
+
+        let request = TestRequest::delete()
+            .uri(&format!("/validation_profiles/{}", profile.id))
+            .to_request();
+        app.fetch(request).assert_status(StatusCode::NO_CONTENT);
+
+        let exists = ValidationProfile::exists(pool.get_ok().deref_mut(), profile.id)
+            .await
+            .expect("Failed to check if validation profile exists");
+        assert!(!exists);
+    }
+
+    #[rstest]
+    async fn validation_profile_name_already_used() {
+        let app = TestAppBuilder::default_app();
+        let pool = app.db_pool();
+        let name = format!("profile_{}", uuid::Uuid::new_v4());
+        ValidationProfile::changeset()
+            .name(name.clone())
+            .rules(HashMap::new())
+            .create(pool.get_ok().deref_mut())
+            .await
+            .expect("Failed to create validation profile");
+
+        let request = TestRequest::post()
+            .uri("/validation_profiles")
+            .set_json(json!({ "name": name }))
+            .to_request();
+        app.fetch(request).assert_status(StatusCode::BAD_REQUEST);
+    }
+
+    #[rstest]
+    async fn get_unexisting_validation_profile() {
+        let app = TestAppBuilder::default_app();
+
+        let request = TestRequest::get()
+            .uri("/validation_profiles/-666")
+            .to_request();
+        app.fetch(request).assert_status(StatusCode::NOT_FOUND);
+    }
+}
@@ -1,32 +1,61 @@
+use std::collections::HashMap;
+use std::ops::DerefMut;
+
+use actix_web::delete;
+use actix_web::get;
 use actix_web::post;
+use actix_web::put;
+use actix_web::web::Bytes;
 use actix_web::web::Data;
 use actix_web::web::Json;
+use actix_web::web::Path;
+use actix_web::web::Query;
+use actix_web::HttpResponse;
 use chrono::NaiveDateTime;
 use chrono::Utc;
 use derivative::Derivative;
+use diesel::prelude::*;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::AsyncConnection;
+use diesel_async::RunQueryDsl;
 use editoast_derive::EditoastError;
 use serde::de::Error as SerdeError;
 use serde::Deserialize;
 use serde::Serialize;
 use std::result::Result as StdResult;
 use thiserror::Error;
+use utoipa::IntoParams;
 use utoipa::ToSchema;
 
 use crate::error::InternalError;
 use crate::error::Result;
+use crate::modelsv2::prelude::*;
 use crate::modelsv2::work_schedules::WorkSchedule;
 use crate::modelsv2::work_schedules::WorkScheduleGroup;
 use crate::modelsv2::work_schedules::WorkScheduleType;
-use crate::modelsv2::Changeset;
-use crate::modelsv2::Create;
-use crate::modelsv2::CreateBatch;
+use crate::modelsv2::DbConnection;
 use crate::modelsv2::DbConnectionPoolV2;
-use crate::modelsv2::Model;
+use crate::views::pagination::PaginationQueryParam;
+use crate::views::pagination::PaginationStats;
 use editoast_schemas::infra::TrackRange;
 
 crate::routes! {
     "/work_schedules" => {
         create,
+        "/import_csv" => {
+            import_csv,
+        },
+        "/overlapping" => {
+            overlapping,
+        },
+        "/group" => {
+            list_groups,
+            "/{id}" => {
+                get_group,
+                update_group,
+                delete_group,
+            },
+        },
     }
 }
 
@@ -34,6 +63,7 @@ editoast_common::schemas! {
     WorkScheduleCreateForm,
     WorkScheduleCreateResponse,
     WorkScheduleItemForm,
+    WorkScheduleOverlappingForm,
 }
 
 #[derive(Debug, Error, EditoastError)]
@@ -42,6 +72,12 @@ pub enum WorkScheduleError {
     #[error("Name '{name}' already used")]
     #[editoast_error(status = 400)]
     NameAlreadyUsed { name: String },
+    #[error("Work schedule group '{work_schedule_group_id}', could not be found")]
+    #[editoast_error(status = 404)]
+    GroupNotFound { work_schedule_group_id: i64 },
+    #[error("Invalid CSV file: {message}")]
+    #[editoast_error(status = 400)]
+    InvalidCsv { message: String },
 }
 
 pub fn map_diesel_error(e: InternalError, name: impl AsRef<str>) -> InternalError {
@@ -128,6 +164,28 @@ struct WorkScheduleCreateResponse {
     work_schedule_group_id: i64,
 }
 
+/// Creates a work schedule group and its work schedules
+async fn create_group(
+    conn: &mut DbConnection,
+    name: String,
+    work_schedules: Vec<WorkScheduleItemForm>,
+) -> Result<WorkScheduleGroup> {
+    let work_schedule_group = WorkScheduleGroup::changeset()
+        .name(name.clone())
+        .creation_date(Utc::now().naive_utc())
+        .create(conn)
+        .await;
+    let work_schedule_group = work_schedule_group.map_err(|e| map_diesel_error(e, name))?;
+
+    let work_schedules_changesets = work_schedules
+        .into_iter()
+        .map(|work_schedule| work_schedule.into_work_schedule_changeset(work_schedule_group.id))
+        .collect::<Vec<_>>();
+    let _work_schedules: Vec<_> =
+        WorkSchedule::create_batch(conn, work_schedules_changesets).await?;
+    Ok(work_schedule_group)
+}
+
 #[utoipa::path(
     tag = "work_schedules",
     request_body = WorkScheduleCreateForm,
@@ -140,33 +198,323 @@ async fn create(
     db_pool: Data<DbConnectionPoolV2>,
     data: Json<WorkScheduleCreateForm>,
 ) -> Result<Json<WorkScheduleCreateResponse>> {
-    let conn = &mut db_pool.get().await?;
+    let WorkScheduleCreateForm {
+        work_schedule_group_name,
+        work_schedules,
+    } = data.into_inner();
 
-    let work_schedule_create_form = data.into_inner();
+    let work_schedule_group = db_pool
+        .get()
+        .await?
+        .transaction::<_, InternalError, _>(|conn| {
+            async move { create_group(conn, work_schedule_group_name, work_schedules).await }
+                .scope_boxed()
+        })
+        .await?;
 
-    // Create the work_schedule_group
-    let work_schedule_group = WorkScheduleGroup::changeset()
-        .name(work_schedule_create_form.work_schedule_group_name.clone())
-        .creation_date(Utc::now().naive_utc())
-        .create(conn)
-        .await;
-    let work_schedule_group = work_schedule_group
-        .map_err(|e| map_diesel_error(e, work_schedule_create_form.work_schedule_group_name))?;
+    Ok(Json(WorkScheduleCreateResponse {
+        work_schedule_group_id: work_schedule_group.id,
+    }))
+}
 
-    // Create work schedules
-    let work_schedules_changesets = work_schedule_create_form
-        .work_schedules
-        .into_iter()
-        .map(|work_schedule| work_schedule.into_work_schedule_changeset(work_schedule_group.id))
-        .collect::<Vec<_>>();
-    let _work_schedules: Vec<_> =
-        WorkSchedule::create_batch(conn, work_schedules_changesets).await?;
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ImportCsvQueryParams {
+    /// The name of the created work schedule group
+    name: String,
+}
+
+/// A line of a CSV file of work schedules
+///
+/// A work schedule spanning several track ranges is described by one line per track range,
+/// all with the same `obj_id`, type and dates.
+#[derive(Debug, Deserialize)]
+struct WorkScheduleCsvRecord {
+    obj_id: String,
+    work_schedule_type: WorkScheduleType,
+    start_date_time: NaiveDateTime,
+    end_date_time: NaiveDateTime,
+    track: String,
+    begin: f64,
+    end: f64,
+}
+
+/// Parses a CSV file of work schedules, merging the lines sharing the same `obj_id`
+fn parse_csv(data: &[u8]) -> Result<Vec<WorkScheduleItemForm>> {
+    let invalid = |message: String| WorkScheduleError::InvalidCsv { message };
+    let mut work_schedules: Vec<WorkScheduleItemForm> = vec![];
+    let mut indexes: HashMap<String, usize> = HashMap::new();
+    for record in csv::Reader::from_reader(data).deserialize() {
+        let record: WorkScheduleCsvRecord = record.map_err(|e| invalid(e.to_string()))?;
+        let track_range = TrackRange::new(&record.track, record.begin, record.end);
+        if let Some(&index) = indexes.get(&record.obj_id) {
+            let work_schedule = &mut work_schedules[index];
+            if work_schedule.work_schedule_type != record.work_schedule_type
+                || work_schedule.start_date_time != record.start_date_time
+                || work_schedule.end_date_time != record.end_date_time
+            {
+                return Err(invalid(format!(
+                    "The lines of the work schedule '{}' don't have the same type and dates",
+                    record.obj_id
+                ))
+                .into());
+            }
+            work_schedule.track_ranges.push(track_range);
+            continue;
+        }
+        if record.start_date_time >= record.end_date_time {
+            return Err(invalid(format!(
+                "The work_schedule start date '{}' must be before the end date '{}'",
+                record.start_date_time, record.end_date_time
+            ))
+            .into());
+        }
+        indexes.insert(record.obj_id.clone(), work_schedules.len());
+        work_schedules.push(WorkScheduleItemForm {
+            start_date_time: record.start_date_time,
+            end_date_time: record.end_date_time,
+            track_ranges: vec![track_range],
+            obj_id: record.obj_id,
+            work_schedule_type: record.work_schedule_type,
+        });
+    }
+    Ok(work_schedules)
+}
+
+/// Create a work schedule group from a CSV file
+///
+/// The file has a header line and the columns `obj_id`, `work_schedule_type`,
+/// `start_date_time`, `end_date_time`, `track`, `begin` and `end`. There is one line per
+/// track range: the lines sharing the same `obj_id` make a single work schedule.
+#[utoipa::path(
+    tag = "work_schedules",
+    params(ImportCsvQueryParams),
+    request_body(content = String, content_type = "text/csv"),
+    responses(
+        (status = 201, body = WorkScheduleCreateResponse, description = "The id of the created work schedule group"),
+    )
+)]
+#[post("")]
+async fn import_csv(
+    db_pool: Data<DbConnectionPoolV2>,
+    Query(ImportCsvQueryParams { name }): Query<ImportCsvQueryParams>,
+    data: Bytes,
+) -> Result<Json<WorkScheduleCreateResponse>> {
+    let work_schedules = parse_csv(&data)?;
+    let work_schedule_group = db_pool
+        .get()
+        .await?
+        .transaction::<_, InternalError, _>(|conn| {
+            async move { create_group(conn, name, work_schedules).await }.scope_boxed()
+        })
+        .await?;
 
     Ok(Json(WorkScheduleCreateResponse {
         work_schedule_group_id: work_schedule_group.id,
     }))
 }
 
+#[derive(Serialize, Deserialize, ToSchema)]
+struct WorkScheduleOverlappingForm {
+    start_date_time: NaiveDateTime,
+    end_date_time: NaiveDateTime,
+    track_ranges: Vec<TrackRange>,
+}
+
+/// Retrieve the work schedules affecting track ranges during a time window
+#[utoipa::path(
+    tag = "work_schedules",
+    request_body = WorkScheduleOverlappingForm,
+    responses(
+        (status = 200, body = Vec<WorkSchedule>, description = "The work schedules overlapping the track ranges and the time window"),
+    )
+)]
+#[post("")]
+async fn overlapping(
+    db_pool: Data<DbConnectionPoolV2>,
+    data: Json<WorkScheduleOverlappingForm>,
+) -> Result<Json<Vec<WorkSchedule>>> {
+    let WorkScheduleOverlappingForm {
+        start_date_time,
+        end_date_time,
+        track_ranges,
+    } = data.into_inner();
+    let conn = &mut db_pool.get().await?;
+    let work_schedules =
+        WorkSchedule::list_in_time_window(conn, start_date_time, end_date_time).await?;
+    Ok(Json(
+        work_schedules
+            .into_iter()
+            .filter(|work_schedule| work_schedule.intersects(&track_ranges))
+            .collect(),
+    ))
+}
+
+#[derive(Serialize, ToSchema)]
+struct WorkScheduleGroupListResponse {
+    #[serde(flatten)]
+    stats: PaginationStats,
+    results: Vec<WorkScheduleGroup>,
+}
+
+/// Retrieve paginated work schedule groups
+#[utoipa::path(
+    tag = "work_schedules",
+    params(PaginationQueryParam),
+    responses(
+        (status = 200, body = inline(WorkScheduleGroupListResponse), description = "The work schedule groups"),
+    )
+)]
+#[get("")]
+async fn list_groups(
+    db_pool: Data<DbConnectionPoolV2>,
+    pagination_params: Query<PaginationQueryParam>,
+) -> Result<Json<WorkScheduleGroupListResponse>> {
+    let settings = pagination_params
+        .validate(1000)?
+        .warn_page_size(100)
+        .into_selection_settings()
+        .order_by(|| WorkScheduleGroup::ID.asc());
+    let (results, stats) =
+        WorkScheduleGroup::list_paginated(db_pool.get().await?.deref_mut(), settings).await?;
+    Ok(Json(WorkScheduleGroupListResponse { stats, results }))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+struct WorkScheduleGroupIdParam {
+    /// A work schedule group ID
+    id: i64,
+}
+
+#[derive(Serialize, ToSchema)]
+struct WorkScheduleListResponse {
+    #[serde(flatten)]
+    stats: PaginationStats,
+    results: Vec<WorkSchedule>,
+}
+
+/// Retrieve the paginated work schedules of a group, by start date
+#[utoipa::path(
+    tag = "work_schedules",
+    params(WorkScheduleGroupIdParam, PaginationQueryParam),
+    responses(
+        (status = 200, body = inline(WorkScheduleListResponse), description = "The work schedules of the group"),
+        (status = 404, description = "Work schedule group not found"),
+    )
+)]
+#[get("")]
+async fn get_group(
+    db_pool: Data<DbConnectionPoolV2>,
+    path: Path<WorkScheduleGroupIdParam>,
+    pagination_params: Query<PaginationQueryParam>,
+) -> Result<Json<WorkScheduleListResponse>> {
+    let work_schedule_group_id = path.id;
+    let conn = &mut db_pool.get().await?;
+    WorkScheduleGroup::retrieve_or_fail(conn, work_schedule_group_id, || {
+        WorkScheduleError::GroupNotFound {
+            work_schedule_group_id,
+        }
+    })
+    .await?;
+
+    let settings = pagination_params
+        .validate(1000)?
+        .warn_page_size(100)
+        .into_selection_settings()
+        .filter(move || WorkSchedule::WORK_SCHEDULE_GROUP_ID.eq(work_schedule_group_id))
+        .order_by(|| WorkSchedule::START_DATE_TIME.asc())
+        .order_by(|| WorkSchedule::ID.asc());
+    let (results, stats) = WorkSchedule::list_paginated(conn, settings).await?;
+    Ok(Json(WorkScheduleListResponse { stats, results }))
+}
+
+/// Rename a work schedule group and replace its work schedules
+#[utoipa::path(
+    tag = "work_schedules",
+    params(WorkScheduleGroupIdParam),
+    request_body = WorkScheduleCreateForm,
+    responses(
+        (status = 200, body = WorkScheduleGroup, description = "The updated work schedule group"),
+        (status = 404, description = "Work schedule group not found"),
+    )
+)]
+#[put("")]
+async fn update_group(
+    db_pool: Data<DbConnectionPoolV2>,
+    path: Path<WorkScheduleGroupIdParam>,
+    data: Json<WorkScheduleCreateForm>,
+) -> Result<Json<WorkScheduleGroup>> {
+    use crate::tables::work_schedule::dsl;
+
+    let work_schedule_group_id = path.id;
+    let WorkScheduleCreateForm {
+        work_schedule_group_name,
+        work_schedules,
+    } = data.into_inner();
+
+    let work_schedule_group = db_pool
+        .get()
+        .await?
+        .transaction::<_, InternalError, _>(|conn| {
+            async move {
+                let work_schedule_group = WorkScheduleGroup::changeset()
+                    .name(work_schedule_group_name.clone())
+                    .update_or_fail(conn, work_schedule_group_id, || {
+                        WorkScheduleError::GroupNotFound {
+                            work_schedule_group_id,
+                        }
+                    })
+                    .await
+                    .map_err(|e| map_diesel_error(e, work_schedule_group_name))?;
+
+                diesel::delete(
+                    dsl::work_schedule
+                        .filter(dsl::work_schedule_group_id.eq(work_schedule_group_id)),
+                )
+                .execute(conn)
+                .await?;
+                let work_schedules_changesets = work_schedules
+                    .into_iter()
+                    .map(|work_schedule| {
+                        work_schedule.into_work_schedule_changeset(work_schedule_group_id)
+                    })
+                    .collect::<Vec<_>>();
+                let _work_schedules: Vec<_> =
+                    WorkSchedule::create_batch(conn, work_schedules_changesets).await?;
+                Ok(work_schedule_group)
+            }
+            .scope_boxed()
+        })
+        .await?;
+    Ok(Json(work_schedule_group))
+}
+
+/// Delete a work schedule group and its work schedules
+#[utoipa::path(
+    tag = "work_schedules",
+    params(WorkScheduleGroupIdParam),
+    responses(
+        (status = 204, description = "The work schedule group and its work schedules have been deleted"),
+        (status = 404, description = "Work schedule group not found"),
+    )
+)]
+#[delete("")]
+async fn delete_group(
+    db_pool: Data<DbConnectionPoolV2>,
+    path: Path<WorkScheduleGroupIdParam>,
+) -> Result<HttpResponse> {
+    let work_schedule_group_id = path.id;
+    let conn = &mut db_pool.get().await?;
+    // The work schedules are deleted in cascade
+    WorkScheduleGroup::delete_static_or_fail(conn, work_schedule_group_id, || {
+        WorkScheduleError::GroupNotFound {
+            work_schedule_group_id,
+        }
+    })
+    .await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
 #[cfg(test)]
 pub mod test {
     use actix_web::http::StatusCode;
@@ -274,4 +622,178 @@ pub mod test {
             "editoast:work_schedule:NameAlreadyUsed"
         );
     }
+
+    fn create_group_request(name: &str) -> actix_http::Request {
+        TestRequest::post()
+            .uri("/work_schedules")
+            .set_json(json!({
+                "work_schedule_group_name": name,
+                "work_schedules": [{
+                    "start_date_time": "2024-01-01T08:00:00",
+                    "end_date_time": "2024-01-01T09:00:00",
+                    "track_ranges": [{"track": "TA0", "begin": 100.0, "end": 200.0}],
+                    "obj_id": "catenary",
+                    "work_schedule_type": "CATENARY"
+                }, {
+                    "start_date_time": "2024-01-01T10:00:00",
+                    "end_date_time": "2024-01-01T11:00:00",
+                    "track_ranges": [{"track": "TA1", "begin": 0.0, "end": 50.0}],
+                    "obj_id": "track",
+                    "work_schedule_type": "TRACK"
+                }]
+            }))
+            .to_request()
+    }
+
+    #[rstest]
+    async fn work_schedule_group_crud() {
+        let app = TestAppBuilder::default_app();
+        let pool = app.db_pool();
+
+        let group_id = app
+            .fetch(create_group_request("work schedule group crud"))
+            .assert_status(StatusCode::OK)
+            .json_into::<WorkScheduleCreateResponse>()
+            .work_schedule_group_id;
+
+        let request = TestRequest::get()
+            .uri(&format!("/work_schedules/group/{group_id}"))
+            .to_request();
+        let work_schedules: serde_json::Value =
+            app.fetch(request).assert_status(StatusCode::OK).json_into();
+        assert_eq!(work_schedules["count"], 2);
+        assert_eq!(work_schedules["results"][0]["obj_id"], "catenary");
+        assert_eq!(work_schedules["results"][1]["obj_id"], "track");
+
+        let request = TestRequest::put()
+            .uri(&format!("/work_schedules/group/{group_id}"))
+            .set_json(json!({
+                "work_schedule_group_name": "renamed work schedule group",
+                "work_schedules": [{
+                    "start_date_time": "2024-01-02T08:00:00",
+                    "end_date_time": "2024-01-02T09:00:00",
+                    "track_ranges": [],
+                    "obj_id": "replacement",
+                    "work_schedule_type": "TRACK"
+                }]
+            }))
+            .to_request();
+        let group: WorkScheduleGroup = app.fetch(request).assert_status(StatusCode::OK).json_into();
+        assert_eq!(group.name, "renamed work schedule group");
+        let work_schedules = WorkSchedule::list(
+            pool.get_ok().deref_mut(),
+            SelectionSettings::new()
+                .filter(move || WorkSchedule::WORK_SCHEDULE_GROUP_ID.eq(group_id)),
+        )
+        .await
+        .unwrap();
+        assert_eq!(work_schedules.len(), 1);
+        assert_eq!(work_schedules[0].obj_id, "replacement");
+
+        let request = TestRequest::delete()
+            .uri(&format!("/work_schedules/group/{group_id}"))
+            .to_request();
+        app.fetch(request).assert_status(StatusCode::NO_CONTENT);
+        let request = TestRequest::get()
+            .uri(&format!("/work_schedules/group/{group_id}"))
+            .to_request();
+        app.fetch(request).assert_status(StatusCode::NOT_FOUND);
+        let work_schedules = WorkSchedule::list(
+            pool.get_ok().deref_mut(),
+            SelectionSettings::new()
+                .filter(move || WorkSchedule::WORK_SCHEDULE_GROUP_ID.eq(group_id)),
+        )
+        .await
+        .unwrap();
+        assert!(work_schedules.is_empty());
+    }
+
+    #[rstest]
+    async fn work_schedule_overlapping() {
+        let app = TestAppBuilder::default_app();
+
+        let group_id = app
+            .fetch(create_group_request("work schedule group overlapping"))
+            .assert_status(StatusCode::OK)
+            .json_into::<WorkScheduleCreateResponse>()
+            .work_schedule_group_id;
+
+        let overlapping = |track: &str, start: &str, end: &str| {
+            let request = TestRequest::post()
+                .uri("/work_schedules/overlapping")
+                .set_json(json!({
+                    "start_date_time": start,
+                    "end_date_time": end,
+                    "track_ranges": [{"track": track, "begin": 150.0, "end": 300.0}],
+                }))
+                .to_request();
+            let work_schedules: Vec<WorkSchedule> =
+                app.fetch(request).assert_status(StatusCode::OK).json_into();
+            work_schedules
+                .into_iter()
+                .filter(|work_schedule| work_schedule.work_schedule_group_id == group_id)
+                .map(|work_schedule| work_schedule.obj_id)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            overlapping("TA0", "2024-01-01T08:30:00", "2024-01-01T12:00:00"),
+            vec!["catenary"]
+        );
+        assert!(overlapping("TA0", "2024-01-01T09:00:00", "2024-01-01T12:00:00").is_empty());
+        // The track range of the second work schedule ends before 150 m
+        assert!(overlapping("TA1", "2024-01-01T08:00:00", "2024-01-01T12:00:00").is_empty());
+    }
+
+    #[rstest]
+    async fn work_schedule_import_csv() {
+        let app = TestAppBuilder::default_app();
+        let pool = app.db_pool();
+
+        let request = TestRequest::post()
+            .uri("/work_schedules/import_csv?name=work%20schedule%20group%20csv")
+            .insert_header(("Content-Type", "text/csv"))
+            .set_payload(
+                "obj_id,work_schedule_type,start_date_time,end_date_time,track,begin,end\n\
+                 catenary,CATENARY,2024-01-01T08:00:00,2024-01-01T09:00:00,TA0,0,100\n\
+                 catenary,CATENARY,2024-01-01T08:00:00,2024-01-01T09:00:00,TA1,0,50\n\
+                 track,TRACK,2024-01-01T10:00:00,2024-01-01T11:00:00,TA1,50,100\n",
+            )
+            .to_request();
+        let group_id = app
+            .fetch(request)
+            .assert_status(StatusCode::OK)
+            .json_into::<WorkScheduleCreateResponse>()
+            .work_schedule_group_id;
+
+        let work_schedules = WorkSchedule::list(
+            pool.get_ok().deref_mut(),
+            SelectionSettings::new()
+                .filter(move || WorkSchedule::WORK_SCHEDULE_GROUP_ID.eq(group_id))
+                .order_by(|| WorkSchedule::START_DATE_TIME.asc()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(work_schedules.len(), 2);
+        assert_eq!(
+            work_schedules[0].track_ranges,
+            vec![
+                TrackRange::new("TA0", 0., 100.),
+                TrackRange::new("TA1", 0., 50.)
+            ]
+        );
+        assert_eq!(
+            work_schedules[1].work_schedule_type,
+            WorkScheduleType::Track
+        );
+    }
+
+    #[test]
+    fn parse_csv_with_inconsistent_lines() {
+        let csv = "obj_id,work_schedule_type,start_date_time,end_date_time,track,begin,end\n\
+                   catenary,CATENARY,2024-01-01T08:00:00,2024-01-01T09:00:00,TA0,0,100\n\
+                   catenary,CATENARY,2024-01-01T08:00:00,2024-01-01T10:00:00,TA1,0,50\n";
+        let error = parse_csv(csv.as_bytes()).unwrap_err();
+        assert_eq!(error.error_type, "editoast:work_schedule:InvalidCsv");
+    }
 }
@@ -10,6 +10,9 @@ export const INFRA_ERRORS_BY_LEVEL: Record<'errors' | 'warnings', Set<InfraError
     'out_of_range',
     'unknown_port_name',
     'node_endpoints_not_unique',
+    'missing_signal_detector',
+    'disconnected_operational_point_parts',
+    'unreachable_track_section',
   ]),
   warnings: new Set([
     'duplicated_group',
@@ -21,6 +24,8 @@ export const INFRA_ERRORS_BY_LEVEL: Record<'errors' | 'warnings', Set<InfraError
     'overlapping_switches',
     'overlapping_electrifications',
     'unused_port',
+    'electrification_gap',
+    'non_monotonic_speed_limit',
   ]),
 };
 
@@ -2349,10 +2349,17 @@ export type ObjectRef = {
   type: ObjectType;
 };
 export type InfraErrorType =
+  | {
+      error_type: 'disconnected_operational_point_parts';
+    }
   | {
       error_type: 'duplicated_group';
       original_group_path: string;
     }
+  | {
+      error_type: 'electrification_gap';
+      range: number[];
+    }
   | {
       error_type: 'empty_object';
     }
@@ -2378,9 +2385,16 @@ export type InfraErrorType =
       endpoint: Endpoint;
       error_type: 'missing_buffer_stop';
     }
+  | {
+      error_type: 'missing_signal_detector';
+    }
   | {
       error_type: 'node_endpoints_not_unique';
     }
+  | {
+      error_type: 'non_monotonic_speed_limit';
+      tag: string;
+    }
   | {
       error_type: 'object_out_of_path';
       reference: ObjectRef;
@@ -2409,6 +2423,9 @@ export type InfraErrorType =
       error_type: 'unknown_port_name';
       port_name: string;
     }
+  | {
+      error_type: 'unreachable_track_section';
+    }
   | {
       error_type: 'unused_port';
       port_name: string;
@@ -2420,7 +2437,9 @@ export type InfraError = InfraErrorType & {
   obj_type: ObjectType;
 };
 export type InfraErrorTypeLabel =
+  | 'disconnected_operational_point_parts'
   | 'duplicated_group'
+  | 'electrification_gap'
   | 'empty_object'
   | 'invalid_group'
   | 'invalid_reference'
@@ -2428,7 +2447,9 @@ export type InfraErrorTypeLabel =
   | 'invalid_switch_ports'
   | 'missing_route'
   | 'missing_buffer_stop'
+  | 'missing_signal_detector'
   | 'node_endpoints_not_unique'
+  | 'non_monotonic_speed_limit'
   | 'object_out_of_path'
   | 'odd_buffer_stop_location'
   | 'out_of_range'
@@ -2436,6 +2457,7 @@ export type InfraErrorTypeLabel =
   | 'overlapping_speed_sections'
   | 'overlapping_switches'
   | 'unknown_port_name'
+  | 'unreachable_track_section'
   | 'unused_port';
 export type BoundingBox = (number & number)[][];
 export type GeoJsonPointValue = number[];
@@ -70,6 +70,18 @@ class OverlappingSwitches(InfraErrorTrait):
     reference: ObjectReference
 
 
+class MissingSignalDetector(InfraErrorTrait):
+    error_type: Literal["missing_signal_detector"] = Field(default="missing_signal_detector")
+
+
+class DisconnectedOperationalPointParts(InfraErrorTrait):
+    error_type: Literal["disconnected_operational_point_parts"] = Field(default="disconnected_operational_point_parts")
+
+
+class UnreachableTrackSection(InfraErrorTrait):
+    error_type: Literal["unreachable_track_section"] = Field(default="unreachable_track_section")
+
+
 # Warnings
 class EmptyObject(InfraWarningTrait):
     error_type: Literal["empty_object"] = Field(default="empty_object")
@@ -107,9 +119,21 @@ class OverlappingElectrifications(InfraWarningTrait):
     reference: ObjectReference
 
 
+class ElectrificationGap(InfraWarningTrait):
+    error_type: Literal["electrification_gap"] = Field(default="electrification_gap")
+    range: Tuple[float, float]
+
+
+class NonMonotonicSpeedLimit(InfraWarningTrait):
+    error_type: Literal["non_monotonic_speed_limit"] = Field(default="non_monotonic_speed_limit")
+    tag: str
+
+
 InfraError = Annotated[
     Union[
+        DisconnectedOperationalPointParts,
         DuplicatedGroup,
+        ElectrificationGap,
         EmptyObject,
         InvalidGroup,
         InvalidReference,
@@ -117,7 +141,9 @@ InfraError = Annotated[
         InvalidSwitchPorts,
         MissingRoute,
         MissingBufferStop,
+        MissingSignalDetector,
         NodeEndpointsNotUnique,
+        NonMonotonicSpeedLimit,
         ObjectOutOfPath,
         OddBufferStopLocation,
         OutOfRange,
@@ -125,6 +151,7 @@ InfraError = Annotated[
         OverlappingSpeedSections,
         OverlappingSwitches,
         UnknownPortName,
+        UnreachableTrackSection,
         UnusedPort,
     ],
     Field(discriminator="error_type"),